@@ -162,10 +162,235 @@ mod ultimate {
     }
 }
 
+/// An SPRT strength test, which plays matches between two MCTS engines to check whether a change
+/// to MCTS makes the AI stronger, rather than just faster.
+///
+/// This is run instead of the benchmarks when the `STRENGTH_TEST` environment variable is set,
+/// like `STRENGTH_TEST=1 cargo bench --features bench`. The engines are set with `ENGINE_A` and
+/// `ENGINE_B` as `expansions,playouts`, and the hypotheses are set with `ELO0` and `ELO1`.
+mod strength {
+    use std::{env, str::FromStr};
+    use tictactoe::{
+        shared::{ai::play_game, board::GameResult},
+        ultimate::board::{GlobalBoard, MctsPlayer},
+        CellShape,
+    };
+
+    /// The probability of accepting H1 when H0 is true.
+    const ALPHA: f64 = 0.05;
+
+    /// The probability of accepting H0 when H1 is true.
+    const BETA: f64 = 0.05;
+
+    /// The z-score of the 95% confidence interval of the Elo estimate.
+    const Z_95: f64 = 1.96;
+
+    /// The number of games after which we give up if the SPRT hasn't finished.
+    const DEFAULT_MAX_GAMES: u32 = 1000;
+
+    /// The settings of an MCTS engine in a match.
+    #[derive(Clone, Copy, Debug)]
+    struct Engine {
+        /// The maximum number of expansions in the MCTS algorithm.
+        max_mcts_expansions: u16,
+
+        /// The number of playouts to do in each iteration of MCTS.
+        playouts: u8,
+    }
+
+    impl Engine {
+        /// Read the settings from the given environment variable as `expansions,playouts`, or use
+        /// the defaults if it's not set.
+        fn from_env(var: &str, default: Self) -> Self {
+            let Ok(value) = env::var(var) else {
+                return default;
+            };
+
+            let (expansions, playouts) = value
+                .split_once(',')
+                .unwrap_or_else(|| panic!("{var} should be `expansions,playouts`"));
+            Self {
+                max_mcts_expansions: parse(var, expansions),
+                playouts: parse(var, playouts),
+            }
+        }
+
+        /// Create a player with these settings.
+        fn player(self) -> MctsPlayer {
+            MctsPlayer::new(self.max_mcts_expansions, self.playouts)
+        }
+    }
+
+    /// Parse a number from the environment variable `var`, panicking with a useful message.
+    fn parse<T: FromStr>(var: &str, value: &str) -> T {
+        value
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid number {value:?} in {var}"))
+    }
+
+    /// Read a number from the given environment variable, or use the default if it's not set.
+    fn env_or<T: FromStr>(var: &str, default: T) -> T {
+        env::var(var).map_or(default, |value| parse(var, &value))
+    }
+
+    /// Return the expected score of a player who is `elo` stronger than their opponent.
+    fn expected_score(elo: f64) -> f64 {
+        1. / (1. + 10f64.powf(-elo / 400.))
+    }
+
+    /// Return the Elo difference that gives the expected score.
+    fn elo_from_score(score: f64) -> f64 {
+        let score = score.clamp(1e-6, 1. - 1e-6);
+        -400. * (1. / score - 1.).log10()
+    }
+
+    /// The results of engine A against engine B so far.
+    #[derive(Clone, Copy, Debug, Default)]
+    struct Tally {
+        /// The number of games won by engine A.
+        wins: u32,
+
+        /// The number of games drawn.
+        draws: u32,
+
+        /// The number of games lost by engine A.
+        losses: u32,
+    }
+
+    impl Tally {
+        /// The total number of games played.
+        fn games(&self) -> u32 {
+            self.wins + self.draws + self.losses
+        }
+
+        /// Return the mean score of engine A and the variance of the score of one game.
+        fn score_and_variance(&self) -> (f64, f64) {
+            let n = self.games() as f64;
+            let (w, d, l) = (self.wins as f64, self.draws as f64, self.losses as f64);
+            let score = (w + d / 2.) / n;
+            let variance =
+                (w * (1. - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2)) / n;
+            (score, variance)
+        }
+
+        /// Return the log-likelihood ratio of H1 (A is `elo1` stronger) against H0 (A is `elo0`
+        /// stronger), using the normal approximation of the GSPRT.
+        fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+            let (score, variance) = self.score_and_variance();
+            if variance == 0. {
+                return 0.;
+            }
+
+            let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+            self.games() as f64 * (s1 - s0) * (2. * score - s0 - s1) / (2. * variance)
+        }
+
+        /// Return the estimated Elo difference of A over B, along with the bounds of its 95%
+        /// confidence interval.
+        fn elo(&self) -> (f64, f64, f64) {
+            let (score, variance) = self.score_and_variance();
+            let margin = Z_95 * (variance / self.games() as f64).sqrt();
+            (
+                elo_from_score(score),
+                elo_from_score(score - margin),
+                elo_from_score(score + margin),
+            )
+        }
+    }
+
+    /// Play one game of ultimate between the engines, and return the result from the point of
+    /// view of engine A.
+    fn play_one(a: Engine, b: Engine, a_shape: CellShape) -> GameResult {
+        let (mut a, mut b) = (a.player(), b.player());
+        let (x_player, o_player) = match a_shape {
+            CellShape::X => (&mut a, &mut b),
+            CellShape::O => (&mut b, &mut a),
+        };
+        play_game(GlobalBoard::default(), x_player, o_player)
+    }
+
+    /// Play games between engine A and engine B, alternating who plays X, until the SPRT
+    /// accepts a hypothesis or we run out of games, and print the result.
+    pub fn run() {
+        let a = Engine::from_env(
+            "ENGINE_A",
+            Engine {
+                max_mcts_expansions: 3000,
+                playouts: 3,
+            },
+        );
+        let b = Engine::from_env(
+            "ENGINE_B",
+            Engine {
+                max_mcts_expansions: 1000,
+                playouts: 3,
+            },
+        );
+        let elo0: f64 = env_or("ELO0", 0.);
+        let elo1: f64 = env_or("ELO1", 10.);
+        let max_games: u32 = env_or("MAX_GAMES", DEFAULT_MAX_GAMES);
+
+        let lower = (BETA / (1. - ALPHA)).ln();
+        let upper = ((1. - BETA) / ALPHA).ln();
+
+        println!("SPRT of {a:?} against {b:?} with H0: elo = {elo0}, H1: elo = {elo1}");
+        println!("Stopping when LLR leaves [{lower:.2}, {upper:.2}] or after {max_games} games");
+
+        let mut tally = Tally::default();
+        let mut llr = 0.;
+        for game in 0..max_games {
+            let a_shape = if game % 2 == 0 {
+                CellShape::X
+            } else {
+                CellShape::O
+            };
+            match play_one(a, b, a_shape) {
+                GameResult::Won { shape, .. } if shape == a_shape => tally.wins += 1,
+                GameResult::Won { .. } => tally.losses += 1,
+                GameResult::Draw | GameResult::Ongoing => tally.draws += 1,
+            }
+
+            llr = tally.llr(elo0, elo1);
+            let (elo, low, high) = tally.elo();
+            println!(
+                "Game {}: +{} ={} -{}, elo {elo:.1} [{low:.1}, {high:.1}], LLR {llr:.2}",
+                tally.games(),
+                tally.wins,
+                tally.draws,
+                tally.losses
+            );
+
+            if llr <= lower || llr >= upper {
+                break;
+            }
+        }
+
+        if llr >= upper {
+            println!("H1 accepted: engine A is at least {elo1} Elo stronger");
+        } else if llr <= lower {
+            println!("H0 accepted: engine A is at most {elo0} Elo stronger");
+        } else {
+            println!("Inconclusive after {} games", tally.games());
+        }
+    }
+}
+
 criterion::criterion_group!(
     benches,
     normal::bench_eval_and_move,
     ultimate::early_game::bench_move,
     ultimate::late_game::bench_move,
 );
-criterion::criterion_main!(benches);
+
+fn main() {
+    if std::env::var_os("STRENGTH_TEST").is_some() {
+        strength::run();
+        return;
+    }
+
+    benches();
+    criterion::Criterion::default()
+        .configure_from_args()
+        .final_summary();
+}
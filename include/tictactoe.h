@@ -0,0 +1,47 @@
+/* C API for the tic-tac-toe engine. Build the library with `just capi-build`. */
+
+#ifndef TICTACTOE_H
+#define TICTACTOE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+typedef struct TttNormalGame TttNormalGame;
+typedef struct TttUltimateGame TttUltimateGame;
+
+typedef enum TttResult {
+	TTT_ONGOING = 0,
+	TTT_X_WON = 1,
+	TTT_O_WON = 2,
+	TTT_DRAW = 3,
+} TttResult;
+
+typedef enum TttMoveResult {
+	TTT_MOVE_OK = 0,
+	TTT_MOVE_GAME_OVER = 1,
+	TTT_MOVE_WRONG_LOCAL_BOARD = 2,
+	TTT_MOVE_CELL_ALREADY_FULL = 3,
+	TTT_MOVE_OUT_OF_BOUNDS = 4,
+	TTT_MOVE_ILLEGAL = 5,
+} TttMoveResult;
+
+typedef struct TttGlobalCoord {
+	uint8_t x;
+	uint8_t y;
+	uint8_t lx;
+	uint8_t ly;
+} TttGlobalCoord;
+
+TttNormalGame *ttt_normal_new(void);
+void ttt_normal_free(TttNormalGame *game);
+TttMoveResult ttt_normal_make_move(TttNormalGame *game, uint8_t x, uint8_t y);
+TttResult ttt_normal_winner(const TttNormalGame *game);
+bool ttt_normal_ai_move(const TttNormalGame *game, uint8_t *x, uint8_t *y);
+
+TttUltimateGame *ttt_ultimate_new(void);
+void ttt_ultimate_free(TttUltimateGame *game);
+TttMoveResult ttt_ultimate_make_move(TttUltimateGame *game, TttGlobalCoord coord);
+TttResult ttt_ultimate_winner(const TttUltimateGame *game);
+bool ttt_ultimate_ai_move(const TttUltimateGame *game, uint16_t max_mcts_expansions, uint8_t playouts, TttGlobalCoord *coord);
+
+#endif
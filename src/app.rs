@@ -2,12 +2,56 @@
 //! [`eframe::App`](https://docs.rs/eframe/0.19.0/eframe/trait.App.html) to contain all variants. A
 //! variant must implement [`TTTVariantApp`] to be allowed as a variant.
 
-use crate::{normal::NormalTTTApp, shared::gui::centered_square_in_rect, ultimate::UltimateTTTApp};
+use crate::{
+    gravity::GravityTTTApp,
+    normal::{app::config::NormalConfig, NormalTTTApp},
+    series::{Series, SERIES_LENGTHS},
+    settings_file::{SettingsFile, SettingsTransfer},
+    setup_wizard::{SetupChoices, SetupWizard, WizardOutcome},
+    shared::{
+        board::GameResult,
+        crash_report,
+        gui::{centered_square_in_rect, is_wide_layout, show_discard_dialog},
+        ladder::Ladder,
+        profiles::VariantSettings,
+    },
+    ultimate::{app::config::UltimateConfig, UltimateTTTApp},
+};
 use eframe::{
     egui::{self, Context, Ui},
-    epaint::{Pos2, Rect},
+    epaint::{Pos2, Rect, Vec2},
     Storage,
 };
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::mpsc};
+use web_time::Duration;
+
+/// The title of the window when no variant has been selected.
+pub const APP_TITLE: &str = "Tic-tac-toe";
+
+/// The key used to store the selected [`Variant`].
+pub(crate) const VARIANT_KEY: &str = "variant";
+
+/// The key used to store whether the window is fullscreen.
+pub(crate) const FULLSCREEN_KEY: &str = "fullscreen";
+
+/// The key used to store whether presentation mode is on.
+pub(crate) const PRESENTATION_KEY: &str = "presentation";
+
+/// The key used to store the [`ThemePreference`].
+pub(crate) const THEME_KEY: &str = "theme";
+
+/// The key used to store the [`WindowGeometry`].
+#[cfg(not(target_arch = "wasm32"))]
+const WINDOW_GEOMETRY_KEY: &str = "window_geometry";
+
+/// The smallest that the window can be, so that the ultimate board is always big enough to play
+/// on.
+pub const MIN_WINDOW_SIZE: egui::Vec2 = egui::Vec2::new(480., 480.);
+
+/// How long to show the end of a game in kiosk mode before restarting automatically. See
+/// [`LaunchOptions::kiosk`].
+pub const KIOSK_RESTART_DELAY: Duration = Duration::from_secs(5);
 
 /// This trait represents some variant of tic-tac-toe, wrapped up in a GUI app.
 pub trait TTTVariantApp {
@@ -20,35 +64,732 @@ pub trait TTTVariantApp {
     /// [`eframe::App::update`](https://docs.rs/eframe/0.19.0/eframe/trait.App.html#tymethod.update).
     fn show_ui(&mut self, ctx: &Context, _frame: &mut eframe::Frame);
 
+    /// Advance the game without drawing anything, like playing an AI move which has arrived from
+    /// the background. Each app calls this at the start of [`show_ui`](Self::show_ui), but it can
+    /// also be called on its own to play games without a GUI. This method does nothing by default.
+    fn tick(&mut self) {}
+
+    /// Check if there's a game in progress, which would be lost by restarting or leaving the app.
+    fn game_in_progress(&self) -> bool;
+
+    /// Return the config flag for whether to ask for confirmation before discarding a game in
+    /// progress, so that the discard dialog can turn it off.
+    fn confirm_discard_mut(&mut self) -> &mut bool;
+
+    /// Describe the state of the game in a few words, like `"your move"`, which is shown in the
+    /// window title.
+    fn short_status(&self) -> String;
+
+    /// Override the loaded configuration with the given [`LaunchOptions`] and restart the game.
+    /// This method does nothing by default.
+    fn apply_launch_options(&mut self, _options: &LaunchOptions) {}
+
+    /// Turn presentation mode on or off, which is called by the wrapper app before every frame.
+    /// In presentation mode, the app should hide its buttons and enlarge the board for projecting
+    /// a live match. This method does nothing by default.
+    fn set_presentation_mode(&mut self, _presenting: bool) {}
+
+    /// Subscribe to the result of every future game in this app, which will be sent down the
+    /// returned receiver as each game ends, so that the wrapper app can keep the score of a
+    /// [`Series`]. This returns [`None`] by default, for variants which don't report results.
+    fn subscribe_to_results(&mut self) -> Option<mpsc::Receiver<GameResult>> {
+        None
+    }
+
     /// Save the configuration of the app. This method does nothing by default and is equivalent to
     /// [`eframe::App::save`](https://docs.rs/eframe/0.19.0/eframe/trait.App.html#method.save).
     fn save_config(&mut self, _storage: &mut dyn Storage) {}
 }
 
+/// A variant of tic-tac-toe which can be selected on the selection screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variant {
+    /// Normal tic-tac-toe. See [`NormalTTTApp`].
+    Normal,
+
+    /// Ultimate tic-tac-toe. See [`UltimateTTTApp`].
+    Ultimate,
+
+    /// Nine-board tic-tac-toe, which is ultimate tic-tac-toe where winning any local board wins
+    /// the game. See [`UltimateTTTApp::new_nine_board_app`].
+    NineBoard,
+
+    /// Gravity tic-tac-toe, where pieces fall to the bottom of their column. See
+    /// [`GravityTTTApp`].
+    Gravity,
+
+    /// Ultimate tic-tac-toe on a 2x2 grid of local boards, where winning any two local boards wins
+    /// the game. See [`UltimateTTTApp`].
+    SmallUltimate,
+
+    /// Double-ultimate tic-tac-toe, which is ultimate tic-tac-toe where every cell is another
+    /// ultimate board. See [`DoubleUltimateApp`](crate::nested::DoubleUltimateApp).
+    #[cfg(feature = "double-ultimate")]
+    DoubleUltimate,
+}
+
+impl Variant {
+    /// Parse a variant from the hash of a URL, like `#ultimate` or `#nine-board`, ignoring case.
+    pub fn from_url_hash(hash: &str) -> Option<Self> {
+        match hash.trim_start_matches('#').to_ascii_lowercase().as_str() {
+            "normal" => Some(Self::Normal),
+            "ultimate" => Some(Self::Ultimate),
+            "nine-board" => Some(Self::NineBoard),
+            "gravity" => Some(Self::Gravity),
+            "small-ultimate" => Some(Self::SmallUltimate),
+            #[cfg(feature = "double-ultimate")]
+            "double-ultimate" => Some(Self::DoubleUltimate),
+            _ => None,
+        }
+    }
+
+    /// Return the name of this variant, for the window title.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "Tic-Tac-Toe",
+            Self::Ultimate => "Ultimate Tic-Tac-Toe",
+            Self::NineBoard => "Nine-Board Tic-Tac-Toe",
+            Self::Gravity => "Gravity Tic-Tac-Toe",
+            Self::SmallUltimate => "2x2 Ultimate Tic-Tac-Toe",
+            #[cfg(feature = "double-ultimate")]
+            Self::DoubleUltimate => "Double-Ultimate Tic-Tac-Toe",
+        }
+    }
+
+    /// Load the player's position on the AI ladder of this variant from the storage, or return
+    /// [`None`] if this variant has no ladder.
+    fn ladder(self, storage: Option<&dyn Storage>) -> Option<Ladder> {
+        match self {
+            Self::Normal => Some(
+                VariantSettings::<NormalConfig>::load(storage, "normal")
+                    .config
+                    .ladder,
+            ),
+            Self::Ultimate => Some(
+                VariantSettings::<UltimateConfig>::load(storage, "ultimate")
+                    .config
+                    .ladder,
+            ),
+            Self::NineBoard => Some(
+                VariantSettings::<UltimateConfig>::load(storage, "nine_board")
+                    .config
+                    .ladder,
+            ),
+            Self::SmallUltimate => Some(
+                VariantSettings::<UltimateConfig>::load(storage, "small_ultimate")
+                    .config
+                    .ladder,
+            ),
+            _ => None,
+        }
+    }
+
+    /// Create a new app for this variant, optionally loading its config from the storage, and
+    /// then applying the given launch options.
+    fn new_app(
+        self,
+        storage: Option<&dyn Storage>,
+        options: &LaunchOptions,
+    ) -> Box<dyn TTTVariantApp> {
+        let mut app: Box<dyn TTTVariantApp> = match self {
+            Self::Normal => Box::new(NormalTTTApp::new_app(storage)),
+            Self::Ultimate => Box::new(UltimateTTTApp::<3>::new_app(storage)),
+            Self::NineBoard => Box::new(UltimateTTTApp::new_nine_board_app(storage)),
+            Self::Gravity => Box::new(GravityTTTApp::new_app(storage)),
+            Self::SmallUltimate => Box::new(UltimateTTTApp::<2>::new_app(storage)),
+            #[cfg(feature = "double-ultimate")]
+            Self::DoubleUltimate => Box::new(crate::nested::DoubleUltimateApp::new_app(storage)),
+        };
+
+        if *options != LaunchOptions::default() {
+            app.apply_launch_options(options);
+        }
+        app
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    /// Parse a variant from its name, like `ultimate`, ignoring case. See
+    /// [`from_url_hash`](Self::from_url_hash).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_url_hash(s).ok_or_else(|| format!("unknown variant {s:?}"))
+    }
+}
+
+/// Whether the app uses a light or dark theme.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemePreference {
+    /// Follow the theme of the system, or use the dark theme if we can't tell.
+    #[default]
+    System,
+
+    /// Always use the light theme.
+    Light,
+
+    /// Always use the dark theme.
+    Dark,
+}
+
+impl ThemePreference {
+    /// Every theme preference, in the order they're shown in menus.
+    pub const ALL: [Self; 3] = [Self::System, Self::Light, Self::Dark];
+
+    /// Return the name of this theme preference, for menus.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::System => "System",
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+        }
+    }
+
+    /// Get the `egui` visuals for this theme preference, given the theme of the system if it's
+    /// known.
+    pub fn visuals(self, system_theme: Option<eframe::Theme>) -> egui::Visuals {
+        match self {
+            Self::System => system_theme.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+            Self::Light => egui::Visuals::light(),
+            Self::Dark => egui::Visuals::dark(),
+        }
+    }
+}
+
+/// The difficulty of the AI, which maps onto the AI players of each variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Plays randomly.
+    Easy,
+
+    /// Takes wins and blocks losses, but otherwise plays randomly.
+    Medium,
+
+    /// The strongest AI of the variant.
+    Hard,
+
+    /// Gets stronger when the player wins and weaker when they lose.
+    Adaptive,
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    /// Parse a difficulty from its name, like `hard`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Self::Easy),
+            "medium" => Ok(Self::Medium),
+            "hard" => Ok(Self::Hard),
+            "adaptive" => Ok(Self::Adaptive),
+            _ => Err(format!("unknown difficulty {s:?}")),
+        }
+    }
+}
+
+/// Options given when launching the app, like from the command line, which override the saved
+/// config. Every option is optional, and [`None`] means to use the saved config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// The variant to start playing, skipping the selection screen.
+    pub variant: Option<Variant>,
+
+    /// Whether to play against the AI.
+    pub playing_ai: Option<bool>,
+
+    /// The difficulty of the AI.
+    pub difficulty: Option<Difficulty>,
+
+    /// Whether the window starts fullscreen. If this is [`None`], then the window is fullscreen if
+    /// it was last time.
+    pub fullscreen: Option<bool>,
+
+    /// Whether to run in kiosk mode, for a shared touchscreen. This hides the settings and export
+    /// buttons, and restarts the game automatically after [`KIOSK_RESTART_DELAY`].
+    pub kiosk: bool,
+
+    /// Whether the size of the window was chosen at launch, in which case the saved
+    /// [`WindowGeometry`] isn't restored.
+    pub custom_window_size: bool,
+}
+
+/// The position and size of the window on native, which are saved between sessions.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// The position of the top left corner of the window, if it's known.
+    pub position: Option<Pos2>,
+
+    /// The size of the window.
+    pub size: egui::Vec2,
+}
+
+/// Something that the player asked to do with the [`Series`] in the navigation panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SeriesAction {
+    /// Start a new series of at most the given number of games.
+    Start(u32),
+
+    /// Start the next game of the series.
+    NextGame,
+
+    /// Stop playing the series.
+    End,
+}
+
 /// This is the top level wrapper app that contains the variants.
 pub struct TTTApp {
+    /// The variant currently being played, which is saved so that it can be restored when the app
+    /// is opened again.
+    variant: Option<Variant>,
+
+    /// The options that the app was launched with, which are applied to every variant app.
+    launch_options: LaunchOptions,
+
+    /// Whether the window should be fullscreen. This is toggled with F11 and saved between
+    /// sessions.
+    fullscreen: bool,
+
+    /// Whether we've made the window fullscreen, so that we only update the window when
+    /// [`fullscreen`](Self::fullscreen) changes.
+    window_fullscreen: bool,
+
+    /// Whether the app is in presentation mode, for projecting a live match. This hides the
+    /// navigation panel and is passed on to the variant app with
+    /// [`set_presentation_mode`](TTTVariantApp::set_presentation_mode). It's toggled with F10
+    /// and saved between sessions.
+    presentation: bool,
+
+    /// The theme of the app, which is saved between sessions.
+    theme: ThemePreference,
+
+    /// The theme that we've applied to the `egui` context, so that we only update the visuals
+    /// when the theme changes.
+    applied_theme: Option<ThemePreference>,
+
+    /// The wizard shown on the first launch, before the selection screen. See [`SetupWizard`].
+    setup_wizard: Option<SetupWizard>,
+
+    /// The saved geometry of the window, which is restored on the first frame and then cleared.
+    #[cfg(not(target_arch = "wasm32"))]
+    restore_window_geometry: Option<WindowGeometry>,
+
+    /// The geometry of the window when it was last not fullscreen, which is saved so that it can
+    /// be restored next time.
+    #[cfg(not(target_arch = "wasm32"))]
+    window_geometry: Option<WindowGeometry>,
+
+    /// The current title of the window, so that we only update the window when it changes.
+    window_title: String,
+
+    /// Whether we're showing the dialog to confirm going back to the selection screen and
+    /// discarding the game in progress.
+    confirming_back: bool,
+
+    /// The window to export and import all the settings.
+    settings_transfer: SettingsTransfer,
+
+    /// The series being played across normal and ultimate tic-tac-toe, if there is one.
+    series: Option<Series>,
+
+    /// The receiver for the results of the games in the current game of the
+    /// [`series`](Self::series), which is subscribed to each new variant app.
+    series_results: Option<mpsc::Receiver<GameResult>>,
+
     /// This is the variant currently being played. If it's [`None`], then the app will show a
     /// selection screen.
     variant_app: Option<Box<dyn TTTVariantApp>>,
 }
 
 impl TTTApp {
-    /// Create a new wrapper app with no initial variant app.
-    pub fn new(_cc: &eframe::CreationContext) -> Self {
-        Self { variant_app: None }
+    /// Create a new wrapper app, starting with the variant in the URL hash on the web (like
+    /// `#ultimate`), or else the variant that was being played last time, or else the selection
+    /// screen.
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        Self::new_with_options(cc, LaunchOptions::default())
+    }
+
+    /// Create a new wrapper app like [`new`](Self::new), but with the given launch options. The
+    /// variant in the launch options takes priority over the URL hash and the saved variant.
+    pub fn new_with_options(cc: &eframe::CreationContext, launch_options: LaunchOptions) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let url_variant = Variant::from_url_hash(&cc.integration_info.web_info.location.hash);
+        #[cfg(not(target_arch = "wasm32"))]
+        let url_variant = None;
+
+        let variant = launch_options.variant.or(url_variant).or_else(|| {
+            cc.storage
+                .and_then(|storage| eframe::get_value::<Option<Variant>>(storage, VARIANT_KEY))
+                .flatten()
+        });
+
+        let fullscreen = launch_options
+            .fullscreen
+            .or_else(|| {
+                cc.storage
+                    .and_then(|storage| eframe::get_value(storage, FULLSCREEN_KEY))
+            })
+            .unwrap_or(false);
+
+        let presentation = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, PRESENTATION_KEY))
+            .unwrap_or(false);
+
+        let theme = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, THEME_KEY))
+            .unwrap_or_default();
+
+        // We've never saved anything if there's no saved variant, not even `None`, so this is the
+        // first launch. If we've been told what to play, then we don't need the setup wizard
+        let first_launch = cc
+            .storage
+            .map_or(false, |storage| storage.get_string(VARIANT_KEY).is_none());
+        let setup_wizard =
+            (first_launch && variant.is_none() && !launch_options.kiosk).then(SetupWizard::default);
+
+        Self {
+            variant,
+            launch_options,
+            fullscreen,
+            // If we were launched fullscreen, then the window is already fullscreen
+            window_fullscreen: launch_options.fullscreen.unwrap_or(false),
+            presentation,
+            theme,
+            applied_theme: None,
+            setup_wizard,
+            #[cfg(not(target_arch = "wasm32"))]
+            restore_window_geometry: (!launch_options.custom_window_size && !fullscreen)
+                .then(|| {
+                    cc.storage
+                        .and_then(|storage| eframe::get_value(storage, WINDOW_GEOMETRY_KEY))
+                })
+                .flatten(),
+            #[cfg(not(target_arch = "wasm32"))]
+            window_geometry: None,
+            window_title: APP_TITLE.to_string(),
+            confirming_back: false,
+            settings_transfer: SettingsTransfer::default(),
+            series: None,
+            series_results: None,
+            variant_app: variant.map(|variant| variant.new_app(cc.storage, &launch_options)),
+        }
+    }
+
+    /// Draw the panel at the top of the window with the button to go back to the selection screen,
+    /// asking for confirmation first if there's a game in progress, the controls of the
+    /// [`Series`], and the button to import and export settings. The config of the variant app is
+    /// saved before it's closed.
+    fn draw_navigation_panel(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        let mut back_clicked = false;
+        let mut series_action = None;
+        egui::TopBottomPanel::top("navigation_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if self.variant_app.is_some() {
+                    back_clicked = ui
+                        .button("\u{2190} Variants")
+                        .on_hover_text("Go back to choose a different variant")
+                        .clicked();
+                }
+
+                series_action = self.show_series_controls(ui);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .selectable_label(self.settings_transfer.open, "Import/export settings")
+                        .clicked()
+                    {
+                        self.settings_transfer.open = !self.settings_transfer.open;
+                    }
+
+                    if ui
+                        .button("Present")
+                        .on_hover_text(
+                            "Hide all the buttons and enlarge the board to project a live match. \
+                             Press F10 to toggle",
+                        )
+                        .clicked()
+                    {
+                        self.presentation = true;
+                    }
+
+                    ui.menu_button("Theme", |ui| {
+                        for theme in ThemePreference::ALL {
+                            if ui
+                                .radio_value(&mut self.theme, theme, theme.name())
+                                .clicked()
+                            {
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            });
+        });
+
+        self.draw_settings_transfer_window(ctx, frame);
+
+        match series_action {
+            Some(SeriesAction::Start(best_of)) => {
+                self.series = Some(Series::new(best_of));
+                self.start_series_game(frame);
+            }
+            Some(SeriesAction::NextGame) => {
+                if let Some(series) = &mut self.series {
+                    series.next_game();
+                }
+                self.start_series_game(frame);
+            }
+            Some(SeriesAction::End) => self.end_series(),
+            None => (),
+        }
+
+        let Some(app) = &mut self.variant_app else {
+            return;
+        };
+
+        let mut go_back = false;
+        if back_clicked {
+            if app.game_in_progress() && *app.confirm_discard_mut() {
+                self.confirming_back = true;
+            } else {
+                go_back = true;
+            }
+        }
+
+        if self.confirming_back {
+            if let Some(discard) = show_discard_dialog(ctx, app.confirm_discard_mut()) {
+                self.confirming_back = false;
+                go_back = discard;
+            }
+        }
+
+        if go_back {
+            if let Some(storage) = frame.storage_mut() {
+                app.save_config(storage);
+            }
+            self.variant = None;
+            self.variant_app = None;
+            self.end_series();
+            crash_report::clear_context();
+        }
+    }
+
+    /// Show the menu to start a [`Series`], or the score of the series being played with the
+    /// buttons to start its next game and to end it, and return what the player asked to do.
+    fn show_series_controls(&self, ui: &mut Ui) -> Option<SeriesAction> {
+        let mut action = None;
+
+        match &self.series {
+            None => {
+                ui.menu_button("Series", |ui| {
+                    for best_of in SERIES_LENGTHS {
+                        if ui
+                            .button(format!("Best of {best_of}"))
+                            .on_hover_text(
+                                "Alternate between normal and ultimate games, keeping a \
+                                combined score",
+                            )
+                            .clicked()
+                        {
+                            action = Some(SeriesAction::Start(best_of));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+            Some(series) => {
+                ui.separator();
+                ui.label(series.status_text());
+
+                if series.can_start_next_game() && ui.button("Next game \u{2192}").clicked() {
+                    action = Some(SeriesAction::NextGame);
+                }
+                if ui.button("End series").clicked() {
+                    action = Some(SeriesAction::End);
+                }
+            }
+        }
+
+        action
+    }
+
+    /// Start the current game of the [`series`](Self::series) with a new variant app, saving the
+    /// config of the old one first, and subscribe to the results of the new one.
+    fn start_series_game(&mut self, frame: &mut eframe::Frame) {
+        let Some(series) = self.series else {
+            return;
+        };
+
+        if let (Some(app), Some(storage)) = (&mut self.variant_app, frame.storage_mut()) {
+            app.save_config(storage);
+        }
+        self.confirming_back = false;
+        self.select_variant(series.variant(), frame.storage());
+        self.series_results = self
+            .variant_app
+            .as_mut()
+            .and_then(|app| app.subscribe_to_results());
+    }
+
+    /// Count the results of any games which have ended in the current [`series`](Self::series).
+    fn receive_series_results(&mut self) {
+        if let (Some(series), Some(results)) = (&mut self.series, &self.series_results) {
+            for result in results.try_iter() {
+                series.record(result);
+            }
+        }
+    }
+
+    /// Stop playing the current [`series`](Self::series), if there is one.
+    fn end_series(&mut self) {
+        self.series = None;
+        self.series_results = None;
+    }
+
+    /// Draw the window to export and import all the settings, and apply any imported settings.
+    ///
+    /// Importing settings restarts the variant being played, so that it uses the new config.
+    fn draw_settings_transfer_window(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        let (variant, fullscreen, presentation, theme) =
+            (self.variant, self.fullscreen, self.presentation, self.theme);
+        let variant_app = &mut self.variant_app;
+        let Some(mut settings) = self.settings_transfer.show(ctx, || {
+            if let (Some(app), Some(storage)) = (variant_app, frame.storage_mut()) {
+                app.save_config(storage);
+            }
+            SettingsFile {
+                variant,
+                fullscreen,
+                presentation,
+                theme,
+                ..SettingsFile::load(frame.storage())
+            }
+        }) else {
+            return;
+        };
+
+        if let Some(storage) = frame.storage_mut() {
+            settings.save_variants(storage);
+        }
+        self.fullscreen = settings.fullscreen;
+        self.presentation = settings.presentation;
+        self.theme = settings.theme;
+        self.variant = settings.variant;
+        self.confirming_back = false;
+        self.end_series();
+        self.variant_app = settings
+            .variant
+            .map(|variant| variant.new_app(frame.storage(), &self.launch_options));
+    }
+
+    /// Restore the saved window geometry on the first frame, and then keep track of the geometry
+    /// so that it can be saved. The geometry isn't tracked while fullscreen, so that we restore the
+    /// size of the window before it was made fullscreen.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_window_geometry(&mut self, frame: &mut eframe::Frame) {
+        if let Some(geometry) = self.restore_window_geometry.take() {
+            if let Some(position) = geometry.position {
+                frame.set_window_pos(position);
+            }
+            frame.set_window_size(geometry.size.max(MIN_WINDOW_SIZE));
+            return;
+        }
+
+        let window_info = &frame.info().window_info;
+        if !self.fullscreen && !window_info.fullscreen {
+            self.window_geometry = Some(WindowGeometry {
+                position: window_info.position,
+                size: window_info.size,
+            });
+        }
+    }
+
+    /// Leave the setup wizard and start playing a game with the choices made in it.
+    fn finish_setup(&mut self, choices: SetupChoices, storage: Option<&dyn Storage>) {
+        let options = LaunchOptions {
+            variant: Some(choices.variant),
+            playing_ai: Some(choices.playing_ai),
+            difficulty: choices.playing_ai.then_some(choices.difficulty),
+            ..self.launch_options
+        };
+
+        self.setup_wizard = None;
+        self.theme = choices.theme;
+        self.variant = Some(choices.variant);
+        self.variant_app = Some(choices.variant.new_app(storage, &options));
+    }
+
+    /// Start playing the given variant.
+    fn select_variant(&mut self, variant: Variant, storage: Option<&dyn Storage>) {
+        self.variant = Some(variant);
+        self.variant_app = Some(variant.new_app(storage, &self.launch_options));
     }
 }
 
 impl eframe::App for TTTApp {
     fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        if ctx.input().key_pressed(egui::Key::F11) {
+            self.fullscreen = !self.fullscreen;
+        }
+        if self.fullscreen != self.window_fullscreen {
+            frame.set_fullscreen(self.fullscreen);
+            self.window_fullscreen = self.fullscreen;
+        }
+        if ctx.input().key_pressed(egui::Key::F10) {
+            self.presentation = !self.presentation;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_window_geometry(frame);
+
+        let title = match (self.variant, &self.variant_app) {
+            (Some(variant), Some(app)) => {
+                format!("{} \u{2014} {}", variant.name(), app.short_status())
+            }
+            _ => APP_TITLE.to_string(),
+        };
+        if title != self.window_title {
+            frame.set_window_title(&title);
+            self.window_title = title;
+        }
+
+        // Preview the theme being chosen in the setup wizard
+        let theme = self
+            .setup_wizard
+            .map_or(self.theme, |wizard| wizard.choices().theme);
+        if self.applied_theme != Some(theme) {
+            ctx.set_visuals(theme.visuals(frame.info().system_theme));
+            self.applied_theme = Some(theme);
+        }
+
+        if let Some(wizard) = &mut self.setup_wizard {
+            match wizard.show(ctx) {
+                Some(WizardOutcome::Finished(choices)) => {
+                    self.finish_setup(choices, frame.storage())
+                }
+                Some(WizardOutcome::Skipped) => self.setup_wizard = None,
+                None => (),
+            }
+            return;
+        }
+
+        // Kiosk mode stays on one variant, and presentation mode hides all the buttons
+        if !self.launch_options.kiosk && !self.presentation {
+            self.draw_navigation_panel(ctx, frame);
+        }
+
         match &mut self.variant_app {
-            Some(app) => app.show_ui(ctx, frame),
+            Some(app) => {
+                app.set_presentation_mode(self.presentation);
+                app.show_ui(ctx, frame);
+                self.receive_series_results();
+            }
             None => {
                 use eframe::epaint::text::{FontFamily, FontId};
                 use egui::style::TextStyle::Button as ButtonTextStyle;
 
-                ctx.request_repaint();
-
                 egui::CentralPanel::default().show(ctx, |ui| {
                     // Make the button font size bigger
                     let mut style = (*ctx.style()).clone();
@@ -64,41 +805,49 @@ impl eframe::App for TTTApp {
 
                     ui.put(rect, |ui: &mut Ui| {
                         ui.allocate_ui_at_rect(rect, |ui| {
-                            // We place the buttons in the top and bottom two fifths of the rect
-                            let Pos2 { x: min_x, y: min_y } = rect.min;
-                            let Pos2 { x: max_x, y: max_y } = rect.max;
-                            let two_fifths = 0.4 * rect.height();
+                            // We place the buttons in columns on wide windows, and rows
+                            // otherwise, with a small gap between each one
+                            let wide = is_wide_layout(ctx);
+                            let side = rect.height();
+                            #[allow(unused_mut)]
+                            let mut buttons = vec![
+                                (Variant::Normal, "Normal"),
+                                (Variant::Ultimate, "Ultimate"),
+                                (Variant::NineBoard, "Nine-board"),
+                                (Variant::Gravity, "Gravity"),
+                                (Variant::SmallUltimate, "2x2 ultimate"),
+                            ];
+                            #[cfg(feature = "double-ultimate")]
+                            buttons.push((Variant::DoubleUltimate, "Double-ultimate"));
 
-                            if ui
-                                .put(
-                                    Rect::from_two_pos(
-                                        rect.min,
-                                        Pos2 {
-                                            x: max_x,
-                                            y: min_y + two_fifths,
-                                        },
-                                    ),
-                                    egui::Button::new("Normal"),
-                                )
-                                .clicked()
-                            {
-                                self.variant_app =
-                                    Some(Box::new(NormalTTTApp::new_app(frame.storage())));
-                            } else if ui
-                                .put(
-                                    Rect::from_two_pos(
-                                        Pos2 {
-                                            x: min_x,
-                                            y: max_y - two_fifths,
-                                        },
-                                        rect.max,
-                                    ),
-                                    egui::Button::new("Ultimate"),
-                                )
-                                .clicked()
-                            {
-                                self.variant_app =
-                                    Some(Box::new(UltimateTTTApp::new_app(frame.storage())));
+                            let step = 1.05 / buttons.len() as f32 * side;
+                            let thickness = step - 0.05 * side;
+
+                            for (i, (variant, label)) in buttons.into_iter().enumerate() {
+                                let start = i as f32 * step;
+                                let button_rect = if wide {
+                                    Rect::from_min_size(
+                                        rect.min + Vec2::new(start, 0.),
+                                        Vec2::new(thickness, side),
+                                    )
+                                } else {
+                                    Rect::from_min_size(
+                                        rect.min + Vec2::new(0., start),
+                                        Vec2::new(side, thickness),
+                                    )
+                                };
+
+                                // Show the ladder level once the player has started climbing
+                                let label = match variant.ladder(frame.storage()) {
+                                    Some(ladder) if ladder.games_played() > 0 => {
+                                        format!("{label}\nLevel {}", ladder.level)
+                                    }
+                                    _ => label.to_string(),
+                                };
+
+                                if ui.put(button_rect, egui::Button::new(label)).clicked() {
+                                    self.select_variant(variant, frame.storage());
+                                }
                             }
                         })
                         .response
@@ -116,8 +865,49 @@ impl eframe::App for TTTApp {
     }
 
     fn save(&mut self, storage: &mut dyn Storage) {
+        eframe::set_value(storage, VARIANT_KEY, &self.variant);
+        eframe::set_value(storage, FULLSCREEN_KEY, &self.fullscreen);
+        eframe::set_value(storage, PRESENTATION_KEY, &self.presentation);
+        eframe::set_value(storage, THEME_KEY, &self.theme);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(geometry) = &self.window_geometry {
+            eframe::set_value(storage, WINDOW_GEOMETRY_KEY, geometry);
+        }
+
         if let Some(app) = &mut self.variant_app {
             app.save_config(storage);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_from_url_hash_test() {
+        assert_eq!(Variant::from_url_hash("#ultimate"), Some(Variant::Ultimate));
+        assert_eq!(Variant::from_url_hash("#Normal"), Some(Variant::Normal));
+        assert_eq!(Variant::from_url_hash("normal"), Some(Variant::Normal));
+        assert_eq!(
+            Variant::from_url_hash("#Nine-Board"),
+            Some(Variant::NineBoard)
+        );
+        assert_eq!(Variant::from_url_hash("#gravity"), Some(Variant::Gravity));
+        assert_eq!(
+            Variant::from_url_hash("#small-ultimate"),
+            Some(Variant::SmallUltimate)
+        );
+        assert_eq!(Variant::from_url_hash(""), None);
+        assert_eq!(Variant::from_url_hash("#chess"), None);
+    }
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!("Ultimate".parse(), Ok(Variant::Ultimate));
+        assert!("chess".parse::<Variant>().is_err());
+        assert_eq!("HARD".parse(), Ok(Difficulty::Hard));
+        assert!("impossible".parse::<Difficulty>().is_err());
+    }
+}
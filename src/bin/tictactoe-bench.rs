@@ -0,0 +1,201 @@
+//! This crate runs a fixed suite of ultimate positions through MCTS and prints the performance of
+//! the search as JSON, so that performance regressions can be tracked per commit without the
+//! statistical runtime of criterion.
+//!
+//! The optional arguments are the maximum number of MCTS iterations, the number of playouts in each
+//! iteration, and the number of times to search each position, like `tictactoe-bench 3000 3 5`.
+
+use serde::Serialize;
+use std::{collections::HashMap, str::FromStr, time::Instant};
+use tictactoe::ultimate::{board::GlobalBoard, test_utils::make_global_board, GlobalCoord};
+
+/// The default maximum number of MCTS iterations, which matches the default setting of the app.
+const DEFAULT_ITERATIONS: u16 = 3000;
+
+/// The default number of playouts in each MCTS iteration, which matches the default setting of
+/// the app.
+const DEFAULT_PLAYOUTS: u8 = 3;
+
+/// The default number of times to search each position.
+const DEFAULT_RUNS: u32 = 5;
+
+/// The settings that the suite was run with.
+#[derive(Debug, Serialize)]
+struct Settings {
+    /// The maximum number of MCTS iterations in each search.
+    iterations: u16,
+
+    /// The number of playouts in each MCTS iteration.
+    playouts: u8,
+
+    /// The number of times that each position was searched.
+    runs: u32,
+}
+
+/// The performance of the search on one position of the suite, over all the runs.
+#[derive(Debug, Serialize)]
+struct PositionResult {
+    /// The name of the position.
+    name: &'static str,
+
+    /// The number of nodes added to the game tree per second, which is one per iteration.
+    nodes_per_sec: f64,
+
+    /// The number of playouts per second.
+    playouts_per_sec: f64,
+
+    /// The move chosen most often.
+    best_move: Option<GlobalCoord>,
+
+    /// The proportion of the runs which chose the [`best_move`](Self::best_move), where 1 means
+    /// that every run agreed.
+    best_move_stability: f64,
+}
+
+/// The results of the whole suite.
+#[derive(Debug, Serialize)]
+struct Report {
+    /// The settings that the suite was run with.
+    settings: Settings,
+
+    /// The results of each position.
+    positions: Vec<PositionResult>,
+
+    /// The number of nodes added to the game tree per second over the whole suite.
+    nodes_per_sec: f64,
+
+    /// The number of playouts per second over the whole suite.
+    playouts_per_sec: f64,
+
+    /// The mean of the best move stabilities of the positions.
+    best_move_stability: f64,
+}
+
+/// Return the positions of the suite along with their names.
+fn positions() -> Vec<(&'static str, GlobalBoard)> {
+    vec![
+        ("empty", GlobalBoard::default()),
+        (
+            "early_game",
+            make_global_board! {
+                next = (0, 2),
+                () (_; _ X _; _) (_; _; X _ _);
+                () (_ O O; _ X _; _) ();
+                () () ();
+            },
+        ),
+        (
+            "middle_game",
+            make_global_board! {
+                next = (1, 1),
+                (_; _ _ O; _) () ();
+                (_; _ X _; _ _ X) (_; _ O X; X X X) (_; O X _; _);
+                (_; _ O _; _) (_; _ O _; _) (X _ _; _ O _; _ _ O);
+            },
+        ),
+        (
+            "late_game",
+            make_global_board! {
+                next = (1, 2),
+                (_ _ X; _ X _; X O O) (X O X; _ X _; X _ _) (O O O; X X _; _ O _);
+                (_; _ X _; _ _ O) (O O O; O X O; O O O) (_; _ X _; _ X _);
+                (O O _; _ X O; _) (_ _ X; _ X _; X _ O) (_ X X; _ X _; _ X _);
+            },
+        ),
+    ]
+}
+
+/// Search the given board `runs` times, and measure the performance of the search. Return the
+/// result along with the total seconds, nodes, and playouts of the runs.
+///
+/// Runs where the move was found without searching add no nodes or playouts.
+fn bench_position(
+    name: &'static str,
+    board: &GlobalBoard,
+    settings: &Settings,
+) -> (PositionResult, f64, u64, u64) {
+    let mut board = board.clone();
+    board.set_ai_shape(board.to_move());
+
+    let mut seconds = 0.;
+    let mut playouts = 0;
+    let mut moves: HashMap<GlobalCoord, u32> = HashMap::new();
+
+    for _ in 0..settings.runs {
+        let start = Instant::now();
+        let (mv, stats) = board.generate_ai_move_with_stats(settings.iterations, settings.playouts);
+        seconds += start.elapsed().as_secs_f64();
+
+        playouts += stats.map_or(0, |stats| u64::from(stats.playouts));
+        if let Some(mv) = mv {
+            *moves.entry(mv).or_default() += 1;
+        }
+    }
+
+    let nodes = playouts / u64::from(settings.playouts.max(1));
+    let (best_move, agreeing_runs) = moves
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .map_or((None, 0), |(mv, count)| (Some(mv), count));
+
+    let result = PositionResult {
+        name,
+        nodes_per_sec: nodes as f64 / seconds,
+        playouts_per_sec: playouts as f64 / seconds,
+        best_move,
+        best_move_stability: f64::from(agreeing_runs) / f64::from(settings.runs.max(1)),
+    };
+    (result, seconds, nodes, playouts)
+}
+
+/// Parse the given argument, or return `default` if there isn't one, exiting with the usage if
+/// it's invalid.
+fn parse_arg<T: FromStr>(arg: Option<String>, name: &str, default: T) -> T {
+    let Some(arg) = arg else {
+        return default;
+    };
+
+    arg.parse().unwrap_or_else(|_| {
+        eprintln!("Usage: tictactoe-bench [iterations] [playouts] [runs]");
+        eprintln!("Invalid {name}: {arg}");
+        std::process::exit(1);
+    })
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let settings = Settings {
+        iterations: parse_arg(args.next(), "iterations", DEFAULT_ITERATIONS),
+        playouts: parse_arg(args.next(), "playouts", DEFAULT_PLAYOUTS),
+        runs: parse_arg(args.next(), "runs", DEFAULT_RUNS),
+    };
+
+    let (mut seconds, mut nodes, mut playouts) = (0., 0, 0);
+    let mut results = vec![];
+    for (name, board) in positions() {
+        let (result, position_seconds, position_nodes, position_playouts) =
+            bench_position(name, &board, &settings);
+        seconds += position_seconds;
+        nodes += position_nodes;
+        playouts += position_playouts;
+        results.push(result);
+    }
+
+    let best_move_stability = results
+        .iter()
+        .map(|result| result.best_move_stability)
+        .sum::<f64>()
+        / results.len() as f64;
+    let report = Report {
+        settings,
+        positions: results,
+        nodes_per_sec: nodes as f64 / seconds,
+        playouts_per_sec: playouts as f64 / seconds,
+        best_move_stability,
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("The report should always be serializable")
+    );
+}
@@ -0,0 +1,71 @@
+//! This crate runs a chat bot for playing tic-tac-toe over IRC. See [`tictactoe::bot`] for the
+//! commands.
+//!
+//! The arguments are the server address, the channel to join, and optionally the nickname, like
+//! `tictactoe-bot irc.libera.chat:6667 '#tictactoe' tictactoe-bot`.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+};
+use tictactoe::bot::Bot;
+
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(address), Some(channel)) = (args.next(), args.next()) else {
+        eprintln!("Usage: tictactoe-bot <server:port> <#channel> [nickname]");
+        std::process::exit(1);
+    };
+    let nickname = args.next().unwrap_or_else(|| "tictactoe-bot".to_string());
+
+    let stream = TcpStream::connect(&address)?;
+    let mut writer = stream.try_clone()?;
+    let mut send = |line: &str| writer.write_all(format!("{line}\r\n").as_bytes());
+
+    send(&format!("NICK {nickname}"))?;
+    send(&format!("USER {nickname} 0 * :Tic-tac-toe bot"))?;
+
+    let mut bot = Bot::default();
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+
+        if let Some(token) = line.strip_prefix("PING ") {
+            send(&format!("PONG {token}"))?;
+            continue;
+        }
+
+        // Messages look like `:prefix COMMAND params :trailing`
+        let Some((prefix, rest)) = line.strip_prefix(':').and_then(|l| l.split_once(' ')) else {
+            continue;
+        };
+        let (params, trailing) = rest.split_once(" :").unwrap_or((rest, ""));
+        let mut params = params.split(' ');
+
+        match params.next() {
+            // The welcome message, after which we can join channels
+            Some("001") => send(&format!("JOIN {channel}"))?,
+            Some("PRIVMSG") => {
+                let Some(target) = params.next() else {
+                    continue;
+                };
+
+                // Reply in the channel, or directly to the sender for private messages
+                let sender = prefix.split('!').next().unwrap_or(prefix);
+                let chat = if target.starts_with('#') {
+                    target
+                } else {
+                    sender
+                };
+
+                if let Some(reply) = bot.handle_message(chat, trailing) {
+                    for reply_line in reply {
+                        send(&format!("PRIVMSG {chat} :{reply_line}"))?;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
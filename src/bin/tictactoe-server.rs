@@ -0,0 +1,54 @@
+//! This crate runs an HTTP server hosting games of tic-tac-toe. See [`tictactoe::server`] for
+//! the API.
+//!
+//! The address to listen on can be given as the first argument, and defaults to `127.0.0.1:8080`.
+
+use std::io::Read;
+use tictactoe::server::GameServer;
+use tiny_http::{Header, Response, Server};
+
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let server = Server::http(&address).expect("Should be able to listen on the address");
+    println!("Listening on http://{address}");
+
+    let mut games = GameServer::default();
+    let header = |name: &str, value: &str| {
+        Header::from_bytes(name, value).expect("Our headers should be valid")
+    };
+    let content_type = header("Content-Type", "application/json");
+
+    // The web app is usually served from a different origin, so we need to allow CORS
+    let cors_headers = [
+        header("Access-Control-Allow-Origin", "*"),
+        header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
+        header("Access-Control-Allow-Headers", "Content-Type"),
+    ];
+
+    for mut request in server.incoming_requests() {
+        if request.method().as_str() == "OPTIONS" {
+            let mut response = Response::empty(204);
+            for cors_header in &cors_headers {
+                response.add_header(cors_header.clone());
+            }
+            let _ = request.respond(response);
+            continue;
+        }
+
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            body.clear();
+        }
+
+        let response = games.handle(request.method().as_str(), request.url(), &body);
+        let mut response = Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(content_type.clone());
+        for cors_header in &cors_headers {
+            response.add_header(cors_header.clone());
+        }
+        let _ = request.respond(response);
+    }
+}
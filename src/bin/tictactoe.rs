@@ -1,21 +1,326 @@
 //! This crate  simply runs the tic-tac-toe GUI app.
+//!
+//! On native, the command line arguments can choose the variant and the AI settings, set up the
+//! window, or play games of the AI against itself without a window. Run with `--help` for details.
 
 use tictactoe::app::TTTApp;
 
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    clap::Parser,
+    tictactoe::{
+        app::{Difficulty, LaunchOptions, Variant, APP_TITLE, MIN_WINDOW_SIZE},
+        gravity::board::{Board as GravityBoard, MinimaxPlayer as GravityMinimaxPlayer},
+        normal::board::{Board, MinimaxPlayer},
+        shared::{
+            ai::{play_game, AiPlayer, Game, GreedyBot, RandomBot},
+            board::GameResult,
+            export::{app_icon_rgba, ICON_SIZE},
+        },
+        ultimate::{
+            board::{GlobalBoard, MctsPlayer, SmallGlobalBoard, WinCondition},
+            dataset,
+        },
+        CellShape,
+    },
+};
+
+/// The maximum number of MCTS expansions for the hard ultimate AI in self-play, which matches the
+/// default setting of the app.
+#[cfg(not(target_arch = "wasm32"))]
+const SELFPLAY_MCTS_EXPANSIONS: u16 = 3000;
+
+/// The number of MCTS playouts for the hard ultimate AI in self-play, which matches the default
+/// setting of the app.
+#[cfg(not(target_arch = "wasm32"))]
+const SELFPLAY_MCTS_PLAYOUTS: u8 = 3;
+
+/// Play tic-tac-toe, or watch the AI play against itself.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The variant to play, either `normal`, `ultimate`, `nine-board`, `gravity`, or
+    /// `small-ultimate`, skipping the selection screen. `double-ultimate` can also be played with
+    /// the `double-ultimate` feature
+    #[arg(long)]
+    variant: Option<Variant>,
+
+    /// Play against the AI
+    #[arg(long, conflicts_with = "no_ai")]
+    ai: bool,
+
+    /// Play against another person instead of the AI
+    #[arg(long)]
+    no_ai: bool,
+
+    /// The difficulty of the AI, either `easy`, `medium`, `hard`, or `adaptive`
+    #[arg(long)]
+    difficulty: Option<Difficulty>,
+
+    /// The initial width of the window
+    #[arg(long, requires = "height")]
+    width: Option<f32>,
+
+    /// The initial height of the window
+    #[arg(long, requires = "width")]
+    height: Option<f32>,
+
+    /// Start in fullscreen. Press F11 to toggle fullscreen at any time
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Run in fullscreen kiosk mode for a shared touchscreen, which hides the settings and export
+    /// buttons and restarts each game automatically after it ends
+    #[arg(long)]
+    kiosk: bool,
+
+    /// Append a JSON line to this file for every game and every AI move, for analysing games later
+    /// or attaching to bug reports
+    #[arg(long, value_name = "PATH")]
+    game_log: Option<std::path::PathBuf>,
+
+    /// Play N games of the AI against itself without opening a window, and print the results. The
+    /// variant defaults to `normal` and the difficulty defaults to `hard`
+    #[arg(long, value_name = "N")]
+    selfplay: Option<u32>,
+
+    /// With `--selfplay`, write every position of the ultimate games where the AI searched to this
+    /// file as CSV, with the outcome of the game and the visits of the search, for training
+    /// evaluation functions. The games are always ultimate with the hard AI
+    #[arg(long, value_name = "PATH", requires = "selfplay")]
+    dataset: Option<std::path::PathBuf>,
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    let options = eframe::NativeOptions::default();
+    tictactoe::shared::crash_report::install_hook();
+    let args = Args::parse();
+
+    if let Some(path) = &args.game_log {
+        if let Err(e) = init_game_log(path) {
+            eprintln!("Failed to open game log {}: {e}", path.display());
+        }
+    }
+
+    if let Some(games) = args.selfplay {
+        if let Some(path) = &args.dataset {
+            write_dataset(path, games);
+            return;
+        }
+
+        selfplay(
+            args.variant.unwrap_or(Variant::Normal),
+            args.difficulty.unwrap_or(Difficulty::Hard),
+            games,
+        );
+        return;
+    }
+
+    let launch_options = LaunchOptions {
+        variant: args.variant,
+        playing_ai: match (args.ai, args.no_ai) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        difficulty: args.difficulty,
+        fullscreen: (args.fullscreen || args.kiosk).then_some(true),
+        kiosk: args.kiosk,
+        custom_window_size: args.width.is_some(),
+    };
+
+    let options = eframe::NativeOptions {
+        initial_window_size: args
+            .width
+            .zip(args.height)
+            .map(|(width, height)| eframe::egui::Vec2::new(width, height).max(MIN_WINDOW_SIZE)),
+        min_window_size: Some(MIN_WINDOW_SIZE),
+        fullscreen: args.fullscreen || args.kiosk,
+        icon_data: app_icon_rgba().ok().map(|rgba| eframe::IconData {
+            rgba,
+            width: ICON_SIZE,
+            height: ICON_SIZE,
+        }),
+        ..Default::default()
+    };
     eframe::run_native(
-        "Tic-tac-toe",
+        APP_TITLE,
         options,
-        Box::new(|cc| Box::new(TTTApp::new(cc))),
+        Box::new(move |cc| Box::new(TTTApp::new_with_options(cc, launch_options))),
     );
 }
 
+/// Log the spans and events of every game and AI move as JSON lines appended to the given file.
+#[cfg(not(target_arch = "wasm32"))]
+fn init_game_log(path: &std::path::Path) -> std::io::Result<()> {
+    use std::{fs::OpenOptions, sync::Mutex};
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    tracing_subscriber::fmt()
+        .json()
+        .with_span_list(true)
+        .with_current_span(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(Mutex::new(file))
+        .init();
+    Ok(())
+}
+
+/// Play the given number of games of the AI against itself and print the result of each game,
+/// followed by the totals.
+///
+/// The adaptive AI adapts to a human player, so it plays at full strength here.
+#[cfg(not(target_arch = "wasm32"))]
+fn selfplay(variant: Variant, difficulty: Difficulty, games: u32) {
+    let (mut x_wins, mut o_wins, mut draws) = (0, 0, 0);
+
+    for game in 1..=games {
+        let result = match variant {
+            Variant::Normal => play_game(
+                Board::default(),
+                &mut *normal_player(difficulty),
+                &mut *normal_player(difficulty),
+            ),
+            Variant::Ultimate => play_game(
+                GlobalBoard::default(),
+                &mut *ultimate_player(difficulty),
+                &mut *ultimate_player(difficulty),
+            ),
+            Variant::NineBoard => play_game(
+                GlobalBoard::default().with_win_condition(WinCondition::AnyBoard),
+                &mut *ultimate_player(difficulty),
+                &mut *ultimate_player(difficulty),
+            ),
+            Variant::Gravity => play_game(
+                GravityBoard::default(),
+                &mut *gravity_player(difficulty),
+                &mut *gravity_player(difficulty),
+            ),
+            Variant::SmallUltimate => play_game(
+                SmallGlobalBoard::empty(CellShape::O, CellShape::X),
+                &mut *ultimate_player(difficulty),
+                &mut *ultimate_player(difficulty),
+            ),
+            #[cfg(feature = "double-ultimate")]
+            Variant::DoubleUltimate => play_game(
+                tictactoe::nested::board::DoubleUltimateBoard::default(),
+                &mut *ultimate_player(difficulty),
+                &mut *ultimate_player(difficulty),
+            ),
+        };
+
+        let description = match result {
+            GameResult::Won {
+                shape: CellShape::X,
+                ..
+            } => {
+                x_wins += 1;
+                "X wins"
+            }
+            GameResult::Won {
+                shape: CellShape::O,
+                ..
+            } => {
+                o_wins += 1;
+                "O wins"
+            }
+            GameResult::Draw => {
+                draws += 1;
+                "draw"
+            }
+            GameResult::Ongoing => "unfinished",
+        };
+        println!("Game {game}: {description}");
+    }
+
+    println!("X won {x_wins}, O won {o_wins}, {draws} draws");
+}
+
+/// Play the given number of ultimate games of the hard AI against itself, and write them to the
+/// given file as a dataset. See [`tictactoe::ultimate::dataset`].
+#[cfg(not(target_arch = "wasm32"))]
+fn write_dataset(path: &std::path::Path, games: u32) {
+    use std::{
+        fs::File,
+        io::{BufWriter, Write},
+    };
+
+    let result = File::create(path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        dataset::write_dataset(
+            &mut out,
+            games,
+            SELFPLAY_MCTS_EXPANSIONS,
+            SELFPLAY_MCTS_PLAYOUTS,
+        )?;
+        out.flush()
+    });
+
+    match result {
+        Ok(()) => println!("Wrote {games} games to {}", path.display()),
+        Err(e) => {
+            eprintln!("Failed to write dataset {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Create the AI player for the normal variant at the given difficulty.
+#[cfg(not(target_arch = "wasm32"))]
+fn normal_player(difficulty: Difficulty) -> Box<dyn AiPlayer<Board>> {
+    match difficulty {
+        Difficulty::Easy => Box::new(RandomBot),
+        Difficulty::Medium => Box::new(GreedyBot),
+        Difficulty::Hard | Difficulty::Adaptive => Box::new(MinimaxPlayer::default()),
+    }
+}
+
+/// Create the AI player for the gravity variant at the given difficulty.
+#[cfg(not(target_arch = "wasm32"))]
+fn gravity_player(difficulty: Difficulty) -> Box<dyn AiPlayer<GravityBoard>> {
+    match difficulty {
+        Difficulty::Easy => Box::new(RandomBot),
+        Difficulty::Medium => Box::new(GreedyBot),
+        Difficulty::Hard | Difficulty::Adaptive => Box::new(GravityMinimaxPlayer),
+    }
+}
+
+/// Create the AI player for the ultimate variants at the given difficulty.
+#[cfg(not(target_arch = "wasm32"))]
+fn ultimate_player<G>(difficulty: Difficulty) -> Box<dyn AiPlayer<G>>
+where
+    G: Game + 'static,
+    MctsPlayer: AiPlayer<G>,
+{
+    match difficulty {
+        Difficulty::Easy => Box::new(RandomBot),
+        Difficulty::Medium => Box::new(GreedyBot),
+        Difficulty::Hard | Difficulty::Adaptive => Box::new(MctsPlayer::new(
+            SELFPLAY_MCTS_EXPANSIONS,
+            SELFPLAY_MCTS_PLAYOUTS,
+        )),
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 fn main() {
     console_error_panic_hook::set_once();
+    tictactoe::shared::crash_report::install_hook();
+
+    #[cfg(feature = "wasm-threads")]
+    wasm_bindgen_futures::spawn_local(async {
+        tictactoe::wasm_threads::init_thread_pool().await;
+        start_web();
+    });
 
+    #[cfg(not(feature = "wasm-threads"))]
+    start_web();
+}
+
+/// Start the app in the canvas of the web page.
+#[cfg(target_arch = "wasm32")]
+fn start_web() {
     let options = eframe::WebOptions::default();
     eframe::start_web(
         "main_canvas_id",
@@ -0,0 +1,344 @@
+//! This module lets people play tic-tac-toe in a chat with text commands, and renders boards with
+//! Unicode. The chat connection itself is handled by the `tictactoe-bot` binary.
+//!
+//! Cells are named like a chess board, with a letter for the column and a number for the row, so
+//! `b2` is the centre of a normal board and `e5` is the centre of an ultimate board.
+
+use crate::{
+    normal::board::{Board, MinimaxPlayer},
+    shared::{ai::AiPlayer, board::GameResult},
+    ultimate::{
+        board::{GlobalBoard, MctsPlayer},
+        GlobalCoord,
+    },
+    CellShape,
+};
+use std::collections::HashMap;
+
+/// The prefix of every command.
+const PREFIX: &str = "!ttt";
+
+/// A game being played in a chat.
+#[derive(Clone, Debug)]
+enum BotGame {
+    Normal(Board),
+    Ultimate(GlobalBoard),
+}
+
+impl BotGame {
+    /// Check if the game has already been won or drawn.
+    fn is_over(&self) -> bool {
+        let result = match self {
+            Self::Normal(board) => board.get_winner(),
+            Self::Ultimate(board) => board.get_winner(),
+        };
+        result != GameResult::Ongoing
+    }
+}
+
+/// A bot playing games in many chats at once, with one game per chat.
+#[derive(Clone, Debug)]
+pub struct Bot {
+    /// The games, by chat.
+    games: HashMap<String, BotGame>,
+
+    /// The maximum number of expansions for the MCTS AI in ultimate games.
+    pub max_mcts_expansions: u16,
+
+    /// The number of playouts in each expansion for the MCTS AI in ultimate games.
+    pub mcts_playouts: u8,
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Self {
+            games: HashMap::new(),
+            max_mcts_expansions: 3000,
+            mcts_playouts: 3,
+        }
+    }
+}
+
+impl Bot {
+    /// Handle a message sent in the given chat, and return the lines to reply with, if the
+    /// message was a command.
+    pub fn handle_message(&mut self, chat: &str, message: &str) -> Option<Vec<String>> {
+        let mut words = message.split_whitespace();
+        if words.next() != Some(PREFIX) {
+            return None;
+        }
+
+        Some(match (words.next(), words.next()) {
+            (Some("new"), kind) => {
+                let game = match kind {
+                    None | Some("normal") => BotGame::Normal(Board::default()),
+                    Some("ultimate") => BotGame::Ultimate(GlobalBoard::default()),
+                    Some(kind) => return Some(vec![format!("Unknown game type `{kind}`")]),
+                };
+                let lines = render_game(&game);
+                self.games.insert(chat.to_string(), game);
+                lines
+            }
+            (Some("play"), Some(cell)) => match self.games.get_mut(chat) {
+                Some(game) => match play_cell(game, cell) {
+                    Ok(()) => render_game(game),
+                    Err(e) => vec![e],
+                },
+                None => no_game(),
+            },
+            (Some("ai"), None) => {
+                let (max_mcts_expansions, mcts_playouts) =
+                    (self.max_mcts_expansions, self.mcts_playouts);
+                match self.games.get_mut(chat) {
+                    Some(game) => {
+                        if play_ai(game, max_mcts_expansions, mcts_playouts) {
+                            render_game(game)
+                        } else {
+                            vec![GAME_OVER.to_string()]
+                        }
+                    }
+                    None => no_game(),
+                }
+            }
+            (Some("show"), None) => match self.games.get(chat) {
+                Some(game) => render_game(game),
+                None => no_game(),
+            },
+            _ => vec![
+                format!("{PREFIX} new [normal|ultimate] - start a new game"),
+                format!("{PREFIX} play <cell> - play in a cell, like b2"),
+                format!("{PREFIX} ai - let the AI play the next move"),
+                format!("{PREFIX} show - show the board"),
+            ],
+        })
+    }
+}
+
+/// The reply when a move is asked for after the game is over.
+const GAME_OVER: &str = "The game is over";
+
+/// The reply when there's no game in the chat.
+fn no_game() -> Vec<String> {
+    vec![format!("No game here yet. Start one with `{PREFIX} new`")]
+}
+
+/// Parse a cell name like `b2` into a column and row, checking that both are less than `size`.
+fn parse_cell(cell: &str, size: usize) -> Option<(usize, usize)> {
+    let mut chars = cell.chars();
+    let column = chars.next()?.to_ascii_lowercase();
+    let row: usize = chars.as_str().parse().ok()?;
+
+    let column = (column as usize).checked_sub('a' as usize)?;
+    let row = row.checked_sub(1)?;
+    (column < size && row < size).then_some((column, row))
+}
+
+/// Convert a column and row on the 9x9 grid of an ultimate board to a [`GlobalCoord`].
+fn grid_to_global_coord((column, row): (usize, usize)) -> GlobalCoord {
+    (column / 3, row / 3, (column % 3, row % 3))
+}
+
+/// Play the shape to move in the named cell, unless the game is over.
+fn play_cell(game: &mut BotGame, cell: &str) -> Result<(), String> {
+    if game.is_over() {
+        return Err(GAME_OVER.to_string());
+    }
+
+    let bad_cell = || format!("`{cell}` isn't a cell");
+    match game {
+        BotGame::Normal(board) => {
+            let coord = parse_cell(cell, 3).ok_or_else(bad_cell)?;
+            let shape = board.to_move();
+            board.make_move(coord, shape).map_err(|e| e.to_string())
+        }
+        BotGame::Ultimate(board) => {
+            let coord = grid_to_global_coord(parse_cell(cell, 9).ok_or_else(bad_cell)?);
+            let shape = board.to_move();
+            board.make_move(coord, shape).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Let the AI play the shape to move, and return whether it played, which it doesn't if the game
+/// is over.
+fn play_ai(game: &mut BotGame, max_mcts_expansions: u16, mcts_playouts: u8) -> bool {
+    if game.is_over() {
+        return false;
+    }
+
+    match game {
+        BotGame::Normal(board) => match MinimaxPlayer::default().choose_move(board) {
+            Some(coord) => {
+                let shape = board.to_move();
+                board.make_move(coord, shape).is_ok()
+            }
+            None => false,
+        },
+        BotGame::Ultimate(board) => {
+            match MctsPlayer::new(max_mcts_expansions, mcts_playouts).choose_move(board) {
+                Some(coord) => {
+                    let shape = board.to_move();
+                    board.make_move(coord, shape).is_ok()
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+/// Render a cell as a single character.
+fn cell_char(cell: Option<CellShape>) -> char {
+    match cell {
+        Some(CellShape::X) => 'X',
+        Some(CellShape::O) => 'O',
+        None => '·',
+    }
+}
+
+/// Render the game, followed by a line saying whose turn it is or who won.
+fn render_game(game: &BotGame) -> Vec<String> {
+    let (mut lines, result, to_move) = match game {
+        BotGame::Normal(board) => (render_normal(board), board.get_winner(), board.to_move()),
+        BotGame::Ultimate(board) => {
+            let mut lines = render_ultimate(board);
+            if let Some((x, y)) = board.next_local_board() {
+                lines.push(format!(
+                    "Play in columns {}-{}, rows {}-{}",
+                    (b'a' + 3 * x as u8) as char,
+                    (b'a' + 3 * x as u8 + 2) as char,
+                    3 * y + 1,
+                    3 * y + 3
+                ));
+            }
+            (lines, board.get_winner(), board.to_move())
+        }
+    };
+
+    lines.push(match result {
+        GameResult::Ongoing => format!("{to_move:?} to move"),
+        GameResult::Draw => "It's a draw".to_string(),
+        GameResult::Won { shape, .. } => format!("{shape:?} wins!"),
+    });
+    lines
+}
+
+/// Render a normal board with box-drawing characters.
+pub fn render_normal(board: &Board) -> Vec<String> {
+    let mut lines = vec!["  a b c".to_string()];
+    for y in 0..3 {
+        if y > 0 {
+            lines.push("  ─┼─┼─".to_string());
+        }
+
+        let cells: Vec<String> = (0..3)
+            .map(|x| cell_char(board.cells()[x][y]).to_string())
+            .collect();
+        lines.push(format!("{} {}", y + 1, cells.join("│")));
+    }
+    lines
+}
+
+/// Render an ultimate board as a 9x9 grid, with heavy box-drawing lines between local boards.
+pub fn render_ultimate(board: &GlobalBoard) -> Vec<String> {
+    let mut lines = vec!["  a b c   d e f   g h i".to_string()];
+    for row in 0..9 {
+        if row > 0 && row % 3 == 0 {
+            lines.push("  ━━━━━━╋━━━━━━━╋━━━━━━".to_string());
+        }
+
+        let mut line = format!("{} ", row + 1);
+        for column in 0..9 {
+            if column > 0 && column % 3 == 0 {
+                line.push_str("┃ ");
+            }
+
+            let (x, y, (lx, ly)) = grid_to_global_coord((column, row));
+            line.push(cell_char(board.local_boards()[x][y].cells()[lx][ly]));
+            line.push(' ');
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal::test_utils::make_board;
+
+    #[test]
+    fn parse_cell_test() {
+        assert_eq!(parse_cell("a1", 3), Some((0, 0)));
+        assert_eq!(parse_cell("B2", 3), Some((1, 1)));
+        assert_eq!(parse_cell("c3", 3), Some((2, 2)));
+        assert_eq!(parse_cell("i9", 9), Some((8, 8)));
+        assert_eq!(parse_cell("d1", 3), None);
+        assert_eq!(parse_cell("a0", 3), None);
+        assert_eq!(parse_cell("a", 3), None);
+        assert_eq!(parse_cell("", 3), None);
+
+        assert_eq!(grid_to_global_coord((4, 4)), (1, 1, (1, 1)));
+        assert_eq!(grid_to_global_coord((7, 2)), (2, 0, (1, 2)));
+    }
+
+    #[test]
+    fn render_normal_test() {
+        let board = make_board!(X _ O; _ X _; _ _ O);
+        assert_eq!(
+            render_normal(&board),
+            vec![
+                "  a b c",
+                "1 X│·│O",
+                "  ─┼─┼─",
+                "2 ·│X│·",
+                "  ─┼─┼─",
+                "3 ·│·│O"
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_message_test() {
+        let mut bot = Bot::default();
+
+        assert_eq!(bot.handle_message("#chat", "hello"), None);
+        assert_eq!(bot.handle_message("#chat", "!ttt show"), Some(no_game()));
+
+        let lines = bot.handle_message("#chat", "!ttt new").unwrap();
+        assert_eq!(lines.last().unwrap(), "X to move");
+
+        let lines = bot.handle_message("#chat", "!ttt play b2").unwrap();
+        assert_eq!(lines[3], "2 ·│X│·");
+        assert_eq!(lines.last().unwrap(), "O to move");
+
+        let lines = bot.handle_message("#chat", "!ttt play b2").unwrap();
+        assert_eq!(lines, vec!["cell already full"]);
+
+        let lines = bot.handle_message("#chat", "!ttt ai").unwrap();
+        assert_eq!(lines.last().unwrap(), "X to move");
+
+        let lines = bot.handle_message("#other", "!ttt new ultimate").unwrap();
+        assert_eq!(lines.len(), 13);
+
+        let lines = bot.handle_message("#other", "!ttt play e9").unwrap();
+        assert_eq!(lines[11], "9 · · · ┃ · X · ┃ · · ·");
+        assert_eq!(lines[12], "Play in columns d-f, rows 7-9");
+
+        // Nobody can play once the game is over
+        bot.handle_message("#won", "!ttt new").unwrap();
+        for cell in ["a1", "a2", "b1", "b2"] {
+            bot.handle_message("#won", &format!("!ttt play {cell}"))
+                .unwrap();
+        }
+        let lines = bot.handle_message("#won", "!ttt play c1").unwrap();
+        assert_eq!(lines.last().unwrap(), "X wins!");
+
+        let lines = bot.handle_message("#won", "!ttt play b3").unwrap();
+        assert_eq!(lines, vec![GAME_OVER]);
+        let lines = bot.handle_message("#won", "!ttt ai").unwrap();
+        assert_eq!(lines, vec![GAME_OVER]);
+
+        let lines = bot.handle_message("#won", "!ttt show").unwrap();
+        assert_eq!(lines[5], "3 ·│·│·");
+    }
+}
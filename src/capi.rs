@@ -0,0 +1,292 @@
+//! This module exports a small C API so that the engine can be embedded in other programs. See
+//! `include/tictactoe.h` for the matching header.
+//!
+//! Games are created with `ttt_*_new` and must be freed with the matching `ttt_*_free`. Every
+//! other function takes a pointer returned from `ttt_*_new` which has not yet been freed.
+
+use crate::{
+    normal::board::{self as normal, Board, MinimaxPlayer},
+    shared::{ai::AiPlayer, board::GameResult},
+    ultimate::board::{self as ultimate, GlobalBoard, MctsPlayer},
+    CellShape,
+};
+
+/// The state of a game, as returned by the `ttt_*_winner` functions.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TttResult {
+    /// The game is still being played.
+    Ongoing = 0,
+
+    /// X has won.
+    XWon = 1,
+
+    /// O has won.
+    OWon = 2,
+
+    /// The game ended in a draw.
+    Draw = 3,
+}
+
+impl From<GameResult> for TttResult {
+    fn from(result: GameResult) -> Self {
+        match result {
+            GameResult::Ongoing => Self::Ongoing,
+            GameResult::Won {
+                shape: CellShape::X,
+                ..
+            } => Self::XWon,
+            GameResult::Won {
+                shape: CellShape::O,
+                ..
+            } => Self::OWon,
+            GameResult::Draw => Self::Draw,
+        }
+    }
+}
+
+/// The result of trying to make a move, as returned by the `ttt_*_make_move` functions.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TttMoveResult {
+    /// The move was made.
+    Ok = 0,
+
+    /// The game has already finished.
+    GameOver = 1,
+
+    /// The move was in a local board which is not the next local board. Only used for ultimate
+    /// games.
+    WrongLocalBoard = 2,
+
+    /// The chosen cell already has a shape in it.
+    CellAlreadyFull = 3,
+
+    /// The given coordinate is out of bounds.
+    OutOfBounds = 4,
+
+    /// The move is illegal for any other reason. The C API always plays the shape to move from a
+    /// free opening, so this should never happen, but it's reported rather than panicking across
+    /// the FFI boundary.
+    IllegalMove = 5,
+}
+
+/// A coordinate in an ultimate game, with the local board first and then the cell within it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TttGlobalCoord {
+    pub x: u8,
+    pub y: u8,
+    pub lx: u8,
+    pub ly: u8,
+}
+
+/// Create a new normal game where X moves first.
+#[no_mangle]
+pub extern "C" fn ttt_normal_new() -> *mut Board {
+    Box::into_raw(Box::default())
+}
+
+/// Free a normal game.
+///
+/// # Safety
+///
+/// `game` must have been returned by [`ttt_normal_new`] and not already freed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_normal_free(game: *mut Board) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Play the shape to move in the given cell.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_normal_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ttt_normal_make_move(game: *mut Board, x: u8, y: u8) -> TttMoveResult {
+    let board = &mut *game;
+    if board.get_winner() != GameResult::Ongoing {
+        return TttMoveResult::GameOver;
+    }
+
+    let shape = board.to_move();
+    match board.make_move((x as usize, y as usize), shape) {
+        Ok(()) => TttMoveResult::Ok,
+        Err(normal::MoveError::WrongTurn) => TttMoveResult::IllegalMove,
+        Err(normal::MoveError::CellAlreadyFull) => TttMoveResult::CellAlreadyFull,
+        Err(normal::MoveError::OutOfBounds) => TttMoveResult::OutOfBounds,
+    }
+}
+
+/// Get the state of the game.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_normal_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ttt_normal_winner(game: *const Board) -> TttResult {
+    (*game).get_winner().into()
+}
+
+/// Write the best move for the shape to move into `x` and `y`, and return whether there was a
+/// move to make.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_normal_new`], and `x` and `y` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_normal_ai_move(game: *const Board, x: *mut u8, y: *mut u8) -> bool {
+    match MinimaxPlayer::default().choose_move(&*game) {
+        Some((mx, my)) => {
+            *x = mx as u8;
+            *y = my as u8;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Create a new ultimate game where X moves first.
+#[no_mangle]
+pub extern "C" fn ttt_ultimate_new() -> *mut GlobalBoard {
+    Box::into_raw(Box::default())
+}
+
+/// Free an ultimate game.
+///
+/// # Safety
+///
+/// `game` must have been returned by [`ttt_ultimate_new`] and not already freed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_ultimate_free(game: *mut GlobalBoard) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Play the shape to move at the given coordinate.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_ultimate_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ttt_ultimate_make_move(
+    game: *mut GlobalBoard,
+    coord: TttGlobalCoord,
+) -> TttMoveResult {
+    let board = &mut *game;
+    if board.get_winner() != GameResult::Ongoing {
+        return TttMoveResult::GameOver;
+    }
+
+    let TttGlobalCoord { x, y, lx, ly } = coord;
+    let shape = board.to_move();
+    match board.make_move((x as usize, y as usize, (lx as usize, ly as usize)), shape) {
+        Ok(()) => TttMoveResult::Ok,
+        Err(ultimate::MoveError::WrongLocalBoard | ultimate::MoveError::LocalBoardClosed) => {
+            TttMoveResult::WrongLocalBoard
+        }
+        Err(ultimate::MoveError::CellAlreadyFull) => TttMoveResult::CellAlreadyFull,
+        Err(ultimate::MoveError::OutOfBounds) => TttMoveResult::OutOfBounds,
+        Err(
+            ultimate::MoveError::WrongTurn
+            | ultimate::MoveError::OpeningNotChosen
+            | ultimate::MoveError::NoOpeningChoice,
+        ) => TttMoveResult::IllegalMove,
+    }
+}
+
+/// Get the state of the game.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_ultimate_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ttt_ultimate_winner(game: *const GlobalBoard) -> TttResult {
+    (*game).get_winner().into()
+}
+
+/// Write the best move for the shape to move according to MCTS into `coord`, and return whether
+/// there was a move to make.
+///
+/// At least one playout is always done after each expansion, even if `playouts` is 0.
+///
+/// # Safety
+///
+/// `game` must be a live pointer returned by [`ttt_ultimate_new`], and `coord` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_ultimate_ai_move(
+    game: *const GlobalBoard,
+    max_mcts_expansions: u16,
+    playouts: u8,
+    coord: *mut TttGlobalCoord,
+) -> bool {
+    match MctsPlayer::new(max_mcts_expansions, playouts.max(1)).choose_move(&*game) {
+        Some((x, y, (lx, ly))) => {
+            *coord = TttGlobalCoord {
+                x: x as u8,
+                y: y as u8,
+                lx: lx as u8,
+                ly: ly as u8,
+            };
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_game_test() {
+        unsafe {
+            let game = ttt_normal_new();
+            assert_eq!(ttt_normal_make_move(game, 1, 1), TttMoveResult::Ok);
+            assert_eq!(
+                ttt_normal_make_move(game, 1, 1),
+                TttMoveResult::CellAlreadyFull
+            );
+            assert_eq!(ttt_normal_make_move(game, 3, 0), TttMoveResult::OutOfBounds);
+            assert_eq!(ttt_normal_winner(game), TttResult::Ongoing);
+
+            let (mut x, mut y) = (0, 0);
+            assert!(ttt_normal_ai_move(game, &mut x, &mut y));
+            assert_eq!(ttt_normal_make_move(game, x, y), TttMoveResult::Ok);
+
+            ttt_normal_free(game);
+        }
+    }
+
+    #[test]
+    fn ultimate_game_test() {
+        unsafe {
+            let game = ttt_ultimate_new();
+            let coord = TttGlobalCoord {
+                x: 1,
+                y: 1,
+                lx: 0,
+                ly: 2,
+            };
+            assert_eq!(ttt_ultimate_make_move(game, coord), TttMoveResult::Ok);
+            assert_eq!(
+                ttt_ultimate_make_move(game, coord),
+                TttMoveResult::WrongLocalBoard
+            );
+
+            // No playouts are treated as one, so the search still works
+            let mut ai_coord = coord;
+            assert!(ttt_ultimate_ai_move(game, 100, 0, &mut ai_coord));
+            assert_eq!((ai_coord.x, ai_coord.y), (0, 2));
+            assert_eq!(ttt_ultimate_make_move(game, ai_coord), TttMoveResult::Ok);
+            assert_eq!(ttt_ultimate_winner(game), TttResult::Ongoing);
+
+            ttt_ultimate_free(game);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! This module implements the `par_iter()` method on iterators to call `iter()` instead.
 //!
 //! This only exists to reduce config attributes when we call `par_iter()`
-//! but we're compiling for Wasm.
+//! but we're compiling for Wasm without the `wasm-threads` feature.
 
 use std::slice::Iter;
 
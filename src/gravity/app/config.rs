@@ -0,0 +1,123 @@
+//! This module handles app configuration.
+
+use super::GravityTTTApp;
+use crate::CellShape;
+use eframe::egui::{self, Context, Ui};
+use serde::{Deserialize, Serialize};
+
+/// The kinds of AI that the player can play against. See [`AiPlayer`](crate::shared::ai::AiPlayer).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GravityAiPlayer {
+    /// An easy player which plays randomly. See [`RandomBot`](crate::shared::ai::RandomBot).
+    Random,
+
+    /// A medium player which takes wins and blocks losses. See
+    /// [`GreedyBot`](crate::shared::ai::GreedyBot).
+    Greedy,
+
+    /// A perfect player using minimax. See [`MinimaxPlayer`](crate::gravity::board::MinimaxPlayer).
+    #[default]
+    Minimax,
+}
+
+/// A struct representing the app configuration, meant to be saved and loaded between sessions.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GravityConfig {
+    /// Which shape the player uses. X always plays first.
+    pub player_shape: CellShape,
+
+    /// Whether the player is playing against an AI.
+    pub playing_ai: bool,
+
+    /// Which AI the player is playing against.
+    pub ai_player: GravityAiPlayer,
+
+    /// Whether to ask for confirmation before discarding a game in progress, like when
+    /// restarting. See [`show_discard_dialog`](crate::shared::gui::show_discard_dialog).
+    pub confirm_discard: bool,
+}
+
+impl Default for GravityConfig {
+    fn default() -> Self {
+        Self {
+            player_shape: CellShape::X,
+            playing_ai: true,
+            ai_player: GravityAiPlayer::default(),
+            confirm_discard: true,
+        }
+    }
+}
+
+/// The settings in [`GravityConfig`] that define a game, which only take effect when a new game
+/// starts. All the other settings take effect immediately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GravityGameSettings {
+    /// Which shape the player uses.
+    pub player_shape: CellShape,
+
+    /// Whether the player is playing against an AI.
+    pub playing_ai: bool,
+}
+
+impl GravityConfig {
+    /// Get the settings that define a game, which only take effect when a new game starts.
+    pub fn game_settings(&self) -> GravityGameSettings {
+        GravityGameSettings {
+            player_shape: self.player_shape,
+            playing_ai: self.playing_ai,
+        }
+    }
+}
+
+impl GravityTTTApp {
+    /// Draw the settings window as a non-collapsible, non-resizable, closable `egui` window.
+    pub fn draw_settings_window(&mut self, ctx: &Context) {
+        let mut open = self.showing_settings_window;
+        egui::Window::new("Settings")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| self.show_settings(ui));
+        self.showing_settings_window = open;
+    }
+
+    /// Show the settings in the given [`Ui`].
+    pub fn show_settings(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.config.playing_ai, "Play against AI");
+        ui.add_enabled_ui(self.config.playing_ai, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Player shape");
+                ui.radio_value(&mut self.config.player_shape, CellShape::X, "X")
+                    .on_hover_text("X plays first");
+                ui.radio_value(&mut self.config.player_shape, CellShape::O, "O");
+            });
+            ui.horizontal(|ui| {
+                ui.label("AI difficulty");
+                ui.radio_value(&mut self.config.ai_player, GravityAiPlayer::Random, "Easy")
+                    .on_hover_text("Plays randomly");
+                ui.radio_value(
+                    &mut self.config.ai_player,
+                    GravityAiPlayer::Greedy,
+                    "Medium",
+                )
+                .on_hover_text("Takes wins and blocks losses, but otherwise plays randomly");
+                ui.radio_value(&mut self.config.ai_player, GravityAiPlayer::Minimax, "Hard")
+                    .on_hover_text("Plays perfectly with minimax");
+            });
+        });
+        ui.checkbox(
+            &mut self.config.confirm_discard,
+            "Ask before discarding a game in progress",
+        );
+        if self.config.game_settings() != self.game_settings {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Some changes will apply to the next game.");
+                if ui.button("Restart now").clicked() {
+                    self.request_restart();
+                }
+            });
+        }
+    }
+}
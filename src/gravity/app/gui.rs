@@ -0,0 +1,101 @@
+//! This module only exists to separate the long methods used for drawing the board and cells.
+
+use super::GravityTTTApp;
+use crate::shared::{
+    board::GameResult,
+    gui::{
+        board_line_shapes_in_rect, centered_square_in_rect, draw_cellshape_in_rect,
+        draw_game_over_banner_in_rect, draw_winning_line_in_rect, request_repaint,
+    },
+};
+use eframe::{
+    egui::{self, Context, Painter, Rect, Sense, Ui},
+    epaint::{Color32, Pos2, Vec2},
+};
+
+impl GravityTTTApp {
+    /// Draw the board in the given rect.
+    ///
+    /// Clicking anywhere in a column drops the player's piece into it, and hovering over a column
+    /// shows where the piece would land.
+    pub fn draw_board(&mut self, ctx: &Context, ui: &mut Ui, rect: Rect) {
+        request_repaint(ctx, false, self.waiting_on_move());
+
+        let painter = Painter::new(
+            ctx.clone(),
+            egui::LayerId::new(egui::Order::Background, egui::Id::new("board_painter")),
+            rect,
+        );
+        painter.extend(board_line_shapes_in_rect(&rect, Color32::GRAY));
+
+        let cell_length = rect.size().x / 3.0;
+        let cell_rect = |(x, y): (usize, usize)| {
+            Rect::from_min_size(
+                Pos2::new(
+                    rect.min.x + (x as f32 * cell_length),
+                    rect.min.y + (y as f32 * cell_length),
+                ),
+                Vec2::splat(cell_length),
+            )
+        };
+
+        for x in 0..3 {
+            for y in 0..3 {
+                draw_cellshape_in_rect(
+                    &painter,
+                    &centered_square_in_rect(cell_rect((x, y)), 0.8),
+                    self.board.cells[x][y],
+                    false,
+                );
+            }
+        }
+
+        let playable = self.board.get_winner() == GameResult::Ongoing
+            && !self.waiting_on_move()
+            && self.ai_shape() != Some(self.board.to_move());
+
+        for column in 0..3 {
+            let column_rect = Rect::from_min_size(
+                Pos2::new(rect.min.x + (column as f32 * cell_length), rect.min.y),
+                Vec2::new(cell_length, rect.height()),
+            );
+            let response = ui.allocate_rect(
+                column_rect,
+                if playable {
+                    Sense::click()
+                } else {
+                    Sense::hover()
+                },
+            );
+
+            // Show where the piece would land
+            if playable && response.hovered() {
+                if let Some(coord) = self.board.landing_cell(column) {
+                    draw_cellshape_in_rect(
+                        &painter,
+                        &centered_square_in_rect(cell_rect(coord), 0.8),
+                        Some(self.board.to_move()),
+                        true,
+                    );
+                }
+            }
+
+            if response.clicked() {
+                // A full column just ignores the click
+                let _ = self.player_drop_piece(column);
+            }
+        }
+
+        let dark_mode = ui.ctx().style().visuals.dark_mode;
+        match self.board.get_winner() {
+            GameResult::Won {
+                line: [start_coord, _, end_coord],
+                ..
+            } => draw_winning_line_in_rect(&rect, &painter, dark_mode, start_coord, end_coord),
+            GameResult::Draw => {
+                draw_game_over_banner_in_rect(&rect, &painter, dark_mode, "It's a draw!")
+            }
+            GameResult::Ongoing => (),
+        }
+    }
+}
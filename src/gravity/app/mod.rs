@@ -0,0 +1,410 @@
+//! This module handles the `egui` interface to the game.
+
+pub mod config;
+mod gui;
+
+use self::config::{GravityAiPlayer, GravityConfig, GravityGameSettings};
+use super::{
+    board::{Board, MinimaxPlayer, MoveError},
+    Column,
+};
+use crate::{
+    app::{Difficulty, LaunchOptions, TTTVariantApp},
+    shared::{
+        ai::{AiPlayer, GreedyBot, RandomBot},
+        ai_task::{AiTask, MoveChannel},
+        board::GameResult,
+        gui::{centered_square_in_rect, show_discard_dialog},
+        profiles::{Profiles, VariantSettings},
+        record::GameRecord,
+        status::{short_status_text, status_text},
+    },
+    CellShape,
+};
+use eframe::{
+    egui::{self, Context},
+    epaint::Color32,
+};
+use web_time::{Duration, Instant};
+
+/// The minimum time that the AI will take to make a move, so that it doesn't appear instant.
+const AI_MOVE_DELAY: Duration = Duration::from_millis(200);
+
+/// The struct to hold the state of the app.
+pub struct GravityTTTApp {
+    /// The configuration of the app.
+    config: GravityConfig,
+
+    /// The settings that the current game was started with. Changes to these settings in
+    /// [`config`](Self::config) only take effect when a new game starts.
+    game_settings: GravityGameSettings,
+
+    /// The named configs that the player can switch between, which are saved along with the
+    /// config.
+    profiles: Profiles<GravityConfig>,
+
+    /// Whether the settings window is currently being shown.
+    showing_settings_window: bool,
+
+    /// The actual board itself.
+    board: Board,
+
+    /// The handle to the AI move currently being computed, if there is one. See
+    /// [`waiting_on_move`](Self::waiting_on_move).
+    ai_task: Option<AiTask>,
+
+    /// The generation of the current game, which is incremented every time the game is restarted.
+    ///
+    /// Every AI move is tagged with the generation of the game it was computed for, so that moves
+    /// computed for a previous game can be discarded.
+    generation: u32,
+
+    /// The channel that we get the AI moves back from, tagged with the
+    /// [`generation`](Self::generation) of the game.
+    ai_moves: MoveChannel<Column>,
+
+    /// The record of the current game, with the column of each move.
+    record: GameRecord<Column>,
+
+    /// When the current turn started, so that the [`record`](Self::record) can time each move.
+    turn_started_at: Instant,
+
+    /// Whether we're showing the dialog to confirm restarting a game in progress. See
+    /// [`request_restart`](Self::request_restart).
+    confirming_restart: bool,
+}
+
+impl Default for GravityTTTApp {
+    fn default() -> Self {
+        Self::new_with_config(GravityConfig::default())
+    }
+}
+
+impl GravityTTTApp {
+    /// Create a new app with the given config.
+    ///
+    /// See [`start_game`](Self::start_game).
+    fn new_with_config(config: GravityConfig) -> Self {
+        let mut app = Self {
+            config,
+            game_settings: config.game_settings(),
+            profiles: Profiles::new(config),
+            showing_settings_window: false,
+            board: Board::default(),
+            ai_task: None,
+            generation: 0,
+            ai_moves: MoveChannel::default(),
+            record: GameRecord::new(CellShape::X),
+            turn_started_at: Instant::now(),
+            confirming_restart: false,
+        };
+
+        app.start_game();
+        app
+    }
+
+    /// Return the shape of the AI, if we're playing against the AI.
+    fn ai_shape(&self) -> Option<CellShape> {
+        self.game_settings
+            .playing_ai
+            .then(|| self.game_settings.player_shape.other())
+    }
+
+    /// Reset the board to start a new game with the current config, including its
+    /// [`game_settings`](Self::game_settings).
+    ///
+    /// X always plays first, so if the AI is X, then we also start an AI move in the background.
+    fn start_game(&mut self) {
+        self.game_settings = self.config.game_settings();
+        self.board = Board::new(self.game_settings.player_shape.other());
+        self.record = GameRecord::new(CellShape::X);
+        self.turn_started_at = Instant::now();
+        self.ai_task = None;
+
+        if self.ai_shape() == Some(CellShape::X) {
+            self.start_ai_move();
+        }
+    }
+
+    /// Start a new game with the current config.
+    ///
+    /// Any AI move currently being computed is cancelled, and the
+    /// [`generation`](Self::generation) is incremented so that any stale AI moves will be ignored.
+    fn restart_game(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        self.showing_settings_window = false;
+        self.start_game();
+    }
+
+    /// Restart the game, unless there's a game in progress and
+    /// [`GravityConfig::confirm_discard`] is set, in which case we ask for confirmation first.
+    fn request_restart(&mut self) {
+        if self.config.confirm_discard && self.game_in_progress() {
+            self.confirming_restart = true;
+        } else {
+            self.restart_game();
+        }
+    }
+
+    /// Show the dialog to confirm restarting a game in progress if we're waiting for
+    /// confirmation, and restart the game if it's confirmed.
+    fn draw_restart_dialog(&mut self, ctx: &Context) {
+        if !self.confirming_restart {
+            return;
+        }
+
+        if let Some(discard) = show_discard_dialog(ctx, &mut self.config.confirm_discard) {
+            self.confirming_restart = false;
+            if discard {
+                self.restart_game();
+            }
+        }
+    }
+
+    /// Drop a piece of the shape to move into the given column and record the move.
+    fn drop_piece(&mut self, column: Column) -> Result<(), MoveError> {
+        self.board.drop_piece(column, self.board.to_move())?;
+        self.record
+            .push_move(column, self.turn_started_at.elapsed());
+        self.turn_started_at = Instant::now();
+        Ok(())
+    }
+
+    /// Drop the player's piece into the given column, and then start the AI move if we're playing
+    /// against the AI and the game is still going.
+    fn player_drop_piece(&mut self, column: Column) -> Result<(), MoveError> {
+        if self.waiting_on_move() || self.ai_shape() == Some(self.board.to_move()) {
+            return Err(MoveError::WrongTurn);
+        }
+
+        self.drop_piece(column)?;
+        if self.ai_shape().is_some() && self.board.get_winner() == GameResult::Ongoing {
+            self.start_ai_move();
+        }
+        Ok(())
+    }
+
+    /// Start computing an AI move in the background with an [`AiTask`], which is sent down
+    /// [`ai_moves`](Self::ai_moves) after [`AI_MOVE_DELAY`].
+    fn start_ai_move(&mut self) {
+        let board = self.board.clone();
+        let generation = self.generation;
+        let ai_player = self.config.ai_player;
+
+        self.ai_task = Some(AiTask::spawn(
+            AI_MOVE_DELAY,
+            self.ai_moves.sender(),
+            move |_| {
+                let mv = match ai_player {
+                    GravityAiPlayer::Random => RandomBot.choose_move(&board),
+                    GravityAiPlayer::Greedy => GreedyBot.choose_move(&board),
+                    GravityAiPlayer::Minimax => MinimaxPlayer.choose_move(&board),
+                };
+                (generation, mv)
+            },
+        ));
+    }
+
+    /// Check if we're currently waiting for the AI to make a move, which is exactly while there is
+    /// an [`ai_task`](Self::ai_task).
+    fn waiting_on_move(&self) -> bool {
+        self.ai_task.is_some()
+    }
+
+    /// Return the AI move if it has been computed for the current game, discarding any moves
+    /// computed for previous games. This is `Some(None)` if the AI couldn't find a move.
+    fn receive_ai_move(&mut self) -> Option<Option<Column>> {
+        self.ai_moves.receive(self.generation)
+    }
+
+    /// Play the AI move if it has arrived.
+    fn handle_ai_move(&mut self) {
+        if !self.waiting_on_move() {
+            return;
+        }
+
+        if let Some(column) = self.receive_ai_move() {
+            self.ai_task = None;
+            if let Some(column) = column {
+                // The AI only ever chooses legal moves
+                let _ = self.drop_piece(column);
+            }
+        }
+    }
+
+    /// Describe the state of the game, like `"O to play - you are X, the AI is O"`.
+    fn status_text(&self) -> String {
+        status_text(
+            self.board.get_winner(),
+            self.board.to_move(),
+            self.ai_shape(),
+            None,
+        )
+    }
+}
+
+impl TTTVariantApp for GravityTTTApp {
+    /// Create a new app, loading the config from the storage if possible.
+    fn new_app(storage: Option<&dyn eframe::Storage>) -> Self
+    where
+        Self: Sized,
+    {
+        let VariantSettings { config, profiles } = VariantSettings::load(storage, "gravity");
+
+        Self {
+            profiles,
+            ..Self::new_with_config(config)
+        }
+    }
+
+    /// Play the AI move if it has arrived.
+    fn tick(&mut self) {
+        self.handle_ai_move();
+    }
+
+    /// Show the app itself.
+    fn show_ui(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.tick();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                use eframe::epaint::{FontFamily, FontId};
+                use egui::TextStyle::Button;
+
+                let mut style = (*ctx.style()).clone();
+                style
+                    .text_styles
+                    .insert(Button, FontId::new(30., FontFamily::Proportional));
+                ui.set_style(style);
+
+                if ui
+                    .add(egui::Button::new("\u{27F3}").fill(Color32::TRANSPARENT))
+                    .clicked()
+                {
+                    self.request_restart();
+                }
+
+                if ui
+                    .add(egui::Button::new("\u{2699}").fill(Color32::TRANSPARENT))
+                    .clicked()
+                {
+                    self.showing_settings_window = !self.showing_settings_window;
+                }
+
+                if self.waiting_on_move() {
+                    ui.label("Thinking\u{2026}");
+                }
+            });
+
+            ui.label(self.status_text());
+            if self.board.get_winner() != GameResult::Ongoing {
+                self.record.show_timing_stats(ui);
+            }
+
+            let board_rect = centered_square_in_rect(ui.available_rect_before_wrap(), 0.95);
+            self.draw_board(ctx, ui, board_rect);
+        });
+
+        if self.showing_settings_window {
+            self.draw_settings_window(ctx);
+        }
+
+        self.draw_restart_dialog(ctx);
+    }
+
+    fn game_in_progress(&self) -> bool {
+        !self.record.moves.is_empty() && self.board.get_winner() == GameResult::Ongoing
+    }
+
+    fn confirm_discard_mut(&mut self) -> &mut bool {
+        &mut self.config.confirm_discard
+    }
+
+    fn short_status(&self) -> String {
+        short_status_text(
+            self.board.get_winner(),
+            self.board.to_move(),
+            self.ai_shape(),
+        )
+    }
+
+    fn apply_launch_options(&mut self, options: &LaunchOptions) {
+        if let Some(playing_ai) = options.playing_ai {
+            self.config.playing_ai = playing_ai;
+        }
+        if let Some(difficulty) = options.difficulty {
+            self.config.ai_player = match difficulty {
+                Difficulty::Easy => GravityAiPlayer::Random,
+                Difficulty::Medium => GravityAiPlayer::Greedy,
+                Difficulty::Hard | Difficulty::Adaptive => GravityAiPlayer::Minimax,
+            };
+        }
+
+        self.restart_game();
+    }
+
+    fn save_config(&mut self, storage: &mut dyn eframe::Storage) {
+        let mut settings = VariantSettings {
+            config: self.config,
+            profiles: self.profiles.clone(),
+        };
+        settings.save(storage, "gravity");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_drop_piece_test() {
+        let mut app = GravityTTTApp::new_with_config(GravityConfig {
+            playing_ai: false,
+            ..GravityConfig::default()
+        });
+        assert!(!app.game_in_progress());
+
+        app.player_drop_piece(1).unwrap();
+        app.player_drop_piece(1).unwrap();
+        assert_eq!(
+            app.board.cells[1],
+            [None, Some(CellShape::O), Some(CellShape::X)]
+        );
+        assert_eq!(app.record.moves, vec![1, 1]);
+        assert!(app.game_in_progress());
+
+        app.player_drop_piece(1).unwrap();
+        assert_eq!(app.player_drop_piece(1), Err(MoveError::ColumnFull));
+        assert_eq!(app.record.moves.len(), 3);
+
+        // The player can't move while the AI is thinking
+        let mut app = GravityTTTApp::default();
+        app.player_drop_piece(0).unwrap();
+        assert!(app.waiting_on_move());
+        assert_eq!(app.player_drop_piece(2), Err(MoveError::WrongTurn));
+    }
+
+    #[test]
+    fn receive_ai_move_test() {
+        let mut app = GravityTTTApp::default();
+        assert_eq!(app.receive_ai_move(), None);
+
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some(2)))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), Some(Some(2)));
+
+        let old_generation = app.generation;
+        app.restart_game();
+        app.ai_moves
+            .sender()
+            .send((old_generation, Some(0)))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), None);
+    }
+}
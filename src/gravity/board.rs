@@ -0,0 +1,317 @@
+//! This module handles the board and the AI player.
+
+use super::Column;
+use crate::{
+    normal::Coord,
+    shared::{
+        self,
+        ai::{AiPlayer, Game},
+        board::{CellShape, GameResult},
+    },
+};
+use serde::Serialize;
+use thiserror::Error;
+
+/// An enum to represent possible errors arising from dropping a piece. See [`Board::drop_piece`].
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum MoveError {
+    /// A piece has been dropped with a shape which is not the [`to_move`](Board::to_move).
+    #[error("wrong shape to move")]
+    WrongTurn,
+
+    /// The chosen column has no empty cells left.
+    #[error("column already full")]
+    ColumnFull,
+
+    /// The given column is out of bounds.
+    #[error("column out of bounds")]
+    OutOfBounds,
+}
+
+/// A struct to represent a gravity tic-tac-toe board, where pieces are dropped into columns.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Board {
+    /// This 2D array represents all the cells, and is indexed as `cells[x][y]`, with the layout as so:
+    ///
+    /// ```text
+    /// (0, 0) | (1, 0) | (2, 0)
+    /// ------------------------
+    /// (0, 1) | (1, 1) | (2, 1)
+    /// ------------------------
+    /// (0, 2) | (1, 2) | (2, 2)
+    /// ```
+    ///
+    /// The bottom row is `y = 2`, so pieces fill each column from `y = 2` upwards.
+    pub cells: [[Option<CellShape>; 3]; 3],
+
+    /// This is the shape that the AI will play as.
+    pub ai_shape: CellShape,
+
+    /// The shape which must make the next move.
+    to_move: CellShape,
+
+    /// The winner of the board, which is updated by each move so that we don't have to check
+    /// every line in [`get_winner`](Self::get_winner).
+    winner: GameResult,
+}
+
+impl Default for Board {
+    /// Return a board with [`O`](CellShape::O) as the default AI shape.
+    fn default() -> Self {
+        Self::new(CellShape::O)
+    }
+}
+
+impl Board {
+    /// Create a new, empty board, where [`X`](CellShape::X) plays first.
+    pub fn new(ai_shape: CellShape) -> Self {
+        Self::new_with_first_shape(ai_shape, CellShape::X)
+    }
+
+    /// Create a new, empty board, where the given shape plays first.
+    pub fn new_with_first_shape(ai_shape: CellShape, first_shape: CellShape) -> Self {
+        Self {
+            cells: [[None; 3]; 3],
+            ai_shape,
+            to_move: first_shape,
+            winner: GameResult::Ongoing,
+        }
+    }
+
+    /// Return the shape which must make the next move.
+    pub fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    /// Return the cell that a piece dropped in the given column would land in, which is the
+    /// lowest empty cell, or [`None`] if the column is full or doesn't exist.
+    pub fn landing_cell(&self, column: Column) -> Option<Coord> {
+        let cells = self.cells.get(column)?;
+        (0..3)
+            .rev()
+            .find(|&y| cells[y].is_none())
+            .map(|y| (column, y))
+    }
+
+    /// Drop a piece of the given shape into the given column, and return the cell that it landed
+    /// in.
+    ///
+    /// This method will also update the [`to_move`](Self::to_move) and the cached winner.
+    pub fn drop_piece(&mut self, column: Column, shape: CellShape) -> Result<Coord, MoveError> {
+        if column > 2 {
+            return Err(MoveError::OutOfBounds);
+        }
+
+        if shape != self.to_move {
+            return Err(MoveError::WrongTurn);
+        }
+
+        let (x, y) = self.landing_cell(column).ok_or(MoveError::ColumnFull)?;
+        self.cells[x][y] = Some(shape);
+        self.to_move = shape.other();
+
+        if self.winner == GameResult::Ongoing {
+            if let Some((shape, line)) = shared::board::get_winning_line_through(self.cells, (x, y))
+            {
+                self.winner = GameResult::Won { shape, line };
+            } else if shared::board::is_board_full(self.cells) {
+                self.winner = GameResult::Draw;
+            }
+        }
+
+        Ok((x, y))
+    }
+
+    /// Skip the turn of the shape which is next to move, so that the other shape moves next.
+    pub fn skip_turn(&mut self) {
+        self.to_move = self.to_move.other();
+    }
+
+    /// Return the result of the game so far.
+    ///
+    /// The result is cached and updated by [`drop_piece`](Self::drop_piece), so this is cheap.
+    pub fn get_winner(&self) -> GameResult {
+        self.winner
+    }
+
+    /// Return an iterator over the columns which aren't full, which are the legal moves.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Column> + '_ {
+        (0..3).filter(|&column| self.landing_cell(column).is_some())
+    }
+
+    /// Return the number of empty cells on the board.
+    fn empty_cells(&self) -> usize {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|cell| cell.is_none())
+            .count()
+    }
+
+    /// Return the board after the shape to move drops a piece in the given legal column.
+    fn after_move(&self, column: Column) -> Self {
+        let mut board = self.clone();
+        board
+            .drop_piece(column, self.to_move)
+            .expect("A legal move should never cause a `MoveError`");
+        board
+    }
+
+    /// Score the position with minimax for the shape which is next to move.
+    ///
+    /// Positive numbers mean that the shape to move can force a win, negative numbers mean that
+    /// it will lose against perfect play, and 0 means a draw. Quicker wins and slower losses have
+    /// scores further from 0.
+    fn negamax(&self) -> i8 {
+        match self.winner {
+            GameResult::Won { shape, .. } => {
+                let score = 1 + self.empty_cells() as i8;
+                if shape == self.to_move {
+                    score
+                } else {
+                    -score
+                }
+            }
+            GameResult::Draw => 0,
+            GameResult::Ongoing => self
+                .legal_moves()
+                .map(|column| -self.after_move(column).negamax())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Evaluate the position for the AI, with the given shape to play next.
+    ///
+    /// Positive numbers are always good for the AI; negative numbers are always good for the player.
+    pub fn evaluate_position(&self, shape_to_play: CellShape) -> i8 {
+        let mut board = self.clone();
+        if board.to_move != shape_to_play {
+            board.skip_turn();
+        }
+
+        let score = board.negamax();
+        if shape_to_play == self.ai_shape {
+            score
+        } else {
+            -score
+        }
+    }
+
+    /// Return the best column for the shape which is next to move, which could be none if the
+    /// board is full or the game is over.
+    pub fn generate_ai_move(&self) -> Option<Column> {
+        if self.winner != GameResult::Ongoing {
+            return None;
+        }
+
+        self.legal_moves()
+            .max_by_key(|&column| -self.after_move(column).negamax())
+    }
+}
+
+impl Game for Board {
+    type Move = Column;
+
+    fn moves(&self) -> Vec<Column> {
+        self.legal_moves().collect()
+    }
+
+    fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    fn play(&mut self, mv: Column) -> bool {
+        self.drop_piece(mv, self.to_move).is_ok()
+    }
+
+    fn skip_turn(&mut self) {
+        self.skip_turn();
+    }
+
+    fn get_winner(&self) -> GameResult {
+        self.winner
+    }
+}
+
+/// An [`AiPlayer`] which uses minimax to play perfectly. See [`Board::generate_ai_move`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinimaxPlayer;
+
+impl AiPlayer<Board> for MinimaxPlayer {
+    fn choose_move(&mut self, game: &Board) -> Option<Column> {
+        game.generate_ai_move()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::ai::perft;
+
+    #[test]
+    fn drop_piece_test() {
+        let mut board = Board::default();
+        assert_eq!(board.drop_piece(1, CellShape::X), Ok((1, 2)));
+        assert_eq!(board.drop_piece(1, CellShape::O), Ok((1, 1)));
+        assert_eq!(board.drop_piece(1, CellShape::O), Err(MoveError::WrongTurn));
+        assert_eq!(
+            board.drop_piece(3, CellShape::X),
+            Err(MoveError::OutOfBounds)
+        );
+        assert_eq!(board.drop_piece(1, CellShape::X), Ok((1, 0)));
+        assert_eq!(
+            board.drop_piece(1, CellShape::O),
+            Err(MoveError::ColumnFull)
+        );
+
+        assert_eq!(board.landing_cell(1), None);
+        assert_eq!(board.landing_cell(0), Some((0, 2)));
+        assert_eq!(board.legal_moves().collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn winner_test() {
+        // X fills the bottom row while O stacks on top of it
+        let mut board = Board::default();
+        for column in [0, 0, 1, 1, 2] {
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+            assert!(board.play(column));
+        }
+
+        assert_eq!(
+            board.get_winner(),
+            GameResult::Won {
+                shape: CellShape::X,
+                line: [(0, 2), (1, 2), (2, 2)]
+            }
+        );
+    }
+
+    #[test]
+    fn perft_test() {
+        // There are 3 columns to choose from until one of them is full, which can first happen
+        // after 3 moves
+        let board = Board::default();
+        let counts: Vec<u64> = (0..=4).map(|depth| perft(&board, depth)).collect();
+        assert_eq!(counts, vec![1, 3, 9, 27, 24 * 3 + 3 * 2]);
+    }
+
+    #[test]
+    fn generate_ai_move_test() {
+        let mut board = Board::default();
+        for column in [0, 0, 1] {
+            board.play(column);
+        }
+
+        // O has to block X in the bottom row
+        assert_eq!(board.to_move(), CellShape::O);
+        assert_eq!(board.generate_ai_move(), Some(2));
+        assert_eq!(MinimaxPlayer.choose_move(&board), Some(2));
+
+        // X can win straight away
+        board.skip_turn();
+        assert_eq!(board.generate_ai_move(), Some(2));
+        assert!(board.evaluate_position(CellShape::X) < 0);
+    }
+}
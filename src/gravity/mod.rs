@@ -0,0 +1,10 @@
+//! This module models gravity tic-tac-toe, where pieces fall to the bottom of the column that they
+//! were dropped in, like in Connect Four, and provides a GUI interface.
+
+/// A column of the board, numbered from 0 on the left, which is where a piece is dropped.
+pub type Column = usize;
+
+pub mod app;
+pub mod board;
+
+pub use self::app::GravityTTTApp;
@@ -0,0 +1,113 @@
+//! This module exports the ultimate engine to JavaScript with `wasm-bindgen`, so that it can be
+//! used in other web frontends without the egui app.
+//!
+//! Build it with `wasm-bindgen` using the `js` feature. See the `js-build` recipe.
+
+use crate::{
+    shared::{ai::AiPlayer, board::GameResult},
+    ultimate::{
+        board::{GlobalBoard, MctsPlayer},
+        GlobalCoord,
+    },
+    CellShape,
+};
+use wasm_bindgen::prelude::*;
+
+/// Flatten a coordinate into `[x, y, lx, ly]`.
+fn flatten_coord((x, y, (lx, ly)): GlobalCoord) -> [u8; 4] {
+    [x as u8, y as u8, lx as u8, ly as u8]
+}
+
+/// A game of ultimate tic-tac-toe, where X moves first.
+///
+/// Coordinates are passed to and from JS as `[x, y, lx, ly]`, where `(x, y)` is the local board
+/// and `(lx, ly)` is the cell within it.
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct UltimateGame {
+    board: GlobalBoard,
+}
+
+#[wasm_bindgen]
+impl UltimateGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return all the legal moves, flattened into one array of `[x, y, lx, ly, x, y, ...]`.
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<u8> {
+        self.board.legal_moves().flat_map(flatten_coord).collect()
+    }
+
+    /// Play the shape to move at the given coordinate, throwing an error if the move is illegal
+    /// or the game is over.
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, x: u8, y: u8, lx: u8, ly: u8) -> Result<(), JsError> {
+        if self.is_over() {
+            return Err(JsError::new("the game is over"));
+        }
+
+        let shape = self.board.to_move();
+        self.board
+            .make_move((x as usize, y as usize, (lx as usize, ly as usize)), shape)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Return the best move for the shape to move according to MCTS with the given number of
+    /// expansions, or `undefined` if the game is over.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub fn best_move(&self, iters: u16) -> Option<Vec<u8>> {
+        if self.is_over() {
+            return None;
+        }
+
+        MctsPlayer::new(iters, 1)
+            .choose_move(&self.board)
+            .map(|coord| flatten_coord(coord).to_vec())
+    }
+
+    /// Return the shape to move, as `"X"` or `"O"`.
+    #[wasm_bindgen(js_name = toMove)]
+    pub fn to_move(&self) -> String {
+        self.board.to_move().to_string()
+    }
+
+    /// Return the local board that the next move must be played in as `[x, y]`, or `undefined`
+    /// if any is allowed.
+    #[wasm_bindgen(js_name = nextLocalBoard)]
+    pub fn next_local_board(&self) -> Option<Vec<u8>> {
+        self.board
+            .next_local_board()
+            .map(|(x, y)| vec![x as u8, y as u8])
+    }
+
+    /// Return the shape in the given cell, as `"X"`, `"O"`, or `undefined` if the cell is empty
+    /// or out of bounds.
+    pub fn cell(&self, x: u8, y: u8, lx: u8, ly: u8) -> Option<String> {
+        let cell = *self
+            .board
+            .local_boards()
+            .get(x as usize)?
+            .get(y as usize)?
+            .cells()
+            .get(lx as usize)?
+            .get(ly as usize)?;
+        cell.map(|shape: CellShape| shape.to_string())
+    }
+
+    /// Return the winning shape, as `"X"` or `"O"`, or `undefined` if nobody has won.
+    pub fn winner(&self) -> Option<String> {
+        match self.board.get_winner() {
+            GameResult::Won { shape, .. } => Some(shape.to_string()),
+            GameResult::Ongoing | GameResult::Draw => None,
+        }
+    }
+
+    /// Check if the game is over, either by a win or a draw.
+    #[wasm_bindgen(js_name = isOver)]
+    pub fn is_over(&self) -> bool {
+        self.board.get_winner() != GameResult::Ongoing
+    }
+}
@@ -2,17 +2,42 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+pub mod gravity;
 pub mod normal;
 pub mod ultimate;
 
 pub mod app;
+pub mod series;
+pub mod settings_file;
+pub mod setup_wizard;
 pub mod shared;
 
 pub use self::shared::board::CellShape;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm-threads")))]
 mod fake_par_iter;
 
+#[cfg(all(target_arch = "wasm32", feature = "wasm-threads"))]
+pub mod wasm_threads;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "bot"))]
+pub mod bot;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "double-ultimate")]
+pub mod nested;
+
+#[cfg(all(target_arch = "wasm32", feature = "js"))]
+pub mod js;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "server"))]
+pub mod server;
+
 #[cfg(all(test, not(feature = "bench")))]
 pub(crate) mod test_utils;
 
@@ -0,0 +1,397 @@
+//! This module handles the zoomable `egui` interface to double-ultimate tic-tac-toe.
+//!
+//! The board is far too small to play on all at once, so only one level is shown in detail. The
+//! player clicks on a board to zoom into it, and plays in a cell once they've zoomed all the way
+//! in. After every move, the view zooms to the board that the next move must be played in.
+
+use super::{board::DoubleUltimateBoard, NestedCoord};
+use crate::{
+    app::{LaunchOptions, TTTVariantApp},
+    shared::{
+        ai::{AiPlayer, Game},
+        ai_task::{AiTask, MoveChannel},
+        board::GameResult,
+        gui::{
+            board_line_shapes_in_rect, centered_square_in_rect, draw_cellshape_in_rect,
+            draw_game_over_banner_in_rect, draw_winning_line_in_rect, request_repaint,
+            show_discard_dialog,
+        },
+        status::{position_name, short_status_text},
+    },
+    ultimate::board::{MctsPlayer, MoveError},
+    CellShape,
+};
+use eframe::{
+    egui::{self, Context, Painter, Sense},
+    epaint::{Color32, Pos2, Rect, Vec2},
+    Storage,
+};
+use web_time::Duration;
+
+/// The minimum time that the AI will take to make a move, so that it doesn't appear instant.
+const AI_MOVE_DELAY: Duration = Duration::from_millis(200);
+
+/// The maximum number of MCTS expansions for the AI.
+const MAX_MCTS_EXPANSIONS: u16 = 1000;
+
+/// The number of MCTS playouts for the AI in each iteration.
+const MCTS_PLAYOUTS: u8 = 2;
+
+/// The struct to hold the state of the app.
+pub struct DoubleUltimateApp {
+    /// The actual board itself.
+    board: DoubleUltimateBoard,
+
+    /// The path to the board being shown, which is empty when the whole board is shown.
+    zoom: Vec<(usize, usize)>,
+
+    /// Whether we're playing against the AI.
+    playing_ai: bool,
+
+    /// Whether to ask for confirmation before discarding a game in progress.
+    confirm_discard: bool,
+
+    /// Whether we're showing the dialog to confirm restarting a game in progress.
+    confirming_restart: bool,
+
+    /// The number of moves played in the current game.
+    moves_played: usize,
+
+    /// The handle to the AI move currently being computed, if there is one.
+    ai_task: Option<AiTask>,
+
+    /// The generation of the current game, which is incremented every time the game is restarted,
+    /// so that AI moves computed for a previous game can be discarded.
+    generation: u32,
+
+    /// The channel that we get the AI moves back from, tagged with the
+    /// [`generation`](Self::generation) of the game.
+    ai_moves: MoveChannel<NestedCoord<3>>,
+}
+
+impl Default for DoubleUltimateApp {
+    fn default() -> Self {
+        Self {
+            board: DoubleUltimateBoard::default(),
+            zoom: vec![],
+            playing_ai: true,
+            confirm_discard: true,
+            confirming_restart: false,
+            moves_played: 0,
+            ai_task: None,
+            generation: 0,
+            ai_moves: MoveChannel::default(),
+        }
+    }
+}
+
+impl DoubleUltimateApp {
+    /// Return the shape that the AI plays as, if we're playing against the AI.
+    fn ai_shape(&self) -> Option<CellShape> {
+        self.playing_ai.then(|| self.board.ai_shape())
+    }
+
+    /// Restart the game, throwing away any AI move being computed.
+    fn restart_game(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+        self.board = DoubleUltimateBoard::default();
+        self.zoom.clear();
+        self.moves_played = 0;
+        self.confirming_restart = false;
+    }
+
+    /// Restart the game, asking for confirmation first if there's a game in progress.
+    fn request_restart(&mut self) {
+        if self.confirm_discard && self.game_in_progress() {
+            self.confirming_restart = true;
+        } else {
+            self.restart_game();
+        }
+    }
+
+    /// Play the given move as the shape to move, zoom to the board that the next move must be
+    /// played in, and start the AI move if it's the AI's turn.
+    fn play_move(&mut self, coord: NestedCoord<3>) -> Result<(), MoveError> {
+        self.board.make_move(coord, self.board.to_move())?;
+        self.moves_played += 1;
+        self.zoom = self.board.target().to_vec();
+
+        if self.board.get_winner() == GameResult::Ongoing
+            && self.ai_shape() == Some(self.board.to_move())
+        {
+            self.start_ai_move();
+        }
+        Ok(())
+    }
+
+    /// Start computing the AI move in the background.
+    fn start_ai_move(&mut self) {
+        let board = self.board.clone();
+        let generation = self.generation;
+
+        self.ai_task = Some(AiTask::spawn(
+            AI_MOVE_DELAY,
+            self.ai_moves.sender(),
+            move |_| {
+                let mv = MctsPlayer::new(MAX_MCTS_EXPANSIONS, MCTS_PLAYOUTS).choose_move(&board);
+                (generation, mv)
+            },
+        ));
+    }
+
+    /// Play the AI move if it has been computed for the current game.
+    fn receive_ai_move(&mut self) {
+        if let Some(mv) = self.ai_moves.receive(self.generation) {
+            self.ai_task = None;
+            if let Some(mv) = mv {
+                // The AI only ever chooses legal moves
+                let _ = self.play_move(mv);
+            }
+        }
+    }
+
+    /// Handle a click on the square at the given position in the board being shown. Clicking on a
+    /// board zooms into it, and clicking on a cell plays in it.
+    fn click_square(&mut self, coord: (usize, usize)) -> Result<(), MoveError> {
+        let mut path = self.zoom.clone();
+        path.push(coord);
+
+        match NestedCoord::<3>::try_from(path.as_slice()) {
+            Ok(mv) => {
+                if self.board.get_winner() != GameResult::Ongoing
+                    || self.ai_shape() == Some(self.board.to_move())
+                {
+                    return Err(MoveError::WrongTurn);
+                }
+                self.play_move(mv)
+            }
+            Err(_) => {
+                self.zoom = path;
+                Ok(())
+            }
+        }
+    }
+
+    /// Describe the board being shown, like `"Whole board > top-left"`.
+    fn zoom_description(&self) -> String {
+        let mut description = "Whole board".to_string();
+        for &coord in &self.zoom {
+            description.push_str(" > ");
+            description.push_str(position_name(coord));
+        }
+        description
+    }
+
+    /// Draw the square at the end of the given path in the given rect, which is a cell or a
+    /// board with all the boards inside it, and highlight it if the next move must be played in it.
+    fn draw_square(
+        &self,
+        painter: &Painter,
+        rect: Rect,
+        path: &mut Vec<(usize, usize)>,
+        dark: bool,
+    ) {
+        if path.len() == 3 {
+            draw_cellshape_in_rect(
+                painter,
+                &centered_square_in_rect(rect, 0.8),
+                self.board.shape_at(path),
+                false,
+            );
+            return;
+        }
+
+        let target = self.board.target();
+        let is_target = if path.len() == self.zoom.len() {
+            path.starts_with(target)
+        } else {
+            path.as_slice() == target
+        };
+        if is_target && self.board.get_winner() == GameResult::Ongoing {
+            painter.rect_filled(rect, 0., Color32::from_rgba_unmultiplied(255, 215, 0, 32));
+        }
+
+        let line_color = if path.len() == self.zoom.len() {
+            Color32::GRAY
+        } else if dark {
+            Color32::DARK_GRAY
+        } else {
+            Color32::LIGHT_GRAY
+        };
+        painter.extend(board_line_shapes_in_rect(&rect, line_color));
+
+        let square_length = rect.width() / 3.;
+        for x in 0..3 {
+            for y in 0..3 {
+                let square_rect = Rect::from_min_size(
+                    rect.min + Vec2::new(x as f32, y as f32) * square_length,
+                    Vec2::splat(square_length),
+                );
+                path.push((x, y));
+                self.draw_square(
+                    painter,
+                    centered_square_in_rect(square_rect, 0.9),
+                    path,
+                    dark,
+                );
+                path.pop();
+            }
+        }
+
+        if path.len() > self.zoom.len() {
+            let winner = self.board.board_winner(path).map(|(shape, _)| shape);
+            draw_cellshape_in_rect(painter, &rect, winner, true);
+        } else if let Some((_, line)) = self.board.board_winner(path) {
+            draw_winning_line_in_rect(&rect, painter, dark, line[0], line[2]);
+        }
+    }
+
+    /// Draw the board being shown in the given rect and handle clicks on it.
+    fn draw_board(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        let dark = ui.ctx().style().visuals.dark_mode;
+        let response = ui.allocate_rect(rect, Sense::click());
+        let painter = ui.painter_at(rect);
+
+        let mut path = self.zoom.clone();
+        self.draw_square(&painter, rect, &mut path, dark);
+
+        match self.board.get_winner() {
+            GameResult::Ongoing => (),
+            GameResult::Draw => draw_game_over_banner_in_rect(&rect, &painter, dark, "Draw"),
+            GameResult::Won { shape, .. } => {
+                draw_game_over_banner_in_rect(&rect, &painter, dark, &format!("{shape:?} wins!"))
+            }
+        }
+
+        if let Some(Pos2 { x, y }) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        {
+            let square_length = rect.width() / 3.;
+            let coord = (
+                (((x - rect.min.x) / square_length) as usize).min(2),
+                (((y - rect.min.y) / square_length) as usize).min(2),
+            );
+            let _ = self.click_square(coord);
+        }
+    }
+}
+
+impl TTTVariantApp for DoubleUltimateApp {
+    /// Create a new app. The experimental variant doesn't save any config yet.
+    fn new_app(_storage: Option<&dyn Storage>) -> Self {
+        Self::default()
+    }
+
+    fn tick(&mut self) {
+        self.receive_ai_move();
+    }
+
+    fn show_ui(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.tick();
+        request_repaint(ctx, false, self.ai_task.is_some());
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("\u{27F3} Restart").clicked() {
+                    self.request_restart();
+                }
+                if ui
+                    .add_enabled(!self.zoom.is_empty(), egui::Button::new("Zoom out"))
+                    .clicked()
+                {
+                    self.zoom.pop();
+                }
+                if ui
+                    .button("Go to next board")
+                    .on_hover_text("Zoom to the board that the next move must be played in")
+                    .clicked()
+                {
+                    self.zoom = self.board.target().to_vec();
+                }
+                if ui
+                    .checkbox(&mut self.playing_ai, "Play against the AI")
+                    .changed()
+                {
+                    self.restart_game();
+                }
+            });
+            ui.label(format!(
+                "{} \u{2014} {}",
+                self.zoom_description(),
+                self.short_status()
+            ));
+
+            let rect = centered_square_in_rect(ui.available_rect_before_wrap(), 0.95);
+            self.draw_board(ui, rect);
+        });
+
+        if self.confirming_restart {
+            if let Some(discard) = show_discard_dialog(ctx, &mut self.confirm_discard) {
+                self.confirming_restart = false;
+                if discard {
+                    self.restart_game();
+                }
+            }
+        }
+    }
+
+    fn game_in_progress(&self) -> bool {
+        self.moves_played > 0 && self.board.get_winner() == GameResult::Ongoing
+    }
+
+    fn confirm_discard_mut(&mut self) -> &mut bool {
+        &mut self.confirm_discard
+    }
+
+    fn short_status(&self) -> String {
+        short_status_text(
+            self.board.get_winner(),
+            self.board.to_move(),
+            self.ai_shape(),
+        )
+    }
+
+    /// Apply whether to play against the AI. The AI always plays at the same strength here.
+    fn apply_launch_options(&mut self, options: &LaunchOptions) {
+        if let Some(playing_ai) = options.playing_ai {
+            self.playing_ai = playing_ai;
+        }
+        self.restart_game();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_square_test() {
+        let mut app = DoubleUltimateApp {
+            playing_ai: false,
+            ..DoubleUltimateApp::default()
+        };
+
+        app.click_square((1, 1)).unwrap();
+        app.click_square((0, 2)).unwrap();
+        assert_eq!(app.zoom, vec![(1, 1), (0, 2)]);
+        assert!(!app.game_in_progress());
+
+        // Clicking a cell plays in it and zooms to the next board
+        app.click_square((2, 0)).unwrap();
+        assert_eq!(
+            app.board.shape_at(&[(1, 1), (0, 2), (2, 0)]),
+            Some(CellShape::X)
+        );
+        assert_eq!(app.zoom, vec![(0, 2), (2, 0)]);
+        assert!(app.game_in_progress());
+        assert_eq!(app.short_status(), "O to play");
+
+        app.zoom = vec![(1, 1), (0, 2)];
+        assert_eq!(app.click_square((1, 1)), Err(MoveError::WrongLocalBoard));
+        assert_eq!(app.zoom_description(), "Whole board > middle > bottom-left");
+    }
+}
@@ -0,0 +1,421 @@
+//! This module handles the backend of a [`NestedBoard`], which is a board of boards of any depth.
+
+use super::NestedCoord;
+use crate::{
+    shared::{
+        self,
+        ai::{AiPlayer, Game},
+        board::GameResult,
+    },
+    ultimate::board::{mcts_move, MctsPlayer, MoveError},
+    CellShape,
+};
+use std::array;
+
+/// A double-ultimate board, which is an ultimate board of ultimate boards.
+pub type DoubleUltimateBoard = NestedBoard<3>;
+
+/// A square of a [`Grid`], which is either a single cell or a smaller grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Square {
+    /// A single cell, which may have been played in.
+    Cell(Option<CellShape>),
+
+    /// A grid of smaller squares.
+    Grid(Box<Grid>),
+}
+
+impl Square {
+    /// Create an empty square with the given number of levels of grids in it.
+    fn new(depth: usize) -> Self {
+        if depth == 0 {
+            Self::Cell(None)
+        } else {
+            Self::Grid(Box::new(Grid::new(depth)))
+        }
+    }
+
+    /// Return the shape which has won this square, which is the shape in it if it's a cell.
+    fn winner(&self) -> Option<CellShape> {
+        match self {
+            Self::Cell(shape) => *shape,
+            Self::Grid(grid) => grid.winner.map(|(shape, _)| shape),
+        }
+    }
+}
+
+/// A 3x3 grid of [`Square`]s, indexed as `squares[x][y]` like every other board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Grid {
+    /// The squares of this grid.
+    squares: [[Square; 3]; 3],
+
+    /// The first winner of this grid, which is cached so that multiple winners can't occur.
+    winner: Option<(CellShape, [(usize, usize); 3])>,
+
+    /// The number of empty cells in this grid and all the grids inside it.
+    empty_cells: usize,
+}
+
+impl Grid {
+    /// Create an empty grid with the given number of levels of grids, including this one.
+    fn new(depth: usize) -> Self {
+        Self {
+            squares: array::from_fn(|_| array::from_fn(|_| Square::new(depth - 1))),
+            winner: None,
+            empty_cells: 9_usize.pow(depth as u32),
+        }
+    }
+
+    /// Return the winners of the squares of this grid, for the shared winner functions.
+    fn winners(&self) -> [[Option<CellShape>; 3]; 3] {
+        array::from_fn(|x| array::from_fn(|y| self.squares[x][y].winner()))
+    }
+
+    /// Return the grid at the end of the given path of coordinates, starting from this grid.
+    fn grid_at(&self, path: &[(usize, usize)]) -> Option<&Grid> {
+        match path.split_first() {
+            None => Some(self),
+            Some((&(x, y), rest)) => match &self.squares[x][y] {
+                Square::Cell(_) => None,
+                Square::Grid(grid) => grid.grid_at(rest),
+            },
+        }
+    }
+
+    /// Return the square at the end of the given non-empty path of coordinates.
+    fn square_at(&self, path: &[(usize, usize)]) -> Option<&Square> {
+        let (&(x, y), rest) = path.split_last()?;
+        self.grid_at(rest).map(|grid| &grid.squares[x][y])
+    }
+
+    /// Place the shape in the empty cell at the end of the given path of coordinates, and update
+    /// the cached winners of every grid along the way. Return whether the cell was empty.
+    fn place(&mut self, path: &[(usize, usize)], shape: CellShape) -> bool {
+        let Some((&(x, y), rest)) = path.split_first() else {
+            return false;
+        };
+
+        let placed = match &mut self.squares[x][y] {
+            Square::Cell(cell @ None) if rest.is_empty() => {
+                *cell = Some(shape);
+                true
+            }
+            Square::Cell(_) => false,
+            Square::Grid(grid) => grid.place(rest, shape),
+        };
+        if !placed {
+            return false;
+        }
+
+        self.empty_cells -= 1;
+        if self.winner.is_none() {
+            self.winner = shared::board::get_winning_line_through(self.winners(), (x, y));
+        }
+        true
+    }
+
+    /// Call `f` with the path to every empty cell in this grid, where each path starts with
+    /// `prefix`.
+    fn for_each_empty(
+        &self,
+        prefix: &mut Vec<(usize, usize)>,
+        f: &mut impl FnMut(&[(usize, usize)]),
+    ) {
+        for x in 0..3 {
+            for y in 0..3 {
+                prefix.push((x, y));
+                match &self.squares[x][y] {
+                    Square::Cell(None) => f(prefix),
+                    Square::Cell(Some(_)) => (),
+                    Square::Grid(grid) => grid.for_each_empty(prefix, f),
+                }
+                prefix.pop();
+            }
+        }
+    }
+}
+
+/// A board of boards nested `DEPTH` levels deep, which generalises
+/// [`GlobalBoard`](crate::ultimate::board::GlobalBoard) to any depth.
+///
+/// Every move sends the opponent to the board at the same position as the cell that was played,
+/// one level down. If that board is full, then they're sent to the board containing it, and so on
+/// up to the whole board. Like [`RuleSet::Standard`](crate::ultimate::board::RuleSet::Standard),
+/// boards which have been won can still be played in until they're full.
+///
+/// A board is won by a line of won boards at the level below, and the game is won by winning the
+/// whole board. The game is drawn when the whole board is full.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NestedBoard<const DEPTH: usize> {
+    /// The whole board.
+    grid: Grid,
+
+    /// This is the shape that the AI will play as.
+    ai_shape: CellShape,
+
+    /// The path to the board in which the next move must be played, which is empty if the move
+    /// can be played anywhere.
+    target: Vec<(usize, usize)>,
+
+    /// The shape which must make the next move.
+    to_move: CellShape,
+}
+
+impl<const DEPTH: usize> Default for NestedBoard<DEPTH> {
+    fn default() -> Self {
+        Self::new(CellShape::O)
+    }
+}
+
+impl<const DEPTH: usize> NestedBoard<DEPTH> {
+    /// Create a new, empty board, where [`X`](CellShape::X) plays first.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `DEPTH` is 0, since a board needs at least one level of cells.
+    pub fn new(ai_shape: CellShape) -> Self {
+        assert!(DEPTH > 0, "A nested board must have at least one level");
+
+        Self {
+            grid: Grid::new(DEPTH),
+            ai_shape,
+            target: vec![],
+            to_move: CellShape::X,
+        }
+    }
+
+    /// Return the shape that the AI plays as.
+    pub fn ai_shape(&self) -> CellShape {
+        self.ai_shape
+    }
+
+    /// Return the path to the board in which the next move must be played, which is empty if the
+    /// move can be played anywhere.
+    pub fn target(&self) -> &[(usize, usize)] {
+        &self.target
+    }
+
+    /// Return the shape in the cell at the end of the given path, or the shape which has won the
+    /// board there if the path is shorter than `DEPTH`.
+    pub fn shape_at(&self, path: &[(usize, usize)]) -> Option<CellShape> {
+        self.grid.square_at(path).and_then(Square::winner)
+    }
+
+    /// Return the winner and winning line of the board at the end of the given path, which is the
+    /// whole board if the path is empty.
+    pub fn board_winner(
+        &self,
+        path: &[(usize, usize)],
+    ) -> Option<(CellShape, [(usize, usize); 3])> {
+        self.grid.grid_at(path).and_then(|grid| grid.winner)
+    }
+
+    /// Check if the board at the end of the given path has no empty cells left.
+    pub fn is_board_full(&self, path: &[(usize, usize)]) -> bool {
+        self.grid
+            .grid_at(path)
+            .map_or(true, |grid| grid.empty_cells == 0)
+    }
+
+    /// Return all the legal moves, reading the boards and then the cells within them column by
+    /// column.
+    pub fn legal_moves(&self) -> Vec<NestedCoord<DEPTH>> {
+        if self.get_winner() != GameResult::Ongoing {
+            return vec![];
+        }
+        let Some(grid) = self.grid.grid_at(&self.target) else {
+            return vec![];
+        };
+
+        let mut moves: Vec<NestedCoord<DEPTH>> = vec![];
+        let mut prefix = self.target.clone();
+        grid.for_each_empty(&mut prefix, &mut |path: &[(usize, usize)]| {
+            moves.push(
+                path.try_into()
+                    .expect("Every path to a cell has `DEPTH` coordinates"),
+            );
+        });
+        moves
+    }
+
+    /// Update the board to reflect a move being made, and send the opponent to the board matching
+    /// the cell that was played in.
+    pub fn make_move(
+        &mut self,
+        coord: NestedCoord<DEPTH>,
+        shape: CellShape,
+    ) -> Result<(), MoveError> {
+        if coord.iter().any(|&(x, y)| x > 2 || y > 2) {
+            return Err(MoveError::OutOfBounds);
+        }
+
+        if shape != self.to_move {
+            return Err(MoveError::WrongTurn);
+        }
+
+        if !coord.starts_with(&self.target) {
+            return Err(MoveError::WrongLocalBoard);
+        }
+
+        if !self.grid.place(&coord, shape) {
+            return Err(MoveError::CellAlreadyFull);
+        }
+
+        self.to_move = shape.other();
+
+        let mut target = coord[1..].to_vec();
+        while !target.is_empty() && self.is_board_full(&target) {
+            target.pop();
+        }
+        self.target = target;
+
+        Ok(())
+    }
+
+    /// Skip the turn of the shape which is next to move, so that the other shape moves next in
+    /// the same board.
+    pub fn skip_turn(&mut self) {
+        self.to_move = self.to_move.other();
+    }
+
+    /// Return the result of the game so far.
+    pub fn get_winner(&self) -> GameResult {
+        match self.grid.winner {
+            Some((shape, line)) => GameResult::Won { shape, line },
+            None if self.grid.empty_cells == 0 => GameResult::Draw,
+            None => GameResult::Ongoing,
+        }
+    }
+}
+
+impl<const DEPTH: usize> Game for NestedBoard<DEPTH> {
+    type Move = NestedCoord<DEPTH>;
+
+    fn moves(&self) -> Vec<NestedCoord<DEPTH>> {
+        self.legal_moves()
+    }
+
+    fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    fn play(&mut self, mv: NestedCoord<DEPTH>) -> bool {
+        self.make_move(mv, self.to_move).is_ok()
+    }
+
+    fn skip_turn(&mut self) {
+        self.skip_turn();
+    }
+
+    fn get_winner(&self) -> GameResult {
+        self.get_winner()
+    }
+}
+
+impl<const DEPTH: usize> AiPlayer<NestedBoard<DEPTH>> for MctsPlayer {
+    /// Choose a move with the generic MCTS engine. No statistics are kept for nested boards.
+    fn choose_move(&mut self, game: &NestedBoard<DEPTH>) -> Option<NestedCoord<DEPTH>> {
+        self.last_stats = None;
+        mcts_move(
+            game,
+            self.max_mcts_expansions,
+            self.playouts,
+            self.draw_reward,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::ai::perft;
+
+    #[test]
+    fn perft_test() {
+        // A depth of 1 is normal tic-tac-toe
+        let board = NestedBoard::<1>::default();
+        let counts: Vec<u64> = (0..=9).map(|depth| perft(&board, depth)).collect();
+        assert_eq!(
+            counts,
+            vec![1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872]
+        );
+
+        // A depth of 2 is ultimate tic-tac-toe
+        let board = NestedBoard::<2>::default();
+        assert_eq!(perft(&board, 1), 81);
+        assert_eq!(perft(&board, 2), 72 * 9 + 9 * 8);
+
+        // Every move sends the opponent to one of the 81 bottom boards, which has 9 empty cells
+        // unless it's the one that was just played in
+        let board = DoubleUltimateBoard::default();
+        assert_eq!(perft(&board, 1), 729);
+        assert_eq!(perft(&board, 2), 720 * 9 + 9 * 8);
+    }
+
+    #[test]
+    fn make_move_test() {
+        let mut board = DoubleUltimateBoard::default();
+        board
+            .make_move([(1, 1), (0, 2), (2, 0)], CellShape::X)
+            .unwrap();
+        assert_eq!(
+            board.shape_at(&[(1, 1), (0, 2), (2, 0)]),
+            Some(CellShape::X)
+        );
+        assert_eq!(board.target(), &[(0, 2), (2, 0)]);
+        assert_eq!(board.to_move(), CellShape::O);
+
+        assert_eq!(
+            board.make_move([(0, 2), (2, 0), (1, 1)], CellShape::X),
+            Err(MoveError::WrongTurn)
+        );
+        assert_eq!(
+            board.make_move([(1, 1), (2, 0), (1, 1)], CellShape::O),
+            Err(MoveError::WrongLocalBoard)
+        );
+        assert_eq!(
+            board.make_move([(0, 2), (2, 0), (3, 1)], CellShape::O),
+            Err(MoveError::OutOfBounds)
+        );
+        assert!(board
+            .legal_moves()
+            .iter()
+            .all(|mv| mv.starts_with(&[(0, 2), (2, 0)])));
+    }
+
+    #[test]
+    fn winner_test() {
+        // X wins the bottom-left local board of the middle ultimate board, while O plays in the
+        // top-left ultimate board
+        let mut board = DoubleUltimateBoard::default();
+        for x in 0..3 {
+            board.target.clear();
+            board
+                .make_move([(1, 1), (0, 2), (x, 0)], CellShape::X)
+                .unwrap();
+            board.target.clear();
+            board
+                .make_move([(0, 0), (x, 1), (0, 0)], CellShape::O)
+                .unwrap();
+        }
+
+        assert_eq!(
+            board.board_winner(&[(1, 1), (0, 2)]),
+            Some((CellShape::X, [(0, 0), (1, 0), (2, 0)]))
+        );
+        assert_eq!(board.shape_at(&[(1, 1), (0, 2)]), Some(CellShape::X));
+        assert_eq!(board.board_winner(&[(1, 1)]), None);
+        assert_eq!(board.board_winner(&[]), None);
+        assert_eq!(board.get_winner(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn mcts_player_test() {
+        let mut board = NestedBoard::<2>::default();
+        board.make_move([(1, 1), (0, 0)], CellShape::X).unwrap();
+
+        let mv = MctsPlayer::new(10, 1).choose_move(&board).unwrap();
+        assert!(board.legal_moves().contains(&mv));
+    }
+}
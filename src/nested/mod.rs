@@ -0,0 +1,18 @@
+//! This module models double-ultimate tic-tac-toe, where each cell of an ultimate board is itself
+//! a whole ultimate board, and provides a zoomable GUI interface.
+//!
+//! The board is generic over its depth with [`NestedBoard`], so that a depth of 1 is normal
+//! tic-tac-toe, a depth of 2 is ultimate tic-tac-toe, and a depth of 3 is double-ultimate.
+//!
+//! This variant is experimental, and is only built with the `double-ultimate` feature.
+
+/// A coordinate of an individual cell in a [`NestedBoard`](board::NestedBoard) of the given depth.
+///
+/// Each coordinate picks a board at the next level down, starting from the whole board, and the
+/// last coordinate picks the cell itself.
+pub type NestedCoord<const DEPTH: usize> = [(usize, usize); DEPTH];
+
+pub mod app;
+pub mod board;
+
+pub use self::app::DoubleUltimateApp;
@@ -1,10 +1,36 @@
 //! This module handles app configuration.
 
 use super::NormalTTTApp;
-use crate::CellShape;
-use eframe::egui::{self, Context};
+use crate::{
+    shared::{adaptive::AdaptiveDifficulty, ladder::Ladder, personality::AiPersonality},
+    CellShape,
+};
+use eframe::egui::{self, Context, Ui};
 use serde::{Deserialize, Serialize};
 
+/// The kinds of AI that the player can play against. See [`AiPlayer`](crate::shared::ai::AiPlayer).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NormalAiPlayer {
+    /// An easy player which plays randomly. See [`RandomBot`](crate::shared::ai::RandomBot).
+    Random,
+
+    /// A medium player which takes wins and blocks losses. See
+    /// [`GreedyBot`](crate::shared::ai::GreedyBot).
+    Greedy,
+
+    /// A perfect player using minimax. See [`MinimaxPlayer`](crate::normal::board::MinimaxPlayer).
+    #[default]
+    Minimax,
+
+    /// A player using minimax which sometimes blunders by playing randomly, more often when the
+    /// player has been losing. See [`AdaptiveDifficulty`].
+    Adaptive,
+
+    /// The player's current level on the ladder, where the AI gets stronger each time the player
+    /// beats it. See [`Ladder`].
+    Ladder,
+}
+
 /// A struct representing the app configuration, meant to be saved and loaded between sessions.
 #[derive(Clone, Copy, Serialize, Deserialize)]
 #[serde(default)]
@@ -17,6 +43,47 @@ pub struct NormalConfig {
 
     /// Whether the player is playing against an AI.
     pub playing_ai: bool,
+
+    /// Whether games start from a random balanced mid-game position instead of an empty board.
+    /// See [`random_start`](crate::shared::chaos::random_start).
+    pub chaos_start: bool,
+
+    /// Which AI the player is playing against.
+    pub ai_player: NormalAiPlayer,
+
+    /// Whether to colour each empty cell by the chance of winning by playing there.
+    pub show_heatmap: bool,
+
+    /// Whether to show coordinate labels around the board, which match the names of the moves in
+    /// the list of moves. See [`cell_name`](crate::shared::status::cell_name).
+    pub show_coordinates: bool,
+
+    /// Whether to explain each AI move with the best moves that it considered. See
+    /// [`MoveExplanation`](crate::shared::explain::MoveExplanation).
+    pub explain_ai_moves: bool,
+
+    /// Whether the AI is stopped from taking the center with its first move, as a handicap.
+    pub ai_avoids_center: bool,
+
+    /// The state of the [`Adaptive`](NormalAiPlayer::Adaptive) AI, which is updated at the end of
+    /// every game against it.
+    pub adaptive: AdaptiveDifficulty,
+
+    /// The player's position on the [`Ladder`](NormalAiPlayer::Ladder), which is updated at the
+    /// end of every game against it without a handicap.
+    pub ladder: Ladder,
+
+    /// The style of play of the minimax AIs, which chooses between equally good moves.
+    pub personality: AiPersonality,
+
+    /// The score of a draw for the minimax AIs, which is 0 by default. A negative score makes the
+    /// AI press for a win against weaker opponents. See
+    /// [`Board::draw_score`](crate::normal::board::Board::draw_score).
+    pub draw_score: i8,
+
+    /// Whether to ask for confirmation before discarding a game in progress, like when
+    /// restarting. See [`show_discard_dialog`](crate::shared::gui::show_discard_dialog).
+    pub confirm_discard: bool,
 }
 
 impl Default for NormalConfig {
@@ -25,17 +92,87 @@ impl Default for NormalConfig {
             player_plays_first: true,
             player_shape: CellShape::X,
             playing_ai: true,
+            chaos_start: false,
+            ai_player: NormalAiPlayer::default(),
+            show_heatmap: false,
+            show_coordinates: false,
+            explain_ai_moves: false,
+            ai_avoids_center: false,
+            adaptive: AdaptiveDifficulty::default(),
+            ladder: Ladder::default(),
+            personality: AiPersonality::default(),
+            draw_score: 0,
+            confirm_discard: true,
+        }
+    }
+}
+
+/// The settings in [`NormalConfig`] that define a game, which only take effect when a new game
+/// starts. All the other settings take effect immediately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NormalGameSettings {
+    /// Whether the player should make the first move.
+    pub player_plays_first: bool,
+
+    /// Which shape the player uses.
+    pub player_shape: CellShape,
+
+    /// Whether the player is playing against an AI.
+    pub playing_ai: bool,
+
+    /// Whether games start from a random balanced mid-game position instead of an empty board.
+    /// See [`random_start`](crate::shared::chaos::random_start).
+    pub chaos_start: bool,
+
+    /// Whether the AI is stopped from taking the center with its first move, as a handicap.
+    pub ai_avoids_center: bool,
+}
+
+impl NormalConfig {
+    /// Get the settings that define a game, which only take effect when a new game starts.
+    pub fn game_settings(&self) -> NormalGameSettings {
+        NormalGameSettings {
+            player_plays_first: self.player_plays_first,
+            player_shape: self.player_shape,
+            playing_ai: self.playing_ai,
+            chaos_start: self.chaos_start,
+            ai_avoids_center: self.ai_avoids_center,
+        }
+    }
+
+    /// Return a copy of this config with the given game settings, like to save the settings that
+    /// the current game was started with. See [`game_settings`](Self::game_settings).
+    pub fn with_game_settings(self, game_settings: NormalGameSettings) -> Self {
+        let NormalGameSettings {
+            player_plays_first,
+            player_shape,
+            playing_ai,
+            chaos_start,
+            ai_avoids_center,
+        } = game_settings;
+
+        Self {
+            player_plays_first,
+            player_shape,
+            playing_ai,
+            chaos_start,
+            ai_avoids_center,
+            ..self
         }
     }
 }
 
 impl NormalTTTApp {
     /// Draw the settings window as a non-collapsible, non-resizable, closable `egui` window.
+    ///
+    /// This is only used in the narrow layout, since the wide layout shows the settings in the
+    /// side panel instead. See [`show_settings`](Self::show_settings).
     pub fn draw_settings_window(&mut self, ctx: &Context) {
+        let mut open = self.showing_settings_window;
         egui::Window::new("Settings")
             .collapsible(false)
             .resizable(false)
-            .open(&mut self.showing_settings_window)
+            .open(&mut open)
             .show(ctx, |ui| {
                 let mut style = (*ctx.style()).clone();
                 for id in style.text_styles.values_mut() {
@@ -43,21 +180,96 @@ impl NormalTTTApp {
                 }
                 ui.set_style(style);
 
-                ui.checkbox(&mut self.config.playing_ai, "Play against AI");
-                ui.add_enabled(
-                    self.config.playing_ai,
-                    egui::Checkbox::new(&mut self.config.player_plays_first, "Player plays first"),
+                self.show_settings(ui);
+            });
+        self.showing_settings_window = open;
+    }
+
+    /// Show the settings in the given [`Ui`].
+    pub fn show_settings(&mut self, ui: &mut Ui) {
+        self.profiles.show_selector(ui, &mut self.config);
+        ui.separator();
+
+        ui.checkbox(&mut self.config.playing_ai, "Play against AI");
+        ui.add_enabled(
+            self.config.playing_ai,
+            egui::Checkbox::new(&mut self.config.player_plays_first, "Player plays first"),
+        );
+        ui.horizontal(|ui| {
+            ui.label(if self.config.playing_ai {
+                "Player shape"
+            } else {
+                "First player shape"
+            });
+            ui.radio_value(&mut self.config.player_shape, CellShape::X, "X");
+            ui.radio_value(&mut self.config.player_shape, CellShape::O, "O");
+        });
+        ui.checkbox(&mut self.config.chaos_start, "Chaos start")
+            .on_hover_text(
+                "Start from a random mid-game position where neither side can force a win",
+            );
+        if self.config.playing_ai {
+            ui.horizontal(|ui| {
+                ui.label("AI difficulty");
+                ui.radio_value(&mut self.config.ai_player, NormalAiPlayer::Random, "Easy")
+                    .on_hover_text("Plays randomly");
+                ui.radio_value(&mut self.config.ai_player, NormalAiPlayer::Greedy, "Medium")
+                    .on_hover_text("Takes wins and blocks losses, but otherwise plays randomly");
+                ui.radio_value(&mut self.config.ai_player, NormalAiPlayer::Minimax, "Hard")
+                    .on_hover_text("Plays perfectly with minimax");
+                ui.radio_value(
+                    &mut self.config.ai_player,
+                    NormalAiPlayer::Adaptive,
+                    "Adaptive",
+                )
+                .on_hover_text("Blunders less when you win and more when you lose");
+                ui.radio_value(&mut self.config.ai_player, NormalAiPlayer::Ladder, "Ladder")
+                    .on_hover_text("Climb a level each time you win, and drop one when you lose");
+            });
+            match self.config.ai_player {
+                NormalAiPlayer::Adaptive => self.config.adaptive.show_settings(ui),
+                NormalAiPlayer::Ladder => self.config.ladder.show_settings(ui),
+                _ => (),
+            }
+            if !matches!(
+                self.config.ai_player,
+                NormalAiPlayer::Random | NormalAiPlayer::Greedy
+            ) {
+                self.config.personality.show_settings(ui);
+                ui.add(
+                    egui::Slider::new(&mut self.config.draw_score, -50..=0)
+                        .clamp_to_range(true)
+                        .text("Draw score (contempt)"),
+                )
+                .on_hover_text(
+                    "Lower scores make the AI treat draws as worse, \
+                    so it holds out for a win for longer",
                 );
-                ui.horizontal(|ui| {
-                    ui.label(if self.config.playing_ai {
-                        "Player shape"
-                    } else {
-                        "First player shape"
-                    });
-                    ui.radio_value(&mut self.config.player_shape, CellShape::X, "X");
-                    ui.radio_value(&mut self.config.player_shape, CellShape::O, "O");
-                });
-                ui.small("Changes will require a game restart.");
+            }
+            ui.checkbox(
+                &mut self.config.ai_avoids_center,
+                "Handicap: AI can't take the center first",
+            )
+            .on_hover_text("The AI's first move must be somewhere other than the center");
+        }
+        ui.checkbox(&mut self.config.show_heatmap, "Show win chance heatmap");
+        ui.checkbox(&mut self.config.show_coordinates, "Show coordinates");
+        ui.add_enabled(
+            self.config.playing_ai,
+            egui::Checkbox::new(&mut self.config.explain_ai_moves, "Explain AI moves"),
+        );
+        ui.checkbox(
+            &mut self.config.confirm_discard,
+            "Ask before discarding a game in progress",
+        );
+        if self.config.game_settings() != self.game_settings {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Some changes will apply to the next game.");
+                if ui.button("Restart now").clicked() {
+                    self.request_restart();
+                }
             });
+        }
     }
 }
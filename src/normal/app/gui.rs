@@ -1,53 +1,87 @@
 //! This module only exists to separate the long methods used for drawing the board and cells.
 
-use super::{send_move_after_delay, NormalTTTApp};
+use super::NormalTTTApp;
 use crate::{
-    shared::gui::{centered_square_in_rect, draw_cellshape_in_rect, draw_winning_line_in_rect},
+    normal::{board::SearchProgress, Coord},
+    shared::{
+        board::GameResult,
+        gui::{
+            board_line_shapes_in_rect, centered_square_in_rect, draw_cellshape_in_rect,
+            draw_coordinate_labels, draw_game_over_banner_in_rect, draw_heat_in_rect,
+            draw_last_move_arrow, draw_thinking_overlay, draw_winning_line_in_rect, piece_scale,
+            request_repaint, SIDE_PANEL_WIDTH,
+        },
+        history::History,
+        status::cell_name,
+    },
     CellShape,
 };
 use eframe::{
-    egui::{self, Context, Painter, Rect, Response, Sense, Shape, Ui},
-    epaint::{Color32, Pos2, Stroke, Vec2},
+    egui::{self, Context, Painter, Rect, Response, Sense, Ui},
+    epaint::{Color32, Pos2, Vec2},
 };
 
 impl NormalTTTApp {
     /// Draw the board in the given rect.
     ///
     /// This method also handles all the updating of the internal [`Board`](crate::normal::board::Board)
-    /// when cells are clicked, and triggers an AI move with [`send_move_after_delay`] if AI is enabled.
+    /// when cells are clicked, and triggers an AI move with [`start_ai_move`](NormalTTTApp::start_ai_move) if AI is enabled.
+    /// The AI's move is played by [`tick`](crate::app::TTTVariantApp::tick) when it arrives.
+    ///
+    /// If we're showing a position from the [`history`](NormalTTTApp::history), then that position
+    /// is drawn instead and the cells can't be clicked, but the game carries on underneath.
     pub fn draw_board(&mut self, ctx: &Context, ui: &mut Ui, rect: Rect) {
-        ctx.request_repaint();
+        let previewing = self.history.is_some();
+
+        // The spinner is only drawn over the game itself, but the AI's move still has to arrive
+        // while we're showing another position
+        request_repaint(
+            ctx,
+            self.waiting_on_move() && !previewing,
+            self.waiting_on_move()
+                || self.blunder_check.is_running()
+                || (self.kiosk && self.game_ended_at.is_some()),
+        );
+
+        let input_enabled = !previewing && !self.waiting_on_move();
+        let board = self
+            .history
+            .as_ref()
+            .map_or(&self.board, History::position)
+            .clone();
 
         let painter = Painter::new(
             ctx.clone(),
             egui::LayerId::new(egui::Order::Background, egui::Id::new("board_painter")),
             rect,
         );
+        let rect = if self.config.show_coordinates {
+            draw_coordinate_labels(&painter, rect, 3, Color32::GRAY)
+        } else {
+            rect
+        };
 
         let cell_length = rect.size().x / 3.0;
         let nums = [0, 1, 2];
-
-        let stroke = Stroke {
-            width: rect.width() / 80.0,
-            color: Color32::GRAY,
+        let centre = |(x, y): Coord| {
+            (
+                rect.min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * cell_length,
+                cell_length,
+            )
         };
-        for i in [1.0, 2.0] {
-            // Draw vertical lines
-            let x = rect.min.x + (i / 3.0) * rect.width();
-            let y = rect.max.y;
-            painter.add(Shape::LineSegment {
-                points: [Pos2 { x, y: rect.min.y }, Pos2 { x, y }],
-                stroke,
-            });
 
-            // Draw horizontal lines
-            let y = rect.min.y + (i / 3.0) * rect.height();
-            let x = rect.max.x;
-            painter.add(Shape::LineSegment {
-                points: [Pos2 { x: rect.min.x, y }, Pos2 { x, y }],
-                stroke,
-            });
-        }
+        self.drawings.handle_input(ui, rect, |pos| {
+            let offset = (pos - rect.min) / cell_length;
+            Some(((offset.x as usize).min(2), (offset.y as usize).min(2)))
+        });
+
+        painter.extend(board_line_shapes_in_rect(&rect, Color32::GRAY));
+
+        let heatmap = if self.config.show_heatmap && !self.waiting_on_move() && !previewing {
+            self.heatmap().to_vec()
+        } else {
+            vec![]
+        };
 
         for y in nums {
             for x in nums {
@@ -59,35 +93,76 @@ impl NormalTTTApp {
                     Vec2::splat(cell_length),
                 );
 
+                if let Some(&(_, win_chance)) = heatmap.iter().find(|&&(coord, _)| coord == (x, y))
+                {
+                    draw_heat_in_rect(
+                        &painter,
+                        &centered_square_in_rect(cell_rect, 0.9),
+                        win_chance,
+                    );
+                }
+
                 if Self::draw_cell(
                     ui,
                     &painter,
                     cell_rect,
-                    self.board.cells[x][y],
-                    self.board.get_winner().is_err(),
+                    board.cells()[x][y],
+                    input_enabled && board.get_winner() == GameResult::Ongoing,
+                    self.active_shape,
+                    piece_scale(self.presenting),
                 )
                 .clicked()
-                    && !self.waiting_on_move
                 {
-                    self.update_cell(x, y);
-
-                    if self.config.playing_ai {
-                        send_move_after_delay(self.board.clone(), self.mv_tx.clone());
-                        self.waiting_on_move = true;
-                    }
+                    self.click_cell(x, y);
                 }
             }
         }
 
-        if self.waiting_on_move {
-            if let Ok(Some((x, y))) = self.mv_rx.try_recv() {
-                self.update_cell(x, y);
-                self.waiting_on_move = false;
+        // Show the move that the AI is currently considering, unless we're showing another position
+        if let Some(task) = self.ai_task.as_mut().filter(|_| !previewing) {
+            if let Some(&SearchProgress {
+                best_move: (x, y), ..
+            }) = task.progress()
+            {
+                let cell_rect = Rect::from_min_size(
+                    Pos2::new(
+                        rect.min.x + (x as f32 * cell_length),
+                        rect.min.y + (y as f32 * cell_length),
+                    ),
+                    Vec2::splat(cell_length),
+                );
+                draw_cellshape_in_rect(
+                    &painter,
+                    &centered_square_in_rect(cell_rect, 0.8),
+                    Some(self.active_shape),
+                    true,
+                );
+            }
+        }
+        if self.waiting_on_move() && !previewing {
+            draw_thinking_overlay(
+                &painter,
+                &rect,
+                ui.ctx().style().visuals.dark_mode,
+                ctx.input().time,
+            );
+        }
+
+        // Show observers where the last move was played, coming from the move before it
+        if self.presenting {
+            if let [.., from, to] = self.record.moves[..] {
+                draw_last_move_arrow(&painter, centre(from).0, centre(to).0, cell_length);
             }
         }
 
+        self.drawings.draw(&painter, centre);
+
         // Draw the winning line
-        if let Ok((_, [start_coord, _, end_coord])) = self.board.get_winner() {
+        if let GameResult::Won {
+            line: [start_coord, _, end_coord],
+            ..
+        } = board.get_winner()
+        {
             draw_winning_line_in_rect(
                 &rect,
                 &painter,
@@ -95,27 +170,76 @@ impl NormalTTTApp {
                 start_coord,
                 end_coord,
             );
+        } else if board.get_winner() == GameResult::Draw {
+            draw_game_over_banner_in_rect(
+                &rect,
+                &painter,
+                ui.ctx().style().visuals.dark_mode,
+                "It's a draw!",
+            );
         }
     }
 
-    /// Draw a cell in the given rect and return a response indicated whether it was clicked.
+    /// Draw the side panel of the wide layout, with the settings and the list of moves.
+    pub fn draw_side_panel(&mut self, ctx: &Context) {
+        egui::SidePanel::right("side_panel")
+            .resizable(false)
+            .exact_width(SIDE_PANEL_WIDTH)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Settings");
+                    self.show_settings(ui);
+
+                    ui.separator();
+                    ui.heading("Moves");
+                    let selected = self.history.as_ref().map(History::ply);
+                    if let Some(ply) = self
+                        .record
+                        .show_clickable_move_list(ui, selected, cell_name)
+                    {
+                        self.jump_to_ply(ply);
+                    }
+
+                    if let Some(ply) = selected.filter(|&ply| ply > 0) {
+                        ui.separator();
+                        ui.heading(format!("Annotate move {ply}"));
+                        self.record.show_annotation_editor(ui, ply - 1);
+                    }
+                });
+            });
+    }
+
+    /// Draw a cell in the given rect, with the piece taking up the given fraction of it, and return
+    /// a response indicated whether it was clicked.
+    ///
+    /// The cell can only be clicked if it's empty and `input_enabled` is true, which is false while
+    /// the AI is thinking, while showing a position from the history, or when the game is over.
+    /// While a clickable cell is hovered, a faint preview of `hover_shape` is drawn in it, to show
+    /// what clicking it would do.
     fn draw_cell(
         ui: &mut Ui,
         painter: &Painter,
         rect: Rect,
         shape: Option<CellShape>,
-        interactive: bool,
+        input_enabled: bool,
+        hover_shape: CellShape,
+        scale: f32,
     ) -> Response {
-        let rect = centered_square_in_rect(rect, 0.8);
+        let rect = centered_square_in_rect(rect, scale);
 
         draw_cellshape_in_rect(painter, &rect, shape, false);
 
-        ui.allocate_rect(
+        let response = ui.allocate_rect(
             rect,
             match shape {
-                None if interactive => Sense::click(),
+                None if input_enabled => Sense::click(),
                 _ => Sense::focusable_noninteractive(),
             },
-        )
+        );
+        if shape.is_none() && input_enabled && response.hovered() {
+            draw_cellshape_in_rect(painter, &rect, Some(hover_shape), true);
+        }
+
+        response
     }
 }
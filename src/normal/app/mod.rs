@@ -1,51 +1,75 @@
 //! This module handles the `egui` interface to the game.
 
-mod config;
+pub mod config;
 mod gui;
 
-use self::config::NormalConfig;
-use super::{board::Board, Coord};
-use crate::{app::TTTVariantApp, shared::gui::centered_square_in_rect, CellShape};
+use self::config::{NormalAiPlayer, NormalConfig, NormalGameSettings};
+use super::{
+    board::{Board, MinimaxPlayer, SearchProgress},
+    Coord,
+};
+use crate::{
+    app::{Difficulty, LaunchOptions, TTTVariantApp, KIOSK_RESTART_DELAY},
+    shared::{
+        ai::{AiPlayer, GreedyBot, RandomBot},
+        ai_task::{AiTask, MoveChannel},
+        blunders::BlunderCheck,
+        board::GameResult,
+        chaos::random_start,
+        crash_report::{self, CrashContext},
+        drawings::BoardDrawings,
+        events::{EventBus, GameEvent},
+        explain::MoveExplanation,
+        export::{normal_board_shapes, show_export_buttons},
+        gui::{
+            centered_square_in_rect, is_wide_layout, show_discard_dialog, show_file_drop_hint,
+            show_turn_indicator,
+        },
+        history::History,
+        ladder::LadderPolicy,
+        profiles::{Profiles, VariantSettings},
+        record::GameRecord,
+        save_slots::{LoadedGame, SaveSlots, SavedGame},
+        status::{cell_name, short_status_text, status_text},
+    },
+    CellShape,
+};
 use eframe::{
     egui::{self, Context},
     epaint::Color32,
 };
-use std::sync::mpsc;
+use rand::seq::SliceRandom;
+use std::{ops::RangeInclusive, sync::mpsc};
 use web_time::{Duration, Instant};
 
-/// This method sends an AI-generated move down an `mpsc` channel after 200ms.
-#[cfg(not(target_arch = "wasm32"))]
-pub fn send_move_after_delay(board: Board, tx: mpsc::Sender<Option<Coord>>) {
-    use std::thread;
-
-    thread::spawn(move || {
-        let start = Instant::now();
-        let mv = board.generate_ai_move();
-        thread::sleep(Duration::from_millis(200) - start.elapsed());
-        let _ = tx.send(mv);
-    });
-}
+/// The minimum time that the AI will take to make a move, so that it doesn't appear instant.
+const AI_MOVE_DELAY: Duration = Duration::from_millis(200);
 
-/// This method sends an AI-generated move down an `mpsc` channel after 200ms.
-#[cfg(target_arch = "wasm32")]
-pub fn send_move_after_delay(board: Board, tx: mpsc::Sender<Option<Coord>>) {
-    let start = Instant::now();
-    let mv = board.generate_ai_move();
+/// The range of the number of random moves played to reach the starting position in chaos mode.
+/// See [`NormalConfig::chaos_start`].
+const CHAOS_PLIES: RangeInclusive<usize> = 2..=4;
 
-    gloo_timers::callback::Timeout::new(
-        (Duration::from_millis(200) - start.elapsed()).as_millis() as u32,
-        move || {
-            let _ = tx.send(mv);
-        },
-    )
-    .forget();
-}
+/// The chance that the [`Adaptive`](NormalAiPlayer::Adaptive) AI plays a random move when it's at
+/// its weakest. This is also the chance for the lowest level of the
+/// [`Ladder`](NormalAiPlayer::Ladder) which uses minimax.
+const MAX_BLUNDER_PROBABILITY: f32 = 0.6;
 
 /// The struct to hold the state of the app.
 pub struct NormalTTTApp {
     /// The configuration of the app.
     config: NormalConfig,
 
+    /// The settings that the current game was started with. Changes to these settings in
+    /// [`config`](Self::config) only take effect when a new game starts.
+    game_settings: NormalGameSettings,
+
+    /// The named configs that the player can switch between. The selected profile is updated
+    /// from [`config`](Self::config) when the config is saved.
+    profiles: Profiles<NormalConfig>,
+
+    /// The games that the player has saved to load later.
+    save_slots: SaveSlots<NormalConfig, Coord>,
+
     /// Whether the settings window is currently being shown.
     showing_settings_window: bool,
 
@@ -57,16 +81,77 @@ pub struct NormalTTTApp {
     /// See [`update_cell`](NormalTTTApp::update_cell).
     active_shape: CellShape,
 
-    /// Whether we're currently waiting for the AI to make a move.
-    waiting_on_move: bool,
+    /// The handle to the AI move currently being computed, if there is one, which reports the
+    /// progress of the AI search. See [`waiting_on_move`](Self::waiting_on_move).
+    ai_task: Option<AiTask<SearchProgress>>,
 
-    /// The AI moves are computed in a background thread to make the UI more snappy. This is the
-    /// sender that we pass to the background thread to get the AI move back.
-    mv_tx: mpsc::Sender<Option<Coord>>,
+    /// The generation of the current game, which is incremented every time the game is restarted.
+    ///
+    /// Every AI move is tagged with the generation of the game it was computed for, so that moves
+    /// computed for a previous game can be discarded.
+    generation: u32,
 
     /// The AI moves are computed in a background thread to make the UI more snappy. This is the
-    /// receiver that receives the computed AI moves.
-    mv_rx: mpsc::Receiver<Option<Coord>>,
+    /// channel that we get them back from, tagged with the [`generation`](Self::generation) of
+    /// the game.
+    ai_moves: MoveChannel<Coord>,
+
+    /// The most recently computed heatmap, along with the board and active shape that it was
+    /// computed for. See [`heatmap`](Self::heatmap).
+    heatmap: Option<(Board, CellShape, Vec<(Coord, f32)>)>,
+
+    /// The explanation of the most recent AI move, if [`NormalConfig::explain_ai_moves`] is
+    /// enabled.
+    explanation: Option<MoveExplanation<Coord>>,
+
+    /// The sender that we pass to the background thread to get the explanation of the AI move
+    /// back, tagged with the [`generation`](Self::generation) of the game.
+    explanation_tx: mpsc::Sender<(u32, MoveExplanation<Coord>)>,
+
+    /// The receiver that receives the explanations of AI moves, tagged with the
+    /// [`generation`](Self::generation) of the game.
+    explanation_rx: mpsc::Receiver<(u32, MoveExplanation<Coord>)>,
+
+    /// The record of the current game, used to export the game as a GIF.
+    record: GameRecord<Coord>,
+
+    /// The history of the current game, if the user has clicked a move in the list of moves to
+    /// see the position after it. The position is only a preview, and the game carries on
+    /// underneath it until the user [plays from there](Self::play_from_history).
+    history: Option<History<Board>>,
+
+    /// The arrows and highlighted cells drawn over the board with the right mouse button.
+    drawings: BoardDrawings<Coord>,
+
+    /// The check for the first losing mistake of each shape, which starts when the game ends. See
+    /// [`start_blunder_check`](Self::start_blunder_check).
+    blunder_check: BlunderCheck<Coord>,
+
+    /// Whether we're showing the dialog to confirm restarting a game in progress. See
+    /// [`request_restart`](Self::request_restart).
+    confirming_restart: bool,
+
+    /// Whether the app is in kiosk mode, which hides the settings and export buttons and restarts
+    /// the game automatically. See [`LaunchOptions::kiosk`].
+    kiosk: bool,
+
+    /// Whether the app is in presentation mode, which hides all the buttons and shows a big turn
+    /// indicator, bigger pieces, and an arrow to the last move. See
+    /// [`TTTVariantApp::set_presentation_mode`].
+    presenting: bool,
+
+    /// When the current turn started, so that the [`record`](Self::record) can time each move.
+    turn_started_at: Instant,
+
+    /// When the current game ended, if it has. Used to restart automatically in kiosk mode.
+    game_ended_at: Option<Instant>,
+
+    /// The subscribers to the [`GameEvent`]s of this app. See
+    /// [`subscribe_to_events`](Self::subscribe_to_events).
+    events: EventBus<Coord>,
+
+    /// The `tracing` span of the current game, which the spans of AI moves are children of.
+    game_span: tracing::Span,
 }
 
 impl Default for NormalTTTApp {
@@ -78,50 +163,614 @@ impl Default for NormalTTTApp {
 impl NormalTTTApp {
     /// Create a new app with the given config.
     ///
-    /// If [`NormalConfig::player_plays_first`] is false, then we also start an AI move in the
-    /// background by calling [`send_move_after_delay`].
+    /// See [`start_game`](Self::start_game).
     fn new_with_config(config: NormalConfig) -> Self {
-        let (mv_tx, mv_rx) = mpsc::channel();
+        let (explanation_tx, explanation_rx) = mpsc::channel();
 
-        let board = Board::new(config.player_shape.other());
-        let waiting_on_move = config.playing_ai && !config.player_plays_first;
+        let mut app = Self {
+            config,
+            game_settings: config.game_settings(),
+            profiles: Profiles::new(config),
+            save_slots: SaveSlots::default(),
+            showing_settings_window: false,
+            board: Board::new(config.player_shape.other()),
+            active_shape: config.player_shape,
+            ai_task: None,
+            generation: 0,
+            ai_moves: MoveChannel::default(),
+            heatmap: None,
+            explanation: None,
+            explanation_tx,
+            explanation_rx,
+            record: GameRecord::new(config.player_shape),
+            history: None,
+            drawings: BoardDrawings::default(),
+            blunder_check: BlunderCheck::default(),
+            confirming_restart: false,
+            kiosk: false,
+            presenting: false,
+            turn_started_at: Instant::now(),
+            game_ended_at: None,
+            events: EventBus::new(),
+            game_span: tracing::Span::none(),
+        };
 
-        let active_shape = if waiting_on_move && config.playing_ai {
-            send_move_after_delay(board.clone(), mv_tx.clone());
-            config.player_shape.other()
+        app.start_game();
+        app
+    }
+
+    /// Reset the board to start a new game with the current config, including its
+    /// [`game_settings`](Self::game_settings).
+    ///
+    /// If [`NormalConfig::chaos_start`] is set, then the board starts from a random balanced
+    /// position instead of being empty, with the moves that reach it in the
+    /// [`record`](Self::record).
+    ///
+    /// If it's the AI's turn, like when [`NormalConfig::player_plays_first`] is false, then we also
+    /// start an AI move in the background by calling [`start_ai_move`](Self::start_ai_move).
+    fn start_game(&mut self) {
+        self.start_game_with_settings(self.config.game_settings());
+    }
+
+    /// Start a new game like [`start_game`](Self::start_game), but with the given game settings
+    /// instead of the ones from the config, which is left alone.
+    fn start_game_with_settings(&mut self, game_settings: NormalGameSettings) {
+        self.game_settings = game_settings;
+        let ai_plays_first =
+            self.game_settings.playing_ai && !self.game_settings.player_plays_first;
+        self.active_shape = if ai_plays_first {
+            self.game_settings.player_shape.other()
         } else {
-            config.player_shape
+            self.game_settings.player_shape
         };
+        self.board =
+            Board::new_with_first_shape(self.game_settings.player_shape.other(), self.active_shape);
+        self.game_span = tracing::info_span!(
+            "game",
+            variant = "normal",
+            generation = self.generation,
+            playing_ai = self.game_settings.playing_ai,
+            ai_player = ?self.config.ai_player
+        );
+        self.record = GameRecord::new(self.active_shape);
+        self.history = None;
+        self.drawings.clear();
+        self.blunder_check.cancel();
+        self.ai_task = None;
+        self.explanation = None;
+        self.turn_started_at = Instant::now();
+        self.game_ended_at = None;
 
-        Self {
-            config,
-            showing_settings_window: false,
-            board,
-            active_shape,
-            waiting_on_move,
-            mv_tx,
-            mv_rx,
+        if self.game_settings.chaos_start {
+            let (board, moves) = random_start(
+                &self.board,
+                CHAOS_PLIES,
+                &mut rand::thread_rng(),
+                |board| board.is_valid_position().is_ok(),
+                Board::is_balanced,
+            );
+            self.board = board;
+            self.record.moves = moves;
+            self.active_shape = self.board.to_move();
+        }
+
+        self.update_crash_context();
+
+        if self.ai_shape() == Some(self.active_shape) {
+            self.start_ai_move();
         }
     }
 
     /// Update the interior state of the app with the current config.
     ///
-    /// See [`Self::new_with_config`]
+    /// Any AI move currently being computed is cancelled, and the
+    /// [`generation`](Self::generation) is incremented so that any stale AI moves will be ignored.
     fn restart_game(&mut self) {
-        *self = Self::new_with_config(self.config);
+        self.restart_game_with_settings(self.config.game_settings());
+    }
+
+    /// Start a new game like [`restart_game`](Self::restart_game), but with the given game
+    /// settings instead of the ones from the config.
+    fn restart_game_with_settings(&mut self, game_settings: NormalGameSettings) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        self.showing_settings_window = false;
+        self.start_game_with_settings(game_settings);
+        self.events.emit(GameEvent::GameRestarted);
+    }
+
+    /// Start a rematch of the game that just ended with the sides swapped, so that the player
+    /// takes the other shape and the other turn to move first.
+    ///
+    /// The swap only applies to the rematch, so the config isn't changed, and the game after the
+    /// rematch goes back to the settings in the config.
+    fn rematch_swapped(&mut self) {
+        let mut game_settings = self.game_settings;
+        game_settings.player_shape = game_settings.player_shape.other();
+        game_settings.player_plays_first = !game_settings.player_plays_first;
+        self.restart_game_with_settings(game_settings);
+    }
+
+    /// Restart the game, unless there's a game in progress and
+    /// [`NormalConfig::confirm_discard`] is set, in which case we ask for confirmation first.
+    fn request_restart(&mut self) {
+        if self.config.confirm_discard && self.game_in_progress() {
+            self.confirming_restart = true;
+        } else {
+            self.restart_game();
+        }
+    }
+
+    /// Show the dialog to confirm restarting a game in progress if we're waiting for
+    /// confirmation, and restart the game if it's confirmed.
+    fn draw_restart_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirming_restart {
+            return;
+        }
+
+        if let Some(discard) = show_discard_dialog(ctx, &mut self.config.confirm_discard) {
+            self.confirming_restart = false;
+            if discard {
+                self.restart_game();
+            }
+        }
+    }
+
+    /// Check if the player can [switch sides](Self::switch_sides) with the AI, which is only
+    /// possible in a game against the AI which is still going.
+    fn can_switch_sides(&self) -> bool {
+        self.ai_shape().is_some() && self.board.get_winner() == GameResult::Ongoing
+    }
+
+    /// Hand the player's position to the AI and continue as the other shape.
+    ///
+    /// Any AI move currently being computed is cancelled, and the
+    /// [`generation`](Self::generation) is incremented so that it will be ignored. If it's then
+    /// the AI's turn, the AI starts a new move straight away.
+    fn switch_sides(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        self.game_settings.player_shape = self.game_settings.player_shape.other();
+        self.board.ai_shape = self.game_settings.player_shape.other();
+        self.explanation = None;
+        self.update_crash_context();
+        tracing::info!(
+            parent: &self.game_span,
+            player_shape = ?self.game_settings.player_shape,
+            "switched sides"
+        );
+
+        if self.ai_shape() == Some(self.active_shape) {
+            self.start_ai_move();
+        }
+    }
+
+    /// In kiosk mode, restart the game once [`KIOSK_RESTART_DELAY`] has passed since it ended.
+    fn kiosk_auto_restart(&mut self) {
+        if self.kiosk
+            && self
+                .game_ended_at
+                .map_or(false, |time| time.elapsed() >= KIOSK_RESTART_DELAY)
+        {
+            self.restart_game();
+        }
+    }
+
+    /// Subscribe to the [`GameEvent`]s of this app, which will be sent down the returned receiver.
+    pub fn subscribe_to_events(&mut self) -> mpsc::Receiver<GameEvent<Coord>> {
+        self.events.subscribe()
+    }
+
+    /// Update the state of the game which is attached to crash reports. See
+    /// [`crash_report`](crate::shared::crash_report).
+    fn update_crash_context(&self) {
+        crash_report::set_context(&CrashContext {
+            variant: "normal",
+            board: &self.board,
+            config: &self.config,
+            record: &self.record,
+        });
+    }
+
+    /// Check if the AI is currently stopped from taking the center by the
+    /// [`NormalConfig::ai_avoids_center`] handicap, which lasts until the AI has made a move.
+    fn ai_avoids_center(&self) -> bool {
+        self.game_settings.playing_ai
+            && self.game_settings.ai_avoids_center
+            && !self
+                .board
+                .cells()
+                .iter()
+                .flatten()
+                .any(|&cell| cell == Some(self.board.ai_shape))
+    }
+
+    /// Start computing an AI move in the background with an [`AiTask`].
+    ///
+    /// The move is computed with [`Board::generate_ai_move_iterative`], which reports its
+    /// intermediate results as the progress of the task, and the move is sent down
+    /// [`ai_moves`](Self::ai_moves) after 200ms. If the AI has to [avoid the
+    /// center](Self::ai_avoids_center), then the move is computed with
+    /// [`Board::generate_ai_move_avoiding`] instead.
+    ///
+    /// If [`NormalConfig::explain_ai_moves`] is enabled, then the explanation of the move is sent
+    /// down [`explanation_tx`](Self::explanation_tx) as soon as the move has been found.
+    ///
+    /// Nothing is started if the game is already over.
+    fn start_ai_move(&mut self) {
+        if self.board.get_winner() != GameResult::Ongoing {
+            return;
+        }
+
+        let mut board = self.board.clone();
+        board.draw_score = self.config.draw_score;
+        let generation = self.generation;
+        let avoid_center = self.ai_avoids_center();
+        let personality = self.config.personality;
+        let draw_score = self.config.draw_score;
+        let explanation_tx = self
+            .config
+            .explain_ai_moves
+            .then(|| self.explanation_tx.clone());
+
+        // The lowest levels of the ladder play like the random and greedy AIs, and the rest use
+        // minimax but blunder less often at each level
+        let (ai_player, blunder_probability) =
+            match (self.config.ai_player, self.config.ladder.policy()) {
+                (NormalAiPlayer::Ladder, LadderPolicy::Random) => (NormalAiPlayer::Random, 0.),
+                (NormalAiPlayer::Ladder, LadderPolicy::Greedy) => (NormalAiPlayer::Greedy, 0.),
+                (NormalAiPlayer::Ladder, LadderPolicy::Search { strength }) => (
+                    NormalAiPlayer::Ladder,
+                    (1. - strength) * MAX_BLUNDER_PROBABILITY,
+                ),
+                (ai_player, _) => (
+                    ai_player,
+                    self.config
+                        .adaptive
+                        .interpolate(MAX_BLUNDER_PROBABILITY, 0.),
+                ),
+            };
+        let game_span = self.game_span.clone();
+
+        self.ai_task = Some(AiTask::spawn_with_progress(
+            AI_MOVE_DELAY,
+            self.ai_moves.sender(),
+            move |deadline, progress_tx| {
+                let span = tracing::info_span!(
+                    parent: &game_span,
+                    "ai_move",
+                    position_hash = board.position_hash(),
+                    ai_player = ?ai_player,
+                    avoid_center
+                );
+                let _guard = span.enter();
+
+                let mv = match ai_player {
+                    // There's at most one shape on the board before the AI's first move, so the
+                    // greedy AI has nothing to win or block and plays randomly anyway
+                    NormalAiPlayer::Random | NormalAiPlayer::Greedy if avoid_center => board
+                        .legal_moves()
+                        .filter(|&coord| coord != (1, 1))
+                        .collect::<Vec<_>>()
+                        .choose(&mut rand::thread_rng())
+                        .copied(),
+                    NormalAiPlayer::Minimax | NormalAiPlayer::Adaptive | NormalAiPlayer::Ladder
+                        if avoid_center =>
+                    {
+                        board.generate_ai_move_avoiding(&[(1, 1)])
+                    }
+                    NormalAiPlayer::Random => RandomBot.choose_move(&board),
+                    NormalAiPlayer::Greedy => GreedyBot.choose_move(&board),
+                    NormalAiPlayer::Adaptive | NormalAiPlayer::Ladder
+                        if rand::random::<f32>() < blunder_probability =>
+                    {
+                        RandomBot.choose_move(&board)
+                    }
+                    NormalAiPlayer::Minimax | NormalAiPlayer::Adaptive | NormalAiPlayer::Ladder => {
+                        MinimaxPlayer {
+                            deadline,
+                            progress_tx: Some(progress_tx),
+                            personality,
+                            seed: None,
+                            draw_score,
+                        }
+                        .choose_move(&board)
+                    }
+                };
+                tracing::info!(chosen = ?mv, "chose a move");
+
+                if let (Some(explanation_tx), Some(mv)) = (explanation_tx, mv) {
+                    // The random and greedy AIs don't consider any candidates
+                    let mut candidates = match ai_player {
+                        NormalAiPlayer::Minimax
+                        | NormalAiPlayer::Adaptive
+                        | NormalAiPlayer::Ladder => board.annotated_moves(),
+                        NormalAiPlayer::Random | NormalAiPlayer::Greedy => vec![],
+                    };
+                    if avoid_center {
+                        candidates.retain(|candidate| candidate.mv != (1, 1));
+                    }
+                    let _ = explanation_tx
+                        .send((generation, MoveExplanation::new(&board, mv, candidates)));
+                }
+
+                (generation, mv)
+            },
+        ));
+    }
+
+    /// Check if we're currently waiting for the AI to make a move, which is exactly while there is
+    /// an [`ai_task`](Self::ai_task).
+    fn waiting_on_move(&self) -> bool {
+        self.ai_task.is_some()
+    }
+
+    /// Return the AI move for the current game if it has arrived, which is `Some(None)` if the AI
+    /// couldn't find a move.
+    ///
+    /// Any moves from previous [`generation`](Self::generation)s are discarded.
+    fn receive_ai_move(&self) -> Option<Option<Coord>> {
+        self.ai_moves.receive(self.generation)
+    }
+
+    /// Play the AI move if it has arrived, and stop waiting for it even if the AI couldn't find
+    /// one.
+    fn handle_ai_move(&mut self) {
+        if !self.waiting_on_move() {
+            return;
+        }
+
+        if let Some(mv) = self.receive_ai_move() {
+            self.ai_task = None;
+            if let Some((x, y)) = mv {
+                self.update_cell(x, y);
+            }
+        }
+    }
+
+    /// Update the [`explanation`](Self::explanation) with the most recent one for the current
+    /// game, if any have arrived.
+    ///
+    /// Any explanations from previous [`generation`](Self::generation)s are discarded.
+    fn receive_explanation(&mut self) {
+        let generation = self.generation;
+
+        if let Some((_, explanation)) = self
+            .explanation_rx
+            .try_iter()
+            .filter(|&(g, _)| g == generation)
+            .last()
+        {
+            self.explanation = Some(explanation);
+        }
+    }
+
+    /// Return every empty cell along with the chance that the
+    /// [`active_shape`](Self::active_shape) wins by playing there, from 0 to 1.
+    ///
+    /// The chances are computed from [`Board::evaluate_moves`], and are cached until the board
+    /// changes.
+    fn heatmap(&mut self) -> &[(Coord, f32)] {
+        let up_to_date = matches!(
+            &self.heatmap,
+            Some((board, shape, _)) if *board == self.board && *shape == self.active_shape
+        );
+
+        if !up_to_date {
+            let sign = if self.active_shape == self.board.ai_shape {
+                1.
+            } else {
+                -1.
+            };
+
+            let win_chances = self
+                .board
+                .evaluate_moves(self.active_shape)
+                .into_iter()
+                .map(|(coord, score)| (coord, (sign * score as f32 + 100.) / 200.))
+                .collect();
+
+            self.heatmap = Some((self.board.clone(), self.active_shape, win_chances));
+        }
+
+        match &self.heatmap {
+            Some((_, _, win_chances)) => win_chances.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Return the shape of the AI, if we're playing against it.
+    fn ai_shape(&self) -> Option<CellShape> {
+        self.game_settings
+            .playing_ai
+            .then(|| self.game_settings.player_shape.other())
+    }
+
+    /// Describe the state of the game in words for the status bar, like whose turn it is and
+    /// which shape the AI is.
+    pub fn status_text(&self) -> String {
+        status_text(
+            self.board.get_winner(),
+            self.active_shape,
+            self.ai_shape(),
+            None,
+        )
     }
 
     /// Update the board to reflect a cell being clicked.
     ///
     /// This method uses [`active_shape`](NormalTTTApp::active_shape) as the shape to place in the cell.
+    /// Moves are ignored once the game is over, so that the end of the game is only handled once.
     fn update_cell(&mut self, x: usize, y: usize) {
-        if x > 2 || y > 2 {
+        if self.board.get_winner() != GameResult::Ongoing {
             return;
         }
 
-        if self.board.cells[x][y].is_none() {
-            self.board.cells[x][y] = Some(self.active_shape);
+        if self.board.make_move((x, y), self.active_shape).is_ok() {
+            self.record
+                .push_move((x, y), self.turn_started_at.elapsed());
+            self.turn_started_at = Instant::now();
+            self.update_crash_context();
+            tracing::debug!(
+                parent: &self.game_span,
+                mv = ?(x, y),
+                shape = ?self.active_shape,
+                "move played"
+            );
+            self.events.emit(GameEvent::MovePlayed {
+                mv: (x, y),
+                shape: self.active_shape,
+            });
             self.active_shape = self.active_shape.other();
+
+            let result = self.board.get_winner();
+            if result != GameResult::Ongoing {
+                tracing::info!(
+                    parent: &self.game_span,
+                    result = ?result,
+                    moves = ?self.record.moves,
+                    "game ended"
+                );
+                self.events.emit(GameEvent::GameEnded(result));
+                self.game_ended_at = Some(Instant::now());
+
+                // Nobody is there to look at the mistakes in kiosk mode
+                if !self.kiosk {
+                    self.start_blunder_check();
+                }
+
+                if self.game_settings.playing_ai
+                    && self.config.ai_player == NormalAiPlayer::Adaptive
+                {
+                    self.config
+                        .adaptive
+                        .record_result(result, self.game_settings.player_shape);
+                }
+
+                // A handicap would make it too easy to climb the ladder
+                if self.game_settings.playing_ai
+                    && self.config.ai_player == NormalAiPlayer::Ladder
+                    && !self.game_settings.ai_avoids_center
+                {
+                    self.config
+                        .ladder
+                        .record_result(result, self.game_settings.player_shape);
+                }
+            }
+        }
+    }
+
+    /// Play the player's move in the clicked cell, and then start the AI move if we're playing
+    /// against the AI and the game is still going.
+    fn click_cell(&mut self, x: usize, y: usize) {
+        self.update_cell(x, y);
+
+        if self.game_settings.playing_ai {
+            self.start_ai_move();
+        }
+    }
+
+    /// Load the given saved game by replaying its moves with the game settings that it was started
+    /// with, and return whether it was loaded. The config itself isn't changed.
+    ///
+    /// If any of the moves are illegal, then nothing is loaded. If it's then the AI's turn, the AI
+    /// starts a move straight away.
+    fn load_saved_game(&mut self, saved: &SavedGame<NormalConfig, Coord>) -> bool {
+        let game_settings = saved.config.game_settings();
+        let mut positions = saved.record.replay(Board::new_with_first_shape(
+            game_settings.player_shape.other(),
+            saved.record.first_shape,
+        ));
+        if positions.len() != saved.record.moves.len() + 1 {
+            return false;
+        }
+
+        // The moves of a chaos start are already in the record
+        self.restart_game_with_settings(NormalGameSettings {
+            chaos_start: false,
+            ..game_settings
+        });
+
+        self.continue_from_position(
+            positions
+                .pop()
+                .expect("There should always be a starting position"),
+            saved.record.clone(),
+        );
+        true
+    }
+
+    /// Return the empty board that the current game started from.
+    fn starting_position(&self) -> Board {
+        Board::new_with_first_shape(
+            self.game_settings.player_shape.other(),
+            self.record.first_shape,
+        )
+    }
+
+    /// Show the position after the given number of moves of the current game as a preview.
+    fn jump_to_ply(&mut self, ply: usize) {
+        if self.history.is_none() {
+            self.history = Some(History::new(&self.record, self.starting_position()));
+        }
+        if let Some(history) = &mut self.history {
+            history.set_ply(ply);
+        }
+    }
+
+    /// Start looking for the first losing mistake of each shape in the background, by evaluating
+    /// every position of the game perfectly with minimax.
+    fn start_blunder_check(&mut self) {
+        let positions = self.record.replay(self.starting_position());
+        self.blunder_check
+            .start(&self.record, positions, |board: &Board| {
+                match board.evaluate_position(board.to_move()) {
+                    0 => None,
+                    eval if eval > 0 => Some(board.ai_shape),
+                    _ => Some(board.ai_shape.other()),
+                }
+            });
+    }
+
+    /// Fork the game at the position being previewed, throwing away the moves after it, and carry
+    /// on playing from there. See [`history`](Self::history).
+    fn play_from_history(&mut self) {
+        if let Some(history) = self.history.take() {
+            let (board, record) = history.fork(&self.record);
+            self.continue_from_position(board, record);
+        }
+    }
+
+    /// Carry on the game from the given position, reached by the moves in the given record.
+    ///
+    /// Any AI move currently being computed is thrown away, and if it's then the AI's turn, the
+    /// AI starts a new move straight away.
+    fn continue_from_position(&mut self, board: Board, record: GameRecord<Coord>) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+        self.explanation = None;
+
+        self.board = board;
+        self.active_shape = self.board.to_move();
+        self.record = record;
+        self.history = None;
+        self.blunder_check.cancel();
+        self.turn_started_at = Instant::now();
+        self.game_ended_at = None;
+        self.update_crash_context();
+
+        if self.board.get_winner() == GameResult::Ongoing
+            && self.ai_shape() == Some(self.active_shape)
+        {
+            self.start_ai_move();
         }
     }
 }
@@ -131,16 +780,41 @@ impl TTTVariantApp for NormalTTTApp {
     where
         Self: Sized,
     {
-        let config = storage.map_or_else(NormalConfig::default, |storage| {
-            eframe::get_value(storage, "normal_config").unwrap_or_default()
-        });
+        let VariantSettings { config, profiles } = VariantSettings::load(storage, "normal");
 
-        Self::new_with_config(config)
+        Self {
+            profiles,
+            save_slots: SaveSlots::load(storage, "normal"),
+            ..Self::new_with_config(config)
+        }
+    }
+
+    /// Restart the game in kiosk mode once it's been over for long enough, and play the AI move
+    /// if it has arrived.
+    fn tick(&mut self) {
+        self.kiosk_auto_restart();
+        self.handle_ai_move();
     }
 
     /// Show the app itself.
     fn show_ui(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.tick();
+
+        // Kiosk mode and presentation mode have no settings, so they don't need the side panel
+        let wide_layout = !self.kiosk && !self.presenting && is_wide_layout(ctx);
+        if wide_layout {
+            self.draw_side_panel(ctx);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            // Presentation mode has no buttons, just a big turn indicator above the board
+            if self.presenting {
+                show_turn_indicator(ui, &self.status_text());
+                let board_rect = centered_square_in_rect(ui.available_rect_before_wrap(), 0.95);
+                self.draw_board(ctx, ui, board_rect);
+                return;
+            }
+
             // Show the restart game and settings buttons
             ui.horizontal(|ui| {
                 use eframe::epaint::{FontFamily, FontId};
@@ -156,37 +830,195 @@ impl TTTVariantApp for NormalTTTApp {
                     .add(egui::Button::new("\u{27F3}").fill(Color32::TRANSPARENT))
                     .clicked()
                 {
-                    self.restart_game();
+                    self.request_restart();
                 }
 
-                if ui
-                    .add(
-                        egui::Button::new("\u{2699}").fill(if self.showing_settings_window {
-                            if ctx.style().visuals.dark_mode {
-                                Color32::from_rgb(0x00, 0x5C, 0x80)
+                if !wide_layout
+                    && !self.kiosk
+                    && ui
+                        .add(
+                            egui::Button::new("\u{2699}").fill(if self.showing_settings_window {
+                                if ctx.style().visuals.dark_mode {
+                                    Color32::from_rgb(0x00, 0x5C, 0x80)
+                                } else {
+                                    Color32::from_rgb(0x90, 0xD1, 0xFF)
+                                }
                             } else {
-                                Color32::from_rgb(0x90, 0xD1, 0xFF)
-                            }
-                        } else {
-                            Color32::TRANSPARENT
-                        }),
-                    )
-                    .clicked()
+                                Color32::TRANSPARENT
+                            }),
+                        )
+                        .clicked()
                 {
                     self.showing_settings_window = !self.showing_settings_window;
                 }
+
+                if !self.kiosk {
+                    show_export_buttons(ui, |size| normal_board_shapes(&self.board, size));
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if self.board.get_winner() != GameResult::Ongoing {
+                        crate::shared::export::show_gif_export_button(ui, |size| {
+                            crate::shared::export::normal_game_frames(&self.record, size)
+                        });
+                    }
+                }
+
+                if !self.kiosk
+                    && ui
+                        .add(egui::Button::new("\u{1F4BE}").fill(Color32::TRANSPARENT))
+                        .on_hover_text("Save and load games")
+                        .clicked()
+                {
+                    self.save_slots.open = !self.save_slots.open;
+                }
+
+                if !self.kiosk
+                    && ui
+                        .add_enabled(
+                            self.can_switch_sides(),
+                            egui::Button::new("\u{21C4}").fill(Color32::TRANSPARENT),
+                        )
+                        .on_hover_text("Switch sides with the AI")
+                        .clicked()
+                {
+                    self.switch_sides();
+                }
+
+                self.drawings.show_clear_button(ui);
+
+                if self.ai_avoids_center() {
+                    ui.label("Handicap: the AI can't take the center with its first move");
+                }
+
+                if let Some(task) = &mut self.ai_task {
+                    ui.label(match task.progress() {
+                        Some(&SearchProgress {
+                            depth,
+                            best_move: (x, y),
+                        }) => format!("Thinking\u{2026} (depth {depth}, considering ({x}, {y}))"),
+                        None => "Thinking\u{2026}".to_string(),
+                    });
+                }
             });
 
-            self.draw_board(ctx, ui, centered_square_in_rect(ui.clip_rect(), 0.9));
+            ui.label(self.status_text());
+            if let Some(history) = &self.history {
+                let (ply, last_ply) = (history.ply(), history.last_ply());
+                ui.horizontal(|ui| {
+                    ui.label(format!("Showing move {ply} of {last_ply}"));
+                    if ply < last_ply
+                        && ui
+                            .button("Play from here")
+                            .on_hover_text("Throw away the later moves and play on from here")
+                            .clicked()
+                    {
+                        self.play_from_history();
+                    }
+                    if ui.button("Back to the game").clicked() {
+                        self.history = None;
+                    }
+                });
+            }
+            if self.board.get_winner() != GameResult::Ongoing {
+                self.record.show_timing_stats(ui);
+                if let Some(ply) = self.blunder_check.show(ui, cell_name) {
+                    self.jump_to_ply(ply);
+                }
+
+                if !self.kiosk
+                    && ui
+                        .button("Rematch (swap sides)")
+                        .on_hover_text("Play again as the other shape, and swap who moves first")
+                        .clicked()
+                {
+                    self.rematch_swapped();
+                }
+            }
+
+            let board_rect = if wide_layout {
+                centered_square_in_rect(ui.available_rect_before_wrap(), 0.95)
+            } else {
+                centered_square_in_rect(ui.clip_rect(), 0.9)
+            };
+            self.draw_board(ctx, ui, board_rect);
         });
 
-        if self.showing_settings_window {
+        self.receive_explanation();
+        if let Some(explanation) = &self.explanation {
+            // Wait until the move is actually shown to explain it
+            if self.config.explain_ai_moves && !self.waiting_on_move() && !self.presenting {
+                explanation.show_window(ctx, cell_name);
+            }
+        }
+
+        if !self.kiosk && !self.presenting {
+            show_file_drop_hint(ctx, "Drop a game file to load it");
+            let dropped = self.save_slots.receive_dropped_file(ctx);
+            let loaded = self.save_slots.show_window(ctx, || SavedGame {
+                config: self.config.with_game_settings(self.game_settings),
+                record: self.record.clone(),
+            });
+
+            // There's no analysis mode here, so games from files are loaded like any other
+            if let Some(LoadedGame::Slot(saved) | LoadedGame::File(saved)) =
+                loaded.or(dropped.map(LoadedGame::File))
+            {
+                self.load_saved_game(&saved);
+            }
+        }
+
+        if self.showing_settings_window && !wide_layout && !self.presenting {
             self.draw_settings_window(ctx);
         }
+
+        self.draw_restart_dialog(ctx);
+    }
+
+    fn game_in_progress(&self) -> bool {
+        !self.record.moves.is_empty() && self.board.get_winner() == GameResult::Ongoing
+    }
+
+    fn confirm_discard_mut(&mut self) -> &mut bool {
+        &mut self.config.confirm_discard
+    }
+
+    fn short_status(&self) -> String {
+        short_status_text(self.board.get_winner(), self.active_shape, self.ai_shape())
+    }
+
+    fn apply_launch_options(&mut self, options: &LaunchOptions) {
+        if let Some(playing_ai) = options.playing_ai {
+            self.config.playing_ai = playing_ai;
+        }
+        if let Some(difficulty) = options.difficulty {
+            self.config.ai_player = match difficulty {
+                Difficulty::Easy => NormalAiPlayer::Random,
+                Difficulty::Medium => NormalAiPlayer::Greedy,
+                Difficulty::Hard => NormalAiPlayer::Minimax,
+                Difficulty::Adaptive => NormalAiPlayer::Adaptive,
+            };
+        }
+
+        self.kiosk = options.kiosk;
+
+        self.restart_game();
+    }
+
+    fn set_presentation_mode(&mut self, presenting: bool) {
+        self.presenting = presenting;
+    }
+
+    fn subscribe_to_results(&mut self) -> Option<mpsc::Receiver<GameResult>> {
+        Some(self.events.subscribe_to_results())
     }
 
     fn save_config(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, "normal_config", &self.config);
+        let mut settings = VariantSettings {
+            config: self.config,
+            profiles: self.profiles.clone(),
+        };
+        settings.save(storage, "normal");
+        self.save_slots.save(storage, "normal");
     }
 }
 
@@ -227,4 +1059,415 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn adaptive_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            ai_player: NormalAiPlayer::Adaptive,
+            ..NormalConfig::default()
+        });
+
+        // The player wins, so the AI should get stronger
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+        assert_eq!(app.config.adaptive.games_played, 1);
+        assert!(app.config.adaptive.strength > 0.5);
+    }
+
+    #[test]
+    fn adaptive_click_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            ai_player: NormalAiPlayer::Adaptive,
+            ..NormalConfig::default()
+        });
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            app.update_cell(x, y);
+        }
+
+        // The player wins with a click, and no AI move is started after the game has ended
+        app.click_cell(2, 0);
+        assert!(app.ai_task.is_none());
+        let strength = app.config.adaptive.strength;
+        assert_eq!(app.config.adaptive.games_played, 1);
+
+        // Even if an AI move arrives, it's not played, so the result is only recorded once
+        app.ai_task = Some(AiTask::placeholder());
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((2, 1))))
+            .unwrap();
+        app.tick();
+        assert!(app.board.cells()[2][1].is_none());
+        assert_eq!(app.config.adaptive.games_played, 1);
+        assert_eq!(app.config.adaptive.strength, strength);
+    }
+
+    #[test]
+    fn ladder_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            ai_player: NormalAiPlayer::Ladder,
+            ..NormalConfig::default()
+        });
+
+        // The player wins, so they climb a level
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+        assert_eq!(app.config.ladder.level, 2);
+
+        // Games with a handicap don't count
+        app.config.ai_avoids_center = true;
+        app.restart_game();
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+        assert_eq!(app.config.ladder.level, 2);
+        assert_eq!(app.config.ladder.games_played(), 1);
+    }
+
+    #[test]
+    fn ladder_click_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            ai_player: NormalAiPlayer::Ladder,
+            ..NormalConfig::default()
+        });
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            app.update_cell(x, y);
+        }
+
+        // The player's winning click doesn't start an AI move after the game has ended
+        app.click_cell(2, 0);
+        assert!(app.ai_task.is_none());
+        assert!(!app.waiting_on_move());
+        app.handle_ai_move();
+        assert_eq!(app.config.ladder.level, 2);
+        assert_eq!(app.config.ladder.games_played(), 1);
+
+        // And moves after the end of the game are ignored
+        let board = app.board.clone();
+        app.update_cell(2, 1);
+        assert_eq!(app.board, board);
+        assert_eq!(app.record.moves.len(), 5);
+        assert_eq!(app.config.ladder.level, 2);
+    }
+
+    #[test]
+    fn apply_launch_options_test() {
+        let mut app = NormalTTTApp::default();
+        app.apply_launch_options(&LaunchOptions {
+            playing_ai: Some(false),
+            difficulty: Some(Difficulty::Easy),
+            ..LaunchOptions::default()
+        });
+        assert!(!app.config.playing_ai);
+        assert_eq!(app.config.ai_player, NormalAiPlayer::Random);
+        assert!(!app.waiting_on_move());
+    }
+
+    #[test]
+    fn game_settings_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig::default());
+        assert_eq!(app.ai_shape(), Some(CellShape::O));
+
+        // Game-defining settings only apply to the next game
+        app.config.player_shape = CellShape::O;
+        app.config.show_heatmap = true;
+        assert_eq!(app.ai_shape(), Some(CellShape::O));
+        assert_ne!(app.config.game_settings(), app.game_settings);
+
+        app.restart_game();
+        assert_eq!(app.ai_shape(), Some(CellShape::X));
+        assert_eq!(app.config.game_settings(), app.game_settings);
+
+        // Including whether we're playing against the AI at all
+        app.config.playing_ai = false;
+        assert_eq!(app.ai_shape(), Some(CellShape::X));
+        app.restart_game();
+        assert_eq!(app.ai_shape(), None);
+        assert!(!app.can_switch_sides());
+    }
+
+    #[test]
+    fn chaos_start_test() {
+        let app = NormalTTTApp::new_with_config(NormalConfig {
+            playing_ai: false,
+            chaos_start: true,
+            ..NormalConfig::default()
+        });
+
+        assert!(CHAOS_PLIES.contains(&app.record.moves.len()));
+        assert!(app.board.is_balanced());
+        assert_eq!(app.active_shape, app.board.to_move());
+        assert_eq!(
+            app.record.replay(Board::new(CellShape::O)).last(),
+            Some(&app.board)
+        );
+
+        // The AI moves straight away if it's the AI's turn
+        let app = NormalTTTApp::new_with_config(NormalConfig {
+            chaos_start: true,
+            ..NormalConfig::default()
+        });
+        assert_eq!(
+            app.waiting_on_move(),
+            app.ai_shape() == Some(app.board.to_move())
+        );
+    }
+
+    #[test]
+    fn request_restart_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            playing_ai: false,
+            ..NormalConfig::default()
+        });
+
+        // There's nothing to discard yet
+        app.request_restart();
+        assert!(!app.confirming_restart);
+
+        app.update_cell(1, 1);
+        assert!(app.game_in_progress());
+        app.request_restart();
+        assert!(app.confirming_restart);
+        assert_eq!(app.record.moves, vec![(1, 1)]);
+
+        app.confirming_restart = false;
+        app.config.confirm_discard = false;
+        app.request_restart();
+        assert!(!app.confirming_restart);
+        assert!(app.record.moves.is_empty());
+    }
+
+    #[test]
+    fn kiosk_auto_restart_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            playing_ai: false,
+            ..NormalConfig::default()
+        });
+        app.apply_launch_options(&LaunchOptions {
+            kiosk: true,
+            ..LaunchOptions::default()
+        });
+
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+        assert!(app.game_ended_at.is_some());
+
+        // The game shouldn't restart straight away
+        app.kiosk_auto_restart();
+        assert_ne!(app.board.get_winner(), GameResult::Ongoing);
+
+        app.game_ended_at = Some(Instant::now() - KIOSK_RESTART_DELAY);
+        app.kiosk_auto_restart();
+        assert_eq!(app.board.get_winner(), GameResult::Ongoing);
+        assert!(app.record.moves.is_empty());
+        assert_eq!(app.game_ended_at, None);
+    }
+
+    #[test]
+    fn switch_sides_test() {
+        let mut app = NormalTTTApp::default();
+        app.update_cell(1, 1);
+        app.update_cell(0, 0);
+        let old_generation = app.generation;
+
+        // It's the player's turn as X, so the AI takes over as X and moves straight away
+        app.switch_sides();
+        assert_ne!(app.generation, old_generation);
+        assert_eq!(app.ai_shape(), Some(CellShape::X));
+        assert_eq!(app.board.ai_shape, CellShape::X);
+        assert_eq!(app.active_shape, CellShape::X);
+        assert!(app.waiting_on_move());
+
+        // Switching back cancels the AI move and gives the turn back to the player
+        let old_generation = app.generation;
+        app.switch_sides();
+        assert_ne!(app.generation, old_generation);
+        assert_eq!(app.ai_shape(), Some(CellShape::O));
+        assert_eq!(app.board.ai_shape, CellShape::O);
+        assert!(!app.waiting_on_move());
+        assert!(app.ai_task.is_none());
+
+        // The game is over, so there's nothing to switch
+        let mut app = NormalTTTApp::default();
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+        assert!(!app.can_switch_sides());
+    }
+
+    #[test]
+    fn load_saved_game_test() {
+        let mut app = NormalTTTApp::default();
+        app.update_cell(1, 1);
+        app.update_cell(0, 0);
+        let saved = SavedGame {
+            config: app.config.with_game_settings(app.game_settings),
+            record: app.record.clone(),
+        };
+        let board = app.board.clone();
+
+        app.restart_game();
+        assert!(app.load_saved_game(&saved));
+        assert_eq!(app.board, board);
+        assert_eq!(app.active_shape, CellShape::X);
+        assert_eq!(app.record.moves, vec![(1, 1), (0, 0)]);
+        assert!(!app.waiting_on_move());
+
+        // A game with an illegal move isn't loaded
+        let mut illegal = saved.clone();
+        illegal.record.moves.push((1, 1));
+        app.restart_game();
+        assert!(!app.load_saved_game(&illegal));
+        assert!(app.record.moves.is_empty());
+    }
+
+    #[test]
+    fn play_from_history_test() {
+        let mut app = NormalTTTApp::new_with_config(NormalConfig {
+            playing_ai: false,
+            ..NormalConfig::default()
+        });
+        for (x, y) in [(1, 1), (0, 0), (2, 2)] {
+            app.update_cell(x, y);
+        }
+        let board = app.board.clone();
+
+        // Previewing a position leaves the game alone
+        app.jump_to_ply(1);
+        assert_eq!(app.history.as_ref().unwrap().ply(), 1);
+        assert_eq!(app.board, board);
+
+        app.play_from_history();
+        assert!(app.history.is_none());
+        assert_eq!(app.record.moves, vec![(1, 1)]);
+        assert_eq!(app.active_shape, CellShape::O);
+        assert!(app.board.cells()[1][1].is_some());
+        assert_eq!(app.board.cells()[0][0], None);
+    }
+
+    #[test]
+    fn rematch_swapped_test() {
+        let mut app = NormalTTTApp::default();
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            app.update_cell(x, y);
+        }
+
+        // The player was X and moved first, so the AI is X and moves first in the rematch
+        app.rematch_swapped();
+        assert_eq!(app.ai_shape(), Some(CellShape::X));
+        assert_eq!(app.active_shape, CellShape::X);
+        assert!(app.waiting_on_move());
+        assert_eq!(app.config.player_shape, CellShape::X);
+        assert!(app.config.player_plays_first);
+
+        // The game after the rematch goes back to the config
+        app.restart_game();
+        assert_eq!(app.ai_shape(), Some(CellShape::O));
+        assert_eq!(app.active_shape, CellShape::X);
+        assert!(!app.waiting_on_move());
+    }
+
+    #[test]
+    fn events_test() {
+        let mut app = NormalTTTApp::default();
+        let events = app.subscribe_to_events();
+
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            app.update_cell(x, y);
+        }
+        app.update_cell(1, 1);
+        app.update_cell(2, 0);
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                GameEvent::MovePlayed {
+                    mv: (0, 0),
+                    shape: CellShape::X
+                },
+                GameEvent::MovePlayed {
+                    mv: (0, 1),
+                    shape: CellShape::O
+                },
+                GameEvent::MovePlayed {
+                    mv: (1, 0),
+                    shape: CellShape::X
+                },
+                GameEvent::MovePlayed {
+                    mv: (1, 1),
+                    shape: CellShape::O
+                },
+                GameEvent::MovePlayed {
+                    mv: (2, 0),
+                    shape: CellShape::X
+                },
+                GameEvent::GameEnded(GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0), (1, 0), (2, 0)]
+                }),
+            ]
+        );
+
+        app.restart_game();
+        assert_eq!(events.try_recv(), Ok(GameEvent::GameRestarted));
+    }
+
+    #[test]
+    fn tick_test() {
+        let mut app = NormalTTTApp::default();
+
+        // Nothing happens until the AI's move arrives
+        app.ai_task = Some(AiTask::placeholder());
+        app.tick();
+        assert!(app.waiting_on_move());
+
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((1, 1))))
+            .unwrap();
+        app.tick();
+        assert!(!app.waiting_on_move());
+        assert!(app.board.cells()[1][1].is_some());
+
+        // We stop waiting if the AI couldn't find a move
+        app.ai_task = Some(AiTask::placeholder());
+        app.ai_moves.sender().send((app.generation, None)).unwrap();
+        app.tick();
+        assert!(!app.waiting_on_move());
+    }
+
+    #[test]
+    fn receive_ai_move_test() {
+        let mut app = NormalTTTApp::default();
+        assert_eq!(app.receive_ai_move(), None);
+
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((1, 1))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), Some(Some((1, 1))));
+
+        let old_generation = app.generation;
+        app.restart_game();
+        assert_ne!(app.generation, old_generation);
+
+        app.ai_moves
+            .sender()
+            .send((old_generation, Some((0, 0))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), None);
+
+        app.ai_moves
+            .sender()
+            .send((old_generation, Some((0, 0))))
+            .unwrap();
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((2, 2))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), Some(Some((2, 2))));
+    }
 }
@@ -3,19 +3,59 @@
 use super::Coord;
 use crate::shared::{
     self,
-    board::{CellShape, WinnerError},
+    ai::{search_rng, AiPlayer, Game},
+    board::{CellShape, GameResult, InvalidPosition},
+    explain::{Candidate, CandidateScore},
+    personality::{AiPersonality, GridGame},
 };
 use itertools::Itertools;
-use rand::seq::SliceRandom;
+use rand::{rngs::SmallRng, seq::SliceRandom};
+use serde::Serialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    iter,
+    sync::mpsc,
+};
+use thiserror::Error;
+use web_time::Instant;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threads"))]
 use rayon::prelude::*;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm-threads")))]
 use crate::fake_par_iter::VecParIter;
 
+/// An intermediate result of an iterative-deepening search, sent after each depth is completed.
+///
+/// See [`Board::generate_ai_move_iterative`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchProgress {
+    /// The depth that has just been fully searched.
+    pub depth: u8,
+
+    /// The best move found at this depth.
+    pub best_move: Coord,
+}
+
+/// An enum to represent possible errors arising from making a move. See [`Board::make_move`].
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum MoveError {
+    /// A move has been made with a shape which is not the [`to_move`](Board::to_move).
+    #[error("wrong shape to move")]
+    WrongTurn,
+
+    /// The chosen cell already has a shape in it.
+    #[error("cell already full")]
+    CellAlreadyFull,
+
+    /// The given coordinate is out of bounds.
+    #[error("coordinate out of bounds")]
+    OutOfBounds,
+}
+
 /// A struct to represent a simple tic-tac-toe board.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Board {
     /// This 2D array represents all the cells, and is indexed as `cells[x][y]`, with the layout as so:
     ///
@@ -26,73 +66,241 @@ pub struct Board {
     /// ------------------------
     /// (0, 2) | (1, 2) | (2, 2)
     /// ```
-    pub cells: [[Option<CellShape>; 3]; 3],
+    ///
+    /// This is private so that the cached winner and shape to move can't get out of sync with it.
+    cells: [[Option<CellShape>; 3]; 3],
 
     /// This is the shape that the AI will play as.
     ///
     /// Board positions where this shape wins are considered good, and positions where the other
     /// shape wins are considered bad.
     pub ai_shape: CellShape,
+
+    /// The score of a drawn position for the AI, which is 0 by default. A negative score is
+    /// contempt for draws, so that the AI prefers risky lines where the opponent could go wrong
+    /// over lines which are certain to draw, like against weaker opponents.
+    pub draw_score: i8,
+
+    /// The shape which must make the next move.
+    to_move: CellShape,
+
+    /// The winner of the board, which is updated by each move so that we don't have to check
+    /// every line in [`get_winner`](Self::get_winner).
+    winner: GameResult,
 }
 
 impl Board {
-    /// Create a new, empty board.
+    /// Create a new, empty board, where [`X`](CellShape::X) plays first.
     pub fn new(shape_to_maximise: CellShape) -> Self {
+        Self::new_with_first_shape(shape_to_maximise, CellShape::X)
+    }
+
+    /// Create a new, empty board, where the given shape plays first.
+    pub fn new_with_first_shape(shape_to_maximise: CellShape, first_shape: CellShape) -> Self {
         Self {
             cells: [[None; 3]; 3],
             ai_shape: shape_to_maximise,
+            draw_score: 0,
+            to_move: first_shape,
+            winner: GameResult::Ongoing,
         }
     }
 
-    /// Return the winner of the current board. See [`shared::board::get_winner`].
+    /// Return the cells of the board, indexed as `cells[x][y]`.
+    pub fn cells(&self) -> [[Option<CellShape>; 3]; 3] {
+        self.cells
+    }
+
+    /// Return the shape which must make the next move.
+    pub fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    /// Return a hash of the cells and the shape to move, to identify the position in logs.
+    pub fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        self.to_move.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Skip the turn of the shape which is next to move, so that the other shape moves next.
+    ///
+    /// This is used for handicaps, where one player gets an extra move.
+    pub fn skip_turn(&mut self) {
+        self.to_move = self.to_move.other();
+    }
+
+    /// Update the board to reflect a move being made.
+    ///
+    /// This method will also update the [`to_move`](Self::to_move).
+    pub fn make_move(&mut self, coord: Coord, shape: CellShape) -> Result<(), MoveError> {
+        let (x, y) = coord;
+
+        if x > 2 || y > 2 {
+            return Err(MoveError::OutOfBounds);
+        }
+
+        if shape != self.to_move {
+            return Err(MoveError::WrongTurn);
+        }
+
+        if self.cells[x][y].is_some() {
+            return Err(MoveError::CellAlreadyFull);
+        }
+
+        self.place(coord, shape);
+        Ok(())
+    }
+
+    /// Put the shape in the given cell without checking that the move is legal, and update the
+    /// [`to_move`](Self::to_move) and the cached winner.
+    fn place(&mut self, (x, y): Coord, shape: CellShape) {
+        self.cells[x][y] = Some(shape);
+        self.to_move = shape.other();
+
+        if self.winner == GameResult::Ongoing {
+            if let Some((shape, line)) = shared::board::get_winning_line_through(self.cells, (x, y))
+            {
+                self.winner = GameResult::Won { shape, line };
+            } else if shared::board::is_board_full(self.cells) {
+                self.winner = GameResult::Draw;
+            }
+        }
+    }
+
+    /// Return the result of the game so far.
+    ///
+    /// The result is cached and updated by [`make_move`](Self::make_move), so this is cheap.
     #[inline(always)]
-    pub fn get_winner(&self) -> Result<(CellShape, [(usize, usize); 3]), WinnerError> {
-        shared::board::get_winner(self.cells)
+    pub fn get_winner(&self) -> GameResult {
+        self.winner
+    }
+
+    /// Check that this position could have been reached in a real game.
+    ///
+    /// # Errors
+    ///
+    /// See [`shared::board::check_shape_counts`] and [`shared::board::check_winner`].
+    pub fn is_valid_position(&self) -> Result<(), InvalidPosition> {
+        shared::board::check_shape_counts(self.cells.iter().flatten(), self.to_move)?;
+        shared::board::check_winner(shared::board::get_winner(self.cells), self.to_move)
     }
 
-    /// Return a vector of the coordinates of empty cells in the board.
+    /// Return an iterator over the coordinates of empty cells in the board, which are the legal
+    /// moves.
     ///
     /// This method searches columns before rows.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.cells.iter().enumerate().flat_map(|(col, row_vec)| {
+            row_vec
+                .iter()
+                .enumerate()
+                .filter_map(move |(row, val)| match val {
+                    None => Some((col, row)),
+                    Some(_) => None,
+                })
+        })
+    }
+
+    /// Return a vector of the coordinates of empty cells in the board. See
+    /// [`legal_moves`](Self::legal_moves).
     fn empty_cells(&self) -> Vec<Coord> {
-        self.cells
-            .iter()
-            .enumerate()
-            .flat_map(|(col, row_vec)| {
-                row_vec
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(row, val)| match val {
-                        None => Some((col, row)),
-                        Some(_) => None,
-                    })
-                    .collect::<Vec<Coord>>()
-            })
-            .collect()
+        self.legal_moves().collect()
     }
 
     /// Evaluate the current position of the board, with the context of which shape is playing next.
     ///
     /// Positive numbers are always good for the AI; negative numbers are always good for the player.
     ///
-    /// A win for the AI shape is 100. A win for the opponent is -100. For any other position, we
+    /// A win for the AI shape is 100. A win for the opponent is -100. A draw is the
+    /// [`draw_score`](Self::draw_score), which is 0 by default. For any other position, we
     /// iterate over all possible moves and evaluate each of them, swapping the shape for each
     /// recursion. We also multiple the result of the recursive call by 0.9. This means that
     /// creating or blocking a win in the short term is prioritised over long term play.
     pub fn evaluate_position(&self, shape_to_play: CellShape) -> i8 {
+        self.evaluate_position_to_depth(shape_to_play, 9)
+    }
+
+    /// Evaluate the current position like [`evaluate_position`](Board::evaluate_position), and
+    /// also return the principal variation, which is the line of moves that both shapes would
+    /// play with perfect play, starting with a move for `shape_to_play`.
+    ///
+    /// When several moves are equally good, the first one in the order of
+    /// [`legal_moves`](Self::legal_moves) is chosen. The line is empty if the game is over.
+    ///
+    /// This is one search, where each position passes the line of its best move back up as the
+    /// search unwinds, so that the line doesn't need searching for again.
+    pub fn evaluate_position_with_line(&self, shape_to_play: CellShape) -> (i8, Vec<Coord>) {
         match self.get_winner() {
-            Ok((x, _)) if x == self.ai_shape => 100,
-            Ok((x, _)) if x == self.ai_shape.other() => -100,
-            Ok(_) => unreachable!(),
-            Err(WinnerError::MultipleWinners | WinnerError::BoardFullNoWinner) => 0,
-            Err(WinnerError::NoWinnerYet) => {
+            GameResult::Won { shape, .. } if shape == self.ai_shape => (100, vec![]),
+            GameResult::Won { .. } => (-100, vec![]),
+            GameResult::Draw => (self.draw_score, vec![]),
+            GameResult::Ongoing => {
+                let evaluations: Vec<(Coord, (i8, Vec<Coord>))> = self
+                    .empty_cells()
+                    .par_iter()
+                    .map(|&mv| {
+                        let mut new_board = self.clone();
+                        new_board.place(mv, shape_to_play);
+                        (
+                            mv,
+                            new_board.evaluate_position_with_line(shape_to_play.other()),
+                        )
+                    })
+                    .collect();
+
+                let best = if shape_to_play == self.ai_shape {
+                    evaluations
+                        .into_iter()
+                        .rev()
+                        .max_by_key(|&(_, (score, _))| score)
+                } else {
+                    evaluations.into_iter().min_by_key(|&(_, (score, _))| score)
+                };
+                let (mv, (score, line)) =
+                    best.expect("We should never iterate over zero empty cells");
+
+                // Further moves are discounted like in `evaluate_position_to_depth`
+                (
+                    (0.9 * score as f32) as i8,
+                    iter::once(mv).chain(line).collect(),
+                )
+            }
+        }
+    }
+
+    /// Evaluate the current position like [`evaluate_position`](Board::evaluate_position), but
+    /// only look `depth` moves ahead, so that the cost of the search is bounded.
+    ///
+    /// Any position which is not decided after `depth` moves is given a score of 0, so a score of
+    /// 0 here doesn't mean that the position is a draw with perfect play. A depth of 9 or more
+    /// searches to the end of the game and is equivalent to
+    /// [`evaluate_position`](Board::evaluate_position).
+    pub fn evaluate_to_depth(&self, depth: u8, shape_to_play: CellShape) -> i8 {
+        self.evaluate_position_to_depth(shape_to_play, depth.min(9))
+    }
+
+    /// Evaluate the current position like [`evaluate_position`](Board::evaluate_position), but
+    /// only look `depth` moves ahead.
+    ///
+    /// Any position which is not decided after `depth` moves is given a score of 0.
+    fn evaluate_position_to_depth(&self, shape_to_play: CellShape, depth: u8) -> i8 {
+        match self.get_winner() {
+            GameResult::Won { shape, .. } if shape == self.ai_shape => 100,
+            GameResult::Won { .. } => -100,
+            GameResult::Draw => self.draw_score,
+            GameResult::Ongoing if depth == 0 => 0,
+            GameResult::Ongoing => {
                 let empty_cells = self.empty_cells();
 
                 let map = empty_cells.par_iter().map(|&(x, y)| -> i8 {
                     let mut new_board = self.clone();
-                    new_board.cells[x][y] = Some(shape_to_play);
+                    new_board.place((x, y), shape_to_play);
                     // Further moves after this one are considered less important than creating or
                     // blocking a win in the short term
-                    (0.9 * new_board.evaluate_position(shape_to_play.other()) as f32) as i8
+                    (0.9 * new_board.evaluate_position_to_depth(shape_to_play.other(), depth - 1)
+                        as f32) as i8
                 });
 
                 if shape_to_play == self.ai_shape {
@@ -106,6 +314,108 @@ impl Board {
         }
     }
 
+    /// Return the move that should be played without searching, if there is one.
+    ///
+    /// The AI always plays in the center when possible, and plays in a random corner when there's
+    /// only one shape on the board and the center is full.
+    fn opening_move(empty_cells: &[Coord], rng: &mut SmallRng) -> Option<Coord> {
+        // Go in the center when possible
+        if empty_cells.contains(&(1, 1)) {
+            Some((1, 1))
+
+        // When there's only one shape on the board and the center is full
+        } else if empty_cells.len() >= 8 {
+            Some(
+                **[(0, 0), (2, 2), (0, 2), (2, 0)]
+                    .iter()
+                    .filter(|&x| empty_cells.contains(x))
+                    .collect::<Vec<_>>()
+                    .choose(rng)
+                    .unwrap(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Evaluate each of the given cells as a move for `shape_to_play`, looking `depth` moves ahead
+    /// after that move.
+    fn evaluate_moves_to_depth(
+        &self,
+        empty_cells: &[Coord],
+        shape_to_play: CellShape,
+        depth: u8,
+    ) -> Vec<(Coord, i8)> {
+        empty_cells
+            .to_vec()
+            .par_iter()
+            .map(|&(x, y)| -> (Coord, i8) {
+                let mut new_board = self.clone();
+                new_board.place((x, y), shape_to_play);
+                (
+                    (x, y),
+                    new_board.evaluate_position_to_depth(shape_to_play.other(), depth),
+                )
+            })
+            .collect()
+    }
+
+    /// Check if neither shape can force a win from this position, so that the game is a draw
+    /// with perfect play. Used to choose fair starting positions for chaos mode. See
+    /// [`shared::chaos::random_start`].
+    pub fn is_balanced(&self) -> bool {
+        let board = Self {
+            draw_score: 0,
+            ..self.clone()
+        };
+        board.get_winner() == GameResult::Ongoing && board.evaluate_position(board.to_move) == 0
+    }
+
+    /// Return every possible move for `shape_to_play`, along with the evaluation of the position
+    /// after that move is played. See [`evaluate_position`](Board::evaluate_position).
+    ///
+    /// Like [`evaluate_position`](Board::evaluate_position), the evaluations are always from the
+    /// perspective of the AI. If the game is already over, then there are no possible moves.
+    pub fn evaluate_moves(&self, shape_to_play: CellShape) -> Vec<(Coord, i8)> {
+        if self.get_winner() != GameResult::Ongoing {
+            return vec![];
+        }
+
+        self.evaluate_moves_to_depth(&self.empty_cells(), shape_to_play, 9)
+    }
+
+    /// Return every possible move for the AI, annotated with its minimax score, to explain the AI's
+    /// choice of move. See [`evaluate_moves`](Self::evaluate_moves).
+    pub fn annotated_moves(&self) -> Vec<Candidate<Coord>> {
+        self.evaluate_moves(self.ai_shape)
+            .into_iter()
+            .map(|(mv, score)| Candidate {
+                mv,
+                score: CandidateScore::Minimax(score),
+            })
+            .collect()
+    }
+
+    /// Return the best move for the AI when looking `depth` moves ahead, choosing randomly
+    /// between equally good moves with `rng`.
+    fn best_move_to_depth(&self, empty_cells: &[Coord], depth: u8, rng: &mut SmallRng) -> Coord {
+        Self::best_move(
+            &self.evaluate_moves_to_depth(empty_cells, self.ai_shape, depth),
+            rng,
+        )
+    }
+
+    /// Return the best of the given evaluated moves for the AI, choosing randomly between equally
+    /// good moves with `rng`.
+    fn best_move(evaluations: &[(Coord, i8)], rng: &mut SmallRng) -> Coord {
+        evaluations
+            .iter()
+            .max_set_by_key(|&(_, x)| x)
+            .choose(rng)
+            .unwrap()
+            .0
+    }
+
     /// Return the optimal position for the AI to play in.
     ///
     /// The optimal move is generated by looking at all possible moves and evaluating each
@@ -116,43 +426,206 @@ impl Board {
     ///
     /// If the board is full, then we return `None`.
     pub fn generate_ai_move(&self) -> Option<Coord> {
-        if self.empty_cells().is_empty() {
+        self.search_with_rng(&mut search_rng(None))
+            .map(|(mv, _)| mv)
+    }
+
+    /// Find the optimal position for the AI to play in like
+    /// [`generate_ai_move`](Self::generate_ai_move), choosing between equally good moves with
+    /// `rng`.
+    ///
+    /// Return the move along with the evaluation of every move, which is `None` if the move was
+    /// an opening move that didn't need a search. See [`opening_move`](Self::opening_move).
+    fn search_with_rng(&self, rng: &mut SmallRng) -> Option<(Coord, Option<Vec<(Coord, i8)>>)> {
+        let empty_cells = self.empty_cells();
+        if empty_cells.is_empty() {
             return None;
         }
 
+        if let Some(mv) = Self::opening_move(&empty_cells, rng) {
+            return Some((mv, None));
+        }
+
+        let evaluations = self.evaluate_moves_to_depth(&empty_cells, self.ai_shape, 9);
+        Some((Self::best_move(&evaluations, rng), Some(evaluations)))
+    }
+
+    /// Return the optimal position for the AI to play in like
+    /// [`generate_ai_move`](Self::generate_ai_move), but without playing in any of the given
+    /// cells. This is used for handicaps, like stopping the AI from taking the center.
+    ///
+    /// # Errors
+    ///
+    /// If every empty cell is avoided, then we return `None`.
+    pub fn generate_ai_move_avoiding(&self, avoid: &[Coord]) -> Option<Coord> {
+        let empty_cells: Vec<Coord> = self
+            .empty_cells()
+            .into_iter()
+            .filter(|coord| !avoid.contains(coord))
+            .collect();
+        if empty_cells.is_empty() {
+            return None;
+        }
+
+        Some(self.best_move_to_depth(&empty_cells, 9, &mut search_rng(None)))
+    }
+
+    /// Return the optimal position for the AI to play in, searching with iterative deepening.
+    ///
+    /// We search one move ahead, then two moves ahead, and so on until we reach the end of the
+    /// game. After each depth is completed, the best move so far is sent down `progress_tx` as a
+    /// [`SearchProgress`]. If the `deadline` passes, we stop searching deeper and return the
+    /// best move found so far.
+    ///
+    /// When the search runs to completion, the result is equivalent to
+    /// [`generate_ai_move`](Board::generate_ai_move).
+    ///
+    /// # Errors
+    ///
+    /// If the board is full, then we return `None`.
+    pub fn generate_ai_move_iterative(
+        &self,
+        deadline: Option<Instant>,
+        progress_tx: &mpsc::Sender<SearchProgress>,
+    ) -> Option<Coord> {
+        self.search_iterative_with_rng(deadline, progress_tx, &mut search_rng(None))
+            .map(|(mv, _)| mv)
+    }
+
+    /// Search with iterative deepening like
+    /// [`generate_ai_move_iterative`](Self::generate_ai_move_iterative), choosing between equally
+    /// good moves with `rng`.
+    ///
+    /// Return the move along with the evaluation of every move at the deepest depth searched,
+    /// like [`search_with_rng`](Self::search_with_rng).
+    fn search_iterative_with_rng(
+        &self,
+        deadline: Option<Instant>,
+        progress_tx: &mpsc::Sender<SearchProgress>,
+        rng: &mut SmallRng,
+    ) -> Option<(Coord, Option<Vec<(Coord, i8)>>)> {
         let empty_cells = self.empty_cells();
+        if empty_cells.is_empty() {
+            return None;
+        }
 
-        // Go in the center when possible
-        if empty_cells.contains(&(1, 1)) {
-            Some((1, 1))
+        if let Some(mv) = Self::opening_move(&empty_cells, rng) {
+            let _ = progress_tx.send(SearchProgress {
+                depth: 0,
+                best_move: mv,
+            });
+            return Some((mv, None));
+        }
 
-        // When there's only one shape on the board and the center is full
-        } else if empty_cells.len() >= 8 {
-            Some(
-                **[(0, 0), (2, 2), (0, 2), (2, 0)]
-                    .iter()
-                    .filter(|&x| empty_cells.contains(x))
-                    .collect::<Vec<_>>()
-                    .choose(&mut rand::thread_rng())
-                    .unwrap(),
-            )
-        } else {
-            Some(
-                empty_cells
-                    .par_iter()
-                    .map(|&(x, y)| -> (Coord, i8) {
-                        let mut new_board = self.clone();
-                        new_board.cells[x][y] = Some(self.ai_shape);
-                        ((x, y), new_board.evaluate_position(self.ai_shape.other()))
-                    })
-                    .collect::<Vec<_>>()
-                    .iter()
-                    .max_set_by_key(|&(_, x)| x)
-                    .choose(&mut rand::thread_rng())
-                    .unwrap()
-                    .0,
-            )
+        let mut best = None;
+        for depth in 0..empty_cells.len() as u8 {
+            if best.is_some() && deadline.map_or(false, |d| Instant::now() >= d) {
+                break;
+            }
+
+            let evaluations = self.evaluate_moves_to_depth(&empty_cells, self.ai_shape, depth);
+            let mv = Self::best_move(&evaluations, rng);
+            best = Some((mv, Some(evaluations)));
+            let _ = progress_tx.send(SearchProgress {
+                depth: depth + 1,
+                best_move: mv,
+            });
+        }
+
+        best
+    }
+}
+
+impl Game for Board {
+    type Move = Coord;
+
+    fn moves(&self) -> Vec<Coord> {
+        self.empty_cells()
+    }
+
+    fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    fn play(&mut self, mv: Coord) -> bool {
+        self.make_move(mv, self.to_move).is_ok()
+    }
+
+    fn skip_turn(&mut self) {
+        self.skip_turn();
+    }
+
+    fn get_winner(&self) -> GameResult {
+        self.winner
+    }
+}
+
+impl GridGame for Board {
+    fn grid_of_move(&self, mv: Coord) -> ([[Option<CellShape>; 3]; 3], (usize, usize)) {
+        (self.cells, mv)
+    }
+}
+
+/// An [`AiPlayer`] which uses minimax to play perfectly. See [`Board::generate_ai_move`].
+#[derive(Clone, Debug, Default)]
+pub struct MinimaxPlayer {
+    /// The time after which to stop searching deeper. See
+    /// [`Board::generate_ai_move_iterative`].
+    pub deadline: Option<Instant>,
+
+    /// The sender to report the progress of the search down. If this is `None`, then we don't
+    /// search iteratively, so the `deadline` is ignored.
+    pub progress_tx: Option<mpsc::Sender<SearchProgress>>,
+
+    /// The personality which chooses between the moves that are as good as the one the search
+    /// found. This is skipped if the search was stopped early by the `deadline`.
+    pub personality: AiPersonality,
+
+    /// The seed for choosing between equally good moves, so that the same position always gets
+    /// the same move. If this is `None`, then the choice is random. See [`search_rng`].
+    pub seed: Option<u64>,
+
+    /// The score of a draw for the AI, which is 0 for a perfect player that is happy to draw. See
+    /// [`Board::draw_score`].
+    pub draw_score: i8,
+}
+
+impl AiPlayer<Board> for MinimaxPlayer {
+    fn choose_move(&mut self, game: &Board) -> Option<Coord> {
+        let mut board = game.clone();
+        board.ai_shape = board.to_move;
+        board.draw_score = self.draw_score;
+
+        let mut rng = search_rng(self.seed);
+        let (mv, evaluations) = match &self.progress_tx {
+            Some(progress_tx) => {
+                board.search_iterative_with_rng(self.deadline, progress_tx, &mut rng)
+            }
+            None => board.search_with_rng(&mut rng),
+        }?;
+
+        if self.personality == AiPersonality::Balanced
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            return Some(mv);
         }
+
+        // The search finished, so its evaluations are the full-depth ones, unless it played an
+        // opening move without searching
+        let evaluations = evaluations.unwrap_or_else(|| board.evaluate_moves(board.ai_shape));
+        let score = evaluations
+            .iter()
+            .find(|&&(coord, _)| coord == mv)
+            .map_or(0, |&(_, score)| score);
+        let equally_good: Vec<Coord> = evaluations
+            .into_iter()
+            .filter(|&(_, other)| other == score)
+            .map(|(coord, _)| coord)
+            .collect();
+
+        self.personality.choose(&board, &equally_good).or(Some(mv))
     }
 }
 
@@ -168,6 +641,8 @@ impl Board {
     pub fn with_cell_array(cells: [[Option<CellShape>; 3]; 3]) -> Self {
         Self {
             cells,
+            to_move: shared::board::infer_shape_to_move(cells.iter().flatten()),
+            winner: shared::board::get_winner(cells).into(),
             ..Default::default()
         }
     }
@@ -221,6 +696,81 @@ mod tests {
         assert_eq!(board.empty_cells(), vec![(0, 2), (1, 2), (2, 2)]);
     }
 
+    #[test]
+    fn is_valid_position_test() {
+        assert_eq!(Board::default().is_valid_position(), Ok(()));
+        assert_eq!(make_board!(X X X; O O _; _).is_valid_position(), Ok(()));
+
+        assert_eq!(
+            make_board!(X X _; _; _).is_valid_position(),
+            Err(InvalidPosition::TooManyMoves(CellShape::X))
+        );
+        assert_eq!(
+            make_board!(X X X; O O O; _).is_valid_position(),
+            Err(InvalidPosition::MultipleWinners)
+        );
+        assert_eq!(
+            make_board!(X X X; O O _; O _ _).is_valid_position(),
+            Err(InvalidPosition::MoveAfterWin(CellShape::X))
+        );
+
+        let mut board = make_board!(X _ _; _; _);
+        board.to_move = CellShape::X;
+        assert_eq!(
+            board.is_valid_position(),
+            Err(InvalidPosition::WrongShapeToMove(CellShape::X))
+        );
+    }
+
+    #[test]
+    fn legal_moves_test() {
+        assert_eq!(Board::default().legal_moves().count(), 9);
+
+        let board = make_board!(X _ O; _ X _; O _ X);
+        assert_eq!(
+            board.legal_moves().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0), (1, 2), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn make_move_test() {
+        let mut board = Board::default();
+
+        assert_eq!(
+            board.make_move((1, 1), CellShape::O),
+            Err(MoveError::WrongTurn)
+        );
+        assert_eq!(board.make_move((1, 1), CellShape::X), Ok(()));
+        assert_eq!(board, make_board!(_; _ X _; _));
+        assert_eq!(board.to_move(), CellShape::O);
+
+        assert_eq!(
+            board.make_move((1, 1), CellShape::O),
+            Err(MoveError::CellAlreadyFull)
+        );
+        assert_eq!(
+            board.make_move((0, 3), CellShape::O),
+            Err(MoveError::OutOfBounds)
+        );
+        assert_eq!(board, make_board!(_; _ X _; _));
+
+        assert_eq!(
+            board.make_move((2, 0), CellShape::X),
+            Err(MoveError::WrongTurn)
+        );
+        assert_eq!(board.make_move((2, 0), CellShape::O), Ok(()));
+        assert_eq!(board, make_board!(_ _ O; _ X _; _));
+        assert_eq!(board.to_move(), CellShape::X);
+
+        let mut board = Board::new_with_first_shape(CellShape::X, CellShape::O);
+        assert_eq!(
+            board.make_move((1, 1), CellShape::X),
+            Err(MoveError::WrongTurn)
+        );
+        assert_eq!(board.make_move((1, 1), CellShape::O), Ok(()));
+    }
+
     #[test]
     fn evaluate_position_test() {
         // X|O|
@@ -268,6 +818,111 @@ mod tests {
         assert_eq!(board.evaluate_position(CellShape::O), 0);
     }
 
+    #[test]
+    fn evaluate_to_depth_test() {
+        // X|O|
+        //  |X|O
+        //  | |
+        let board = make_board!(X O _; _ X _; _);
+        assert_eq!(board.evaluate_to_depth(0, CellShape::X), 0);
+        assert_eq!(board.evaluate_to_depth(1, CellShape::X), -90);
+        assert_eq!(
+            board.evaluate_to_depth(u8::MAX, CellShape::X),
+            board.evaluate_position(CellShape::X)
+        );
+
+        // X wins straight away, but that's only seen when looking at least one move ahead
+        let board = make_board!(X X _; O O _; _);
+        assert_eq!(board.evaluate_to_depth(0, CellShape::X), 0);
+        assert_eq!(board.evaluate_to_depth(1, CellShape::X), -90);
+
+        // A finished game is scored the same at any depth
+        let board = make_board!(O X _; _ O X; X _ O);
+        assert_eq!(board.evaluate_to_depth(0, CellShape::X), 100);
+    }
+
+    #[test]
+    fn evaluate_position_with_line_test() {
+        // X|O|
+        //  |X|O
+        //  | |
+        let board = make_board!(X O _; _ X _; _);
+        let (score, line) = board.evaluate_position_with_line(CellShape::X);
+        assert_eq!(score, -90);
+        assert_eq!(line, vec![(2, 2)]);
+
+        // O must block, and then it's a draw with perfect play
+        let board = make_board!(X X _; _ O _; _);
+        let (score, line) = board.evaluate_position_with_line(CellShape::O);
+        assert_eq!(score, 0);
+        assert_eq!(line[0], (2, 0));
+
+        let mut replayed = board.clone();
+        for mv in line {
+            assert!(Game::play(&mut replayed, mv));
+        }
+        assert_eq!(replayed.get_winner(), GameResult::Draw);
+
+        // There's no line once the game is over
+        let board = make_board!(X O _; _ X O; O _ X);
+        assert_eq!(
+            board.evaluate_position_with_line(CellShape::O),
+            (-100, vec![])
+        );
+    }
+
+    #[test]
+    fn is_balanced_test() {
+        assert!(Board::default().is_balanced());
+
+        // X can win straight away
+        assert!(!make_board!(X X _; O O _; _).is_balanced());
+
+        // O has to take the center, but then it's a draw
+        assert!(make_board!(X _ _; _; _).is_balanced());
+
+        // The game is already over
+        assert!(!make_board!(X X X; O O _; _).is_balanced());
+    }
+
+    #[test]
+    fn evaluate_moves_test() {
+        // X|O|X
+        // X|X|O
+        // O| |O
+        let board = make_board!(X O X; X X O; O _ O);
+        assert_eq!(board.evaluate_moves(CellShape::X), vec![((1, 2), 0)]);
+        assert_eq!(board.evaluate_moves(CellShape::O), vec![((1, 2), 100)]);
+
+        // X|O|
+        // X|O|O
+        // X|O|
+        let board = make_board!(X O _; X O O; X O _);
+        assert_eq!(board.evaluate_moves(CellShape::X), vec![]);
+
+        //  | |X
+        //  |X|O
+        //  | |
+        let board = make_board!(_ _ X; _ X O; _);
+        let moves = board.evaluate_moves(CellShape::O);
+        assert_eq!(moves.len(), 6);
+        assert_eq!(
+            moves.iter().max_by_key(|&&(_, score)| score).unwrap().0,
+            (0, 2)
+        );
+
+        assert_eq!(
+            board.annotated_moves(),
+            moves
+                .into_iter()
+                .map(|(mv, score)| Candidate {
+                    mv,
+                    score: CandidateScore::Minimax(score)
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn generate_ai_move_test() {
         //  | |X
@@ -312,4 +967,139 @@ mod tests {
         let board = make_board!(O O X; O X X; O X X);
         assert_eq!(board.generate_ai_move(), None);
     }
+
+    #[test]
+    fn generate_ai_move_avoiding_test() {
+        let mv = Board::default().generate_ai_move_avoiding(&[(1, 1)]);
+        assert!(mv.is_some());
+        assert_ne!(mv, Some((1, 1)));
+
+        //  | |X
+        //  |X|O
+        //  | |
+        let board = make_board!(_ _ X; _ X O; _);
+        assert_ne!(board.generate_ai_move_avoiding(&[(0, 2)]), Some((0, 2)));
+
+        // O|X|X
+        // X|O|O
+        // O|X|
+        let board = make_board!(O X X; X O O; O X _);
+        assert_eq!(board.generate_ai_move_avoiding(&[(2, 2)]), None);
+    }
+
+    #[test]
+    fn skip_turn_test() {
+        let mut board = make_board!(_; _ X _; _);
+        assert_eq!(board.to_move(), CellShape::O);
+
+        board.skip_turn();
+        assert_eq!(board.to_move(), CellShape::X);
+        assert_eq!(
+            board.make_move((0, 0), CellShape::O),
+            Err(MoveError::WrongTurn)
+        );
+        assert_eq!(board.make_move((0, 0), CellShape::X), Ok(()));
+        assert_eq!(board.to_move(), CellShape::O);
+    }
+
+    #[test]
+    fn minimax_player_test() {
+        let mut player = MinimaxPlayer::default();
+
+        // X should block O's win, even though the board's AI shape is O
+        let board = make_board!(X _ _; O O _; X _ _);
+        assert_eq!(board.to_move(), CellShape::X);
+        assert_eq!(player.choose_move(&board), Some((2, 1)));
+
+        let (progress_tx, progress_rx) = mpsc::channel();
+        player.progress_tx = Some(progress_tx);
+        assert_eq!(player.choose_move(&board), Some((2, 1)));
+        assert!(progress_rx.try_iter().count() > 0);
+
+        // A personality only chooses between equally good moves, so X still has to block
+        for personality in AiPersonality::ALL {
+            player.personality = personality;
+            assert_eq!(player.choose_move(&board), Some((2, 1)));
+        }
+
+        // Any corner is as good as another, but the same seed always chooses the same one
+        let board = make_board!(_; _ X _; _);
+        let mut player = MinimaxPlayer {
+            seed: Some(1963),
+            ..MinimaxPlayer::default()
+        };
+        let mv = player.choose_move(&board);
+        assert!([(0, 0), (2, 2), (0, 2), (2, 0)].contains(&mv.unwrap()));
+        for _ in 0..10 {
+            assert_eq!(player.choose_move(&board), mv);
+        }
+    }
+
+    #[test]
+    fn draw_score_test() {
+        // X|O|X
+        // X|X|O
+        // O| |O
+        let mut board = make_board!(X O X; X X O; O _ O);
+        board.draw_score = -20;
+        // X blocks O's win and draws, which is now worse for the AI (O) than before
+        assert_eq!(board.evaluate_position(CellShape::X), -18);
+        assert_eq!(board.evaluate_position(CellShape::O), 90);
+
+        // But contempt doesn't change whether a position is balanced
+        let board = Board {
+            draw_score: -20,
+            ..make_board!(X _ _; _ O _; _)
+        };
+        assert!(board.is_balanced());
+    }
+
+    #[test]
+    fn generate_ai_move_iterative_test() {
+        let (tx, rx) = mpsc::channel();
+
+        //  | |X
+        //  |X|O
+        //  | |
+        let board = make_board!(_ _ X; _ X O; _);
+        assert_eq!(board.generate_ai_move_iterative(None, &tx), Some((0, 2)));
+        let progress: Vec<SearchProgress> = rx.try_iter().collect();
+        assert_eq!(
+            progress.iter().map(|p| p.depth).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+        assert_eq!(progress.last().unwrap().best_move, (0, 2));
+
+        // O| |O
+        //  |X|
+        // X| |X
+        let board = make_board!(O _ O; _ X _; X _ X);
+        assert_eq!(board.generate_ai_move_iterative(None, &tx), Some((1, 0)));
+        assert_eq!(rx.try_iter().count(), 4);
+
+        // An opening move is sent without any search
+        let board = make_board!(X _ _; _; _);
+        assert_eq!(board.generate_ai_move_iterative(None, &tx), Some((1, 1)));
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![SearchProgress {
+                depth: 0,
+                best_move: (1, 1)
+            }]
+        );
+
+        // A deadline in the past still searches one move ahead
+        let board = make_board!(X O X; X O _; _);
+        assert_eq!(
+            board.generate_ai_move_iterative(Some(Instant::now()), &tx),
+            Some((1, 2))
+        );
+        assert_eq!(rx.try_iter().count(), 1);
+
+        // O|X|X
+        // X|O|O
+        // O|X|X
+        let board = make_board!(O X X; X O O; O X X);
+        assert_eq!(board.generate_ai_move_iterative(None, &tx), None);
+    }
 }
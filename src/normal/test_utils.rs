@@ -139,15 +139,15 @@ mod tests {
         // -----
         // O| |
         let board = make_board!(X X _; _ O _; O _ _);
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], Some(CellShape::X));
-        assert_eq!(board.cells[2][0], None);
-        assert_eq!(board.cells[0][1], None);
-        assert_eq!(board.cells[1][1], Some(CellShape::O));
-        assert_eq!(board.cells[2][1], None);
-        assert_eq!(board.cells[0][2], Some(CellShape::O));
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], None);
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], Some(CellShape::X));
+        assert_eq!(board.cells()[2][0], None);
+        assert_eq!(board.cells()[0][1], None);
+        assert_eq!(board.cells()[1][1], Some(CellShape::O));
+        assert_eq!(board.cells()[2][1], None);
+        assert_eq!(board.cells()[0][2], Some(CellShape::O));
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], None);
 
         // X| |O
         // -----
@@ -155,15 +155,15 @@ mod tests {
         // -----
         //  | |
         let board = make_board!(X _ O; X O _; _);
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], None);
-        assert_eq!(board.cells[2][0], Some(CellShape::O));
-        assert_eq!(board.cells[0][1], Some(CellShape::X));
-        assert_eq!(board.cells[1][1], Some(CellShape::O));
-        assert_eq!(board.cells[2][1], None);
-        assert_eq!(board.cells[0][2], None);
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], None);
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], None);
+        assert_eq!(board.cells()[2][0], Some(CellShape::O));
+        assert_eq!(board.cells()[0][1], Some(CellShape::X));
+        assert_eq!(board.cells()[1][1], Some(CellShape::O));
+        assert_eq!(board.cells()[2][1], None);
+        assert_eq!(board.cells()[0][2], None);
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], None);
 
         // X|X|O
         // -----
@@ -171,14 +171,14 @@ mod tests {
         // -----
         // O| |O
         let board = make_board!(X X O; O X X; O _ O);
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], Some(CellShape::X));
-        assert_eq!(board.cells[2][0], Some(CellShape::O));
-        assert_eq!(board.cells[0][1], Some(CellShape::O));
-        assert_eq!(board.cells[1][1], Some(CellShape::X));
-        assert_eq!(board.cells[2][1], Some(CellShape::X));
-        assert_eq!(board.cells[0][2], Some(CellShape::O));
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], Some(CellShape::O));
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], Some(CellShape::X));
+        assert_eq!(board.cells()[2][0], Some(CellShape::O));
+        assert_eq!(board.cells()[0][1], Some(CellShape::O));
+        assert_eq!(board.cells()[1][1], Some(CellShape::X));
+        assert_eq!(board.cells()[2][1], Some(CellShape::X));
+        assert_eq!(board.cells()[0][2], Some(CellShape::O));
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], Some(CellShape::O));
     }
 }
@@ -0,0 +1,180 @@
+//! This module exposes the game engine to Python with `pyo3`, so that matches can be scripted and
+//! agents can be trained against the AI.
+//!
+//! Build it with `maturin` using the `python` feature. See the `python-develop` recipe.
+
+use crate::{
+    normal::board::{Board, MinimaxPlayer},
+    shared::{ai::AiPlayer, board::GameResult},
+    ultimate::board::{GlobalBoard, MctsPlayer},
+    CellShape,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// The message of the `ValueError` raised for moves after the game is over.
+const GAME_OVER: &str = "the game is over";
+
+/// Convert a shape to the string used to represent it in Python.
+fn shape_to_str(shape: CellShape) -> &'static str {
+    match shape {
+        CellShape::X => "X",
+        CellShape::O => "O",
+    }
+}
+
+/// Convert a game result to the winning shape, if there is one.
+fn winner_to_str(result: GameResult) -> Option<&'static str> {
+    match result {
+        GameResult::Won { shape, .. } => Some(shape_to_str(shape)),
+        GameResult::Ongoing | GameResult::Draw => None,
+    }
+}
+
+/// A game of normal tic-tac-toe, where X moves first.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct NormalGame {
+    board: Board,
+}
+
+#[pymethods]
+impl NormalGame {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return all the legal moves as `(x, y)` tuples.
+    fn legal_moves(&self) -> Vec<(usize, usize)> {
+        self.board.legal_moves().collect()
+    }
+
+    /// Play the shape to move at the given cell, raising a `ValueError` if the move is illegal or
+    /// the game is over.
+    fn make_move(&mut self, x: usize, y: usize) -> PyResult<()> {
+        if self.is_over() {
+            return Err(PyValueError::new_err(GAME_OVER));
+        }
+
+        let shape = self.board.to_move();
+        self.board
+            .make_move((x, y), shape)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Return the best move for the shape to move according to minimax, or `None` if the game is
+    /// over.
+    fn best_move(&self) -> Option<(usize, usize)> {
+        if self.is_over() {
+            return None;
+        }
+
+        MinimaxPlayer::default().choose_move(&self.board)
+    }
+
+    /// Return the shape to move, as `"X"` or `"O"`.
+    fn to_move(&self) -> &'static str {
+        shape_to_str(self.board.to_move())
+    }
+
+    /// Return the winning shape, or `None` if nobody has won.
+    fn winner(&self) -> Option<&'static str> {
+        winner_to_str(self.board.get_winner())
+    }
+
+    /// Check if the game is over, either by a win or a draw.
+    fn is_over(&self) -> bool {
+        self.board.get_winner() != GameResult::Ongoing
+    }
+
+    /// Return the cells, indexed by `[x][y]`, as `"X"`, `"O"`, or `None`.
+    fn cells(&self) -> Vec<Vec<Option<&'static str>>> {
+        self.board
+            .cells()
+            .iter()
+            .map(|column| column.iter().map(|cell| cell.map(shape_to_str)).collect())
+            .collect()
+    }
+}
+
+/// A game of ultimate tic-tac-toe, where X moves first.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct UltimateGame {
+    board: GlobalBoard,
+}
+
+#[pymethods]
+impl UltimateGame {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return all the legal moves as `(x, y, (lx, ly))` tuples.
+    fn legal_moves(&self) -> Vec<(usize, usize, (usize, usize))> {
+        self.board.legal_moves().collect()
+    }
+
+    /// Play the shape to move at the given cell of the given local board, raising a `ValueError`
+    /// if the move is illegal or the game is over.
+    fn make_move(&mut self, x: usize, y: usize, lx: usize, ly: usize) -> PyResult<()> {
+        if self.is_over() {
+            return Err(PyValueError::new_err(GAME_OVER));
+        }
+
+        let shape = self.board.to_move();
+        self.board
+            .make_move((x, y, (lx, ly)), shape)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Return the best move for the shape to move according to MCTS, or `None` if the game is
+    /// over.
+    ///
+    /// At least one playout is always done after each expansion, even if `playouts` is 0.
+    #[pyo3(signature = (max_mcts_expansions = 3000, playouts = 3))]
+    fn best_move(
+        &self,
+        py: Python<'_>,
+        max_mcts_expansions: u16,
+        playouts: u8,
+    ) -> Option<(usize, usize, (usize, usize))> {
+        if self.is_over() {
+            return None;
+        }
+
+        let board = self.board.clone();
+        py.allow_threads(move || {
+            MctsPlayer::new(max_mcts_expansions, playouts.max(1)).choose_move(&board)
+        })
+    }
+
+    /// Return the shape to move, as `"X"` or `"O"`.
+    fn to_move(&self) -> &'static str {
+        shape_to_str(self.board.to_move())
+    }
+
+    /// Return the local board that the next move must be played in, or `None` if any is allowed.
+    fn next_local_board(&self) -> Option<(usize, usize)> {
+        self.board.next_local_board()
+    }
+
+    /// Return the winning shape, or `None` if nobody has won.
+    fn winner(&self) -> Option<&'static str> {
+        winner_to_str(self.board.get_winner())
+    }
+
+    /// Check if the game is over, either by a win or a draw.
+    fn is_over(&self) -> bool {
+        self.board.get_winner() != GameResult::Ongoing
+    }
+}
+
+/// The `tictactoe` Python module.
+#[pymodule]
+fn tictactoe(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<NormalGame>()?;
+    m.add_class::<UltimateGame>()?;
+    Ok(())
+}
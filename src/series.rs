@@ -0,0 +1,205 @@
+//! This module provides [`Series`], a best-of match which alternates between normal and ultimate
+//! tic-tac-toe, and keeps a combined score across both of them.
+//!
+//! The series is owned by the wrapper [`TTTApp`](crate::app::TTTApp), which gets the result of
+//! each game from the variant app with
+//! [`subscribe_to_results`](crate::app::TTTVariantApp::subscribe_to_results).
+
+use crate::{app::Variant, shared::board::GameResult, CellShape};
+
+/// The lengths of series that the player can choose from.
+pub const SERIES_LENGTHS: [u32; 3] = [3, 5, 7];
+
+/// A best-of series of games, alternating between normal and ultimate tic-tac-toe and starting
+/// with normal. The score is kept by shape, since each shape keeps its player between games.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Series {
+    /// The most games that will be played. The series is won by winning more than half of them.
+    best_of: u32,
+
+    /// The index of the current game, starting from 0.
+    game: u32,
+
+    /// Whether the current game has ended. Any more results are ignored until the next game
+    /// starts, like when the finished game is restarted.
+    game_over: bool,
+
+    /// The number of games won by X.
+    x_wins: u32,
+
+    /// The number of games won by O.
+    o_wins: u32,
+
+    /// The number of games drawn.
+    draws: u32,
+}
+
+impl Series {
+    /// Start a new series of at most the given number of games.
+    pub fn new(best_of: u32) -> Self {
+        Self {
+            best_of,
+            game: 0,
+            game_over: false,
+            x_wins: 0,
+            o_wins: 0,
+            draws: 0,
+        }
+    }
+
+    /// Return the variant of the current game, which alternates between normal and ultimate.
+    pub fn variant(&self) -> Variant {
+        if self.game % 2 == 0 {
+            Variant::Normal
+        } else {
+            Variant::Ultimate
+        }
+    }
+
+    /// Return the number of games which have been played to the end.
+    pub fn games_played(&self) -> u32 {
+        self.x_wins + self.o_wins + self.draws
+    }
+
+    /// Count the result of the current game, returning whether it was counted. Only the first
+    /// result of each game counts, and unfinished games don't count at all.
+    pub fn record(&mut self, result: GameResult) -> bool {
+        if self.game_over || self.is_over() {
+            return false;
+        }
+
+        match result {
+            GameResult::Ongoing => return false,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Won {
+                shape: CellShape::X,
+                ..
+            } => self.x_wins += 1,
+            GameResult::Won {
+                shape: CellShape::O,
+                ..
+            } => self.o_wins += 1,
+        }
+
+        self.game_over = true;
+        true
+    }
+
+    /// Check if the series has ended, which is when either shape has won more than half of the
+    /// games, or when all the games have been played.
+    pub fn is_over(&self) -> bool {
+        let majority = self.best_of / 2 + 1;
+        self.x_wins >= majority || self.o_wins >= majority || self.games_played() >= self.best_of
+    }
+
+    /// Check if the current game has ended and there's another game to play.
+    pub fn can_start_next_game(&self) -> bool {
+        self.game_over && !self.is_over()
+    }
+
+    /// Move on to the next game if the current game has ended, and return its variant.
+    pub fn next_game(&mut self) -> Option<Variant> {
+        if !self.can_start_next_game() {
+            return None;
+        }
+
+        self.game += 1;
+        self.game_over = false;
+        Some(self.variant())
+    }
+
+    /// Return the shape which won the series once it's over, or [`None`] if it's still going or
+    /// ended in a tie.
+    pub fn winner(&self) -> Option<CellShape> {
+        if !self.is_over() || self.x_wins == self.o_wins {
+            None
+        } else if self.x_wins > self.o_wins {
+            Some(CellShape::X)
+        } else {
+            Some(CellShape::O)
+        }
+    }
+
+    /// Describe the score and progress of the series, like `"Best of 3, game 2: X 1 - 0 O"` or
+    /// `"X wins the series: X 2 - 1 O"`.
+    pub fn status_text(&self) -> String {
+        let mut score = format!("X {} - {} O", self.x_wins, self.o_wins);
+        match self.draws {
+            0 => (),
+            1 => score.push_str(", 1 draw"),
+            draws => score.push_str(&format!(", {draws} draws")),
+        }
+
+        if !self.is_over() {
+            format!("Best of {}, game {}: {score}", self.best_of, self.game + 1)
+        } else {
+            match self.winner() {
+                Some(shape) => format!("{shape:?} wins the series: {score}"),
+                None => format!("The series is tied: {score}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A win for the given shape, with a line that doesn't matter.
+    fn won(shape: CellShape) -> GameResult {
+        GameResult::Won {
+            shape,
+            line: [(0, 0), (1, 1), (2, 2)],
+        }
+    }
+
+    #[test]
+    fn series_test() {
+        let mut series = Series::new(3);
+        assert_eq!(series.variant(), Variant::Normal);
+        assert_eq!(series.next_game(), None);
+
+        assert!(!series.record(GameResult::Ongoing));
+        assert!(series.record(won(CellShape::X)));
+        assert_eq!(series.status_text(), "Best of 3, game 1: X 1 - 0 O");
+
+        // Restarting the finished game doesn't count
+        assert!(!series.record(won(CellShape::O)));
+
+        assert_eq!(series.next_game(), Some(Variant::Ultimate));
+        assert!(series.record(GameResult::Draw));
+        assert_eq!(series.next_game(), Some(Variant::Normal));
+        assert!(series.record(won(CellShape::X)));
+
+        assert!(series.is_over());
+        assert_eq!(series.winner(), Some(CellShape::X));
+        assert_eq!(series.next_game(), None);
+        assert_eq!(series.status_text(), "X wins the series: X 2 - 0 O, 1 draw");
+    }
+
+    #[test]
+    fn series_tie_test() {
+        let mut series = Series::new(3);
+        series.record(won(CellShape::X));
+        series.next_game();
+        series.record(won(CellShape::O));
+        series.next_game();
+        series.record(GameResult::Draw);
+
+        assert!(series.is_over());
+        assert_eq!(series.winner(), None);
+        assert_eq!(
+            series.status_text(),
+            "The series is tied: X 1 - 1 O, 1 draw"
+        );
+
+        // A shape with a majority wins without playing every game
+        let mut series = Series::new(5);
+        for _ in 0..3 {
+            series.record(won(CellShape::O));
+            series.next_game();
+        }
+        assert_eq!(series.games_played(), 3);
+        assert_eq!(series.winner(), Some(CellShape::O));
+    }
+}
@@ -0,0 +1,382 @@
+//! This module hosts games behind a small HTTP/JSON API. The HTTP itself is handled by the
+//! `tictactoe-server` binary, and this module only maps requests to responses.
+//!
+//! The API is:
+//! - `POST /games` with `{"kind": "normal"}` or `{"kind": "ultimate"}` creates a game
+//! - `GET /games/{id}` returns the state of a game
+//! - `POST /games/{id}/moves` with `{"x": 0, "y": 0}` plays a move for the shape to move, and
+//!   ultimate games also need `"lx"` and `"ly"` for the cell in the local board
+//! - `POST /games/{id}/ai-move` plays the AI's move for the shape to move
+//!
+//! Every successful request returns the [`GameState`] of the game. Moves in a game which is
+//! already over are rejected with a 409.
+//!
+//! Requests under `/rooms` are handled by the [`relay`], which lets two devices play each other.
+
+pub mod relay;
+
+use self::relay::Relay;
+use crate::{
+    normal::board::{Board, MinimaxPlayer},
+    shared::{ai::AiPlayer, board::GameResult},
+    ultimate::board::{GlobalBoard, MctsPlayer},
+    CellShape,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The variant of tic-tac-toe being played in a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameKind {
+    /// Normal tic-tac-toe.
+    Normal,
+
+    /// Ultimate tic-tac-toe.
+    Ultimate,
+}
+
+/// A game being hosted by the server.
+#[derive(Clone, Debug)]
+enum ServerGame {
+    Normal(Board),
+    Ultimate(GlobalBoard),
+}
+
+impl ServerGame {
+    /// Check if the game has already been won or drawn.
+    fn is_over(&self) -> bool {
+        let result = match self {
+            Self::Normal(board) => board.get_winner(),
+            Self::Ultimate(board) => board.get_winner(),
+        };
+        result != GameResult::Ongoing
+    }
+}
+
+/// The body of a request to create a game.
+#[derive(Deserialize)]
+struct NewGameRequest {
+    kind: GameKind,
+}
+
+/// The body of a request to make a move.
+#[derive(Deserialize)]
+struct MoveRequest {
+    x: usize,
+    y: usize,
+    lx: Option<usize>,
+    ly: Option<usize>,
+}
+
+/// The state of a game, as returned by the API.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GameState {
+    /// The ID of the game, used in the URLs.
+    pub id: u64,
+
+    /// The variant of the game.
+    pub kind: GameKind,
+
+    /// The shape to move.
+    pub to_move: CellShape,
+
+    /// One of `"ongoing"`, `"draw"`, or `"won"`.
+    pub result: &'static str,
+
+    /// The winning shape, if there is one.
+    pub winner: Option<CellShape>,
+
+    /// The local board that the next move must be played in, if this is an ultimate game and
+    /// the move is restricted.
+    pub next_local_board: Option<(usize, usize)>,
+
+    /// The cells, indexed by `[x][y]` for normal games and by `[x][y][lx][ly]` for ultimate
+    /// games.
+    pub cells: serde_json::Value,
+}
+
+/// An HTTP response to send back to the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The JSON body.
+    pub body: String,
+}
+
+impl Response {
+    /// Create a response with the given status and JSON body.
+    fn json<T: Serialize>(status: u16, body: &T) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).expect("Responses should serialize to JSON"),
+        }
+    }
+
+    /// Create an error response with the given status and message.
+    fn error(status: u16, message: impl ToString) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message.to_string() }))
+    }
+}
+
+/// A server hosting many games at once.
+#[derive(Clone, Debug)]
+pub struct GameServer {
+    /// The games being hosted, by ID.
+    games: HashMap<u64, ServerGame>,
+
+    /// The ID to give to the next game.
+    next_id: u64,
+
+    /// The relay for games between two devices.
+    relay: Relay,
+
+    /// The maximum number of expansions for the MCTS AI in ultimate games.
+    pub max_mcts_expansions: u16,
+
+    /// The number of playouts in each expansion for the MCTS AI in ultimate games.
+    pub mcts_playouts: u8,
+}
+
+impl Default for GameServer {
+    fn default() -> Self {
+        Self {
+            games: HashMap::new(),
+            next_id: 0,
+            relay: Relay::default(),
+            max_mcts_expansions: 3000,
+            mcts_playouts: 3,
+        }
+    }
+}
+
+impl GameServer {
+    /// Handle a request with the given method, path, and body, and return the response.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match (method, segments.as_slice()) {
+            ("POST", ["games"]) => match serde_json::from_str::<NewGameRequest>(body) {
+                Ok(NewGameRequest { kind }) => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.games.insert(
+                        id,
+                        match kind {
+                            GameKind::Normal => ServerGame::Normal(Board::default()),
+                            GameKind::Ultimate => ServerGame::Ultimate(GlobalBoard::default()),
+                        },
+                    );
+                    Response::json(201, &self.state(id))
+                }
+                Err(e) => Response::error(400, e),
+            },
+            ("GET", ["games", id]) => match self.find_game(id) {
+                Ok(id) => Response::json(200, &self.state(id)),
+                Err(response) => response,
+            },
+            ("POST", ["games", id, "moves"]) => match self.find_game(id) {
+                Ok(id) => match serde_json::from_str::<MoveRequest>(body) {
+                    Ok(request) => self.make_move(id, request),
+                    Err(e) => Response::error(400, e),
+                },
+                Err(response) => response,
+            },
+            ("POST", ["games", id, "ai-move"]) => match self.find_game(id) {
+                Ok(id) => self.make_ai_move(id),
+                Err(response) => response,
+            },
+            ("GET" | "POST", ["rooms", rest @ ..]) => self.relay.handle(method, rest, body),
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    /// Parse the game ID and check that the game exists.
+    fn find_game(&self, id: &str) -> Result<u64, Response> {
+        match id.parse() {
+            Ok(id) if self.games.contains_key(&id) => Ok(id),
+            _ => Err(Response::error(404, "no such game")),
+        }
+    }
+
+    /// Play the requested move in the given game.
+    fn make_move(&mut self, id: u64, MoveRequest { x, y, lx, ly }: MoveRequest) -> Response {
+        let game = self.games.get_mut(&id).expect("The game should exist");
+        if game.is_over() {
+            return Response::error(409, "the game is over");
+        }
+
+        let result = match game {
+            ServerGame::Normal(board) => {
+                let shape = board.to_move();
+                board.make_move((x, y), shape).map_err(|e| e.to_string())
+            }
+            ServerGame::Ultimate(board) => match (lx, ly) {
+                (Some(lx), Some(ly)) => {
+                    let shape = board.to_move();
+                    board
+                        .make_move((x, y, (lx, ly)), shape)
+                        .map_err(|e| e.to_string())
+                }
+                _ => Err("ultimate moves need lx and ly".to_string()),
+            },
+        };
+
+        match result {
+            Ok(()) => Response::json(200, &self.state(id)),
+            Err(e) => Response::error(409, e),
+        }
+    }
+
+    /// Play the AI's move in the given game.
+    fn make_ai_move(&mut self, id: u64) -> Response {
+        let game = self.games.get_mut(&id).expect("The game should exist");
+        if game.is_over() {
+            return Response::error(409, "the game is over");
+        }
+
+        let played = match game {
+            ServerGame::Normal(board) => match MinimaxPlayer::default().choose_move(board) {
+                Some(coord) => {
+                    let shape = board.to_move();
+                    board.make_move(coord, shape).is_ok()
+                }
+                None => false,
+            },
+            ServerGame::Ultimate(board) => {
+                match MctsPlayer::new(self.max_mcts_expansions, self.mcts_playouts)
+                    .choose_move(board)
+                {
+                    Some(coord) => {
+                        let shape = board.to_move();
+                        board.make_move(coord, shape).is_ok()
+                    }
+                    None => false,
+                }
+            }
+        };
+
+        if played {
+            Response::json(200, &self.state(id))
+        } else {
+            Response::error(409, "the game is over")
+        }
+    }
+
+    /// Get the state of the given game.
+    fn state(&self, id: u64) -> GameState {
+        let (kind, to_move, result, next_local_board, cells) =
+            match self.games.get(&id).expect("The game should exist") {
+                ServerGame::Normal(board) => (
+                    GameKind::Normal,
+                    board.to_move(),
+                    board.get_winner(),
+                    None,
+                    serde_json::to_value(board.cells()),
+                ),
+                ServerGame::Ultimate(board) => (
+                    GameKind::Ultimate,
+                    board.to_move(),
+                    board.get_winner(),
+                    board.next_local_board(),
+                    serde_json::to_value(
+                        board.local_boards().map(|column| column.map(|b| b.cells())),
+                    ),
+                ),
+            };
+
+        GameState {
+            id,
+            kind,
+            to_move,
+            result: match result {
+                GameResult::Ongoing => "ongoing",
+                GameResult::Draw => "draw",
+                GameResult::Won { .. } => "won",
+            },
+            winner: match result {
+                GameResult::Won { shape, .. } => Some(shape),
+                _ => None,
+            },
+            next_local_board,
+            cells: cells.expect("Cells should serialize to JSON"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_test() {
+        let mut server = GameServer {
+            max_mcts_expansions: 100,
+            mcts_playouts: 1,
+            ..GameServer::default()
+        };
+
+        let response = server.handle("POST", "/games", r#"{"kind": "normal"}"#);
+        assert_eq!(response.status, 201);
+        assert!(response.body.contains(r#""id":0"#));
+
+        let response = server.handle("POST", "/games/0/moves", r#"{"x": 1, "y": 1}"#);
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains(r#""to_move":"O""#));
+
+        let response = server.handle("POST", "/games/0/moves", r#"{"x": 1, "y": 1}"#);
+        assert_eq!(response.status, 409);
+
+        let response = server.handle("POST", "/games/0/ai-move", "");
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains(r#""to_move":"X""#));
+
+        let response = server.handle("POST", "/games", r#"{"kind": "ultimate"}"#);
+        assert_eq!(response.status, 201);
+
+        let response = server.handle("POST", "/games/1/moves", r#"{"x": 1, "y": 1}"#);
+        assert_eq!(response.status, 409);
+
+        let response = server.handle(
+            "POST",
+            "/games/1/moves",
+            r#"{"x": 1, "y": 1, "lx": 0, "ly": 2}"#,
+        );
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains(r#""next_local_board":[0,2]"#));
+
+        let response = server.handle("POST", "/games/1/ai-move", "");
+        assert_eq!(response.status, 200);
+
+        assert_eq!(server.handle("GET", "/games/1", "").status, 200);
+        assert_eq!(server.handle("GET", "/games/2", "").status, 404);
+        assert_eq!(server.handle("GET", "/nothing", "").status, 404);
+        assert_eq!(server.handle("POST", "/games", "{}").status, 400);
+    }
+
+    #[test]
+    fn game_over_test() {
+        let mut server = GameServer::default();
+        server.handle("POST", "/games", r#"{"kind": "normal"}"#);
+
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            let body = format!(r#"{{"x": {x}, "y": {y}}}"#);
+            assert_eq!(server.handle("POST", "/games/0/moves", &body).status, 200);
+        }
+        let state = server.handle("GET", "/games/0", "").body;
+
+        // Neither the player nor the AI can move once X has won
+        let response = server.handle("POST", "/games/0/moves", r#"{"x": 2, "y": 1}"#);
+        assert_eq!(response.status, 409);
+        assert!(response.body.contains("the game is over"));
+
+        let response = server.handle("POST", "/games/0/ai-move", "");
+        assert_eq!(response.status, 409);
+        assert!(response.body.contains("the game is over"));
+
+        assert_eq!(server.handle("GET", "/games/0", "").body, state);
+    }
+}
@@ -0,0 +1,175 @@
+//! This module pairs two devices with a short game code and relays moves between them. The moves
+//! are not checked, since the clients already validate them with their own boards.
+//!
+//! The API is:
+//! - `POST /rooms` creates a room and returns a [`RoomJoined`] as player `0`
+//! - `POST /rooms/{code}/join` joins a room and returns a [`RoomJoined`] as player `1`
+//! - `POST /rooms/{code}/moves` with a [`SendMove`] sends a move to the other player
+//! - `GET /rooms/{code}/moves/{since}` returns the [`RoomMoves`] from index `since` onwards
+
+use super::Response;
+use crate::shared::relay::{RoomJoined, RoomMoves, SendMove};
+use rand::{seq::SliceRandom, thread_rng};
+use std::collections::HashMap;
+
+/// The characters used in room codes, without any that are easy to confuse.
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+/// The length of room codes.
+const CODE_LENGTH: usize = 5;
+
+/// A room that two players can share.
+#[derive(Clone, Debug, Default)]
+struct Room {
+    /// Whether the second player has joined.
+    joined: bool,
+
+    /// Every move played so far, in order.
+    moves: Vec<Vec<usize>>,
+}
+
+/// A relay holding many rooms at once.
+#[derive(Clone, Debug, Default)]
+pub struct Relay {
+    /// The rooms, by code.
+    rooms: HashMap<String, Room>,
+}
+
+impl Relay {
+    /// Handle a request with the given method, path segments after `rooms`, and body.
+    pub fn handle(&mut self, method: &str, segments: &[&str], body: &str) -> Response {
+        match (method, segments) {
+            ("POST", []) => {
+                let code = self.new_code();
+                self.rooms.insert(code.clone(), Room::default());
+                Response::json(201, &RoomJoined { code, player: 0 })
+            }
+            ("POST", [code, "join"]) => match self.rooms.get_mut(*code) {
+                Some(room) if room.joined => Response::error(409, "room is full"),
+                Some(room) => {
+                    room.joined = true;
+                    Response::json(
+                        200,
+                        &RoomJoined {
+                            code: code.to_string(),
+                            player: 1,
+                        },
+                    )
+                }
+                None => Response::error(404, "no such room"),
+            },
+            ("POST", [code, "moves"]) => match self.rooms.get_mut(*code) {
+                Some(room) => match serde_json::from_str::<SendMove>(body) {
+                    Ok(SendMove { player, .. }) if player as usize != room.moves.len() % 2 => {
+                        Response::error(409, "not your turn")
+                    }
+                    Ok(SendMove { coord, .. }) => {
+                        room.moves.push(coord);
+                        Response::json(200, &serde_json::json!({ "count": room.moves.len() }))
+                    }
+                    Err(e) => Response::error(400, e),
+                },
+                None => Response::error(404, "no such room"),
+            },
+            ("GET", [code, "moves", since]) => match (self.rooms.get(*code), since.parse()) {
+                (Some(room), Ok(since)) => Response::json(
+                    200,
+                    &RoomMoves {
+                        joined: room.joined,
+                        moves: room.moves.iter().skip(since).cloned().collect(),
+                    },
+                ),
+                (Some(_), Err(e)) => Response::error(400, e),
+                (None, _) => Response::error(404, "no such room"),
+            },
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    /// Generate a random room code which isn't already in use.
+    fn new_code(&self) -> String {
+        let mut rng = thread_rng();
+        loop {
+            let code: String = (0..CODE_LENGTH)
+                .map(|_| *CODE_CHARS.choose(&mut rng).unwrap() as char)
+                .collect();
+            if !self.rooms.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_test() {
+        let mut relay = Relay::default();
+
+        let response = relay.handle("POST", &[], "");
+        assert_eq!(response.status, 201);
+        let RoomJoined { code, player } = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(player, 0);
+        assert_eq!(code.len(), CODE_LENGTH);
+
+        let moves = |relay: &mut Relay, since: &str| -> RoomMoves {
+            serde_json::from_str(
+                &relay
+                    .handle("GET", &[code.as_str(), "moves", since], "")
+                    .body,
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            moves(&mut relay, "0"),
+            RoomMoves {
+                joined: false,
+                moves: vec![]
+            }
+        );
+
+        let response = relay.handle("POST", &[code.as_str(), "join"], "");
+        assert_eq!(
+            serde_json::from_str::<RoomJoined>(&response.body).unwrap(),
+            RoomJoined {
+                code: code.clone(),
+                player: 1
+            }
+        );
+        assert_eq!(
+            relay.handle("POST", &[code.as_str(), "join"], "").status,
+            409
+        );
+
+        let send = |player, coord: &str| format!(r#"{{"player": {player}, "coord": {coord}}}"#);
+        assert_eq!(
+            relay
+                .handle("POST", &[code.as_str(), "moves"], &send(1, "[1, 1]"))
+                .status,
+            409
+        );
+        assert_eq!(
+            relay
+                .handle("POST", &[code.as_str(), "moves"], &send(0, "[1, 1]"))
+                .status,
+            200
+        );
+        assert_eq!(
+            relay
+                .handle("POST", &[code.as_str(), "moves"], &send(1, "[0, 2]"))
+                .status,
+            200
+        );
+
+        assert_eq!(
+            moves(&mut relay, "1"),
+            RoomMoves {
+                joined: true,
+                moves: vec![vec![0, 2]]
+            }
+        );
+        assert_eq!(relay.handle("GET", &["NOPE", "moves", "0"], "").status, 404);
+    }
+}
@@ -0,0 +1,267 @@
+//! This module provides [`SettingsFile`], which holds all the settings of the app so that they can
+//! be exported and imported as JSON, like to move them between the desktop and web builds.
+
+use crate::{
+    app::{ThemePreference, Variant, FULLSCREEN_KEY, PRESENTATION_KEY, THEME_KEY, VARIANT_KEY},
+    gravity::app::config::GravityConfig,
+    normal::app::config::NormalConfig,
+    shared::profiles::VariantSettings,
+    ultimate::app::config::UltimateConfig,
+};
+use eframe::{egui, Storage};
+use serde::{Deserialize, Serialize};
+
+/// The name suggested when saving the settings to a file.
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_FILE_NAME: &str = "tictactoe-settings.json";
+
+/// All the settings of the app, including the config and profiles of every variant.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsFile {
+    /// The variant being played, if any.
+    pub variant: Option<Variant>,
+
+    /// Whether the window is fullscreen.
+    pub fullscreen: bool,
+
+    /// Whether the app is in presentation mode.
+    pub presentation: bool,
+
+    /// The theme of the app.
+    pub theme: ThemePreference,
+
+    /// The settings of the normal variant.
+    pub normal: VariantSettings<NormalConfig>,
+
+    /// The settings of the ultimate variant.
+    pub ultimate: VariantSettings<UltimateConfig>,
+
+    /// The settings of the nine-board variant, which shares its config with the ultimate variant.
+    pub nine_board: VariantSettings<UltimateConfig>,
+
+    /// The settings of the gravity variant.
+    pub gravity: VariantSettings<GravityConfig>,
+
+    /// The settings of the 2x2 ultimate variant, which shares its config with the ultimate
+    /// variant.
+    pub small_ultimate: VariantSettings<UltimateConfig>,
+}
+
+impl SettingsFile {
+    /// Load all the settings from the storage.
+    ///
+    /// The storage is only updated when the app saves, so the config of the variant being played
+    /// should be saved to the storage first.
+    pub fn load(storage: Option<&dyn Storage>) -> Self {
+        Self {
+            variant: storage
+                .and_then(|storage| eframe::get_value(storage, VARIANT_KEY))
+                .flatten(),
+            fullscreen: storage
+                .and_then(|storage| eframe::get_value(storage, FULLSCREEN_KEY))
+                .unwrap_or(false),
+            presentation: storage
+                .and_then(|storage| eframe::get_value(storage, PRESENTATION_KEY))
+                .unwrap_or(false),
+            theme: storage
+                .and_then(|storage| eframe::get_value(storage, THEME_KEY))
+                .unwrap_or_default(),
+            normal: VariantSettings::load(storage, "normal"),
+            ultimate: VariantSettings::load(storage, "ultimate"),
+            nine_board: VariantSettings::load(storage, "nine_board"),
+            gravity: VariantSettings::load(storage, "gravity"),
+            small_ultimate: VariantSettings::load(storage, "small_ultimate"),
+        }
+    }
+
+    /// Save the settings of every variant to the storage, so that they're loaded by the next
+    /// variant app to be created.
+    pub fn save_variants(&mut self, storage: &mut dyn Storage) {
+        self.normal.save(storage, "normal");
+        self.ultimate.save(storage, "ultimate");
+        self.nine_board.save(storage, "nine_board");
+        self.gravity.save(storage, "gravity");
+        self.small_ultimate.save(storage, "small_ultimate");
+    }
+
+    /// Serialize the settings as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Settings should serialize to JSON")
+    }
+
+    /// Parse the settings from JSON. Any missing settings are left as their defaults.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid settings file: {e}"))
+    }
+}
+
+/// The state of the window to export and import the [`SettingsFile`].
+#[derive(Default)]
+pub struct SettingsTransfer {
+    /// Whether the window is open.
+    pub open: bool,
+
+    /// The JSON in the text area, which is only used on the web since native uses files.
+    #[cfg(target_arch = "wasm32")]
+    text: String,
+
+    /// The result of the last export or import, shown at the bottom of the window.
+    message: Option<String>,
+}
+
+impl SettingsTransfer {
+    /// Show the window if it's open.
+    ///
+    /// The current settings are only computed if they're exported. If settings are imported,
+    /// they're returned so that the caller can apply them.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        current: impl FnOnce() -> SettingsFile,
+    ) -> Option<SettingsFile> {
+        let mut imported = None;
+        let mut open = self.open;
+
+        egui::Window::new("Import and export settings")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                imported = self.show_contents(ui, current);
+
+                if let Some(message) = &self.message {
+                    ui.small(message);
+                }
+            });
+
+        self.open = open && imported.is_none();
+        imported
+    }
+
+    /// Show buttons to save the settings to a JSON file and load them from one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl FnOnce() -> SettingsFile,
+    ) -> Option<SettingsFile> {
+        ui.label("Move your settings to another computer or to the web version.");
+
+        let mut imported = None;
+        ui.horizontal(|ui| {
+            if ui.button("Export\u{2026}").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(SETTINGS_FILE_NAME)
+                    .save_file()
+                {
+                    self.message = Some(match std::fs::write(&path, current().to_json()) {
+                        Ok(()) => format!("Saved settings to {}", path.display()),
+                        Err(e) => format!("Failed to save settings: {e}"),
+                    });
+                }
+            }
+
+            if ui.button("Import\u{2026}").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    let result = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read settings: {e}"))
+                        .and_then(|json| SettingsFile::from_json(&json));
+                    match result {
+                        Ok(settings) => {
+                            self.message = None;
+                            imported = Some(settings);
+                        }
+                        Err(e) => self.message = Some(e),
+                    }
+                }
+            }
+        });
+
+        imported
+    }
+
+    /// Show a text area to copy the settings out of as JSON and paste them into.
+    #[cfg(target_arch = "wasm32")]
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl FnOnce() -> SettingsFile,
+    ) -> Option<SettingsFile> {
+        ui.label("Move your settings to another browser or to the desktop version.");
+
+        let mut imported = None;
+        ui.horizontal(|ui| {
+            if ui
+                .button("Export")
+                .on_hover_text("Copy your settings as JSON")
+                .clicked()
+            {
+                self.text = current().to_json();
+                ui.output().copied_text = self.text.clone();
+                self.message = Some("Copied settings to the clipboard".to_string());
+            }
+
+            if ui
+                .add_enabled(!self.text.trim().is_empty(), egui::Button::new("Import"))
+                .on_hover_text("Load the settings pasted below")
+                .clicked()
+            {
+                match SettingsFile::from_json(&self.text) {
+                    Ok(settings) => {
+                        self.message = None;
+                        self.text.clear();
+                        imported = Some(settings);
+                    }
+                    Err(e) => self.message = Some(e),
+                }
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(300.)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.text)
+                        .code_editor()
+                        .hint_text("Paste settings here"),
+                );
+            });
+
+        imported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shared::profiles::DEFAULT_PROFILE, CellShape};
+
+    #[test]
+    fn json_round_trip_test() {
+        let mut settings = SettingsFile {
+            variant: Some(Variant::Ultimate),
+            ..SettingsFile::default()
+        };
+        settings.normal.config.player_shape = CellShape::O;
+        settings.ultimate.config.max_mcts_expansions = 42;
+
+        let parsed = SettingsFile::from_json(&settings.to_json()).unwrap();
+        assert_eq!(parsed.variant, Some(Variant::Ultimate));
+        assert_eq!(parsed.normal.config.player_shape, CellShape::O);
+        assert_eq!(parsed.ultimate.config.max_mcts_expansions, 42);
+        assert_eq!(parsed.normal.profiles.selected(), DEFAULT_PROFILE);
+
+        // Missing settings are left as their defaults
+        let parsed = SettingsFile::from_json(r#"{ "fullscreen": true }"#).unwrap();
+        assert!(parsed.fullscreen);
+        assert!(!parsed.presentation);
+        assert_eq!(parsed.variant, None);
+
+        assert!(SettingsFile::from_json("not json").is_err());
+    }
+}
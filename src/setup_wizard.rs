@@ -0,0 +1,223 @@
+//! This module provides [`SetupWizard`], which is shown on the first launch to choose the basic
+//! settings and then start a game straight away.
+
+use crate::app::{Difficulty, ThemePreference, Variant};
+use eframe::egui;
+
+/// The choices made in the [`SetupWizard`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetupChoices {
+    /// The variant to play.
+    pub variant: Variant,
+
+    /// Whether to play against the AI.
+    pub playing_ai: bool,
+
+    /// The difficulty of the AI, which is ignored if not playing against it.
+    pub difficulty: Difficulty,
+
+    /// The theme of the app.
+    pub theme: ThemePreference,
+}
+
+impl Default for SetupChoices {
+    fn default() -> Self {
+        Self {
+            variant: Variant::Normal,
+            playing_ai: true,
+            difficulty: Difficulty::Medium,
+            theme: ThemePreference::default(),
+        }
+    }
+}
+
+/// A page of the [`SetupWizard`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Step {
+    /// Choose the [`variant`](SetupChoices::variant).
+    #[default]
+    Variant,
+
+    /// Choose whether to play against the AI.
+    Opponent,
+
+    /// Choose the [`difficulty`](SetupChoices::difficulty), which is skipped if not playing
+    /// against the AI.
+    Difficulty,
+
+    /// Choose the [`theme`](SetupChoices::theme).
+    Theme,
+}
+
+/// The result of the [`SetupWizard`] once the player leaves it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WizardOutcome {
+    /// The player went through the whole wizard and wants to start a game with these choices.
+    Finished(SetupChoices),
+
+    /// The player skipped the wizard, so we should go to the selection screen.
+    Skipped,
+}
+
+/// A short sequence of pages shown on the first launch, which asks for the variant, the opponent,
+/// the difficulty, and the theme.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SetupWizard {
+    /// The page currently being shown.
+    step: Step,
+
+    /// The choices made so far.
+    choices: SetupChoices,
+}
+
+impl SetupWizard {
+    /// Get the choices made so far, which can be used to preview the theme.
+    pub fn choices(&self) -> SetupChoices {
+        self.choices
+    }
+
+    /// Go to the next page, skipping the difficulty if not playing against the AI. Returns false
+    /// if this is the last page.
+    fn next(&mut self) -> bool {
+        self.step = match self.step {
+            Step::Variant => Step::Opponent,
+            Step::Opponent if self.choices.playing_ai => Step::Difficulty,
+            Step::Opponent | Step::Difficulty => Step::Theme,
+            Step::Theme => return false,
+        };
+        true
+    }
+
+    /// Go back to the previous page, skipping the difficulty if not playing against the AI.
+    fn back(&mut self) {
+        self.step = match self.step {
+            Step::Variant | Step::Opponent => Step::Variant,
+            Step::Difficulty => Step::Opponent,
+            Step::Theme if self.choices.playing_ai => Step::Difficulty,
+            Step::Theme => Step::Opponent,
+        };
+    }
+
+    /// Show the current page of the wizard in the middle of the screen. Returns the outcome once
+    /// the player finishes or skips the wizard.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<WizardOutcome> {
+        let mut outcome = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 5.);
+                ui.heading("Welcome to tic-tac-toe!");
+                ui.add_space(20.);
+
+                let choices = &mut self.choices;
+                match self.step {
+                    Step::Variant => {
+                        ui.label("Which game would you like to play?");
+                        ui.radio_value(&mut choices.variant, Variant::Normal, "Normal")
+                            .on_hover_text("Classic tic-tac-toe on a 3x3 board");
+                        ui.radio_value(&mut choices.variant, Variant::Ultimate, "Ultimate")
+                            .on_hover_text(
+                                "Nine boards in one, where your move decides where your \
+                                opponent plays next",
+                            );
+                        ui.radio_value(&mut choices.variant, Variant::NineBoard, "Nine-board")
+                            .on_hover_text(
+                                "Like ultimate, but the first to win any one board wins the game",
+                            );
+                        ui.radio_value(&mut choices.variant, Variant::Gravity, "Gravity")
+                            .on_hover_text("Pieces fall to the bottom of the column you choose");
+                        ui.radio_value(
+                            &mut choices.variant,
+                            Variant::SmallUltimate,
+                            "2x2 ultimate",
+                        )
+                        .on_hover_text(
+                            "A gentler introduction to ultimate with four boards, where the \
+                            first to win two boards wins the game",
+                        );
+                    }
+                    Step::Opponent => {
+                        ui.label("Who would you like to play against?");
+                        ui.radio_value(&mut choices.playing_ai, true, "The AI");
+                        ui.radio_value(&mut choices.playing_ai, false, "Another person");
+                    }
+                    Step::Difficulty => {
+                        ui.label("How strong should the AI be?");
+                        ui.radio_value(&mut choices.difficulty, Difficulty::Easy, "Easy")
+                            .on_hover_text("Plays randomly");
+                        ui.radio_value(&mut choices.difficulty, Difficulty::Medium, "Medium")
+                            .on_hover_text("Takes wins and blocks losses");
+                        ui.radio_value(&mut choices.difficulty, Difficulty::Hard, "Hard")
+                            .on_hover_text("Plays as well as it can");
+                        ui.radio_value(&mut choices.difficulty, Difficulty::Adaptive, "Adaptive")
+                            .on_hover_text("Gets stronger when you win and weaker when you lose");
+                    }
+                    Step::Theme => {
+                        ui.label("Which theme would you like?");
+                        for theme in ThemePreference::ALL {
+                            ui.radio_value(&mut choices.theme, theme, theme.name());
+                        }
+                    }
+                }
+
+                ui.add_space(20.);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.step != Step::Variant, egui::Button::new("Back"))
+                        .clicked()
+                    {
+                        self.back();
+                    }
+
+                    let next_text = if self.step == Step::Theme {
+                        "Start playing"
+                    } else {
+                        "Next"
+                    };
+                    if ui.button(next_text).clicked() && !self.next() {
+                        outcome = Some(WizardOutcome::Finished(self.choices));
+                    }
+
+                    if ui
+                        .button("Skip")
+                        .on_hover_text("Choose everything yourself later")
+                        .clicked()
+                    {
+                        outcome = Some(WizardOutcome::Skipped);
+                    }
+                });
+            });
+        });
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_test() {
+        let mut wizard = SetupWizard::default();
+        assert!(wizard.next());
+        assert_eq!(wizard.step, Step::Opponent);
+        assert!(wizard.next());
+        assert_eq!(wizard.step, Step::Difficulty);
+        assert!(wizard.next());
+        assert_eq!(wizard.step, Step::Theme);
+        assert!(!wizard.next());
+
+        // The difficulty is skipped when not playing against the AI
+        wizard.choices.playing_ai = false;
+        wizard.back();
+        assert_eq!(wizard.step, Step::Opponent);
+        assert!(wizard.next());
+        assert_eq!(wizard.step, Step::Theme);
+
+        wizard.back();
+        wizard.back();
+        wizard.back();
+        assert_eq!(wizard.step, Step::Variant);
+    }
+}
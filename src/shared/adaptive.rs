@@ -0,0 +1,120 @@
+//! This module provides [`AdaptiveDifficulty`], which tracks how well the player is doing against
+//! the AI and adjusts the strength of the AI to keep games close.
+
+use super::board::GameResult;
+use crate::CellShape;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// How much weight the most recent game has in the
+/// [`recent_win_rate`](AdaptiveDifficulty::recent_win_rate).
+const RECENT_GAME_WEIGHT: f32 = 0.25;
+
+/// How quickly the [`strength`](AdaptiveDifficulty::strength) changes when the player is winning
+/// or losing too often.
+const ADJUSTMENT_RATE: f32 = 0.5;
+
+/// The state of an adaptive AI difficulty, meant to be saved and loaded between sessions.
+///
+/// The AI gets stronger when the player wins more than half of their recent games, and weaker
+/// when they win less than half, so that the games stay close to 50/50. Draws count as half a
+/// win.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveDifficulty {
+    /// The strength of the AI, from 0 for the weakest to 1 for the strongest.
+    pub strength: f32,
+
+    /// A moving average of the player's score in recent games, from 0 if they lost them all to 1
+    /// if they won them all.
+    pub recent_win_rate: f32,
+
+    /// The number of games that have been recorded.
+    pub games_played: u32,
+}
+
+impl Default for AdaptiveDifficulty {
+    fn default() -> Self {
+        Self {
+            strength: 0.5,
+            recent_win_rate: 0.5,
+            games_played: 0,
+        }
+    }
+}
+
+impl AdaptiveDifficulty {
+    /// Update the state with the result of a finished game where the player was playing as
+    /// `player_shape`, and adjust the [`strength`](Self::strength) of the AI.
+    ///
+    /// Ongoing games are ignored.
+    pub fn record_result(&mut self, result: GameResult, player_shape: CellShape) {
+        let score = match result {
+            GameResult::Ongoing => return,
+            GameResult::Draw => 0.5,
+            GameResult::Won { shape, .. } if shape == player_shape => 1.,
+            GameResult::Won { .. } => 0.,
+        };
+
+        self.recent_win_rate += RECENT_GAME_WEIGHT * (score - self.recent_win_rate);
+        self.strength =
+            (self.strength + ADJUSTMENT_RATE * (self.recent_win_rate - 0.5)).clamp(0., 1.);
+        self.games_played += 1;
+    }
+
+    /// Linearly interpolate between the `weakest` and `strongest` values by the current
+    /// [`strength`](Self::strength).
+    pub fn interpolate(&self, weakest: f32, strongest: f32) -> f32 {
+        weakest + self.strength * (strongest - weakest)
+    }
+
+    /// Show the current state in a settings window, with a button to reset it.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "AI strength {:.0}%, your recent win rate {:.0}% ({} games)",
+                100. * self.strength,
+                100. * self.recent_win_rate,
+                self.games_played
+            ));
+
+            if ui.button("Reset").clicked() {
+                *self = Self::default();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_result_test() {
+        let win = GameResult::Won {
+            shape: CellShape::X,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        let mut difficulty = AdaptiveDifficulty::default();
+        difficulty.record_result(GameResult::Ongoing, CellShape::X);
+        assert_eq!(difficulty, AdaptiveDifficulty::default());
+
+        // Draws keep the games close, so the strength shouldn't change
+        difficulty.record_result(GameResult::Draw, CellShape::X);
+        assert_eq!(difficulty.strength, 0.5);
+        assert_eq!(difficulty.games_played, 1);
+
+        difficulty.record_result(win, CellShape::X);
+        assert!(difficulty.recent_win_rate > 0.5);
+        assert!(difficulty.strength > 0.5);
+
+        let mut difficulty = AdaptiveDifficulty::default();
+        for _ in 0..20 {
+            difficulty.record_result(win, CellShape::O);
+        }
+        assert!(difficulty.recent_win_rate < 0.1);
+        assert_eq!(difficulty.strength, 0.);
+        assert_eq!(difficulty.interpolate(10., 20.), 10.);
+    }
+}
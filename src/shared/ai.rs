@@ -0,0 +1,258 @@
+//! This module provides the [`AiPlayer`] trait, which lets different AI backends be plugged into
+//! either variant, along with some simple baseline bots which work for any [`Game`].
+
+use super::board::GameResult;
+use crate::CellShape;
+use rand::{rngs::SmallRng, seq::SliceRandom, thread_rng, SeedableRng};
+
+/// A game that an [`AiPlayer`] can play, like a [`normal`](crate::normal::board::Board) or
+/// [`ultimate`](crate::ultimate::board::GlobalBoard) board.
+pub trait Game: Clone {
+    /// The type of a move in this game.
+    type Move: Copy;
+
+    /// Return a vec of all the legal moves in the current position.
+    fn moves(&self) -> Vec<Self::Move>;
+
+    /// Return the shape which must make the next move.
+    fn to_move(&self) -> CellShape;
+
+    /// Play the given move as the shape which is next to move, and return whether the move was
+    /// legal. If it wasn't legal, then the game is unchanged.
+    fn play(&mut self, mv: Self::Move) -> bool;
+
+    /// Skip the turn of the shape which is next to move, so that the other shape moves next.
+    fn skip_turn(&mut self);
+
+    /// Return the result of the game so far.
+    fn get_winner(&self) -> GameResult;
+
+    /// Play random legal moves in place until the game is over, or until there are no legal
+    /// moves. This is how MCTS plays out a position, so games can override it with something
+    /// faster than calling [`moves`](Self::moves) for every move.
+    fn play_out_randomly(&mut self, rng: &mut SmallRng) {
+        while self.get_winner() == GameResult::Ongoing {
+            let Some(&mv) = self.moves().choose(rng) else {
+                break;
+            };
+
+            let legal = self.play(mv);
+            debug_assert!(legal, "A random legal move should always be playable");
+        }
+    }
+}
+
+/// Return a fast random number generator to use for the whole of one search, so that the hot
+/// loops of the search don't have to reacquire [`thread_rng`] for every random choice.
+///
+/// If there's a `seed`, then the generator is seeded with it, so that the search is deterministic.
+/// Otherwise, it's seeded from [`thread_rng`].
+pub fn search_rng(seed: Option<u64>) -> SmallRng {
+    match seed {
+        Some(seed) => SmallRng::seed_from_u64(seed),
+        None => {
+            SmallRng::from_rng(thread_rng()).expect("Seeding from `thread_rng` should never fail")
+        }
+    }
+}
+
+/// A player which can choose a move in a game of type `G`.
+///
+/// Implement this trait to write your own bot. It gets `&mut self` so that it can keep state
+/// between moves.
+pub trait AiPlayer<G: Game> {
+    /// Choose a move to play in the given position, as the shape which is next to move.
+    ///
+    /// This returns `None` if there are no legal moves.
+    fn choose_move(&mut self, game: &G) -> Option<G::Move>;
+}
+
+/// An [`AiPlayer`] which plays a random legal move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RandomBot;
+
+impl<G: Game> AiPlayer<G> for RandomBot {
+    fn choose_move(&mut self, game: &G) -> Option<G::Move> {
+        game.moves().choose(&mut thread_rng()).copied()
+    }
+}
+
+/// An [`AiPlayer`] which takes a win if it can, and otherwise plays a random move which doesn't
+/// let the opponent win on their next move, if there is one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GreedyBot;
+
+/// Return the game after the given move has been played.
+pub fn after_move<G: Game>(game: &G, mv: G::Move) -> G {
+    let mut game = game.clone();
+    game.play(mv);
+    game
+}
+
+/// Check if the shape which is next to move can win with the given move.
+pub fn is_winning_move<G: Game>(game: &G, mv: G::Move) -> bool {
+    let shape = game.to_move();
+    matches!(after_move(game, mv).get_winner(), GameResult::Won { shape: winner, .. } if winner == shape)
+}
+
+impl<G: Game> AiPlayer<G> for GreedyBot {
+    fn choose_move(&mut self, game: &G) -> Option<G::Move> {
+        let moves = game.moves();
+
+        if let Some(&mv) = moves.iter().find(|&&mv| is_winning_move(game, mv)) {
+            return Some(mv);
+        }
+
+        let safe_moves: Vec<G::Move> = moves
+            .iter()
+            .copied()
+            .filter(|&mv| {
+                let game = after_move(game, mv);
+                game.get_winner() != GameResult::Ongoing
+                    || !game
+                        .moves()
+                        .into_iter()
+                        .any(|reply| is_winning_move(&game, reply))
+            })
+            .collect();
+
+        if safe_moves.is_empty() {
+            moves.choose(&mut thread_rng()).copied()
+        } else {
+            safe_moves.choose(&mut thread_rng()).copied()
+        }
+    }
+}
+
+/// Play a game to the end from the given position, with `x_player` choosing the moves for
+/// [`X`](CellShape::X) and `o_player` choosing the moves for [`O`](CellShape::O), and return the
+/// result.
+///
+/// If a player can't find a legal move, then the game stops early and is returned as it is.
+pub fn play_game<G: Game>(
+    mut game: G,
+    x_player: &mut dyn AiPlayer<G>,
+    o_player: &mut dyn AiPlayer<G>,
+) -> GameResult {
+    while game.get_winner() == GameResult::Ongoing {
+        let player = match game.to_move() {
+            CellShape::X => &mut *x_player,
+            CellShape::O => &mut *o_player,
+        };
+
+        let Some(mv) = player.choose_move(&game) else {
+            break;
+        };
+        if !game.play(mv) {
+            break;
+        }
+    }
+
+    game.get_winner()
+}
+
+/// Count the positions reached by playing every sequence of exactly `depth` legal moves from the
+/// given position, to check that [`Game::moves`] and [`Game::play`] agree with each other and
+/// with the rules.
+///
+/// A game that has ended has no legal moves, so sequences which end the game early aren't
+/// counted.
+#[cfg(any(test, feature = "bench"))]
+pub fn perft<G: Game>(game: &G, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if game.get_winner() != GameResult::Ongoing {
+        return 0;
+    }
+
+    game.moves()
+        .into_iter()
+        .map(|mv| {
+            let mut game = game.clone();
+            assert!(game.play(mv), "Every move from `moves` should be legal");
+            perft(&game, depth - 1)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal::{board::Board, test_utils::make_board};
+
+    #[test]
+    fn random_bot_test() {
+        let board = make_board!(X O X; O X O; _ _ O);
+        for _ in 0..10 {
+            let mv = RandomBot.choose_move(&board).unwrap();
+            assert!(mv == (0, 2) || mv == (1, 2));
+        }
+
+        let board = make_board!(X O X; O X O; O X O);
+        assert_eq!(RandomBot.choose_move(&board), None);
+        assert_eq!(GreedyBot.choose_move(&board), None);
+    }
+
+    #[test]
+    fn greedy_bot_test() {
+        // X can win or block, and winning is better
+        let board = make_board!(X X _; O O _; _);
+        assert_eq!(board.to_move(), CellShape::X);
+        for _ in 0..10 {
+            assert_eq!(GreedyBot.choose_move(&board), Some((2, 0)));
+        }
+
+        // X has to block O
+        let board = make_board!(X _ _; O O _; X _ _);
+        for _ in 0..10 {
+            assert_eq!(GreedyBot.choose_move(&board), Some((2, 1)));
+        }
+
+        let board = Board::default();
+        assert!(GreedyBot.choose_move(&board).is_some());
+    }
+
+    #[test]
+    fn perft_test() {
+        use crate::ultimate::board::{GlobalBoard, RuleSet};
+
+        // The number of games of tic-tac-toe which are still going after each number of moves,
+        // since no game can end before the fifth move
+        let board = Board::default();
+        let counts: Vec<u64> = (0..=9).map(|depth| perft(&board, depth)).collect();
+        assert_eq!(
+            counts,
+            vec![1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872]
+        );
+        assert_eq!(perft(&make_board!(X O X; O X O; O X O), 1), 0);
+
+        // A move in the cell matching its own local board sends the opponent back to that board,
+        // which then has one fewer empty cell
+        for rules in [RuleSet::Standard, RuleSet::ClosedBoards] {
+            let board = GlobalBoard::default().with_rules(rules);
+            assert_eq!(perft(&board, 1), 81);
+            assert_eq!(perft(&board, 2), 72 * 9 + 9 * 8);
+        }
+    }
+
+    #[test]
+    fn play_game_test() {
+        use crate::normal::board::MinimaxPlayer;
+
+        assert_ne!(
+            play_game(Board::default(), &mut RandomBot, &mut GreedyBot),
+            GameResult::Ongoing
+        );
+
+        // Perfect play always ends in a draw
+        assert_eq!(
+            play_game(
+                Board::default(),
+                &mut MinimaxPlayer::default(),
+                &mut MinimaxPlayer::default()
+            ),
+            GameResult::Draw
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! This module provides [`AiTask`], a handle to an AI move being computed in the background, and
+//! [`MoveChannel`], which the apps use to get those moves back.
+//!
+//! The apps hold an [`AiTask`] exactly while they're waiting for the AI to make a move, so that
+//! cancellation, progress, and waiting for the move are all handled in one place.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+};
+use web_time::{Duration, Instant};
+
+#[cfg(target_arch = "wasm32")]
+use std::{cell::RefCell, rc::Rc};
+
+/// A boxed computation to be run by an [`AiTask`] on Wasm.
+#[cfg(target_arch = "wasm32")]
+type PendingWork = Rc<RefCell<Option<Box<dyn FnOnce()>>>>;
+
+/// A handle to an AI move being computed in the background.
+///
+/// When the move has been computed, it gets sent down an `mpsc` channel, unless the task has been
+/// cancelled with [`cancel`](AiTask::cancel). Dropping the handle also cancels the task, so that
+/// a stale move can never arrive after the handle has been thrown away.
+///
+/// While the move is being computed, the task can report its progress, like the move that the AI
+/// is currently considering, which is available from [`progress`](AiTask::progress).
+pub struct AiTask<P = ()> {
+    /// Whether the task has been cancelled. The move is only sent if this is false.
+    cancelled: Arc<AtomicBool>,
+
+    /// The receiver that receives the progress reported by the task.
+    progress_rx: mpsc::Receiver<P>,
+
+    /// The most recent progress reported by the task. See [`progress`](AiTask::progress).
+    progress: Option<P>,
+
+    /// The handle of the background thread computing the move.
+    #[cfg(not(target_arch = "wasm32"))]
+    handle: Option<std::thread::JoinHandle<()>>,
+
+    /// The timeout which will compute and send the move once the delay has elapsed.
+    #[cfg(target_arch = "wasm32")]
+    timeout: Option<gloo_timers::callback::Timeout>,
+
+    /// The work to be done by the timeout, shared with the timeout so that it can be run early in
+    /// [`join`](AiTask::join).
+    #[cfg(target_arch = "wasm32")]
+    work: PendingWork,
+}
+
+impl AiTask {
+    /// Compute a move in the background with `compute_move` and send it down `tx` after
+    /// `min_delay`, without reporting any progress. See
+    /// [`spawn_with_progress`](AiTask::spawn_with_progress).
+    pub fn spawn<M, F>(min_delay: Duration, tx: mpsc::Sender<M>, compute_move: F) -> Self
+    where
+        M: Send + 'static,
+        F: FnOnce(Option<Instant>) -> M + Send + 'static,
+    {
+        Self::spawn_with_progress(min_delay, tx, |deadline, _| compute_move(deadline))
+    }
+}
+
+impl<P: Send + 'static> AiTask<P> {
+    /// Spawn a background thread which computes a move with `compute_move` and sends it down `tx`
+    /// no sooner than `min_delay` after the task was spawned.
+    ///
+    /// `compute_move` is given a deadline, which is the time at which the move will be sent if it
+    /// has been computed by then, and a sender to report its progress down.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn_with_progress<M, F>(
+        min_delay: Duration,
+        tx: mpsc::Sender<M>,
+        compute_move: F,
+    ) -> Self
+    where
+        M: Send + 'static,
+        F: FnOnce(Option<Instant>, mpsc::Sender<P>) -> M + Send + 'static,
+    {
+        use std::thread;
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (progress_tx, progress_rx) = mpsc::channel();
+
+        let handle = {
+            let cancelled = Arc::clone(&cancelled);
+            thread::spawn(move || {
+                let deadline = Instant::now() + min_delay;
+                let mv = compute_move(Some(deadline), progress_tx);
+                thread::sleep(deadline.saturating_duration_since(Instant::now()));
+
+                if !cancelled.load(Ordering::Acquire) {
+                    let _ = tx.send(mv);
+                }
+            })
+        };
+
+        Self {
+            cancelled,
+            progress_rx,
+            progress: None,
+            handle: Some(handle),
+        }
+    }
+
+    /// Set a timeout which computes a move with `compute_move` and sends it down `tx` after
+    /// `min_delay`.
+    ///
+    /// There are no threads on Wasm, so the move is only computed once the delay has elapsed,
+    /// which means `compute_move` is not given a deadline, and any progress it reports is only
+    /// available once the move has been sent.
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn_with_progress<M, F>(
+        min_delay: Duration,
+        tx: mpsc::Sender<M>,
+        compute_move: F,
+    ) -> Self
+    where
+        M: Send + 'static,
+        F: FnOnce(Option<Instant>, mpsc::Sender<P>) -> M + Send + 'static,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (progress_tx, progress_rx) = mpsc::channel();
+
+        let work: PendingWork = {
+            let cancelled = Arc::clone(&cancelled);
+            let work: Box<dyn FnOnce()> = Box::new(move || {
+                if !cancelled.load(Ordering::Acquire) {
+                    let _ = tx.send(compute_move(None, progress_tx));
+                }
+            });
+            Rc::new(RefCell::new(Some(work)))
+        };
+
+        let timeout = {
+            let work = Rc::clone(&work);
+            gloo_timers::callback::Timeout::new(min_delay.as_millis() as u32, move || {
+                let work = work.borrow_mut().take();
+                if let Some(work) = work {
+                    work();
+                }
+            })
+        };
+
+        Self {
+            cancelled,
+            progress_rx,
+            progress: None,
+            timeout: Some(timeout),
+            work,
+        }
+    }
+}
+
+impl<P> AiTask<P> {
+    /// Create a task which never computes or sends anything, for tests which send the move
+    /// themselves.
+    #[cfg(test)]
+    pub(crate) fn placeholder() -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            progress_rx: mpsc::channel().1,
+            progress: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            handle: None,
+            #[cfg(target_arch = "wasm32")]
+            timeout: None,
+            #[cfg(target_arch = "wasm32")]
+            work: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Return the most recent progress reported by the task, if it has reported any yet.
+    pub fn progress(&mut self) -> Option<&P> {
+        if let Some(progress) = self.progress_rx.try_iter().last() {
+            self.progress = Some(progress);
+        }
+        self.progress.as_ref()
+    }
+
+    /// Cancel the task, so that its move will never be sent.
+    ///
+    /// On native, the background thread will still run to completion, but its result is thrown
+    /// away.
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(timeout) = self.timeout.take() {
+                timeout.cancel();
+            }
+            self.work.borrow_mut().take();
+        }
+    }
+
+    /// Check if the task has finished, which means the move has been sent unless the task was
+    /// cancelled.
+    pub fn is_finished(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.handle
+                .as_ref()
+                .map_or(true, |handle| handle.is_finished())
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.work.borrow().is_none()
+        }
+    }
+
+    /// Wait for the task to finish, which means the move has been sent unless the task was
+    /// cancelled.
+    ///
+    /// On Wasm, we can't block, so if the delay hasn't elapsed yet, the move is computed and
+    /// sent immediately.
+    pub fn join(mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(timeout) = self.timeout.take() {
+                timeout.cancel();
+            }
+
+            let work = self.work.borrow_mut().take();
+            if let Some(work) = work {
+                work();
+            }
+        }
+    }
+}
+
+impl<P> Drop for AiTask<P> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// A move computed by an [`AiTask`], tagged with the generation of the game it was computed for.
+/// The move is `None` if the AI couldn't find one.
+pub type MoveMessage<M> = (u32, Option<M>);
+
+/// A channel for the moves computed by [`AiTask`]s, shared by all the apps.
+///
+/// Each app has a generation which is incremented every time the game is restarted, and every
+/// move is tagged with the generation of the game it was computed for, so that moves computed for
+/// a previous game can be discarded by [`receive`](Self::receive).
+pub struct MoveChannel<M> {
+    /// The sender that we pass to the background tasks.
+    tx: mpsc::Sender<MoveMessage<M>>,
+
+    /// The receiver that receives the computed moves.
+    rx: mpsc::Receiver<MoveMessage<M>>,
+}
+
+impl<M> Default for MoveChannel<M> {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+}
+
+impl<M> MoveChannel<M> {
+    /// Return a sender to pass to [`AiTask::spawn`].
+    pub fn sender(&self) -> mpsc::Sender<MoveMessage<M>> {
+        self.tx.clone()
+    }
+
+    /// Return the move for the game with the given generation if it has arrived, discarding any
+    /// moves from other generations.
+    ///
+    /// The inner option is `None` if the task finished without finding a move, like when the game
+    /// was already over, so that the app can stop waiting for it.
+    pub fn receive(&self, generation: u32) -> Option<Option<M>> {
+        self.rx
+            .try_iter()
+            .filter(|&(g, _)| g == generation)
+            .last()
+            .map(|(_, mv)| mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_test() {
+        let (tx, rx) = mpsc::channel();
+        let task = AiTask::spawn(Duration::from_millis(10), tx, |deadline| {
+            (deadline, Some(5))
+        });
+
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+        task.join();
+
+        // The deadline is sent back so that it's checked here, since a panic in the task's thread
+        // wouldn't fail the test
+        let (deadline, mv) = rx.try_recv().unwrap();
+        assert!(deadline.is_some());
+        assert_eq!(mv, Some(5));
+    }
+
+    #[test]
+    fn progress_test() {
+        let (tx, rx) = mpsc::channel();
+        let mut task = AiTask::spawn_with_progress(Duration::ZERO, tx, |_, progress_tx| {
+            for depth in 1..=3 {
+                progress_tx.send(depth).unwrap();
+            }
+            Some(5)
+        });
+
+        // Only the latest progress is kept, and it's still there after the move has been sent
+        assert_eq!(rx.recv(), Ok(Some(5)));
+        assert_eq!(task.progress(), Some(&3));
+        assert_eq!(task.progress(), Some(&3));
+
+        assert_eq!(AiTask::<u8>::placeholder().progress(), None);
+    }
+
+    #[test]
+    fn move_channel_test() {
+        let channel = MoveChannel::default();
+        assert_eq!(channel.receive(1), None);
+
+        channel.sender().send((0, Some((0, 0)))).unwrap();
+        channel.sender().send((1, Some((1, 1)))).unwrap();
+        assert_eq!(channel.receive(1), Some(Some((1, 1))));
+
+        // A task which finished without a move is told apart from one which hasn't finished yet
+        channel.sender().send((1, None)).unwrap();
+        assert_eq!(channel.receive(1), Some(None));
+        assert_eq!(channel.receive(1), None);
+
+        // Moves from other generations are thrown away once they've been received
+        channel.sender().send((1, Some((2, 2)))).unwrap();
+        assert_eq!(channel.receive(2), None);
+        assert_eq!(channel.receive(1), None);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (tx, rx) = mpsc::channel();
+        let mut task = AiTask::spawn(Duration::from_millis(10), tx.clone(), |_| Some(5));
+        task.cancel();
+        task.join();
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+
+        // Dropping the handle also cancels the task
+        drop(AiTask::spawn(Duration::from_millis(10), tx, |_| Some(5)));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+    }
+}
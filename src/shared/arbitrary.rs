@@ -0,0 +1,260 @@
+//! This module provides [`Arbitrary`] instances for the boards of both variants, so that their
+//! invariants can be checked with `proptest`.
+//!
+//! Normal and global boards are only ever generated by playing legal moves from the start of a
+//! game, so every generated position is one that can really be reached.
+
+use super::{ai::Game, board::GameResult};
+use crate::{
+    normal::board::Board,
+    ultimate::board::{DrawRule, GlobalBoard, LocalBoard, OpeningRule, RuleSet, WinCondition},
+    CellShape,
+};
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::vec,
+    prop_oneof,
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+use std::fmt::Debug;
+
+/// The most moves that can be played in a game of normal tic-tac-toe.
+const MAX_NORMAL_MOVES: usize = 9;
+
+/// The most moves that can be played in a game of ultimate tic-tac-toe.
+const MAX_ULTIMATE_MOVES: usize = 81;
+
+/// Generate games reached by playing up to `max_moves` random legal moves from `start`, stopping
+/// early if the game ends.
+pub fn reachable<G>(start: G, max_moves: usize) -> BoxedStrategy<G>
+where
+    G: Game + Debug + 'static,
+{
+    vec(any::<Index>(), 0..=max_moves)
+        .prop_map(move |picks| {
+            let mut game = start.clone();
+            for pick in picks {
+                let moves = game.moves();
+                if game.get_winner() != GameResult::Ongoing || moves.is_empty() {
+                    break;
+                }
+
+                let legal = game.play(moves[pick.index(moves.len())]);
+                debug_assert!(legal, "Every move from `moves` should be legal");
+            }
+            game
+        })
+        .boxed()
+}
+
+/// Generate a game which is still going along with one of its legal moves.
+pub fn with_legal_move<G>() -> BoxedStrategy<(G, G::Move)>
+where
+    G: Game + Arbitrary + Debug + 'static,
+    G::Move: Debug,
+{
+    any::<G>()
+        .prop_filter("the game should have legal moves", |game| {
+            game.get_winner() == GameResult::Ongoing && !game.moves().is_empty()
+        })
+        .prop_flat_map(|game| {
+            let moves = game.moves();
+            (Just(game), select(moves))
+        })
+        .boxed()
+}
+
+impl Arbitrary for CellShape {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![Just(CellShape::X), Just(CellShape::O)].boxed()
+    }
+}
+
+impl Arbitrary for Board {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        reachable(Board::default(), MAX_NORMAL_MOVES)
+    }
+}
+
+impl Arbitrary for LocalBoard {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Moves are spread across the whole global board and won local boards can still be played in,
+    /// so any cells can be reached in a local board.
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        any::<[[Option<CellShape>; 3]; 3]>()
+            .prop_map(LocalBoard::with_cells)
+            .boxed()
+    }
+}
+
+impl Arbitrary for RuleSet {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![Just(RuleSet::Standard), Just(RuleSet::ClosedBoards)].boxed()
+    }
+}
+
+impl Arbitrary for OpeningRule {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(OpeningRule::Free),
+            Just(OpeningRule::CentreBoard),
+            Just(OpeningRule::SecondPlayerChooses),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for WinCondition {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(WinCondition::LineOfBoards),
+            Just(WinCondition::AnyBoard)
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for DrawRule {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![Just(DrawRule::Draw), Just(DrawRule::MostBoards)].boxed()
+    }
+}
+
+impl Arbitrary for GlobalBoard {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// If the second player chooses the opening local board, then it's chosen before any moves,
+    /// since there are no legal moves until it is.
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<RuleSet>(),
+            any::<WinCondition>(),
+            any::<DrawRule>(),
+            any::<OpeningRule>(),
+            0..3usize,
+            0..3usize,
+        )
+            .prop_flat_map(|(rules, win_condition, draw_rule, opening, x, y)| {
+                let mut start = GlobalBoard::default()
+                    .with_rules(rules)
+                    .with_win_condition(win_condition)
+                    .with_draw_rule(draw_rule)
+                    .with_opening(opening);
+                if start.awaiting_opening_choice() {
+                    start
+                        .choose_opening_board((x, y))
+                        .expect("The opening board should be on the board");
+                }
+                reachable(start, MAX_ULTIMATE_MOVES)
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ultimate::board::LocalBoardState;
+    use proptest::proptest;
+
+    /// Play random moves from `moves` after the game has ended as well as before, and check that
+    /// the winner never changes once the game is over.
+    fn check_winner_never_regresses<G: Game>(mut game: G, picks: Vec<Index>) {
+        let mut winner = game.get_winner();
+        for pick in picks {
+            let moves = game.moves();
+            if moves.is_empty() {
+                break;
+            }
+
+            assert!(game.play(moves[pick.index(moves.len())]));
+            if winner != GameResult::Ongoing {
+                assert_eq!(game.get_winner(), winner);
+            }
+            winner = game.get_winner();
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn normal_make_move_never_panics(
+            mut board in any::<Board>(),
+            coord in (0..4usize, 0..4usize),
+            shape in any::<CellShape>(),
+        ) {
+            let _ = board.make_move(coord, shape);
+        }
+
+        #[test]
+        fn ultimate_make_move_never_panics(
+            mut board in any::<GlobalBoard>(),
+            coord in (0..4usize, 0..4usize, (0..4usize, 0..4usize)),
+            shape in any::<CellShape>(),
+        ) {
+            let _ = board.make_move(coord, shape);
+        }
+
+        #[test]
+        fn legal_moves_are_accepted(
+            (normal, normal_mv) in with_legal_move::<Board>(),
+            (ultimate, ultimate_mv) in with_legal_move::<GlobalBoard>(),
+        ) {
+            let (mut normal, mut ultimate) = (normal, ultimate);
+            assert!(normal.play(normal_mv));
+            assert!(ultimate.play(ultimate_mv));
+        }
+
+        #[test]
+        fn normal_winner_never_regresses(
+            board in any::<Board>(),
+            picks in vec(any::<Index>(), 0..=MAX_NORMAL_MOVES),
+        ) {
+            check_winner_never_regresses(board, picks);
+        }
+
+        #[test]
+        fn ultimate_winner_never_regresses(
+            board in any::<GlobalBoard>(),
+            picks in vec(any::<Index>(), 0..=MAX_ULTIMATE_MOVES),
+        ) {
+            check_winner_never_regresses(board, picks);
+        }
+
+        #[test]
+        fn local_board_state_matches_winner(board in any::<LocalBoard>()) {
+            match board.get_winner() {
+                GameResult::Won { shape, .. } => {
+                    assert_eq!(board.state(), LocalBoardState::Won(shape));
+                }
+                GameResult::Draw => {
+                    assert_eq!(board.state(), LocalBoardState::Drawn);
+                }
+                GameResult::Ongoing => {
+                    assert_eq!(board.state(), LocalBoardState::Open);
+                }
+            }
+        }
+    }
+}
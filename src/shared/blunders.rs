@@ -0,0 +1,235 @@
+//! This module provides [`BlunderCheck`], which finds the first losing mistake of each shape once a
+//! game is over, by evaluating every position of the game in the background.
+
+use super::{ai::Game, ai_task::AiTask, board::GameResult, record::GameRecord};
+use crate::CellShape;
+use eframe::egui;
+use std::sync::mpsc;
+use web_time::Duration;
+
+/// A move which took a position that wasn't lost for the shape that played it and made it lost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blunder<M> {
+    /// The shape which played the move.
+    pub shape: CellShape,
+
+    /// The number of moves played before the move, so the position that it was played in has this
+    /// ply.
+    pub ply: usize,
+
+    /// The move itself.
+    pub mv: M,
+}
+
+/// Return the first blunder of each shape, in the order that they were played.
+///
+/// `winners` gives the shape which is winning in the position before the first move and after
+/// each move, or [`None`] if neither shape is.
+pub fn find_blunders<M: Copy>(
+    moves: &[(CellShape, M)],
+    winners: &[Option<CellShape>],
+) -> Vec<Blunder<M>> {
+    let mut blunders: Vec<Blunder<M>> = vec![];
+
+    for (ply, (&(shape, mv), pair)) in moves.iter().zip(winners.windows(2)).enumerate() {
+        let lost = Some(shape.other());
+        if pair[0] != lost
+            && pair[1] == lost
+            && !blunders.iter().any(|blunder| blunder.shape == shape)
+        {
+            blunders.push(Blunder { shape, ply, mv });
+        }
+    }
+
+    blunders
+}
+
+/// The check for the first losing mistake of each shape in a finished game. See [`find_blunders`].
+pub struct BlunderCheck<M> {
+    /// The handle to the check being computed, if there is one.
+    task: Option<AiTask>,
+
+    /// The receiver that receives the blunders. Each check gets a new channel, so that the result
+    /// of a cancelled check can never arrive.
+    rx: Option<mpsc::Receiver<Vec<Blunder<M>>>>,
+
+    /// The blunders that were found, once the check has finished.
+    blunders: Option<Vec<Blunder<M>>>,
+}
+
+impl<M> Default for BlunderCheck<M> {
+    fn default() -> Self {
+        Self {
+            task: None,
+            rx: None,
+            blunders: None,
+        }
+    }
+}
+
+impl<M: Copy + Send + 'static> BlunderCheck<M> {
+    /// Start checking the game with the given record in the background, throwing away any
+    /// previous check. `positions` are the positions of the game, from
+    /// [`GameRecord::replay`].
+    ///
+    /// `winner` returns the shape which is winning in an ongoing position, if either is, according
+    /// to the engine of the variant. Finished positions are judged by their result instead.
+    pub fn start<G, F>(&mut self, record: &GameRecord<M>, positions: Vec<G>, winner: F)
+    where
+        G: Game<Move = M> + Send + 'static,
+        F: Fn(&G) -> Option<CellShape> + Send + 'static,
+    {
+        let moves = record.moves_with_shapes();
+        let (tx, rx) = mpsc::channel();
+
+        self.cancel();
+        self.rx = Some(rx);
+        self.task = Some(AiTask::spawn(Duration::ZERO, tx, move |_| {
+            let winners: Vec<_> = positions
+                .iter()
+                .map(|game| match game.get_winner() {
+                    GameResult::Won { shape, .. } => Some(shape),
+                    GameResult::Draw => None,
+                    GameResult::Ongoing => winner(game),
+                })
+                .collect();
+            find_blunders(&moves, &winners)
+        }));
+    }
+
+    /// Cancel the check and forget any blunders that it found.
+    pub fn cancel(&mut self) {
+        if let Some(mut task) = self.task.take() {
+            task.cancel();
+        }
+        self.rx = None;
+        self.blunders = None;
+    }
+
+    /// Check if the check is still being computed.
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Update the [`blunders`](Self::blunders) if the check has just finished.
+    fn receive_blunders(&mut self) {
+        if let Some(blunders) = self.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.blunders = Some(blunders);
+            self.task = None;
+        }
+    }
+
+    /// Return the blunders that were found, if the check has finished.
+    pub fn blunders(&mut self) -> Option<&[Blunder<M>]> {
+        self.receive_blunders();
+        self.blunders.as_deref()
+    }
+
+    /// Show the result of the check in the given [`Ui`](egui::Ui), using `format_move` to show
+    /// the moves, with a button to analyse the position before each blunder. Return the ply of the
+    /// position whose button was clicked, if one was.
+    pub fn show(&mut self, ui: &mut egui::Ui, format_move: impl Fn(M) -> String) -> Option<usize> {
+        self.receive_blunders();
+        let running = self.is_running();
+        let mut clicked = None;
+
+        match self.blunders.as_deref() {
+            Some([]) => {
+                ui.label("Neither side made a losing mistake");
+            }
+            Some(blunders) => {
+                for &Blunder { shape, ply, mv } in blunders {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{shape:?}'s first losing mistake was move {}, {}",
+                            ply + 1,
+                            format_move(mv)
+                        ));
+                        if ui
+                            .button("Analyse")
+                            .on_hover_text("Go to the position before the mistake")
+                            .clicked()
+                        {
+                            clicked = Some(ply);
+                        }
+                    });
+                }
+            }
+            None if running => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Checking for mistakes\u{2026}");
+                });
+            }
+            None => (),
+        }
+
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CellShape::{O, X};
+
+    #[test]
+    fn find_blunders_test() {
+        let moves = [(X, 0), (O, 1), (X, 2), (O, 3), (X, 4)];
+
+        // O throws away a draw, X gives the win back, and then O throws it away again
+        let winners = [None, None, Some(X), Some(O), Some(X), Some(X)];
+        assert_eq!(
+            find_blunders(&moves, &winners),
+            vec![
+                Blunder {
+                    shape: O,
+                    ply: 1,
+                    mv: 1
+                },
+                Blunder {
+                    shape: X,
+                    ply: 2,
+                    mv: 2
+                },
+            ]
+        );
+
+        // A shape which was already lost can't blunder
+        let winners = [Some(O), Some(O), Some(O), Some(O), Some(O), Some(O)];
+        assert!(find_blunders(&moves, &winners).is_empty());
+    }
+
+    #[test]
+    fn blunder_check_test() {
+        use crate::normal::board::Board;
+
+        let mut record = GameRecord::new(X);
+        record
+            .moves
+            .extend([(0, 0), (0, 1), (1, 1), (2, 1), (2, 2)]);
+        let positions = record.replay(Board::new(O));
+
+        let mut check = BlunderCheck::default();
+        check.start(&record, positions, |board: &Board| {
+            match board.evaluate_position(board.to_move()) {
+                0 => None,
+                eval if eval > 0 => Some(board.ai_shape),
+                _ => Some(board.ai_shape.other()),
+            }
+        });
+        check.task.take().unwrap().join();
+
+        assert_eq!(
+            check.blunders(),
+            Some(
+                &[Blunder {
+                    shape: O,
+                    ply: 1,
+                    mv: (0, 1)
+                }][..]
+            )
+        );
+        assert!(!check.is_running());
+    }
+}
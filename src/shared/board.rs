@@ -2,6 +2,7 @@
 
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 
 /// An enum for the shape of a cell on the board.
@@ -20,10 +21,75 @@ impl CellShape {
             Self::O => Self::X,
         }
     }
+
+    /// Return an iterator over both shapes, starting with X.
+    pub fn iter() -> impl Iterator<Item = Self> {
+        [Self::X, Self::O].into_iter()
+    }
+}
+
+impl fmt::Display for CellShape {
+    /// Write the shape as `X` or `O`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::X => "X",
+            Self::O => "O",
+        })
+    }
+}
+
+/// The error returned when parsing a [`CellShape`] from a character which isn't `X` or `O`.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+#[error("{0:?} isn't X or O")]
+pub struct ParseShapeError(pub char);
+
+impl TryFrom<char> for CellShape {
+    type Error = ParseShapeError;
+
+    /// Parse a shape from `X` or `O`, ignoring case.
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            'X' | 'x' => Ok(Self::X),
+            'O' | 'o' => Ok(Self::O),
+            _ => Err(ParseShapeError(c)),
+        }
+    }
+}
+
+/// The result of a game, as returned by the `get_winner` methods of the variant boards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum GameResult {
+    /// Neither player has won, but the board is not full, so a win could occur.
+    Ongoing,
+
+    /// The game is over and neither player won.
+    Draw,
+
+    /// The game has been won by the given shape with the given line.
+    Won {
+        /// The shape which won.
+        shape: CellShape,
+
+        /// The coordinates of the winning line.
+        line: [(usize, usize); 3],
+    },
+}
+
+impl From<Result<(CellShape, [(usize, usize); 3]), WinnerError>> for GameResult {
+    /// Convert the result of [`get_winner`] into a [`GameResult`].
+    ///
+    /// [`MultipleWinners`](WinnerError::MultipleWinners) is considered a draw.
+    fn from(result: Result<(CellShape, [(usize, usize); 3]), WinnerError>) -> Self {
+        match result {
+            Ok((shape, line)) => Self::Won { shape, line },
+            Err(WinnerError::NoWinnerYet) => Self::Ongoing,
+            Err(WinnerError::BoardFullNoWinner | WinnerError::MultipleWinners) => Self::Draw,
+        }
+    }
 }
 
 /// A possible error that could occur when trying to find a winner,
-#[derive(Debug, Error, PartialEq)]
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
 pub enum WinnerError {
     /// Neither player has won, but the board is not full, so a win could occur.
     #[error("Neither player has won yet")]
@@ -48,8 +114,139 @@ pub fn is_board_full(cells: [[Option<CellShape>; 3]; 3]) -> bool {
     cells.iter().flatten().filter(|cell| cell.is_some()).count() == 9
 }
 
+/// An enum to represent the ways in which a board position can be invalid. See
+/// [`check_shape_counts`] and the `is_valid_position` methods of the variant boards.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum InvalidPosition {
+    /// This shape has made at least two more moves than the other shape.
+    #[error("{0:?} has made too many moves")]
+    TooManyMoves(CellShape),
+
+    /// This shape is set to move next, but it has made more moves than the other shape.
+    #[error("{0:?} can't be the next shape to move")]
+    WrongShapeToMove(CellShape),
+
+    /// Both players have won.
+    #[error("both players have won")]
+    MultipleWinners,
+
+    /// This shape has won, but the other shape has moved since then.
+    #[error("the game continued after {0:?} won")]
+    MoveAfterWin(CellShape),
+
+    /// Both players have won the local board with these coordinates.
+    #[error("both players have won local board {0:?}")]
+    MultipleLocalWinners((usize, usize)),
+
+    /// The next local board is out of bounds or already full.
+    #[error("local board {0:?} can't be the next local board")]
+    InvalidNextLocalBoard((usize, usize)),
+}
+
+/// Return the number of `(X, O)` shapes in the given cells.
+fn count_shapes<'a>(cells: impl IntoIterator<Item = &'a Option<CellShape>>) -> (usize, usize) {
+    cells.into_iter().fold((0, 0), |(xs, os), cell| match cell {
+        Some(CellShape::X) => (xs + 1, os),
+        Some(CellShape::O) => (xs, os + 1),
+        None => (xs, os),
+    })
+}
+
+/// Return the shape which should play next in a position with the given cells, assuming that
+/// [`X`](CellShape::X) played first.
+///
+/// This is only a guess, since we can't tell who played first from the cells alone.
+pub fn infer_shape_to_move<'a>(
+    cells: impl IntoIterator<Item = &'a Option<CellShape>>,
+) -> CellShape {
+    let (xs, os) = count_shapes(cells);
+
+    if xs > os {
+        CellShape::O
+    } else {
+        CellShape::X
+    }
+}
+
+/// Check that the number of each shape in the given cells is consistent with the players taking
+/// turns, and with `to_move` being the next shape to move.
+///
+/// # Errors
+///
+/// - [`TooManyMoves`](InvalidPosition::TooManyMoves): One shape has made at least two more moves
+///   than the other.
+/// - [`WrongShapeToMove`](InvalidPosition::WrongShapeToMove): `to_move` has made one more move
+///   than the other shape.
+pub fn check_shape_counts<'a>(
+    cells: impl IntoIterator<Item = &'a Option<CellShape>>,
+    to_move: CellShape,
+) -> Result<(), InvalidPosition> {
+    let (xs, os) = count_shapes(cells);
+
+    if xs > os + 1 {
+        Err(InvalidPosition::TooManyMoves(CellShape::X))
+    } else if os > xs + 1 {
+        Err(InvalidPosition::TooManyMoves(CellShape::O))
+    } else if (xs > os && to_move == CellShape::X) || (os > xs && to_move == CellShape::O) {
+        Err(InvalidPosition::WrongShapeToMove(to_move))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that the result of a `get_winner()` call is consistent with `to_move` being the next
+/// shape to move.
+///
+/// # Errors
+///
+/// - [`MultipleWinners`](InvalidPosition::MultipleWinners): Both players have won.
+/// - [`MoveAfterWin`](InvalidPosition::MoveAfterWin): The winner is set to move next, which means
+///   that the other shape has moved since the game was won.
+pub fn check_winner(
+    winner: Result<(CellShape, [(usize, usize); 3]), WinnerError>,
+    to_move: CellShape,
+) -> Result<(), InvalidPosition> {
+    match winner {
+        Err(WinnerError::MultipleWinners) => Err(InvalidPosition::MultipleWinners),
+        Ok((shape, _)) if shape == to_move => Err(InvalidPosition::MoveAfterWin(shape)),
+        _ => Ok(()),
+    }
+}
+
+/// Return the (at most 4) lines of a 3x3 grid which pass through the given cell.
+pub fn lines_through((x, y): (usize, usize)) -> Vec<[(usize, usize); 3]> {
+    let mut lines = vec![[(x, 0), (x, 1), (x, 2)], [(0, y), (1, y), (2, y)]];
+    if x + y == 2 {
+        lines.push([(0, 2), (1, 1), (2, 0)]);
+    }
+    if x == y {
+        lines.push([(0, 0), (1, 1), (2, 2)]);
+    }
+    lines
+}
+
+/// Return the winner and winning line if the shape in the given cell has won with a line through
+/// that cell.
+///
+/// This only checks the (at most 4) lines through the cell, so it's much cheaper than
+/// [`get_winner`] when we know which cell was the last to change.
+pub fn get_winning_line_through(
+    cells: [[Option<CellShape>; 3]; 3],
+    (x, y): (usize, usize),
+) -> Option<(CellShape, [(usize, usize); 3])> {
+    let shape = cells[x][y]?;
+
+    lines_through((x, y))
+        .into_iter()
+        .find(|line| line.iter().all(|&(x, y)| cells[x][y] == Some(shape)))
+        .map(|line| (shape, line))
+}
+
 /// Return the winner in the current board position, or a variant of [`WinnerError`] if there is no winner.
 ///
+/// Unlike the `get_winner` methods of the variant boards, this function works with any
+/// arrangement of cells, even ones which can't be reached by legal moves.
+///
 /// If there are multiple winning lines but they have the same winner (a configuration possible in
 /// certain variants), then that shape wins. The winning line in this case is *one* of the lines
 /// where a win occured, but no guarantees are given as to which line it will be.
@@ -129,25 +326,60 @@ pub fn get_winner(
 mod tests {
     use super::*;
 
+    #[test]
+    fn cell_shape_test() {
+        assert_eq!(
+            CellShape::iter().collect::<Vec<_>>(),
+            vec![CellShape::X, CellShape::O]
+        );
+
+        for shape in CellShape::iter() {
+            let c = shape.to_string().chars().next().unwrap();
+            assert_eq!(CellShape::try_from(c), Ok(shape));
+            assert_eq!(CellShape::try_from(c.to_ascii_lowercase()), Ok(shape));
+        }
+        assert_eq!(CellShape::try_from('.'), Err(ParseShapeError('.')));
+    }
+
+    #[test]
+    fn get_winning_line_through_test() {
+        use crate::normal::test_utils::make_board;
+
+        let cells = make_board!(X X X; O O _; _).cells();
+        assert_eq!(
+            get_winning_line_through(cells, (1, 0)),
+            Some((CellShape::X, [(0, 0), (1, 0), (2, 0)]))
+        );
+        assert_eq!(get_winning_line_through(cells, (0, 1)), None);
+        assert_eq!(get_winning_line_through(cells, (2, 1)), None);
+
+        let cells = make_board!(O X X; X O _; _ _ O).cells();
+        assert_eq!(
+            get_winning_line_through(cells, (2, 2)),
+            Some((CellShape::O, [(0, 0), (1, 1), (2, 2)]))
+        );
+        assert_eq!(get_winning_line_through(cells, (1, 0)), None);
+    }
+
     #[test]
     fn get_winner_test() {
         use crate::normal::{board::Board, test_utils::make_board};
 
         let board = Board::default();
-        assert_eq!(board.get_winner(), Err(WinnerError::NoWinnerYet));
+        assert_eq!(get_winner(board.cells()), Err(WinnerError::NoWinnerYet));
 
         // X| |
         //  |O|
         //  | |
         let board = make_board!(X _ _; _ O _; _);
-        assert_eq!(board.get_winner(), Err(WinnerError::NoWinnerYet));
+        assert_eq!(get_winner(board.cells()), Err(WinnerError::NoWinnerYet));
 
         // X|O|X
         //  |X|O
         //  |O|X
         let board = make_board!(X O X; _ X O; _ O X);
         assert_eq!(
-            board.get_winner(),
+            get_winner(board.cells()),
             Ok((CellShape::X, [(0, 0), (1, 1), (2, 2)]))
         );
 
@@ -156,7 +388,7 @@ mod tests {
         // O|X|X
         let board = make_board!(O X O; X O X; O X X);
         assert_eq!(
-            board.get_winner(),
+            get_winner(board.cells()),
             Ok((CellShape::O, [(0, 2), (1, 1), (2, 0)]))
         );
 
@@ -165,7 +397,7 @@ mod tests {
         // X|X|X
         let board = make_board!(O X O; O O X; X X X);
         assert_eq!(
-            board.get_winner(),
+            get_winner(board.cells()),
             Ok((CellShape::X, [(0, 2), (1, 2), (2, 2)]))
         );
 
@@ -173,24 +405,33 @@ mod tests {
         // O|X|X
         // X|X|O
         let board = make_board!(X O O; O X X; X X O);
-        assert_eq!(board.get_winner(), Err(WinnerError::BoardFullNoWinner));
+        assert_eq!(
+            get_winner(board.cells()),
+            Err(WinnerError::BoardFullNoWinner)
+        );
 
         // X|X|X
         // O|O|O
         //  | |
         let board = make_board!(X X X; O O O; _);
-        assert_eq!(board.get_winner(), Err(WinnerError::MultipleWinners));
+        assert_eq!(get_winner(board.cells()), Err(WinnerError::MultipleWinners));
 
         // X| |O
         // X|X|O
         // O|O|O
         let board = make_board!(X _ O; X X O; O O O);
-        assert!(matches!(board.get_winner(), Ok((CellShape::O, [_, _, _]))));
+        assert!(matches!(
+            get_winner(board.cells()),
+            Ok((CellShape::O, [_, _, _]))
+        ));
 
         // O| |X
         // O|O|X
         // X|X|X
         let board = make_board!(O _ X; O O X; X X X);
-        assert!(matches!(board.get_winner(), Ok((CellShape::X, [_, _, _]))));
+        assert!(matches!(
+            get_winner(board.cells()),
+            Ok((CellShape::X, [_, _, _]))
+        ));
     }
 }
@@ -0,0 +1,135 @@
+//! This module generates the random mid-game positions that games start from in chaos mode, so
+//! that experienced players get fresh situations instead of the same opening every time.
+
+use super::{ai::Game, board::GameResult};
+use rand::{seq::SliceRandom, Rng};
+use std::ops::RangeInclusive;
+
+/// The number of random positions to try before settling for one which isn't balanced. See
+/// [`random_start`].
+const MAX_ATTEMPTS: u32 = 50;
+
+/// Play `plies` random legal moves from `start`, and return the position along with the moves
+/// played to reach it, or `None` if the game ended along the way.
+fn random_position<G: Game, R: Rng>(
+    start: &G,
+    plies: usize,
+    rng: &mut R,
+) -> Option<(G, Vec<G::Move>)> {
+    let mut game = start.clone();
+    let mut moves = Vec::with_capacity(plies);
+
+    for _ in 0..plies {
+        let mv = *game.moves().choose(rng)?;
+        let legal = game.play(mv);
+        debug_assert!(legal, "Every move from `moves` should be legal");
+        moves.push(mv);
+
+        if game.get_winner() != GameResult::Ongoing {
+            return None;
+        }
+    }
+
+    if game.moves().is_empty() {
+        None
+    } else {
+        Some((game, moves))
+    }
+}
+
+/// Generate a random starting position by playing a random number of random legal moves from
+/// `start`, with the number of moves taken from `plies`, and return it along with the moves played
+/// to reach it.
+///
+/// A position is only used if the game is still going and `is_valid` accepts it, and we try up to
+/// [`MAX_ATTEMPTS`] positions to find one which `is_balanced` also accepts. If none of them are
+/// balanced, then we use the first valid one, and if none of them are valid, then we use `start`
+/// with no moves.
+pub fn random_start<G, R>(
+    start: &G,
+    plies: RangeInclusive<usize>,
+    rng: &mut R,
+    is_valid: impl Fn(&G) -> bool,
+    mut is_balanced: impl FnMut(&G) -> bool,
+) -> (G, Vec<G::Move>)
+where
+    G: Game,
+    R: Rng,
+{
+    let mut fallback = None;
+
+    for _ in 0..MAX_ATTEMPTS {
+        let plies = rng.gen_range(plies.clone());
+        let Some((game, moves)) = random_position(start, plies, rng) else {
+            continue;
+        };
+
+        if !is_valid(&game) {
+            continue;
+        }
+
+        if is_balanced(&game) {
+            return (game, moves);
+        }
+
+        if fallback.is_none() {
+            fallback = Some((game, moves));
+        }
+    }
+
+    fallback.unwrap_or_else(|| (start.clone(), vec![]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal::board::Board, shared::record::GameRecord, CellShape};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn random_start_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let start = Board::default();
+
+        for _ in 0..20 {
+            let (board, moves) = random_start(
+                &start,
+                2..=4,
+                &mut rng,
+                |board| board.is_valid_position().is_ok(),
+                |board| board.is_balanced(),
+            );
+
+            assert!((2..=4).contains(&moves.len()));
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+            assert!(board.is_valid_position().is_ok());
+            assert!(board.is_balanced());
+
+            let record = GameRecord {
+                first_shape: CellShape::X,
+                moves,
+                skipped_turns: vec![],
+                move_times: vec![],
+                annotations: BTreeMap::new(),
+            };
+            assert_eq!(record.replay(start.clone()).last(), Some(&board));
+        }
+    }
+
+    #[test]
+    fn random_start_fallback_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let start = Board::default();
+
+        // Nothing is balanced, so we settle for a valid position
+        let (board, moves) = random_start(&start, 3..=3, &mut rng, |_| true, |_| false);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+        // Nothing is valid, so we start from the start
+        let (board, moves) = random_start(&start, 3..=3, &mut rng, |_| false, |_| true);
+        assert_eq!(board, start);
+        assert!(moves.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! This module provides [`GameClock`], a chess clock which gives each shape a limited amount of
+//! time for all of its moves, and [`TimeControl`], which is the preset that a clock starts from.
+
+use crate::CellShape;
+use serde::{Deserialize, Serialize};
+use web_time::{Duration, Instant};
+
+/// The time limits that a game can be played with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeControl {
+    /// There's no clock, so each player can take as long as they like.
+    #[default]
+    Untimed,
+
+    /// Each shape has one minute for the whole game, with one second added after each of its
+    /// moves.
+    Blitz,
+}
+
+impl TimeControl {
+    /// Create a clock for this time control, or return [`None`] if the game is untimed.
+    pub fn clock(self) -> Option<GameClock> {
+        match self {
+            Self::Untimed => None,
+            Self::Blitz => Some(GameClock::new(
+                Duration::from_secs(60),
+                Duration::from_secs(1),
+            )),
+        }
+    }
+}
+
+/// A chess clock for the two shapes, where only the clock of the shape to move is running.
+///
+/// After each move, the shape that moved gets the increment added to its time, and the clock of
+/// the other shape starts. If the time of the running shape reaches zero, then it has flagged,
+/// and the clock stops for good.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameClock {
+    /// The time that X had left when its clock was last stopped.
+    x_remaining: Duration,
+
+    /// The time that O had left when its clock was last stopped.
+    o_remaining: Duration,
+
+    /// The time added to the clock of a shape after each of its moves.
+    increment: Duration,
+
+    /// The shape whose clock is running and when it started, if either clock is running.
+    running: Option<(CellShape, Instant)>,
+
+    /// The shape which ran out of time, if either has.
+    flagged: Option<CellShape>,
+}
+
+impl GameClock {
+    /// Create a stopped clock where both shapes have the given time, and get the given increment
+    /// after each move.
+    pub fn new(initial: Duration, increment: Duration) -> Self {
+        Self {
+            x_remaining: initial,
+            o_remaining: initial,
+            increment,
+            running: None,
+            flagged: None,
+        }
+    }
+
+    /// A mutable reference to the stored time of the given shape.
+    fn stored_mut(&mut self, shape: CellShape) -> &mut Duration {
+        match shape {
+            CellShape::X => &mut self.x_remaining,
+            CellShape::O => &mut self.o_remaining,
+        }
+    }
+
+    /// Return the time that the given shape has left at the given instant.
+    fn remaining_at(&self, shape: CellShape, now: Instant) -> Duration {
+        let stored = match shape {
+            CellShape::X => self.x_remaining,
+            CellShape::O => self.o_remaining,
+        };
+
+        match self.running {
+            Some((running, started)) if running == shape => {
+                stored.saturating_sub(now.saturating_duration_since(started))
+            }
+            _ => stored,
+        }
+    }
+
+    /// Return the time that the given shape has left.
+    pub fn remaining(&self, shape: CellShape) -> Duration {
+        self.remaining_at(shape, Instant::now())
+    }
+
+    /// Return the shape whose clock is running, if either is.
+    pub fn running(&self) -> Option<CellShape> {
+        self.running.map(|(shape, _)| shape)
+    }
+
+    /// Return the shape which ran out of time, if either has.
+    pub fn flagged(&self) -> Option<CellShape> {
+        self.flagged
+    }
+
+    /// Start the clock of the given shape at the given instant, stopping the other clock.
+    fn start_at(&mut self, shape: CellShape, now: Instant) {
+        if self.flagged.is_some() {
+            return;
+        }
+
+        self.stop_at(now);
+        self.running = Some((shape, now));
+    }
+
+    /// Start the clock of the given shape, stopping the other clock. This does nothing once a
+    /// shape has flagged.
+    pub fn start(&mut self, shape: CellShape) {
+        self.start_at(shape, Instant::now());
+    }
+
+    /// Stop the running clock at the given instant, keeping the time that it had left.
+    fn stop_at(&mut self, now: Instant) {
+        if let Some((shape, _)) = self.running {
+            let remaining = self.remaining_at(shape, now);
+            *self.stored_mut(shape) = remaining;
+            self.running = None;
+        }
+    }
+
+    /// Stop the running clock without adding the increment, like when the game has ended.
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now());
+    }
+
+    /// Stop the clock of the running shape after its move at the given instant, add the
+    /// increment to its time, and start the clock of the other shape.
+    fn press_at(&mut self, now: Instant) {
+        if self.update_at(now).is_some() {
+            return;
+        }
+
+        let Some((shape, _)) = self.running else {
+            return;
+        };
+        self.stop_at(now);
+        *self.stored_mut(shape) += self.increment;
+        self.start_at(shape.other(), now);
+    }
+
+    /// Stop the clock of the running shape after its move, add the increment to its time, and
+    /// start the clock of the other shape. The move doesn't count if the running shape had
+    /// already run out of time.
+    pub fn press(&mut self) {
+        self.press_at(Instant::now());
+    }
+
+    /// Check if the running shape had run out of time at the given instant, and stop the clock
+    /// for good if it had. See [`update`](Self::update).
+    fn update_at(&mut self, now: Instant) -> Option<CellShape> {
+        if let Some((shape, _)) = self.running {
+            if self.remaining_at(shape, now).is_zero() {
+                self.stop_at(now);
+                self.flagged = Some(shape);
+            }
+        }
+
+        self.flagged
+    }
+
+    /// Check if the running shape has run out of time, and stop the clock for good if it has.
+    ///
+    /// Return the shape which ran out of time, if either has.
+    pub fn update(&mut self) -> Option<CellShape> {
+        self.update_at(Instant::now())
+    }
+}
+
+/// Format the given time on a clock, like `"0:59"`, or with tenths of a second under ten
+/// seconds, like `"9.3"`.
+pub fn clock_text(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs < 10 {
+        format!("{}.{}", secs, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_clock_test() {
+        let mut clock = GameClock::new(Duration::from_secs(10), Duration::from_secs(1));
+        let start = Instant::now();
+        let secs = |secs: u64| start + Duration::from_secs(secs);
+
+        clock.start_at(CellShape::X, start);
+        assert_eq!(clock.running(), Some(CellShape::X));
+        assert_eq!(
+            clock.remaining_at(CellShape::X, secs(3)),
+            Duration::from_secs(7)
+        );
+        assert_eq!(
+            clock.remaining_at(CellShape::O, secs(3)),
+            Duration::from_secs(10)
+        );
+
+        // X moves after 3 seconds and gets the increment
+        clock.press_at(secs(3));
+        assert_eq!(clock.running(), Some(CellShape::O));
+        assert_eq!(
+            clock.remaining_at(CellShape::X, secs(5)),
+            Duration::from_secs(8)
+        );
+        assert_eq!(
+            clock.remaining_at(CellShape::O, secs(5)),
+            Duration::from_secs(8)
+        );
+
+        // O moves after 9 seconds, so it has 1 second left plus the increment
+        clock.press_at(secs(12));
+        assert_eq!(
+            clock.remaining_at(CellShape::O, secs(12)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(clock.update_at(secs(12)), None);
+
+        // X runs out of time, and its move doesn't count
+        assert_eq!(clock.update_at(secs(19)), None);
+        clock.press_at(secs(20));
+        assert_eq!(clock.flagged(), Some(CellShape::X));
+        assert_eq!(clock.running(), None);
+        assert_eq!(clock.remaining_at(CellShape::X, secs(30)), Duration::ZERO);
+        assert_eq!(
+            clock.remaining_at(CellShape::O, secs(30)),
+            Duration::from_secs(2)
+        );
+
+        // The clock can't be restarted once a shape has flagged
+        clock.start_at(CellShape::O, secs(30));
+        assert_eq!(clock.running(), None);
+    }
+
+    #[test]
+    fn time_control_test() {
+        assert_eq!(TimeControl::Untimed.clock(), None);
+
+        let clock = TimeControl::Blitz.clock().unwrap();
+        assert_eq!(clock.remaining(CellShape::X), Duration::from_secs(60));
+        assert_eq!(clock.running(), None);
+    }
+
+    #[test]
+    fn clock_text_test() {
+        assert_eq!(clock_text(Duration::from_secs(60)), "1:00");
+        assert_eq!(clock_text(Duration::from_secs(59)), "0:59");
+        assert_eq!(clock_text(Duration::from_millis(9_380)), "9.3");
+        assert_eq!(clock_text(Duration::ZERO), "0.0");
+    }
+}
@@ -0,0 +1,178 @@
+//! This module provides a panic hook which writes a crash report with the state of the current
+//! game, so that players can attach a reproducible bug report.
+//!
+//! The apps keep the context up to date with [`set_context`] whenever the game changes, and the
+//! hook installed by [`install_hook`] adds the most recent context to the report.
+
+use super::record::GameRecord;
+use serde::Serialize;
+use serde_json::Value;
+use std::{panic, sync::Mutex};
+
+/// The most recent state of the game, serialized as JSON so that the panic hook doesn't have to
+/// know about the types of either variant.
+static CONTEXT: Mutex<Option<Value>> = Mutex::new(None);
+
+/// The state of a game to attach to a crash report. See [`set_context`].
+#[derive(Serialize)]
+pub struct CrashContext<'a, B, C, M> {
+    /// The name of the variant being played.
+    pub variant: &'static str,
+
+    /// The current board.
+    pub board: &'a B,
+
+    /// The config of the app.
+    pub config: &'a C,
+
+    /// The moves of the game so far.
+    pub record: &'a GameRecord<M>,
+}
+
+/// A crash report, which is written as JSON.
+#[derive(Serialize)]
+struct CrashReport {
+    /// The version of the app.
+    version: &'static str,
+
+    /// The message of the panic.
+    message: String,
+
+    /// The location of the panic in the source code, if known.
+    location: Option<String>,
+
+    /// The most recent context given to [`set_context`], if any.
+    context: Option<Value>,
+}
+
+/// Replace the context that will be attached to a crash report.
+pub fn set_context<B, C, M>(context: &CrashContext<'_, B, C, M>)
+where
+    B: Serialize,
+    C: Serialize,
+    M: Serialize,
+{
+    let Ok(value) = serde_json::to_value(context) else {
+        return;
+    };
+
+    if let Ok(mut guard) = CONTEXT.lock() {
+        *guard = Some(value);
+    }
+}
+
+/// Forget the context, like when leaving a variant, so that it's not attached to a crash report.
+pub fn clear_context() {
+    if let Ok(mut guard) = CONTEXT.lock() {
+        *guard = None;
+    }
+}
+
+/// Build the crash report for the given panic as pretty-printed JSON.
+fn build_report(info: &panic::PanicInfo<'_>) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+
+    let report = CrashReport {
+        version: env!("CARGO_PKG_VERSION"),
+        message,
+        location: info.location().map(ToString::to_string),
+        // The mutex might be poisoned by the panic itself, but the context is still usable
+        context: CONTEXT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone(),
+    };
+
+    serde_json::to_string_pretty(&report).unwrap_or_else(|e| format!("{{\"error\": \"{e}\"}}"))
+}
+
+/// Install a panic hook which writes a crash report to a file in the temporary directory, after
+/// running the previous hook.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = std::env::temp_dir().join(format!("tictactoe-crash-{secs}.json"));
+
+        // The panic might have happened before a tracing subscriber was installed, or without
+        // one being installed at all, so we also tell the user directly on stderr, like the
+        // previous hook does with the panic message itself
+        match std::fs::write(&path, build_report(info)) {
+            Ok(()) => {
+                tracing::error!(path = %path.display(), "saved crash report");
+                eprintln!(
+                    "A crash report has been saved to {}. Please attach it to a bug report.",
+                    path.display()
+                );
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), "failed to save crash report: {e}");
+                eprintln!("Failed to save crash report: {e}");
+            }
+        }
+    }));
+}
+
+/// Install a panic hook which logs a crash report to the browser console, after running the
+/// previous hook, like the one from `console_error_panic_hook`.
+#[cfg(target_arch = "wasm32")]
+pub fn install_hook() {
+    use wasm_bindgen::prelude::wasm_bindgen;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        fn error(message: &str);
+    }
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        error(&format!(
+            "Please copy this crash report into a bug report:\n{}",
+            build_report(info)
+        ));
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellShape;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn context_test() {
+        let record = GameRecord {
+            first_shape: CellShape::X,
+            moves: vec![(1, 1)],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        };
+        set_context(&CrashContext {
+            variant: "normal",
+            board: &[[1, 2], [3, 4]],
+            config: &true,
+            record: &record,
+        });
+
+        let context = CONTEXT.lock().unwrap().clone().unwrap();
+        assert_eq!(context["variant"], "normal");
+        assert_eq!(context["board"][1][0], 3);
+        assert_eq!(context["record"]["moves"][0], serde_json::json!([1, 1]));
+
+        clear_context();
+        assert!(CONTEXT.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! This module provides [`BoardDrawings`], the arrows and highlighted cells that can be drawn over
+//! a board with the right mouse button, like in chess GUIs, to explain plans during analysis or
+//! presentations.
+
+use super::gui::draw_arrow_between_cells;
+use eframe::{
+    egui::{self, Painter},
+    epaint::{Color32, Pos2, Rect, Stroke},
+};
+
+/// The colour of the drawn arrows and markers.
+const DRAWING_COLOR: Color32 = Color32::from_rgba_premultiplied(20, 110, 25, 200);
+
+/// The arrows and highlighted cells drawn over a board, where `C` is the type of a cell
+/// coordinate.
+///
+/// Dragging with the right mouse button from one cell to another draws an arrow between them, and
+/// right clicking a cell highlights it. Doing either again removes it, and left clicking the board
+/// clears everything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoardDrawings<C> {
+    /// The arrows, from the first cell to the second.
+    arrows: Vec<(C, C)>,
+
+    /// The highlighted cells.
+    markers: Vec<C>,
+
+    /// The cell where the right mouse button was pressed, if it's being held down.
+    drag_start: Option<C>,
+}
+
+impl<C> Default for BoardDrawings<C> {
+    fn default() -> Self {
+        Self {
+            arrows: vec![],
+            markers: vec![],
+            drag_start: None,
+        }
+    }
+}
+
+impl<C: Copy + PartialEq> BoardDrawings<C> {
+    /// Check if nothing has been drawn.
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.markers.is_empty()
+    }
+
+    /// Remove all the arrows and markers.
+    pub fn clear(&mut self) {
+        self.arrows.clear();
+        self.markers.clear();
+    }
+
+    /// Draw an arrow between the given cells, or highlight the cell if they're the same. If the
+    /// arrow or marker is already there, then it's removed instead.
+    pub fn toggle(&mut self, from: C, to: C) {
+        fn toggle_item<T: PartialEq>(items: &mut Vec<T>, item: T) {
+            match items.iter().position(|other| *other == item) {
+                Some(index) => {
+                    items.remove(index);
+                }
+                None => items.push(item),
+            }
+        }
+
+        if from == to {
+            toggle_item(&mut self.markers, from);
+        } else {
+            toggle_item(&mut self.arrows, (from, to));
+        }
+    }
+
+    /// Handle the mouse input over the board in the given rect, where `cell_at` returns the cell at
+    /// a point, if there is one.
+    pub fn handle_input(&mut self, ui: &egui::Ui, rect: Rect, cell_at: impl Fn(Pos2) -> Option<C>) {
+        let input = ui.input();
+        let pointer = &input.pointer;
+        let hovered_cell = pointer
+            .hover_pos()
+            .filter(|&pos| rect.contains(pos))
+            .and_then(&cell_at);
+
+        if pointer.primary_clicked() && hovered_cell.is_some() {
+            self.clear();
+        }
+
+        if pointer.secondary_down() {
+            if self.drag_start.is_none() {
+                self.drag_start = hovered_cell;
+            }
+        } else if let Some(start) = self.drag_start.take() {
+            if let Some(end) = hovered_cell {
+                self.toggle(start, end);
+            }
+        }
+    }
+
+    /// Draw the arrows and markers with the given painter, where `cell_centre` returns the centre
+    /// of a cell and the length of its sides.
+    pub fn draw(&self, painter: &Painter, cell_centre: impl Fn(C) -> (Pos2, f32)) {
+        for &cell in &self.markers {
+            let (centre, cell_length) = cell_centre(cell);
+            painter.circle_stroke(
+                centre,
+                0.45 * cell_length,
+                Stroke::new(cell_length / 15., DRAWING_COLOR),
+            );
+        }
+
+        for &(from, to) in &self.arrows {
+            let (from, cell_length) = cell_centre(from);
+            let (to, _) = cell_centre(to);
+            draw_arrow_between_cells(painter, from, to, cell_length, DRAWING_COLOR);
+        }
+    }
+
+    /// Show a button to clear the drawings in the given [`Ui`](egui::Ui), if there are any.
+    pub fn show_clear_button(&mut self, ui: &mut egui::Ui) {
+        if !self.is_empty()
+            && ui
+                .add(egui::Button::new("\u{2715}").fill(Color32::TRANSPARENT))
+                .on_hover_text("Clear the arrows and highlighted cells")
+                .clicked()
+        {
+            self.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_test() {
+        let mut drawings = BoardDrawings::default();
+        assert!(drawings.is_empty());
+
+        drawings.toggle((0, 0), (2, 2));
+        drawings.toggle((1, 1), (1, 1));
+        drawings.toggle((2, 2), (0, 0));
+        assert_eq!(drawings.arrows, vec![((0, 0), (2, 2)), ((2, 2), (0, 0))]);
+        assert_eq!(drawings.markers, vec![(1, 1)]);
+
+        // Drawing the same arrow or marker again removes it
+        drawings.toggle((0, 0), (2, 2));
+        drawings.toggle((1, 1), (1, 1));
+        assert_eq!(drawings.arrows, vec![((2, 2), (0, 0))]);
+        assert!(drawings.markers.is_empty());
+
+        drawings.clear();
+        assert!(drawings.is_empty());
+    }
+}
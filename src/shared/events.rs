@@ -0,0 +1,137 @@
+//! This module provides [`GameEvent`]s and an [`EventBus`] to send them to anything which is
+//! interested in changes to the state of a game.
+
+use super::board::GameResult;
+use crate::CellShape;
+use std::sync::mpsc;
+
+/// Something that happened in a game, with moves of type `M`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent<M> {
+    /// A move was played by the given shape.
+    MovePlayed {
+        /// The move which was played.
+        mv: M,
+
+        /// The shape which played the move.
+        shape: CellShape,
+    },
+
+    /// The local board at the given coordinates was won by the given shape. This only happens in
+    /// ultimate tic-tac-toe.
+    LocalBoardWon {
+        /// The coordinates of the local board.
+        coord: (usize, usize),
+
+        /// The shape which won the local board.
+        shape: CellShape,
+    },
+
+    /// The game has ended with the given result, which is never [`GameResult::Ongoing`].
+    GameEnded(GameResult),
+
+    /// The game was restarted with an empty board.
+    GameRestarted,
+}
+
+/// A list of subscribers which will each receive every [`GameEvent`] that gets emitted.
+///
+/// Each subscriber gets its own `mpsc` channel, so events can be received on any thread.
+#[derive(Debug)]
+pub struct EventBus<M> {
+    /// The senders for each subscriber.
+    subscribers: Vec<mpsc::Sender<GameEvent<M>>>,
+
+    /// The senders for each subscriber to only the results of games. See
+    /// [`subscribe_to_results`](Self::subscribe_to_results).
+    result_subscribers: Vec<mpsc::Sender<GameResult>>,
+}
+
+impl<M> Default for EventBus<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> EventBus<M> {
+    /// Create a new event bus with no subscribers.
+    pub fn new() -> Self {
+        Self {
+            subscribers: vec![],
+            result_subscribers: vec![],
+        }
+    }
+
+    /// Subscribe to all future events, which will be sent down the returned receiver.
+    ///
+    /// To unsubscribe, just drop the receiver.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<GameEvent<M>> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Subscribe to the result of every future game, which is sent down the returned receiver
+    /// when the game ends, ignoring all the other events.
+    ///
+    /// Unlike [`subscribe`](Self::subscribe), the receiver doesn't depend on the type of the
+    /// moves, so the results of different variants can be received together.
+    pub fn subscribe_to_results(&mut self) -> mpsc::Receiver<GameResult> {
+        let (tx, rx) = mpsc::channel();
+        self.result_subscribers.push(tx);
+        rx
+    }
+}
+
+impl<M: Clone> EventBus<M> {
+    /// Send the event to every subscriber, forgetting any subscribers whose receivers have been
+    /// dropped.
+    pub fn emit(&mut self, event: GameEvent<M>) {
+        if let GameEvent::GameEnded(result) = event {
+            self.result_subscribers.retain(|tx| tx.send(result).is_ok());
+        }
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emit_test() {
+        let mut bus: EventBus<(usize, usize)> = EventBus::new();
+        let rx_1 = bus.subscribe();
+        let rx_2 = bus.subscribe();
+
+        bus.emit(GameEvent::GameRestarted);
+        assert_eq!(rx_1.try_recv(), Ok(GameEvent::GameRestarted));
+        assert_eq!(rx_2.try_recv(), Ok(GameEvent::GameRestarted));
+
+        drop(rx_2);
+        let event = GameEvent::MovePlayed {
+            mv: (1, 1),
+            shape: CellShape::X,
+        };
+        bus.emit(event);
+        assert_eq!(rx_1.try_recv(), Ok(event));
+        assert_eq!(bus.subscribers.len(), 1);
+    }
+
+    #[test]
+    fn subscribe_to_results_test() {
+        let mut bus: EventBus<(usize, usize)> = EventBus::new();
+        let results = bus.subscribe_to_results();
+
+        bus.emit(GameEvent::MovePlayed {
+            mv: (1, 1),
+            shape: CellShape::X,
+        });
+        bus.emit(GameEvent::GameEnded(GameResult::Draw));
+        bus.emit(GameEvent::GameRestarted);
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![GameResult::Draw]
+        );
+    }
+}
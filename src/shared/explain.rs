@@ -0,0 +1,226 @@
+//! This module explains the moves chosen by the AI, with the best candidate moves that it
+//! considered and whether the chosen move blocked or created a threat. See [`MoveExplanation`].
+
+use super::{
+    ai::{after_move, is_winning_move, Game},
+    board::GameResult,
+};
+use eframe::egui;
+use std::fmt;
+
+/// The number of candidate moves to keep in a [`MoveExplanation`].
+pub const CANDIDATE_COUNT: usize = 3;
+
+/// How good the AI thought a candidate move was.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CandidateScore {
+    /// The minimax evaluation of the position after the move, from -100 for a certain loss to 100
+    /// for a certain win. See [`Board::evaluate_moves`](crate::normal::board::Board::evaluate_moves).
+    Minimax(i8),
+
+    /// The MCTS statistics of the move. See
+    /// [`RootStats`](crate::ultimate::board::RootStats).
+    Mcts {
+        /// The number of playouts after the move, which is how much the AI looked at it.
+        playouts: u32,
+
+        /// The proportion of the playouts after the move which were won by the AI.
+        win_rate: f64,
+    },
+}
+
+impl CandidateScore {
+    /// Return a number which is higher for better moves, used to find the best candidates.
+    ///
+    /// MCTS plays the move with the most playouts, so those are ranked by playouts rather than by
+    /// win rate.
+    fn rank(&self) -> f64 {
+        match *self {
+            Self::Minimax(score) => score as f64,
+            Self::Mcts { playouts, .. } => playouts as f64,
+        }
+    }
+}
+
+impl fmt::Display for CandidateScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Minimax(score) => write!(f, "score {score:+}"),
+            Self::Mcts { playouts, win_rate } => {
+                write!(f, "{playouts} playouts, {:.0}% wins", 100. * win_rate)
+            }
+        }
+    }
+}
+
+/// The tactics of a move, from the perspective of the shape which played it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MoveTactics {
+    /// Whether the move won the game.
+    pub wins: bool,
+
+    /// Whether the opponent could have won by playing this move if it were their turn.
+    pub blocks_threat: bool,
+
+    /// Whether the shape which played the move could win with its next move if the opponent
+    /// skipped their turn.
+    pub creates_threat: bool,
+}
+
+impl MoveTactics {
+    /// Find the tactics of playing the given move in the given game.
+    pub fn of<G: Game>(game: &G, mv: G::Move) -> Self {
+        let mut opponent_to_move = game.clone();
+        opponent_to_move.skip_turn();
+
+        let mut after = after_move(game, mv);
+        after.skip_turn();
+
+        Self {
+            wins: is_winning_move(game, mv),
+            blocks_threat: is_winning_move(&opponent_to_move, mv),
+            creates_threat: after.get_winner() == GameResult::Ongoing
+                && after
+                    .moves()
+                    .into_iter()
+                    .any(|reply| is_winning_move(&after, reply)),
+        }
+    }
+}
+
+/// A candidate move considered by the AI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate<M> {
+    /// The move itself.
+    pub mv: M,
+
+    /// How good the AI thought the move was.
+    pub score: CandidateScore,
+}
+
+/// An explanation of a move chosen by the AI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveExplanation<M> {
+    /// The move that the AI chose.
+    pub mv: M,
+
+    /// The best [`CANDIDATE_COUNT`] moves that the AI considered, best first.
+    pub candidates: Vec<Candidate<M>>,
+
+    /// The tactics of the chosen move.
+    pub tactics: MoveTactics,
+}
+
+impl<M: Copy> MoveExplanation<M> {
+    /// Explain the AI playing `mv` in the given game, given every move that it considered.
+    pub fn new<G: Game<Move = M>>(game: &G, mv: M, mut candidates: Vec<Candidate<M>>) -> Self {
+        candidates.sort_by(|a, b| b.score.rank().total_cmp(&a.score.rank()));
+        candidates.truncate(CANDIDATE_COUNT);
+
+        Self {
+            mv,
+            candidates,
+            tactics: MoveTactics::of(game, mv),
+        }
+    }
+
+    /// Show the explanation in a non-collapsible, non-resizable `egui` window, using
+    /// `format_move` to show the moves.
+    pub fn show_window(&self, ctx: &egui::Context, format_move: impl Fn(M) -> String) {
+        egui::Window::new("Why this move?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::splat(-10.))
+            .show(ctx, |ui| {
+                ui.label(format!("The AI played {}.", format_move(self.mv)));
+
+                let MoveTactics {
+                    wins,
+                    blocks_threat,
+                    creates_threat,
+                } = self.tactics;
+                if wins {
+                    ui.label("It won the game.");
+                }
+                if blocks_threat {
+                    ui.label("It blocked a threat to win.");
+                }
+                if creates_threat {
+                    ui.label("It created a threat to win.");
+                }
+
+                if !self.candidates.is_empty() {
+                    ui.separator();
+                    ui.label("Top candidates:");
+                    for (i, Candidate { mv, score }) in self.candidates.iter().enumerate() {
+                        ui.label(format!("{}. {} ({score})", i + 1, format_move(*mv)));
+                    }
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal::{board::Board, test_utils::make_board};
+
+    #[test]
+    fn move_tactics_test() {
+        // X|X|
+        // O| |
+        //  | |
+        let board = make_board!(X X _; O _ _; _);
+        assert_eq!(
+            MoveTactics::of(&board, (2, 0)),
+            MoveTactics {
+                blocks_threat: true,
+                ..MoveTactics::default()
+            }
+        );
+        assert_eq!(
+            MoveTactics::of(&board, (1, 1)),
+            MoveTactics {
+                creates_threat: true,
+                ..MoveTactics::default()
+            }
+        );
+
+        // X|X|
+        // O|O|
+        //  | |
+        let board = make_board!(X X _; O O _; _);
+        assert_eq!(
+            MoveTactics::of(&board, (2, 0)),
+            MoveTactics {
+                wins: true,
+                ..MoveTactics::default()
+            }
+        );
+    }
+
+    #[test]
+    fn move_explanation_test() {
+        let board = Board::default();
+        let candidates = [(0, 0), (1, 1), (2, 2), (0, 2)]
+            .into_iter()
+            .zip([10, 50, -30, 20])
+            .map(|(mv, score)| Candidate {
+                mv,
+                score: CandidateScore::Minimax(score),
+            })
+            .collect();
+
+        let explanation = MoveExplanation::new(&board, (1, 1), candidates);
+        assert_eq!(explanation.mv, (1, 1));
+        assert_eq!(
+            explanation
+                .candidates
+                .iter()
+                .map(|candidate| candidate.mv)
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (0, 2), (0, 0)]
+        );
+        assert_eq!(explanation.tactics, MoveTactics::default());
+    }
+}
@@ -0,0 +1,494 @@
+//! This module renders boards to images, with the same geometry as the GUI, so that positions can
+//! be shared outside the app.
+//!
+//! Boards are first turned into a list of [`Shape`]s with the functions in [`gui`](super::gui),
+//! and then those shapes are written out as an SVG string or rasterized into a PNG.
+
+use super::{
+    board::GameResult,
+    gui::{
+        board_line_shapes_in_rect, cellshape_shapes_in_rect, centered_square_in_rect,
+        grid_line_shapes_in_rect, grid_winning_line_shape_in_rect, winning_line_shape_in_rect,
+    },
+    record::GameRecord,
+};
+use crate::{
+    normal::{board::Board, Coord},
+    ultimate::{board::GlobalBoard, GlobalCoord},
+};
+use eframe::{
+    egui::{self, Ui},
+    epaint::{CircleShape, Color32, Pos2, Rect, Shape, Stroke, Vec2},
+};
+use std::fmt::Write;
+
+/// The width and height of exported images, in pixels.
+pub const IMAGE_SIZE: f32 = 600.;
+
+/// The width and height of the application icon, in pixels. See [`app_icon_rgba`].
+pub const ICON_SIZE: u32 = 64;
+
+/// Get the shapes needed to draw a normal board in a square of the given size.
+pub fn normal_board_shapes(board: &Board, size: f32) -> Vec<Shape> {
+    let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(size));
+    let cell_length = size / 3.;
+
+    let mut shapes = board_line_shapes_in_rect(&rect, Color32::GRAY);
+    for x in 0..3 {
+        for y in 0..3 {
+            let cell_rect = Rect::from_min_size(
+                Pos2::new(x as f32 * cell_length, y as f32 * cell_length),
+                Vec2::splat(cell_length),
+            );
+            shapes.extend(cellshape_shapes_in_rect(
+                &centered_square_in_rect(cell_rect, 0.8),
+                board.cells()[x][y],
+                false,
+            ));
+        }
+    }
+
+    if let GameResult::Won {
+        line: [start_coord, _, end_coord],
+        ..
+    } = board.get_winner()
+    {
+        shapes.push(winning_line_shape_in_rect(
+            &rect,
+            false,
+            start_coord,
+            end_coord,
+        ));
+    }
+
+    shapes
+}
+
+/// Get the shapes needed to draw an ultimate board with `N` local boards along each side in a
+/// square of the given size.
+pub fn ultimate_board_shapes<const N: usize>(board: &GlobalBoard<N>, size: f32) -> Vec<Shape> {
+    let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(size));
+    let local_length = size / N as f32;
+
+    // Match the line colours in the app's light mode
+    let line_color = if board.next_local_board().is_some() {
+        Color32::LIGHT_GRAY
+    } else {
+        Color32::GRAY
+    };
+
+    let mut shapes = grid_line_shapes_in_rect(&rect, line_color, N);
+    for x in 0..N {
+        for y in 0..N {
+            let local_rect = centered_square_in_rect(
+                Rect::from_min_size(
+                    Pos2::new(x as f32 * local_length, y as f32 * local_length),
+                    Vec2::splat(local_length),
+                ),
+                0.85,
+            );
+            let local_board = &board.local_boards()[x][y];
+            let cell_length = local_rect.width() / 3.;
+
+            shapes.extend(board_line_shapes_in_rect(
+                &local_rect,
+                if board.next_local_board() == Some((x, y)) {
+                    Color32::BLACK
+                } else {
+                    line_color
+                },
+            ));
+            for lx in 0..3 {
+                for ly in 0..3 {
+                    let cell_rect = Rect::from_min_size(
+                        local_rect.min
+                            + Vec2::new(lx as f32 * cell_length, ly as f32 * cell_length),
+                        Vec2::splat(cell_length),
+                    );
+                    shapes.extend(cellshape_shapes_in_rect(
+                        &centered_square_in_rect(cell_rect, 0.8),
+                        local_board.cells()[lx][ly],
+                        false,
+                    ));
+                }
+            }
+
+            if let GameResult::Won { shape, .. } = local_board.get_winner() {
+                shapes.extend(cellshape_shapes_in_rect(&local_rect, Some(shape), true));
+            }
+        }
+    }
+
+    if let GameResult::Won {
+        line: [start_coord, _, end_coord],
+        ..
+    } = board.get_winner()
+    {
+        shapes.push(grid_winning_line_shape_in_rect(
+            &rect,
+            false,
+            N,
+            start_coord,
+            end_coord,
+        ));
+    }
+
+    shapes
+}
+
+/// Format a stroke as SVG attributes.
+fn svg_stroke(Stroke { width, color }: Stroke) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        "stroke=\"rgb({r},{g},{b})\" stroke-opacity=\"{:.3}\" stroke-width=\"{width}\"",
+        a as f32 / 255.
+    )
+}
+
+/// Write the given shapes as an SVG image with a white background and the given size.
+///
+/// Only line segments and circles are supported, since those are all the board uses.
+pub fn shapes_to_svg(shapes: &[Shape], size: f32) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+        viewBox=\"0 0 {size} {size}\">\n<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+    );
+
+    for shape in shapes {
+        match shape {
+            Shape::LineSegment {
+                points: [start, end],
+                stroke,
+            } => {
+                let _ = writeln!(
+                    svg,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                    start.x,
+                    start.y,
+                    end.x,
+                    end.y,
+                    svg_stroke(*stroke)
+                );
+            }
+            Shape::Circle(CircleShape {
+                center,
+                radius,
+                stroke,
+                ..
+            }) => {
+                let _ = writeln!(
+                    svg,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{radius}\" fill=\"none\" {}/>",
+                    center.x,
+                    center.y,
+                    svg_stroke(*stroke)
+                );
+            }
+            _ => (),
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Get the frames of a normal game, with one for the empty board and one after each move.
+pub fn normal_game_frames(record: &GameRecord<Coord>, size: f32) -> Vec<Vec<Shape>> {
+    record
+        .replay(Board::new_with_first_shape(
+            record.first_shape.other(),
+            record.first_shape,
+        ))
+        .iter()
+        .map(|board| normal_board_shapes(board, size))
+        .collect()
+}
+
+/// Get the frames of an ultimate game with `N` local boards along each side, with one for the
+/// empty board and one after each move.
+pub fn ultimate_game_frames<const N: usize>(
+    record: &GameRecord<GlobalCoord>,
+    size: f32,
+) -> Vec<Vec<Shape>> {
+    record
+        .replay(GlobalBoard::<N>::empty(
+            record.first_shape.other(),
+            record.first_shape,
+        ))
+        .iter()
+        .map(|board| ultimate_board_shapes(board, size))
+        .collect()
+}
+
+/// Rasterize the given shapes into a pixmap with a white background and the given size.
+#[cfg(not(target_arch = "wasm32"))]
+fn rasterize(shapes: &[Shape], size: u32) -> Result<tiny_skia::Pixmap, String> {
+    use tiny_skia::{Paint, PathBuilder, Pixmap, Transform};
+
+    let mut pixmap = Pixmap::new(size, size).ok_or("image size must be non-zero")?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+
+    for shape in shapes {
+        let (path, Stroke { width, color }) = match shape {
+            Shape::LineSegment {
+                points: [start, end],
+                stroke,
+            } => {
+                let mut builder = PathBuilder::new();
+                builder.move_to(start.x, start.y);
+                builder.line_to(end.x, end.y);
+                (builder.finish(), *stroke)
+            }
+            Shape::Circle(CircleShape {
+                center,
+                radius,
+                stroke,
+                ..
+            }) => (
+                PathBuilder::from_circle(center.x, center.y, *radius),
+                *stroke,
+            ),
+            _ => continue,
+        };
+        let Some(path) = path else {
+            continue;
+        };
+
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(r, g, b, a);
+        paint.anti_alias = true;
+
+        pixmap.stroke_path(
+            &path,
+            &paint,
+            &tiny_skia::Stroke {
+                width,
+                ..Default::default()
+            },
+            Transform::identity(),
+            None,
+        );
+    }
+
+    Ok(pixmap)
+}
+
+/// Rasterize the given shapes into a PNG image with a white background and the given size.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shapes_to_png(shapes: &[Shape], size: u32) -> Result<Vec<u8>, String> {
+    rasterize(shapes, size)?
+        .encode_png()
+        .map_err(|e| e.to_string())
+}
+
+/// Render the application icon, which is a normal board where X has won on the diagonal, as
+/// [`ICON_SIZE`] by [`ICON_SIZE`] RGBA pixels.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn app_icon_rgba() -> Result<Vec<u8>, String> {
+    let record = GameRecord {
+        first_shape: crate::CellShape::X,
+        moves: vec![(0, 0), (2, 0), (1, 1), (0, 2), (2, 2)],
+        skipped_turns: vec![],
+        move_times: vec![],
+        annotations: std::collections::BTreeMap::new(),
+    };
+    let board = record
+        .replay(Board::default())
+        .pop()
+        .expect("Replays should always include the starting position");
+
+    // The background is opaque, so the premultiplied pixels are the same as unmultiplied
+    Ok(rasterize(&normal_board_shapes(&board, ICON_SIZE as f32), ICON_SIZE)?.take())
+}
+
+/// Rasterize the given frames into a looping animated GIF with the given size. Each frame is shown
+/// for 0.8 seconds, and the last frame is held for 3 seconds.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn frames_to_gif(frames: &[Vec<Shape>], size: u16) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut bytes, size, size, &[]).map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        for (i, shapes) in frames.iter().enumerate() {
+            // The background is opaque, so the premultiplied pixels are the same as unmultiplied
+            let mut rgba = rasterize(shapes, size as u32)?.take();
+            let mut frame = gif::Frame::from_rgba_speed(size, size, &mut rgba, 10);
+            frame.delay = if i + 1 == frames.len() { 300 } else { 80 };
+            encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// The name of the [`Id`](egui::Id) under which the most recent failure to save an image is
+/// remembered, so that it can be shown next to the export buttons.
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_ERROR_ID: &str = "export_error";
+
+/// Save the given image to the given file in the current directory. If that fails, then we log a
+/// warning and remember the error, so that it can be shown to the player by
+/// [`show_export_buttons`].
+#[cfg(not(target_arch = "wasm32"))]
+fn save_export(ui: &Ui, path: &str, image: Result<Vec<u8>, String>) {
+    let id = egui::Id::new(EXPORT_ERROR_ID);
+    match image.and_then(|bytes| std::fs::write(path, bytes).map_err(|e| e.to_string())) {
+        Ok(()) => ui.data().remove::<String>(id),
+        Err(e) => {
+            tracing::warn!(path, "failed to save image: {e}");
+            ui.data()
+                .insert_temp(id, format!("Failed to save {path}: {e}"));
+        }
+    }
+}
+
+/// Show buttons to copy the board as an SVG and, on native, to save it as a PNG in the current
+/// directory. The shapes are only computed if a button is clicked.
+///
+/// If saving an image failed, then a warning sign is shown after the buttons, with the error
+/// when it's hovered.
+pub fn show_export_buttons(ui: &mut Ui, shapes: impl Fn(f32) -> Vec<Shape>) {
+    if ui
+        .add(egui::Button::new("SVG").fill(Color32::TRANSPARENT))
+        .on_hover_text("Copy the board as an SVG image")
+        .clicked()
+    {
+        ui.output().copied_text = shapes_to_svg(&shapes(IMAGE_SIZE), IMAGE_SIZE);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if ui
+        .add(egui::Button::new("PNG").fill(Color32::TRANSPARENT))
+        .on_hover_text("Save the board as board.png in the current directory")
+        .clicked()
+    {
+        save_export(
+            ui,
+            "board.png",
+            shapes_to_png(&shapes(IMAGE_SIZE), IMAGE_SIZE as u32),
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let error = ui.data().get_temp::<String>(egui::Id::new(EXPORT_ERROR_ID));
+        if let Some(error) = error {
+            ui.colored_label(Color32::RED, "\u{26A0}")
+                .on_hover_text(error);
+        }
+    }
+}
+
+/// Show a button to save the whole game as an animated GIF in the current directory. The frames
+/// are only computed if the button is clicked, and any failure to save them is shown next to the
+/// [export buttons](show_export_buttons).
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_gif_export_button(ui: &mut Ui, frames: impl Fn(f32) -> Vec<Vec<Shape>>) {
+    if ui
+        .add(egui::Button::new("GIF").fill(Color32::TRANSPARENT))
+        .on_hover_text("Save the whole game as game.gif in the current directory")
+        .clicked()
+    {
+        save_export(
+            ui,
+            "game.gif",
+            frames_to_gif(&frames(IMAGE_SIZE), IMAGE_SIZE as u16),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal::test_utils::make_board, CellShape};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn shapes_to_svg_test() {
+        let board = make_board!(X O _; _ X O; _ _ X);
+        let svg = shapes_to_svg(&normal_board_shapes(&board, 300.), 300.);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+
+        // 4 board lines, 2 lines for each X, and the winning line
+        assert_eq!(svg.matches("<line").count(), 4 + 2 * 3 + 1);
+        assert_eq!(svg.matches("<circle").count(), 2);
+    }
+
+    #[test]
+    fn ultimate_board_shapes_test() {
+        // 4 global lines and 4 lines for each local board
+        assert_eq!(
+            ultimate_board_shapes(&GlobalBoard::default(), 300.).len(),
+            4 + 9 * 4
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn shapes_to_png_test() {
+        let board = make_board!(X O _; _ X O; _ _ X);
+        let png = shapes_to_png(&normal_board_shapes(&board, 100.), 100).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    fn game_frames_test() {
+        let record = GameRecord {
+            first_shape: CellShape::X,
+            moves: vec![(1, 1), (0, 0), (2, 2), (1, 1)],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        };
+        let frames = normal_game_frames(&record, 100.);
+
+        // The illegal last move stops the replay
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            shapes_to_svg(&frames[3], 100.),
+            shapes_to_svg(
+                &normal_board_shapes(&make_board!(O _ _; _ X _; _ _ X), 100.),
+                100.
+            )
+        );
+
+        let record = GameRecord {
+            first_shape: CellShape::O,
+            moves: vec![(1, 1, (0, 2)), (0, 2, (1, 1))],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        };
+        assert_eq!(ultimate_game_frames::<3>(&record, 100.).len(), 3);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn app_icon_rgba_test() {
+        let rgba = app_icon_rgba().unwrap();
+        assert_eq!(rgba.len(), 4 * (ICON_SIZE * ICON_SIZE) as usize);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn frames_to_gif_test() {
+        let record = GameRecord {
+            first_shape: CellShape::X,
+            moves: vec![(1, 1), (0, 0)],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        };
+        let frames = normal_game_frames(&record, 50.);
+        let gif = frames_to_gif(&frames, 50).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+    }
+}
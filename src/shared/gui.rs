@@ -2,9 +2,43 @@
 
 use crate::CellShape;
 use eframe::{
-    egui::Painter,
-    epaint::{CircleShape, Color32, Pos2, Rect, Shape, Stroke, Vec2},
+    egui::{self, Context, Painter, Sense},
+    epaint::{Align2, CircleShape, Color32, FontId, Pos2, Rect, Shape, Stroke, Vec2},
 };
+use std::time::Duration;
+
+/// The width of the side panel in the wide layout. See [`is_wide_layout`].
+pub const SIDE_PANEL_WIDTH: f32 = 320.;
+
+/// Check if the window is wide enough to fit the side panel next to a board which takes up the
+/// full height of the window. If it isn't, then the apps use the narrow layout, with a floating
+/// settings window.
+pub fn is_wide_layout(ctx: &Context) -> bool {
+    let size = ctx.input().screen_rect().size();
+    size.x >= size.y + SIDE_PANEL_WIDTH
+}
+
+/// How often to repaint while we're waiting for something in the background which isn't being
+/// animated, like an evaluation or a running clock. See [`request_repaint`].
+pub const BACKGROUND_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Request the next repaint, since egui only repaints by itself after input.
+///
+/// If something is `animating`, like the spinner of [`draw_thinking_overlay`], then we repaint
+/// straight away so that it's smooth. If we're only `waiting` for something in the background,
+/// then we repaint after [`BACKGROUND_REPAINT_INTERVAL`] to check on it. Otherwise nothing can
+/// change until the next input, so we don't repaint at all.
+pub fn request_repaint(ctx: &Context, animating: bool, waiting: bool) {
+    if animating {
+        ctx.request_repaint();
+    } else if waiting {
+        ctx.request_repaint_after(BACKGROUND_REPAINT_INTERVAL);
+    }
+}
+
+/// The width of the coordinate labels around a board, as a fraction of the width of the rect of
+/// the board. See [`draw_coordinate_labels`].
+const COORDINATE_LABEL_MARGIN: f32 = 0.05;
 
 /// Create a centered square in the given rect, taking up the given percentage of length.
 pub fn centered_square_in_rect(rect: Rect, percent: f32) -> Rect {
@@ -21,10 +55,20 @@ pub fn draw_cellshape_in_rect(
     shape: Option<CellShape>,
     translucent: bool,
 ) {
+    painter.extend(cellshape_shapes_in_rect(rect, shape, translucent));
+}
+
+/// Get the shapes needed to draw the given cellshape in the given rect. See
+/// [`draw_cellshape_in_rect`].
+pub fn cellshape_shapes_in_rect(
+    rect: &Rect,
+    shape: Option<CellShape>,
+    translucent: bool,
+) -> Vec<Shape> {
     let stroke_width = rect.width() / 30.0;
 
     match shape {
-        None => (),
+        None => vec![],
         Some(CellShape::X) => {
             let rect = centered_square_in_rect(*rect, 0.9);
             let tl = rect.min;
@@ -42,7 +86,7 @@ pub fn draw_cellshape_in_rect(
                 },
             };
 
-            painter.extend(vec![
+            vec![
                 Shape::LineSegment {
                     points: [tl, br],
                     stroke,
@@ -51,25 +95,88 @@ pub fn draw_cellshape_in_rect(
                     points: [bl, tr],
                     stroke,
                 },
-            ]);
+            ]
         }
-        Some(CellShape::O) => {
-            painter.add(Shape::Circle(CircleShape {
-                center: rect.center(),
-                radius: rect.width() / 2.2,
-                fill: Color32::TRANSPARENT,
-                stroke: Stroke {
-                    width: stroke_width,
-                    color: if translucent {
-                        let c = Color32::LIGHT_BLUE;
-                        Color32::from_rgba_unmultiplied(c.r(), c.g(), c.b(), 128)
-                    } else {
-                        Color32::LIGHT_BLUE
-                    },
+        Some(CellShape::O) => vec![Shape::Circle(CircleShape {
+            center: rect.center(),
+            radius: rect.width() / 2.2,
+            fill: Color32::TRANSPARENT,
+            stroke: Stroke {
+                width: stroke_width,
+                color: if translucent {
+                    let c = Color32::LIGHT_BLUE;
+                    Color32::from_rgba_unmultiplied(c.r(), c.g(), c.b(), 128)
+                } else {
+                    Color32::LIGHT_BLUE
                 },
-            }));
-        }
+            },
+        })],
+    }
+}
+
+/// Get the shapes for the two vertical and two horizontal lines of a board in the given rect.
+pub fn board_line_shapes_in_rect(rect: &Rect, color: Color32) -> Vec<Shape> {
+    grid_line_shapes_in_rect(rect, color, 3)
+}
+
+/// Get the shapes for the lines between the cells of an `n` by `n` grid in the given rect. See
+/// [`board_line_shapes_in_rect`] for the usual 3x3 board.
+pub fn grid_line_shapes_in_rect(rect: &Rect, color: Color32, n: usize) -> Vec<Shape> {
+    let stroke = Stroke {
+        width: rect.width() / 80.0,
+        color,
     };
+
+    let mut shapes = Vec::with_capacity(2 * n.saturating_sub(1));
+    for i in 1..n {
+        let i = i as f32 / n as f32;
+
+        // Vertical line
+        let x = rect.min.x + i * rect.width();
+        let y = rect.max.y;
+        shapes.push(Shape::LineSegment {
+            points: [Pos2 { x, y: rect.min.y }, Pos2 { x, y }],
+            stroke,
+        });
+
+        // Horizontal line
+        let y = rect.min.y + i * rect.height();
+        let x = rect.max.x;
+        shapes.push(Shape::LineSegment {
+            points: [Pos2 { x: rect.min.x, y }, Pos2 { x, y }],
+            stroke,
+        });
+    }
+    shapes
+}
+
+/// Fill the given rect with a translucent colour representing the given chance of winning, going
+/// from red for a certain loss to green for a certain win.
+pub fn draw_heat_in_rect(painter: &Painter, rect: &Rect, win_chance: f32) {
+    let win_chance = win_chance.clamp(0., 1.);
+
+    painter.rect_filled(
+        *rect,
+        rect.width() / 10.,
+        Color32::from_rgba_unmultiplied(
+            (255. * (1. - win_chance)) as u8,
+            (255. * win_chance) as u8,
+            0,
+            96,
+        ),
+    );
+}
+
+/// Fill the given rect with a translucent blue whose opacity represents the given share of the
+/// AI's attention, from transparent for a move it has ignored to opaque for its favourite move.
+pub fn draw_visits_in_rect(painter: &Painter, rect: &Rect, share: f32) {
+    let share = share.clamp(0., 1.);
+
+    painter.rect_filled(
+        *rect,
+        rect.width() / 10.,
+        Color32::from_rgba_unmultiplied(30, 144, 255, (160. * share) as u8),
+    );
 }
 
 /// Draw the winning line on the board in the given rect between the given start and end coordinates.
@@ -80,6 +187,22 @@ pub fn draw_winning_line_in_rect(
     start_coord: (usize, usize),
     end_coord: (usize, usize),
 ) {
+    painter.add(winning_line_shape_in_rect(
+        rect,
+        dark_mode,
+        start_coord,
+        end_coord,
+    ));
+}
+
+/// Get the shape of the winning line on the board in the given rect. See
+/// [`draw_winning_line_in_rect`].
+pub fn winning_line_shape_in_rect(
+    rect: &Rect,
+    dark_mode: bool,
+    start_coord: (usize, usize),
+    end_coord: (usize, usize),
+) -> Shape {
     let Pos2 { x: min_x, y: min_y } = rect.min;
     let Pos2 { x: max_x, y: max_y } = rect.max;
     let len = rect.width();
@@ -174,7 +297,7 @@ pub fn draw_winning_line_in_rect(
     };
 
     let stroke_width = rect.width() / 90.0;
-    painter.add(Shape::LineSegment {
+    Shape::LineSegment {
         points: [start, end],
         stroke: Stroke {
             width: stroke_width,
@@ -184,5 +307,249 @@ pub fn draw_winning_line_in_rect(
                 Color32::BLACK
             },
         },
+    }
+}
+
+/// Get the shape of the winning line through the cells of an `n` by `n` grid in the given rect,
+/// going from the centre of the start cell to the centre of the end cell and a little beyond.
+///
+/// The usual 3x3 board is drawn with [`winning_line_shape_in_rect`] instead, so that it looks the
+/// same wherever it's drawn.
+pub fn grid_winning_line_shape_in_rect(
+    rect: &Rect,
+    dark_mode: bool,
+    n: usize,
+    start_coord: (usize, usize),
+    end_coord: (usize, usize),
+) -> Shape {
+    if n == 3 {
+        return winning_line_shape_in_rect(rect, dark_mode, start_coord, end_coord);
+    }
+
+    let cell_len = rect.width() / n as f32;
+    let centre =
+        |(x, y): (usize, usize)| rect.min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * cell_len;
+
+    let start = centre(start_coord);
+    let end = centre(end_coord);
+    let overhang = (end - start).normalized() * cell_len * 0.45;
+
+    Shape::LineSegment {
+        points: [start - overhang, end + overhang],
+        stroke: Stroke {
+            width: rect.width() / 90.0,
+            color: if dark_mode {
+                Color32::WHITE
+            } else {
+                Color32::BLACK
+            },
+        },
+    }
+}
+
+/// Draw a banner with the given text across the middle of the board in the given rect, and dim
+/// the board behind it to show that it can't be played on anymore.
+pub fn draw_game_over_banner_in_rect(rect: &Rect, painter: &Painter, dark_mode: bool, text: &str) {
+    let (background, foreground) = if dark_mode {
+        (Color32::from_black_alpha(160), Color32::WHITE)
+    } else {
+        (Color32::from_white_alpha(160), Color32::BLACK)
+    };
+
+    painter.rect_filled(*rect, 0., background);
+
+    let banner = Rect::from_center_size(
+        rect.center(),
+        Vec2::new(rect.width() * 0.8, rect.height() / 5.),
+    );
+    painter.rect_filled(banner, banner.height() / 4., background);
+    painter.text(
+        banner.center(),
+        Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(banner.height() / 2.),
+        foreground,
+    );
+}
+
+/// Draw coordinate labels around the square board in the given rect, which is a grid of `size` by
+/// `size` cells, with a letter above each column and a number left of each row. Return the rect
+/// left for the board itself, which is shrunk to make room for the labels. See
+/// [`cell_name`](super::status::cell_name).
+pub fn draw_coordinate_labels(painter: &Painter, rect: Rect, size: usize, color: Color32) -> Rect {
+    let margin = rect.width() * COORDINATE_LABEL_MARGIN;
+    let board_rect = Rect::from_min_size(
+        rect.min + Vec2::splat(margin),
+        Vec2::splat(rect.width() - margin),
+    );
+    let cell_length = board_rect.width() / size as f32;
+    let font = FontId::proportional(margin * 0.7);
+
+    for i in 0..size {
+        let offset = (i as f32 + 0.5) * cell_length;
+
+        painter.text(
+            Pos2::new(board_rect.min.x + offset, rect.min.y + margin / 2.),
+            Align2::CENTER_CENTER,
+            (b'a' + i as u8) as char,
+            font.clone(),
+            color,
+        );
+        painter.text(
+            Pos2::new(rect.min.x + margin / 2., board_rect.min.y + offset),
+            Align2::CENTER_CENTER,
+            i + 1,
+            font.clone(),
+            color,
+        );
+    }
+
+    board_rect
+}
+
+/// Faintly dim the board in the given rect and draw a spinner in the middle of it, to show that
+/// the AI is thinking and the board can't be played on until it has moved. The spinner turns once
+/// a second, using the given `time` in seconds.
+pub fn draw_thinking_overlay(painter: &Painter, rect: &Rect, dark_mode: bool, time: f64) {
+    let (background, foreground) = if dark_mode {
+        (Color32::from_black_alpha(64), Color32::WHITE)
+    } else {
+        (Color32::from_white_alpha(64), Color32::BLACK)
+    };
+    painter.rect_filled(*rect, 0., background);
+
+    // Three quarters of a circle, starting from an angle which goes round once a second
+    let radius = rect.width() / 16.;
+    let start = (time.fract() * std::f64::consts::TAU) as f32;
+    let points: Vec<Pos2> = (0..=24)
+        .map(|i| {
+            let angle = start + i as f32 / 24. * 1.5 * std::f32::consts::PI;
+            rect.center() + radius * Vec2::angled(angle)
+        })
+        .collect();
+    painter.add(Shape::line(
+        points,
+        Stroke {
+            width: radius / 4.,
+            color: foreground,
+        },
+    ));
+}
+
+/// Return the fraction of a cell that its piece takes up, which is bigger in presentation mode so
+/// that the pieces can be seen from the back of a room.
+pub fn piece_scale(presenting: bool) -> f32 {
+    if presenting {
+        0.95
+    } else {
+        0.8
+    }
+}
+
+/// Draw an arrow from the centre of the cell of the previous move to the centre of the cell of the
+/// last move, for presentation mode. Both ends are pulled in by a third of a cell so that the
+/// arrow doesn't cover the pieces themselves.
+pub fn draw_last_move_arrow(painter: &Painter, from: Pos2, to: Pos2, cell_length: f32) {
+    draw_arrow_between_cells(
+        painter,
+        from,
+        to,
+        cell_length,
+        Color32::from_rgba_unmultiplied(255, 140, 0, 200),
+    );
+}
+
+/// Draw an arrow in the given colour between the centres of two cells, with both ends pulled in by
+/// a third of a cell. Nothing is drawn if the cells are too close together.
+pub fn draw_arrow_between_cells(
+    painter: &Painter,
+    from: Pos2,
+    to: Pos2,
+    cell_length: f32,
+    color: Color32,
+) {
+    let inset = cell_length / 3.;
+    let vec = to - from;
+    if vec.length() <= 2. * inset {
+        return;
+    }
+
+    let dir = vec.normalized();
+    painter.arrow(
+        from + dir * inset,
+        vec - dir * 2. * inset,
+        Stroke {
+            width: cell_length / 15.,
+            color,
+        },
+    );
+}
+
+/// Show the given status in a big font across the top of the board, for presentation mode.
+pub fn show_turn_indicator(ui: &mut egui::Ui, text: &str) {
+    ui.vertical_centered(|ui| {
+        ui.label(egui::RichText::new(text).size(48.).strong());
     });
 }
+
+/// Dim the whole window and show the given hint in the middle while files are being dragged over
+/// it, like to say what dropping them will do.
+pub fn show_file_drop_hint(ctx: &Context, text: &str) {
+    if ctx.input().raw.hovered_files.is_empty() {
+        return;
+    }
+
+    let screen_rect = ctx.input().screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("file_drop_hint"),
+    ));
+    painter.rect_filled(screen_rect, 0., Color32::from_black_alpha(160));
+    painter.text(
+        screen_rect.center(),
+        Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(30.),
+        Color32::WHITE,
+    );
+}
+
+/// Show a modal dialog asking whether to discard the game in progress, and dim and block clicks
+/// on everything behind it.
+///
+/// Return `Some(true)` if the game should be discarded, `Some(false)` if it shouldn't, or `None`
+/// if neither has been chosen yet. `ask_again` is shown as a checkbox so that the dialog can be
+/// turned off.
+pub fn show_discard_dialog(ctx: &Context, ask_again: &mut bool) -> Option<bool> {
+    let screen_rect = ctx.input().screen_rect();
+
+    egui::Area::new("discard_dialog_background")
+        .fixed_pos(screen_rect.min)
+        .show(ctx, |ui| {
+            ui.painter()
+                .rect_filled(screen_rect, 0., Color32::from_black_alpha(128));
+            ui.allocate_rect(screen_rect, Sense::click());
+        });
+
+    let mut choice = None;
+    egui::Area::new("discard_dialog")
+        .order(egui::Order::Foreground)
+        .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label("Discard the game in progress?");
+                ui.checkbox(ask_again, "Ask me again next time");
+
+                ui.horizontal(|ui| {
+                    if ui.button("Discard").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+        });
+
+    choice
+}
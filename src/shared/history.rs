@@ -0,0 +1,110 @@
+//! This module provides [`History`], which lets the user step through the positions of a game and
+//! play on from any of them.
+
+use super::{ai::Game, record::GameRecord};
+
+/// The history of a game, built by replaying its [`GameRecord`], with one of its positions
+/// selected to be shown.
+///
+/// The record itself isn't kept, so that it can still be annotated while stepping through the
+/// history. See [`GameRecord::show_clickable_move_list`] to show the moves to jump between.
+#[derive(Clone, Debug)]
+pub struct History<G> {
+    /// The position before the first move and after each move of the game.
+    positions: Vec<G>,
+
+    /// The number of moves played in the selected position.
+    ply: usize,
+}
+
+impl<G: Game> History<G> {
+    /// Replay the given record from the given starting position, and select the final position.
+    ///
+    /// See [`GameRecord::replay`].
+    pub fn new(record: &GameRecord<G::Move>, start: G) -> Self {
+        let positions = record.replay(start);
+
+        Self {
+            ply: positions.len() - 1,
+            positions,
+        }
+    }
+
+    /// Return the number of moves played in the selected position.
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// Return the number of moves played in the final position.
+    pub fn last_ply(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    /// Select the position after the given number of moves, clamped to the final position.
+    pub fn set_ply(&mut self, ply: usize) {
+        self.ply = ply.min(self.last_ply());
+    }
+
+    /// Return the selected position.
+    pub fn position(&self) -> &G {
+        &self.positions[self.ply]
+    }
+
+    /// Return the final position.
+    pub fn final_position(&self) -> &G {
+        self.positions
+            .last()
+            .expect("There should always be a starting position")
+    }
+
+    /// Fork the game at the selected position, throwing away the moves after it, and return that
+    /// position along with the given record of the game cut down to the moves that reach it.
+    pub fn fork(self, record: &GameRecord<G::Move>) -> (G, GameRecord<G::Move>) {
+        let mut record = record.clone();
+        record.truncate(self.ply);
+        let mut positions = self.positions;
+        positions.truncate(self.ply + 1);
+
+        (
+            positions
+                .pop()
+                .expect("There should always be a starting position"),
+            record,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        normal::{board::Board, test_utils::make_board},
+        CellShape,
+    };
+
+    #[test]
+    fn history_test() {
+        let mut record = GameRecord::new(CellShape::X);
+        for mv in [(1, 1), (0, 0), (2, 2)] {
+            record.push_move(mv, Default::default());
+        }
+        let mut history = History::new(&record, Board::default());
+
+        assert_eq!(history.ply(), 3);
+        assert_eq!(history.last_ply(), 3);
+        assert_eq!(history.position(), &make_board!(O _ _; _ X _; _ _ X));
+
+        history.set_ply(1);
+        assert_eq!(history.position(), &make_board!(_; _ X _; _));
+        assert_eq!(history.final_position(), &make_board!(O _ _; _ X _; _ _ X));
+
+        history.set_ply(10);
+        assert_eq!(history.ply(), 3);
+
+        history.set_ply(1);
+        let (board, record) = history.fork(&record);
+        assert_eq!(board, make_board!(_; _ X _; _));
+        assert_eq!(record.moves, vec![(1, 1)]);
+        assert_eq!(record.move_times.len(), 1);
+    }
+}
@@ -0,0 +1,157 @@
+//! This module provides [`Ladder`], a local rating ladder where the player climbs through levels
+//! of the AI by beating it.
+
+use super::board::GameResult;
+use crate::CellShape;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The highest level of the ladder, where the AI plays at full strength.
+pub const MAX_LADDER_LEVEL: u8 = 10;
+
+/// How the AI plays at a level of the ladder. See [`Ladder::policy`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LadderPolicy {
+    /// Play randomly, like [`RandomBot`](super::ai::RandomBot).
+    Random,
+
+    /// Take wins and block losses, like [`GreedyBot`](super::ai::GreedyBot).
+    Greedy,
+
+    /// Search with the engine of the variant at the given strength, from 0 for the weakest search
+    /// to 1 for the full search.
+    Search {
+        /// The strength of the search, which each variant turns into a search budget.
+        strength: f32,
+    },
+}
+
+/// The player's position on the ladder, meant to be saved and loaded between sessions.
+///
+/// The player starts on level 1, goes up a level when they beat the AI, and goes down a level
+/// when they lose to it. Draws leave the level alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ladder {
+    /// The current level, from 1 to [`MAX_LADDER_LEVEL`].
+    pub level: u8,
+
+    /// The highest level that the player has reached.
+    pub best_level: u8,
+
+    /// The number of games won against the ladder.
+    pub wins: u32,
+
+    /// The number of games drawn against the ladder.
+    pub draws: u32,
+
+    /// The number of games lost against the ladder.
+    pub losses: u32,
+}
+
+impl Default for Ladder {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            best_level: 1,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+}
+
+impl Ladder {
+    /// Update the ladder with the result of a finished game where the player was playing as
+    /// `player_shape`, and move the player up or down a level.
+    ///
+    /// Ongoing games are ignored.
+    pub fn record_result(&mut self, result: GameResult, player_shape: CellShape) {
+        match result {
+            GameResult::Ongoing => return,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Won { shape, .. } if shape == player_shape => {
+                self.wins += 1;
+                self.level = (self.level + 1).min(MAX_LADDER_LEVEL);
+            }
+            GameResult::Won { .. } => {
+                self.losses += 1;
+                self.level = self.level.saturating_sub(1).max(1);
+            }
+        }
+
+        self.best_level = self.best_level.max(self.level);
+    }
+
+    /// Return the number of games that have been played against the ladder.
+    pub fn games_played(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Return how the AI plays at the current level. The first two levels use the random and
+    /// greedy AIs, and the rest search with a bigger budget at each level.
+    pub fn policy(&self) -> LadderPolicy {
+        match self.level {
+            0 | 1 => LadderPolicy::Random,
+            2 => LadderPolicy::Greedy,
+            level => LadderPolicy::Search {
+                strength: (level.min(MAX_LADDER_LEVEL) - 3) as f32 / (MAX_LADDER_LEVEL - 3) as f32,
+            },
+        }
+    }
+
+    /// Show the current level and record in a settings window, with a button to reset them.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Level {} of {MAX_LADDER_LEVEL} (best {}), {} won, {} drawn, {} lost",
+                self.level, self.best_level, self.wins, self.draws, self.losses
+            ));
+
+            if ui.button("Reset").clicked() {
+                *self = Self::default();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_result_test() {
+        let x_wins = GameResult::Won {
+            shape: CellShape::X,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        let mut ladder = Ladder::default();
+        assert_eq!(ladder.policy(), LadderPolicy::Random);
+
+        // Losing on the bottom level stays there
+        ladder.record_result(x_wins, CellShape::O);
+        ladder.record_result(GameResult::Ongoing, CellShape::O);
+        assert_eq!(ladder.level, 1);
+        assert_eq!(ladder.games_played(), 1);
+
+        ladder.record_result(x_wins, CellShape::X);
+        ladder.record_result(GameResult::Draw, CellShape::X);
+        assert_eq!(ladder.level, 2);
+        assert_eq!(ladder.policy(), LadderPolicy::Greedy);
+
+        ladder.record_result(x_wins, CellShape::X);
+        assert_eq!(ladder.policy(), LadderPolicy::Search { strength: 0. });
+
+        for _ in 0..20 {
+            ladder.record_result(x_wins, CellShape::X);
+        }
+        assert_eq!(ladder.level, MAX_LADDER_LEVEL);
+        assert_eq!(ladder.policy(), LadderPolicy::Search { strength: 1. });
+
+        ladder.record_result(x_wins, CellShape::O);
+        assert_eq!(ladder.level, MAX_LADDER_LEVEL - 1);
+        assert_eq!(ladder.best_level, MAX_LADDER_LEVEL);
+        assert_eq!((ladder.wins, ladder.draws, ladder.losses), (22, 1, 2));
+    }
+}
@@ -0,0 +1,192 @@
+//! This module provides [`LearningTable`], which remembers how games against the player went after
+//! the positions that the AI chose in the opening, so that the AI can slowly stop falling for the
+//! same traps.
+
+use super::board::GameResult;
+use crate::CellShape;
+use eframe::{egui, Storage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The number of moves at the start of each game that the AI learns from. Later positions are
+/// almost never repeated, so they would only fill up the table.
+pub const LEARNING_PLIES: usize = 12;
+
+/// How many games the AI's own estimate of a move is worth when it's weighed against the results
+/// of the games after that move. See [`LearningTable::choose`].
+const PRIOR_GAMES: f64 = 4.;
+
+/// The results of the games which reached a position, from the perspective of the AI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultCounts {
+    /// The number of games that the AI won.
+    pub wins: u32,
+
+    /// The number of games that were drawn.
+    pub draws: u32,
+
+    /// The number of games that the AI lost.
+    pub losses: u32,
+}
+
+impl ResultCounts {
+    /// Return the total number of games.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// A table of the results of the games which reached each position that the AI moved into during
+/// the opening, meant to be saved and loaded between sessions.
+///
+/// The positions are identified by their hashes, like
+/// [`GlobalBoard::position_hash`](crate::ultimate::board::GlobalBoard::position_hash).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LearningTable {
+    /// The results of the games after each position.
+    positions: HashMap<u64, ResultCounts>,
+}
+
+impl LearningTable {
+    /// Load the table of the variant with the given name from the storage, using the key
+    /// `{name}_learning`.
+    pub fn load(storage: Option<&dyn Storage>, name: &str) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, &format!("{name}_learning")))
+            .unwrap_or_default()
+    }
+
+    /// Save the table of the variant with the given name to the storage, using the same key as
+    /// [`load`](Self::load).
+    pub fn save(&self, storage: &mut dyn Storage, name: &str) {
+        eframe::set_value(storage, &format!("{name}_learning"), self);
+    }
+
+    /// Return the number of positions in the table.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Check if the table has no positions.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Return the results of the games after the position with the given hash.
+    pub fn counts(&self, position: u64) -> ResultCounts {
+        self.positions.get(&position).copied().unwrap_or_default()
+    }
+
+    /// Record the result of a finished game where the AI was playing as `ai_shape`, for each of
+    /// the given positions that the AI moved into.
+    ///
+    /// Ongoing games are ignored.
+    pub fn record_game(&mut self, positions: &[u64], result: GameResult, ai_shape: CellShape) {
+        if result == GameResult::Ongoing {
+            return;
+        }
+
+        for &position in positions {
+            let counts = self.positions.entry(position).or_default();
+            match result {
+                GameResult::Ongoing => (),
+                GameResult::Draw => counts.draws += 1,
+                GameResult::Won { shape, .. } if shape == ai_shape => counts.wins += 1,
+                GameResult::Won { .. } => counts.losses += 1,
+            }
+        }
+    }
+
+    /// Choose between the given candidate moves, each with the hash of the position after it and
+    /// the AI's own estimate of its score from 0 for a loss to 1 for a win.
+    ///
+    /// Each estimate is blended with the results of the games after that move, where the estimate
+    /// counts as [`PRIOR_GAMES`] games, and the move with the best blended score is chosen. If
+    /// none of the moves have been played before, then we return `None` so that the AI's own
+    /// choice stands.
+    pub fn choose<M: Copy>(
+        &self,
+        candidates: impl IntoIterator<Item = (M, u64, f64)>,
+    ) -> Option<M> {
+        let mut learned_anything = false;
+        let best = candidates
+            .into_iter()
+            .map(|(mv, position, estimate)| {
+                let counts = self.counts(position);
+                learned_anything |= counts.games() > 0;
+
+                let points = f64::from(counts.wins) + f64::from(counts.draws) / 2.;
+                let score =
+                    (PRIOR_GAMES * estimate + points) / (PRIOR_GAMES + f64::from(counts.games()));
+                (mv, score)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(mv, _)| mv);
+
+        best.filter(|_| learned_anything)
+    }
+
+    /// Show the size of the table in a settings window, with a button to forget everything.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Learned from {} positions", self.len()));
+
+            if ui.button("Forget").clicked() {
+                *self = Self::default();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_game_test() {
+        let win = GameResult::Won {
+            shape: CellShape::X,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        let mut table = LearningTable::default();
+        table.record_game(&[1, 2], GameResult::Ongoing, CellShape::X);
+        assert!(table.is_empty());
+
+        table.record_game(&[1, 2], win, CellShape::X);
+        table.record_game(&[1, 3], win, CellShape::O);
+        table.record_game(&[3], GameResult::Draw, CellShape::O);
+        assert_eq!(table.len(), 3);
+        assert_eq!(
+            table.counts(1),
+            ResultCounts {
+                wins: 1,
+                draws: 0,
+                losses: 1
+            }
+        );
+        assert_eq!(table.counts(3).games(), 2);
+        assert_eq!(table.counts(4), ResultCounts::default());
+    }
+
+    #[test]
+    fn choose_test() {
+        let loss = GameResult::Won {
+            shape: CellShape::O,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        // Nothing has been learned about these moves, so the AI's own choice stands
+        let mut table = LearningTable::default();
+        assert_eq!(table.choose([('a', 1, 0.6), ('b', 2, 0.45)]), None);
+
+        // One loss isn't enough to outweigh a better estimate
+        table.record_game(&[1], loss, CellShape::X);
+        assert_eq!(table.choose([('a', 1, 0.6), ('b', 2, 0.45)]), Some('a'));
+
+        // But the AI stops falling for the same trap after a few losses
+        table.record_game(&[1], loss, CellShape::X);
+        assert_eq!(table.choose([('a', 1, 0.6), ('b', 2, 0.45)]), Some('b'));
+    }
+}
@@ -1,4 +1,30 @@
 //! This module provides various types for variant backends and GUIs.
 
+pub mod adaptive;
+pub mod ai;
+pub mod ai_task;
+pub mod blunders;
 pub mod board;
+pub mod chaos;
+pub mod clock;
+pub mod crash_report;
+pub mod drawings;
+pub mod events;
+pub mod explain;
+pub mod export;
 pub mod gui;
+pub mod history;
+pub mod ladder;
+pub mod learning;
+pub mod personality;
+pub mod profiles;
+pub mod record;
+pub mod relay;
+pub mod save_slots;
+pub mod status;
+
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+
+#[cfg(all(target_arch = "wasm32", feature = "relay-client"))]
+pub mod relay_client;
@@ -0,0 +1,265 @@
+//! This module provides [`AiPersonality`], which gives the AI a style of play by choosing between
+//! moves that its engine thinks are about as good as each other.
+
+use super::{
+    ai::{after_move, Game},
+    board::{lines_through, GameResult},
+};
+use crate::CellShape;
+use eframe::egui;
+use itertools::Itertools;
+use rand::{seq::SliceRandom, thread_rng};
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// The personalities that [`Daily`](AiPersonality::Daily) chooses between.
+const DAILY_PERSONALITIES: [AiPersonality; 4] = [
+    AiPersonality::Balanced,
+    AiPersonality::Aggressive,
+    AiPersonality::Defensive,
+    AiPersonality::Tricky,
+];
+
+/// The style of play of the AI.
+///
+/// The engine of each variant finds the moves that it thinks are best, and the personality
+/// chooses between them, so a personality changes how the AI plays without making it weaker.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiPersonality {
+    /// Choose randomly between the best moves.
+    #[default]
+    Balanced,
+
+    /// Prefer moves which make the most two-in-a-rows.
+    Aggressive,
+
+    /// Prefer moves which get in the way of the most of the opponent's pieces.
+    Defensive,
+
+    /// Prefer moves which give the opponent the most chances to go wrong, by counting the replies
+    /// which would let the AI complete a line straight away.
+    Tricky,
+
+    /// Play as a different personality each day, which is the same for everyone on that day.
+    Daily,
+}
+
+/// A [`Game`] where every move is played in a 3x3 grid of cells, so that an [`AiPersonality`]
+/// can judge the lines that a move makes or blocks.
+pub trait GridGame: Game {
+    /// Return the cells of the grid that the given move is played in, along with the coordinate
+    /// of the move in that grid.
+    fn grid_of_move(&self, mv: Self::Move) -> ([[Option<CellShape>; 3]; 3], (usize, usize));
+}
+
+/// Return the number of days since the Unix epoch, in UTC.
+fn days_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86_400)
+}
+
+/// Return the number of `shape`s in the given line of cells.
+fn count_in_line(
+    cells: &[[Option<CellShape>; 3]; 3],
+    line: &[(usize, usize); 3],
+    shape: Option<CellShape>,
+) -> usize {
+    line.iter().filter(|&&(x, y)| cells[x][y] == shape).count()
+}
+
+/// Return the number of lines through the move where the shape to move would have two cells and
+/// the third would be empty, once the move has been played.
+fn threats_created<G: GridGame>(game: &G, mv: G::Move) -> usize {
+    let shape = game.to_move();
+    let (mut cells, (x, y)) = game.grid_of_move(mv);
+    cells[x][y] = Some(shape);
+
+    lines_through((x, y))
+        .iter()
+        .filter(|line| {
+            count_in_line(&cells, line, Some(shape)) == 2 && count_in_line(&cells, line, None) == 1
+        })
+        .count()
+}
+
+/// Return the number of the opponent's pieces in the lines through the move which the shape to
+/// move has no pieces in, since the move stops the opponent from completing those lines.
+fn threats_blocked<G: GridGame>(game: &G, mv: G::Move) -> usize {
+    let shape = game.to_move();
+    let (cells, (x, y)) = game.grid_of_move(mv);
+
+    lines_through((x, y))
+        .iter()
+        .filter(|line| count_in_line(&cells, line, Some(shape)) == 0)
+        .map(|line| count_in_line(&cells, line, Some(shape.other())))
+        .sum()
+}
+
+/// Check if the given move would complete a line for the shape to move.
+fn completes_line<G: GridGame>(game: &G, mv: G::Move) -> bool {
+    let shape = game.to_move();
+    let (mut cells, (x, y)) = game.grid_of_move(mv);
+    cells[x][y] = Some(shape);
+
+    lines_through((x, y))
+        .iter()
+        .any(|line| count_in_line(&cells, line, Some(shape)) == 3)
+}
+
+/// Return the number of replies to the move after which the shape to move could complete a line
+/// straight away, with a search which only looks two moves ahead.
+fn opponent_mistakes<G: GridGame>(game: &G, mv: G::Move) -> usize {
+    let game = after_move(game, mv);
+    if game.get_winner() != GameResult::Ongoing {
+        return 0;
+    }
+
+    game.moves()
+        .into_iter()
+        .filter(|&reply| {
+            let game = after_move(&game, reply);
+            game.get_winner() == GameResult::Ongoing
+                && game.moves().into_iter().any(|mv| completes_line(&game, mv))
+        })
+        .count()
+}
+
+impl AiPersonality {
+    /// All the personalities, in the order that they're shown in the settings.
+    pub const ALL: [Self; 5] = [
+        Self::Balanced,
+        Self::Aggressive,
+        Self::Defensive,
+        Self::Tricky,
+        Self::Daily,
+    ];
+
+    /// Return the name of this personality, to show in the settings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Balanced => "Balanced",
+            Self::Aggressive => "Aggressive",
+            Self::Defensive => "Defensive",
+            Self::Tricky => "Tricky",
+            Self::Daily => "Daily",
+        }
+    }
+
+    /// Return a short description of how this personality plays.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Balanced => "Chooses randomly between the best moves",
+            Self::Aggressive => "Prefers making threats",
+            Self::Defensive => "Prefers blocking your lines",
+            Self::Tricky => "Prefers setting traps that are easy to fall into",
+            Self::Daily => "Plays as a different personality each day",
+        }
+    }
+
+    /// Return the personality to play as on the given number of days since the Unix epoch. This
+    /// is only different to `self` for [`Daily`](Self::Daily), which cycles through the daily
+    /// personalities, so that it's the same for everyone on the same day.
+    pub fn on_day(self, day: u64) -> Self {
+        match self {
+            Self::Daily => DAILY_PERSONALITIES[(day % DAILY_PERSONALITIES.len() as u64) as usize],
+            personality => personality,
+        }
+    }
+
+    /// Return the personality to play as today. See [`on_day`](Self::on_day).
+    pub fn today(self) -> Self {
+        self.on_day(days_since_epoch())
+    }
+
+    /// Choose one of the given moves, which should all be about as good as each other, in the
+    /// style of this personality, breaking ties randomly.
+    ///
+    /// This returns `None` if there are no moves to choose from.
+    pub fn choose<G: GridGame>(self, game: &G, moves: &[G::Move]) -> Option<G::Move> {
+        let personality = self.today();
+        let style = |&&mv: &&G::Move| match personality {
+            Self::Balanced | Self::Daily => 0,
+            Self::Aggressive => threats_created(game, mv),
+            Self::Defensive => threats_blocked(game, mv),
+            Self::Tricky => opponent_mistakes(game, mv),
+        };
+
+        moves
+            .iter()
+            .max_set_by_key(style)
+            .choose(&mut thread_rng())
+            .map(|&&mv| mv)
+    }
+
+    /// Show a combo box to choose the personality in the given [`Ui`](egui::Ui).
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("AI personality");
+            egui::ComboBox::from_id_source("ai_personality")
+                .selected_text(self.name())
+                .show_ui(ui, |ui| {
+                    for personality in Self::ALL {
+                        ui.selectable_value(self, personality, personality.name())
+                            .on_hover_text(personality.description());
+                    }
+                });
+
+            if *self == Self::Daily {
+                ui.label(format!("Today: {}", self.today().name()));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal::{board::Board, test_utils::make_board};
+
+    #[test]
+    fn choose_test() {
+        let board = make_board!(X O _; _ X _; _ _ O);
+        let moves = [(0, 1), (1, 2)];
+
+        for _ in 0..10 {
+            // (0, 1) makes two lines of two, but (1, 2) is the only one in a line with an O
+            assert_eq!(
+                AiPersonality::Aggressive.choose(&board, &moves),
+                Some((0, 1))
+            );
+            assert_eq!(
+                AiPersonality::Defensive.choose(&board, &moves),
+                Some((1, 2))
+            );
+        }
+
+        assert!(AiPersonality::Balanced.choose(&board, &moves).is_some());
+        assert_eq!(AiPersonality::Tricky.choose(&board, &[]), None);
+    }
+
+    #[test]
+    fn opponent_mistakes_test() {
+        // After X plays (0, 1), every reply except blocking at (0, 2) lets X win
+        let board = make_board!(X _ _; _ O _; _);
+        assert_eq!(opponent_mistakes(&board, (0, 1)), 5);
+        assert_eq!(opponent_mistakes(&board, (2, 2)), 0);
+
+        assert!(completes_line(&make_board!(X _ _; X O _; _ O _), (0, 2)));
+        assert!(!completes_line(&board, (0, 1)));
+    }
+
+    #[test]
+    fn on_day_test() {
+        for day in 0..10 {
+            let personality = AiPersonality::Daily.on_day(day);
+            assert_ne!(personality, AiPersonality::Daily);
+            assert_eq!(personality, AiPersonality::Daily.on_day(day));
+            assert_ne!(personality, AiPersonality::Daily.on_day(day + 1));
+        }
+        assert_eq!(AiPersonality::Daily.on_day(0), AiPersonality::Balanced);
+        assert_eq!(AiPersonality::Daily.on_day(6), AiPersonality::Defensive);
+
+        assert_eq!(AiPersonality::Tricky.on_day(1234), AiPersonality::Tricky);
+    }
+}
@@ -0,0 +1,242 @@
+//! This module provides [`Profiles`], which lets the player keep several named configs for a
+//! variant and switch between them, and [`VariantSettings`], which saves them along with the
+//! config in use.
+
+use eframe::{egui, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The name of the profile that every player starts with.
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// A set of named configs for one variant, meant to be saved and loaded between sessions.
+///
+/// One of the profiles is always selected, and the app edits a working copy of its config. That
+/// working copy is written back to the selected profile whenever the player switches profile or
+/// the app saves, so each profile remembers its own changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles<C> {
+    /// The name of the selected profile.
+    selected: String,
+
+    /// The config of every profile, by name.
+    configs: BTreeMap<String, C>,
+
+    /// The name being typed for a new profile in the settings.
+    #[serde(skip)]
+    new_name: String,
+}
+
+impl<C: Clone + Default> Default for Profiles<C> {
+    fn default() -> Self {
+        Self::new(C::default())
+    }
+}
+
+impl<C: Clone> Profiles<C> {
+    /// Create a set of profiles with only the [`DEFAULT_PROFILE`], which has the given config.
+    pub fn new(config: C) -> Self {
+        Self {
+            selected: DEFAULT_PROFILE.to_string(),
+            configs: BTreeMap::from([(DEFAULT_PROFILE.to_string(), config)]),
+            new_name: String::new(),
+        }
+    }
+
+    /// The name of the selected profile.
+    pub fn selected(&self) -> &str {
+        &self.selected
+    }
+
+    /// Iterate over the names of all the profiles in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.configs.keys().map(String::as_str)
+    }
+
+    /// Write the working copy of the config back to the selected profile.
+    pub fn update(&mut self, config: &C) {
+        self.configs.insert(self.selected.clone(), config.clone());
+    }
+
+    /// Select the profile with the given name, saving the working copy of the config to the
+    /// previously selected profile and replacing it with the config of the new one.
+    ///
+    /// Returns false and does nothing if there is no profile with that name.
+    pub fn select(&mut self, name: &str, config: &mut C) -> bool {
+        let Some(new_config) = self.configs.get(name).cloned() else {
+            return false;
+        };
+
+        self.update(config);
+        self.selected = name.to_string();
+        *config = new_config;
+        true
+    }
+
+    /// Create a new profile with the given name and a copy of the working config, and select it.
+    ///
+    /// Returns false and does nothing if the name is empty or already taken.
+    pub fn create(&mut self, name: &str, config: &C) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.configs.contains_key(name) {
+            return false;
+        }
+
+        self.update(config);
+        self.selected = name.to_string();
+        self.configs.insert(self.selected.clone(), config.clone());
+        true
+    }
+
+    /// Delete the selected profile and select the first remaining one, replacing the working
+    /// copy of the config with its config.
+    ///
+    /// Returns false and does nothing if this is the only profile, since one must always be
+    /// selected.
+    pub fn delete_selected(&mut self, config: &mut C) -> bool {
+        if self.configs.len() <= 1 {
+            return false;
+        }
+
+        self.configs.remove(&self.selected);
+        let (name, new_config) = self
+            .configs
+            .iter()
+            .next()
+            .expect("There should be at least one profile left");
+        self.selected = name.clone();
+        *config = new_config.clone();
+        true
+    }
+
+    /// Show a dropdown to switch between the profiles, along with buttons to create and delete
+    /// them, editing the given working copy of the config.
+    pub fn show_selector(&mut self, ui: &mut egui::Ui, config: &mut C) {
+        let mut selected = self.selected.clone();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Profile")
+                .selected_text(&selected)
+                .show_ui(ui, |ui| {
+                    for name in self.configs.keys() {
+                        ui.selectable_value(&mut selected, name.clone(), name);
+                    }
+                });
+
+            if ui
+                .add_enabled(self.configs.len() > 1, egui::Button::new("Delete"))
+                .on_hover_text("Delete this profile")
+                .clicked()
+            {
+                self.delete_selected(config);
+                selected = self.selected.clone();
+            }
+        });
+
+        if selected != self.selected {
+            self.select(&selected, config);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_name)
+                    .hint_text("New profile name")
+                    .desired_width(150.),
+            );
+
+            let name = self.new_name.trim();
+            let valid = !name.is_empty() && !self.configs.contains_key(name);
+            if ui
+                .add_enabled(valid, egui::Button::new("Save as new profile"))
+                .on_hover_text("Create a new profile with the current settings")
+                .clicked()
+            {
+                let name = name.to_string();
+                self.create(&name, config);
+                self.new_name.clear();
+            }
+        });
+    }
+}
+
+/// All the saved settings of one variant, which are the config in use and the profiles.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariantSettings<C> {
+    /// The working copy of the config of the selected profile.
+    pub config: C,
+
+    /// The profiles of the variant.
+    pub profiles: Profiles<C>,
+}
+
+impl<C: Clone + Default> Default for VariantSettings<C> {
+    fn default() -> Self {
+        Self {
+            config: C::default(),
+            profiles: Profiles::default(),
+        }
+    }
+}
+
+impl<C: Clone + Default + Serialize + DeserializeOwned> VariantSettings<C> {
+    /// Load the settings of the variant with the given name from the storage, using the keys
+    /// `{name}_config` and `{name}_profiles`.
+    ///
+    /// Settings that were saved before profiles existed get a single [`DEFAULT_PROFILE`] with
+    /// the saved config.
+    pub fn load(storage: Option<&dyn Storage>, name: &str) -> Self {
+        let config: C = storage
+            .and_then(|storage| eframe::get_value(storage, &format!("{name}_config")))
+            .unwrap_or_default();
+        let profiles = storage
+            .and_then(|storage| eframe::get_value(storage, &format!("{name}_profiles")))
+            .unwrap_or_else(|| Profiles::new(config.clone()));
+
+        Self { config, profiles }
+    }
+
+    /// Save the settings of the variant with the given name to the storage, using the same keys
+    /// as [`load`](Self::load). The selected profile is updated with the config first.
+    pub fn save(&mut self, storage: &mut dyn Storage, name: &str) {
+        self.profiles.update(&self.config);
+        eframe::set_value(storage, &format!("{name}_config"), &self.config);
+        eframe::set_value(storage, &format!("{name}_profiles"), &self.profiles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_test() {
+        let mut config = 1;
+        let mut profiles = Profiles::new(config);
+        assert_eq!(profiles.selected(), DEFAULT_PROFILE);
+
+        assert!(!profiles.create("", &config));
+        assert!(!profiles.create(DEFAULT_PROFILE, &config));
+        assert!(!profiles.delete_selected(&mut config));
+
+        // Changes to the working copy are kept when switching profiles
+        assert!(profiles.create("Training", &config));
+        config = 2;
+        assert!(profiles.select(DEFAULT_PROFILE, &mut config));
+        assert_eq!(config, 1);
+        assert!(profiles.select("Training", &mut config));
+        assert_eq!(config, 2);
+        assert!(!profiles.select("Kid mode", &mut config));
+        assert_eq!(profiles.selected(), "Training");
+
+        assert_eq!(
+            profiles.names().collect::<Vec<_>>(),
+            vec![DEFAULT_PROFILE, "Training"]
+        );
+
+        assert!(profiles.delete_selected(&mut config));
+        assert_eq!(profiles.selected(), DEFAULT_PROFILE);
+        assert_eq!(config, 1);
+        assert_eq!(profiles.names().collect::<Vec<_>>(), vec![DEFAULT_PROFILE]);
+    }
+}
@@ -0,0 +1,499 @@
+//! This module provides [`GameRecord`], which records the moves of a game so that it can be
+//! replayed, exported, or analysed later.
+
+use super::ai::Game;
+use crate::CellShape;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web_time::Duration;
+
+/// A record of the moves in a game, where `M` is the type of a move.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord<M> {
+    /// The shape which made the first move.
+    pub first_shape: CellShape,
+
+    /// The moves, in the order that they were played.
+    pub moves: Vec<M>,
+
+    /// The number of moves which had been played each time that a turn was skipped, in order.
+    /// See [`Game::skip_turn`].
+    #[serde(default)]
+    pub skipped_turns: Vec<usize>,
+
+    /// How long each of the last moves took to play, from the end of the move before it, in
+    /// order. Any moves before these weren't timed, like the random opening of a chaos game.
+    #[serde(default)]
+    pub move_times: Vec<Duration>,
+
+    /// The annotations of the moves, by the index of the move in [`moves`](Self::moves).
+    #[serde(default)]
+    pub annotations: BTreeMap<usize, Annotation>,
+}
+
+/// A symbol to judge a move with, like in chess notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveSymbol {
+    /// `!!`, for a brilliant move.
+    Brilliant,
+
+    /// `!`, for a good move.
+    Good,
+
+    /// `!?`, for an interesting move.
+    Interesting,
+
+    /// `?!`, for a dubious move.
+    Dubious,
+
+    /// `?`, for a mistake.
+    Mistake,
+
+    /// `??`, for a blunder.
+    Blunder,
+}
+
+impl MoveSymbol {
+    /// All the symbols, from best to worst.
+    pub const ALL: [Self; 6] = [
+        Self::Brilliant,
+        Self::Good,
+        Self::Interesting,
+        Self::Dubious,
+        Self::Mistake,
+        Self::Blunder,
+    ];
+
+    /// Return the symbol as it's written after a move, like `"!?"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Brilliant => "!!",
+            Self::Good => "!",
+            Self::Interesting => "!?",
+            Self::Dubious => "?!",
+            Self::Mistake => "?",
+            Self::Blunder => "??",
+        }
+    }
+
+    /// Return a description of what the symbol means.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Brilliant => "Brilliant move",
+            Self::Good => "Good move",
+            Self::Interesting => "Interesting move",
+            Self::Dubious => "Dubious move",
+            Self::Mistake => "Mistake",
+            Self::Blunder => "Blunder",
+        }
+    }
+}
+
+/// An annotation on a move, to explain it when teaching with an annotated game.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Annotation {
+    /// The symbol judging the move, if it has one.
+    pub symbol: Option<MoveSymbol>,
+
+    /// The comment on the move, which may be empty.
+    pub text: String,
+}
+
+impl Annotation {
+    /// Check if the annotation has no symbol and no comment.
+    pub fn is_empty(&self) -> bool {
+        self.symbol.is_none() && self.text.trim().is_empty()
+    }
+}
+
+/// How long one shape took to play its moves in a game. See [`GameRecord::timing_stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimingStats {
+    /// The number of timed moves.
+    pub moves: usize,
+
+    /// The average time taken per move.
+    pub average: Duration,
+
+    /// The longest time taken for a single move.
+    pub longest: Duration,
+}
+
+impl<M> GameRecord<M> {
+    /// Create a new record with no moves, where the given shape moves first.
+    pub fn new(first_shape: CellShape) -> Self {
+        Self {
+            first_shape,
+            moves: vec![],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Record a move which took the given time to play.
+    pub fn push_move(&mut self, mv: M, think_time: Duration) {
+        self.moves.push(mv);
+        self.move_times.push(think_time);
+    }
+
+    /// Return the number of moves at the start of the game which weren't timed.
+    pub fn untimed_moves(&self) -> usize {
+        self.moves.len().saturating_sub(self.move_times.len())
+    }
+
+    /// Keep only the first `len` moves, along with their times and annotations.
+    pub fn truncate(&mut self, len: usize) {
+        let untimed = self.untimed_moves();
+        self.moves.truncate(len);
+        self.move_times.truncate(len.saturating_sub(untimed));
+        self.skipped_turns.retain(|&skip| skip < len);
+        self.annotations.retain(|&index, _| index < len);
+    }
+
+    /// Set the annotation of the move with the given index, or remove it if it's empty.
+    pub fn set_annotation(&mut self, index: usize, annotation: Annotation) {
+        if annotation.is_empty() || index >= self.moves.len() {
+            self.annotations.remove(&index);
+        } else {
+            self.annotations.insert(index, annotation);
+        }
+    }
+
+    /// Show the controls to edit the annotation of the move with the given index in the given
+    /// [`Ui`](egui::Ui).
+    pub fn show_annotation_editor(&mut self, ui: &mut egui::Ui, index: usize) {
+        let mut annotation = self.annotations.get(&index).cloned().unwrap_or_default();
+
+        ui.horizontal_wrapped(|ui| {
+            for symbol in MoveSymbol::ALL {
+                let selected = annotation.symbol == Some(symbol);
+                if ui
+                    .selectable_label(selected, symbol.as_str())
+                    .on_hover_text(symbol.description())
+                    .clicked()
+                {
+                    annotation.symbol = (!selected).then_some(symbol);
+                }
+            }
+        });
+        ui.add(
+            egui::TextEdit::multiline(&mut annotation.text)
+                .hint_text("Comment on this move")
+                .desired_rows(2),
+        );
+
+        if self.annotations.get(&index) != Some(&annotation) {
+            self.set_annotation(index, annotation);
+        }
+    }
+
+    /// Record that the turn of the shape to move was skipped after the moves so far.
+    pub fn skip_turn(&mut self) {
+        self.skipped_turns.push(self.moves.len());
+    }
+}
+
+impl<M: Copy> GameRecord<M> {
+    /// Return each move along with the shape which played it, taking skipped turns into account.
+    pub fn moves_with_shapes(&self) -> Vec<(CellShape, M)> {
+        let mut shape = self.first_shape;
+
+        self.moves
+            .iter()
+            .enumerate()
+            .map(|(index, &mv)| {
+                if self.skipped_turns.contains(&index) {
+                    shape = shape.other();
+                }
+                let played = (shape, mv);
+                shape = shape.other();
+                played
+            })
+            .collect()
+    }
+
+    /// Show the numbered list of moves in the given [`Ui`](egui::Ui), using `format_move` to show
+    /// the moves, along with their annotations.
+    pub fn show_move_list(&self, ui: &mut egui::Ui, format_move: impl Fn(M) -> String) {
+        if self.moves.is_empty() {
+            ui.label("No moves yet");
+            return;
+        }
+
+        for (i, (shape, mv)) in self.moves_with_shapes().into_iter().enumerate() {
+            ui.label(self.move_text(i, shape, format_move(mv)));
+            self.show_annotation_comment(ui, i);
+        }
+    }
+
+    /// Show the numbered list of moves like [`show_move_list`](Self::show_move_list), but where
+    /// each move can be clicked, and the move that reaches the position after `selected` moves is
+    /// highlighted. Return the ply of the position after the move that was clicked, if one was.
+    ///
+    /// This is used to jump to positions with a [`History`](super::history::History).
+    pub fn show_clickable_move_list(
+        &self,
+        ui: &mut egui::Ui,
+        selected: Option<usize>,
+        format_move: impl Fn(M) -> String,
+    ) -> Option<usize> {
+        if self.moves.is_empty() {
+            ui.label("No moves yet");
+            return None;
+        }
+
+        let mut clicked = None;
+        for (i, (shape, mv)) in self.moves_with_shapes().into_iter().enumerate() {
+            if ui
+                .selectable_label(
+                    selected == Some(i + 1),
+                    self.move_text(i, shape, format_move(mv)),
+                )
+                .on_hover_text("Show the position after this move")
+                .clicked()
+            {
+                clicked = Some(i + 1);
+            }
+            self.show_annotation_comment(ui, i);
+        }
+        clicked
+    }
+
+    /// Return the numbered text of the move with the given index for the list of moves, with its
+    /// annotation symbol, like `"3. X (1, 1) !?"`.
+    fn move_text(&self, index: usize, shape: CellShape, formatted_move: String) -> String {
+        let symbol = self
+            .annotations
+            .get(&index)
+            .and_then(|annotation| annotation.symbol);
+        match symbol {
+            Some(symbol) => format!(
+                "{}. {shape:?} {formatted_move} {}",
+                index + 1,
+                symbol.as_str()
+            ),
+            None => format!("{}. {shape:?} {formatted_move}", index + 1),
+        }
+    }
+
+    /// Show the comment on the move with the given index under it in the list of moves, if it has
+    /// one.
+    fn show_annotation_comment(&self, ui: &mut egui::Ui, index: usize) {
+        if let Some(annotation) = self.annotations.get(&index) {
+            if !annotation.text.trim().is_empty() {
+                ui.label(egui::RichText::new(annotation.text.trim()).italics().weak());
+            }
+        }
+    }
+
+    /// Return how long the given shape took to play its timed moves, or [`None`] if it didn't play
+    /// any.
+    pub fn timing_stats(&self, shape: CellShape) -> Option<TimingStats> {
+        let times: Vec<Duration> = self
+            .moves_with_shapes()
+            .into_iter()
+            .skip(self.untimed_moves())
+            .zip(&self.move_times)
+            .filter(|&((played_by, _), _)| played_by == shape)
+            .map(|(_, &time)| time)
+            .collect();
+
+        let longest = times.iter().copied().max()?;
+        Some(TimingStats {
+            moves: times.len(),
+            average: times.iter().sum::<Duration>() / times.len() as u32,
+            longest,
+        })
+    }
+
+    /// Show the average and longest time that each shape took per move in the given
+    /// [`Ui`](egui::Ui), like `"X took 2.3s per move on average, and 5.1s at most"`.
+    pub fn show_timing_stats(&self, ui: &mut egui::Ui) {
+        for shape in CellShape::iter() {
+            if let Some(TimingStats {
+                average, longest, ..
+            }) = self.timing_stats(shape)
+            {
+                ui.label(format!(
+                    "{shape:?} took {:.1}s per move on average, and {:.1}s at most",
+                    average.as_secs_f32(),
+                    longest.as_secs_f32()
+                ));
+            }
+        }
+    }
+
+    /// Replay the moves from the given starting position, which should have
+    /// [`first_shape`](Self::first_shape) to move, and return the starting position followed by
+    /// the position after each move.
+    ///
+    /// Skipped turns are replayed too, but don't add a position of their own. If a move is
+    /// illegal, then the replay stops at the position before it.
+    pub fn replay<G: Game<Move = M>>(&self, start: G) -> Vec<G> {
+        let mut positions = vec![start];
+
+        for (index, &mv) in self.moves.iter().enumerate() {
+            let mut game = positions.last().unwrap().clone();
+            if self.skipped_turns.contains(&index) {
+                game.skip_turn();
+            }
+            if !game.play(mv) {
+                break;
+            }
+            positions.push(game);
+        }
+
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal::{board::Board, test_utils::make_board};
+
+    #[test]
+    fn replay_test() {
+        let record = GameRecord {
+            first_shape: CellShape::X,
+            moves: vec![(1, 1), (0, 0), (1, 1), (2, 2)],
+            skipped_turns: vec![],
+            move_times: vec![],
+            annotations: BTreeMap::new(),
+        };
+        let positions = record.replay(Board::default());
+
+        // The third move is illegal, so we stop before it
+        assert_eq!(
+            positions,
+            vec![
+                make_board!(_; _; _),
+                make_board!(_; _ X _; _),
+                make_board!(O _ _; _ X _; _),
+            ]
+        );
+
+        // X gets a free extra move after their first move
+        let mut record = GameRecord::new(CellShape::X);
+        record.moves.push((1, 1));
+        record.skip_turn();
+        record.moves.extend([(0, 0), (2, 2)]);
+        let positions = record.replay(Board::default());
+
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[3], {
+            let mut board = make_board!(X _ _; _ X _; _ _ O);
+            board.skip_turn();
+            board
+        });
+    }
+
+    #[test]
+    fn moves_with_shapes_test() {
+        let mut record = GameRecord::new(CellShape::O);
+        record.moves.push((1, 1));
+        record.skip_turn();
+        record.moves.extend([(0, 0), (2, 2)]);
+
+        assert_eq!(
+            record.moves_with_shapes(),
+            vec![
+                (CellShape::O, (1, 1)),
+                (CellShape::O, (0, 0)),
+                (CellShape::X, (2, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn annotations_test() {
+        let mut record = GameRecord::new(CellShape::X);
+        record.moves.extend([(1, 1), (0, 0), (2, 2)]);
+
+        record.set_annotation(
+            1,
+            Annotation {
+                symbol: Some(MoveSymbol::Mistake),
+                text: "O should take an edge".to_string(),
+            },
+        );
+        record.set_annotation(
+            2,
+            Annotation {
+                symbol: Some(MoveSymbol::Good),
+                text: String::new(),
+            },
+        );
+        assert_eq!(
+            record.move_text(1, CellShape::O, "(0, 0)".to_string()),
+            "2. O (0, 0) ?"
+        );
+        assert_eq!(
+            record.move_text(0, CellShape::X, "(1, 1)".to_string()),
+            "1. X (1, 1)"
+        );
+
+        // Empty annotations are removed, as are the annotations of moves that aren't played
+        record.set_annotation(2, Annotation::default());
+        record.set_annotation(
+            5,
+            Annotation {
+                symbol: Some(MoveSymbol::Blunder),
+                text: String::new(),
+            },
+        );
+        assert_eq!(record.annotations.keys().collect::<Vec<_>>(), vec![&1]);
+
+        // Annotations survive a round trip, and are kept for old records without them
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            serde_json::from_str::<GameRecord<(usize, usize)>>(&json).unwrap(),
+            record
+        );
+        let old: GameRecord<(usize, usize)> =
+            serde_json::from_str(r#"{"first_shape":"X","moves":[[1,1]]}"#).unwrap();
+        assert!(old.annotations.is_empty());
+
+        record.truncate(1);
+        assert!(record.annotations.is_empty());
+    }
+
+    #[test]
+    fn timing_stats_test() {
+        let secs = Duration::from_secs;
+
+        // The first move is untimed, like the opening of a chaos game
+        let mut record = GameRecord::new(CellShape::X);
+        record.moves.push((1, 1));
+        record.push_move((0, 0), secs(4));
+        record.push_move((2, 2), secs(1));
+        record.push_move((0, 2), secs(2));
+        record.push_move((2, 0), secs(3));
+
+        assert_eq!(record.untimed_moves(), 1);
+        assert_eq!(
+            record.timing_stats(CellShape::O),
+            Some(TimingStats {
+                moves: 2,
+                average: secs(3),
+                longest: secs(4),
+            })
+        );
+        assert_eq!(
+            record.timing_stats(CellShape::X),
+            Some(TimingStats {
+                moves: 2,
+                average: secs(2),
+                longest: secs(3),
+            })
+        );
+
+        record.truncate(2);
+        assert_eq!(record.moves, vec![(1, 1), (0, 0)]);
+        assert_eq!(record.move_times, vec![secs(4)]);
+        assert_eq!(record.timing_stats(CellShape::X), None);
+    }
+}
@@ -0,0 +1,38 @@
+//! This module contains the messages exchanged with the relay, which pairs two devices with a
+//! short game code and passes moves between them. See `server::relay` for the relay itself.
+//!
+//! Moves are passed as lists of numbers so that the relay doesn't need to know which variant is
+//! being played. A [`Coord`](crate::normal::Coord) is `[x, y]` and a
+//! [`GlobalCoord`](crate::ultimate::GlobalCoord) is `[x, y, lx, ly]`.
+
+use serde::{Deserialize, Serialize};
+
+/// The response to creating or joining a room.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomJoined {
+    /// The short code which the other player uses to join the room.
+    pub code: String,
+
+    /// Which player we are in the room. The player who created the room is `0` and moves first.
+    pub player: u8,
+}
+
+/// The body of a request to send a move to the other player.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendMove {
+    /// Which player is sending the move.
+    pub player: u8,
+
+    /// The move being sent.
+    pub coord: Vec<usize>,
+}
+
+/// The response to polling a room for moves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomMoves {
+    /// Whether the second player has joined the room.
+    pub joined: bool,
+
+    /// The moves since the index that was asked for.
+    pub moves: Vec<Vec<usize>>,
+}
@@ -0,0 +1,137 @@
+//! This module talks to the relay from the web app, so that two browsers can be paired with a
+//! short game code and exchange moves. See [`relay`](super::relay) for the messages.
+//!
+//! Every request runs in the background, and the result is sent back over a channel as a
+//! [`RelayMessage`], like moves from an [`AiTask`](super::ai_task::AiTask).
+
+use super::relay::{RoomJoined, RoomMoves, SendMove};
+use std::sync::mpsc::Sender;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Request, RequestInit, Response};
+
+/// A message from the relay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelayMessage {
+    /// We created or joined a room.
+    Joined(RoomJoined),
+
+    /// The moves in the room from index `since` onwards.
+    Moves {
+        /// The index that the moves start from.
+        since: usize,
+
+        /// The moves, and whether the other player has joined.
+        moves: RoomMoves,
+    },
+
+    /// A request failed, with the reason why.
+    Error(String),
+}
+
+/// A client for the relay at a given URL.
+#[derive(Clone, Debug)]
+pub struct RelayClient {
+    /// The base URL of the relay, like `https://example.com/tictactoe-server`.
+    base_url: String,
+
+    /// The sender to send results back to the app.
+    tx: Sender<RelayMessage>,
+}
+
+impl RelayClient {
+    /// Create a new client for the relay at the given URL, which will send results over `tx`.
+    pub fn new(base_url: impl Into<String>, tx: Sender<RelayMessage>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            tx,
+        }
+    }
+
+    /// Create a new room, and get a [`RelayMessage::Joined`] with its code.
+    pub fn create_room(&self) {
+        self.request("POST", "/rooms".to_string(), None, |body| {
+            serde_json::from_str(&body).map(|joined| Some(RelayMessage::Joined(joined)))
+        });
+    }
+
+    /// Join the room with the given code, and get a [`RelayMessage::Joined`].
+    pub fn join_room(&self, code: &str) {
+        self.request("POST", format!("/rooms/{code}/join"), None, |body| {
+            serde_json::from_str(&body).map(|joined| Some(RelayMessage::Joined(joined)))
+        });
+    }
+
+    /// Send a move to the other player in the given room.
+    pub fn send_move(&self, code: &str, send_move: &SendMove) {
+        let body = serde_json::to_string(send_move).expect("Moves should serialize to JSON");
+        self.request("POST", format!("/rooms/{code}/moves"), Some(body), |_| {
+            Ok(None)
+        });
+    }
+
+    /// Ask for the moves in the given room from index `since` onwards, and get a
+    /// [`RelayMessage::Moves`].
+    pub fn poll_moves(&self, code: &str, since: usize) {
+        self.request(
+            "GET",
+            format!("/rooms/{code}/moves/{since}"),
+            None,
+            move |body| {
+                serde_json::from_str(&body).map(|moves| Some(RelayMessage::Moves { since, moves }))
+            },
+        );
+    }
+
+    /// Make a request in the background and send the parsed response back to the app.
+    fn request<F>(&self, method: &'static str, path: String, body: Option<String>, parse: F)
+    where
+        F: FnOnce(String) -> serde_json::Result<Option<RelayMessage>> + 'static,
+    {
+        let url = format!("{}{path}", self.base_url);
+        let tx = self.tx.clone();
+
+        spawn_local(async move {
+            let message = match fetch(method, &url, body.as_deref()).await {
+                Ok((200..=299, text)) => parse(text).unwrap_or_else(|e| {
+                    Some(RelayMessage::Error(format!("bad response from relay: {e}")))
+                }),
+                Ok((status, text)) => Some(RelayMessage::Error(
+                    serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|value| value["error"].as_str().map(str::to_string))
+                        .unwrap_or_else(|| format!("relay returned status {status}")),
+                )),
+                Err(e) => Some(RelayMessage::Error(format!("couldn't reach relay: {e:?}"))),
+            };
+
+            if let Some(message) = message {
+                // The app may have stopped listening, which is fine
+                let _ = tx.send(message);
+            }
+        });
+    }
+}
+
+/// Fetch the given URL and return the status code and body of the response.
+async fn fetch(method: &str, url: &str, body: Option<&str>) -> Result<(u16, String), JsValue> {
+    let mut init = RequestInit::new();
+    init.method(method);
+    if let Some(body) = body {
+        init.body(Some(&JsValue::from_str(body)));
+    }
+
+    let request = Request::new_with_str_and_init(url, &init)?;
+    request.headers().set("Content-Type", "application/json")?;
+
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_request(&request))
+        .await?
+        .dyn_into()?;
+    let text = JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .unwrap_or_default();
+
+    Ok((response.status(), text))
+}
@@ -0,0 +1,411 @@
+//! This module provides [`SaveSlots`], which lets the player save games into named slots and load
+//! them again later, along with the window to manage them.
+//!
+//! Games can also be exported and imported as JSON files, which can be dropped onto the window to
+//! load them. See [`SaveSlots::receive_dropped_file`].
+
+use super::record::GameRecord;
+use eframe::{egui, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The name suggested when exporting a game to a file.
+#[cfg(not(target_arch = "wasm32"))]
+const GAME_FILE_NAME: &str = "tictactoe-game.json";
+
+/// A game saved in a slot, where `C` is the config of the variant and `M` is the type of a move.
+///
+/// Only the moves are saved, since the board can be rebuilt by replaying them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame<C, M> {
+    /// A snapshot of the config, with the settings that the game was started with.
+    pub config: C,
+
+    /// The record of the game.
+    pub record: GameRecord<M>,
+}
+
+impl<C: Serialize, M: Serialize> SavedGame<C, M> {
+    /// Serialize the game as pretty-printed JSON, which is the format of game files.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Games should serialize to JSON")
+    }
+}
+
+impl<C: DeserializeOwned, M: DeserializeOwned> SavedGame<C, M> {
+    /// Parse a game from JSON, like a game file exported by [`to_json`](Self::to_json). This fails
+    /// for games of a different variant, since their moves have a different type.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid game file: {e}"))
+    }
+}
+
+/// A game loaded from the [`SaveSlots`] window, along with where it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadedGame<C, M> {
+    /// The game was loaded from one of the slots, so it should carry on where it left off.
+    Slot(SavedGame<C, M>),
+
+    /// The game was imported from a file, so it should be opened for analysis if the variant can
+    /// do that.
+    File(SavedGame<C, M>),
+}
+
+/// The named save slots of one variant, meant to be saved and loaded between sessions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveSlots<C, M> {
+    /// The saved games, by the name of their slot.
+    slots: BTreeMap<String, SavedGame<C, M>>,
+
+    /// The name being typed for a new slot in the window.
+    #[serde(skip)]
+    new_name: String,
+
+    /// Whether the window is open.
+    #[serde(skip)]
+    pub open: bool,
+
+    /// The JSON in the text area, which is only used on the web since native uses files.
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    text: String,
+
+    /// The result of the last export or import, shown at the bottom of the window.
+    #[serde(skip)]
+    message: Option<String>,
+}
+
+impl<C, M> Default for SaveSlots<C, M> {
+    fn default() -> Self {
+        Self {
+            slots: BTreeMap::new(),
+            new_name: String::new(),
+            open: false,
+            #[cfg(target_arch = "wasm32")]
+            text: String::new(),
+            message: None,
+        }
+    }
+}
+
+impl<C, M> SaveSlots<C, M> {
+    /// Iterate over the names of all the slots in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.keys().map(String::as_str)
+    }
+
+    /// Return the game saved in the slot with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&SavedGame<C, M>> {
+        self.slots.get(name)
+    }
+
+    /// Save the given game into the slot with the given name, replacing any game already in it.
+    ///
+    /// Returns false and does nothing if the name is empty.
+    pub fn insert(&mut self, name: &str, game: SavedGame<C, M>) -> bool {
+        let name = name.trim();
+        if name.is_empty() {
+            return false;
+        }
+
+        self.slots.insert(name.to_string(), game);
+        true
+    }
+
+    /// Delete the slot with the given name, returning false if there is no such slot.
+    pub fn delete(&mut self, name: &str) -> bool {
+        self.slots.remove(name).is_some()
+    }
+}
+
+impl<C, M> SaveSlots<C, M>
+where
+    C: Clone + Serialize + DeserializeOwned,
+    M: Clone + Serialize + DeserializeOwned,
+{
+    /// Show the window to manage the slots if it's open.
+    ///
+    /// The current game is only computed if it's saved or exported. If a game is loaded, it's
+    /// returned so that the caller can replay it, and the window closes.
+    pub fn show_window(
+        &mut self,
+        ctx: &egui::Context,
+        current: impl Fn() -> SavedGame<C, M>,
+    ) -> Option<LoadedGame<C, M>> {
+        let mut loaded = None;
+        let mut open = self.open;
+
+        egui::Window::new("Saved games")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                loaded = self.show_contents(ui, &current);
+
+                ui.separator();
+                if let Some(game) = self.show_file_contents(ui, &current) {
+                    loaded = Some(LoadedGame::File(game));
+                }
+
+                if let Some(message) = &self.message {
+                    ui.small(message);
+                }
+            });
+
+        self.open = open && loaded.is_none();
+        loaded
+    }
+
+    /// Show the list of slots with buttons to load and delete them, and a text box to save the
+    /// current game into a slot.
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl Fn() -> SavedGame<C, M>,
+    ) -> Option<LoadedGame<C, M>> {
+        let mut loaded = None;
+        let mut deleted = None;
+
+        if self.slots.is_empty() {
+            ui.label("No saved games yet");
+        }
+
+        egui::Grid::new("save_slots_grid").show(ui, |ui| {
+            for (name, game) in &self.slots {
+                ui.label(name);
+                ui.label(format!("{} moves", game.record.moves.len()));
+                if ui.button("Load").clicked() {
+                    loaded = Some(LoadedGame::Slot(game.clone()));
+                }
+                if ui.button("Delete").clicked() {
+                    deleted = Some(name.clone());
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(name) = deleted {
+            self.delete(&name);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_name)
+                    .hint_text("Slot name")
+                    .desired_width(150.),
+            );
+
+            let name = self.new_name.trim().to_string();
+            let label = if self.slots.contains_key(&name) {
+                "Overwrite"
+            } else {
+                "Save"
+            };
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new(label))
+                .on_hover_text("Save the current game into this slot")
+                .clicked()
+            {
+                self.insert(&name, current());
+                self.new_name.clear();
+            }
+        });
+
+        loaded
+    }
+
+    /// Show buttons to export the current game to a JSON file and import a game from one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_file_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl Fn() -> SavedGame<C, M>,
+    ) -> Option<SavedGame<C, M>> {
+        ui.label("Share games as files, or drop a game file onto the window to analyse it.");
+
+        let mut imported = None;
+        ui.horizontal(|ui| {
+            if ui.button("Export\u{2026}").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(GAME_FILE_NAME)
+                    .save_file()
+                {
+                    self.message = Some(match std::fs::write(&path, current().to_json()) {
+                        Ok(()) => format!("Saved the game to {}", path.display()),
+                        Err(e) => format!("Failed to save the game: {e}"),
+                    });
+                }
+            }
+
+            if ui.button("Import\u{2026}").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    let result = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read the game: {e}"))
+                        .and_then(|json| SavedGame::from_json(&json));
+                    imported = self.take_result(result);
+                }
+            }
+        });
+
+        imported
+    }
+
+    /// Show a text area to copy the current game out of as JSON and paste a game into.
+    #[cfg(target_arch = "wasm32")]
+    fn show_file_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl Fn() -> SavedGame<C, M>,
+    ) -> Option<SavedGame<C, M>> {
+        ui.label("Share games as JSON, or drop a game file onto the page to analyse it.");
+
+        let mut imported = None;
+        ui.horizontal(|ui| {
+            if ui
+                .button("Export")
+                .on_hover_text("Copy the current game as JSON")
+                .clicked()
+            {
+                self.text = current().to_json();
+                ui.output().copied_text = self.text.clone();
+                self.message = Some("Copied the game to the clipboard".to_string());
+            }
+
+            if ui
+                .add_enabled(!self.text.trim().is_empty(), egui::Button::new("Import"))
+                .on_hover_text("Load the game pasted below")
+                .clicked()
+            {
+                let result = SavedGame::from_json(&self.text);
+                imported = self.take_result(result);
+                if imported.is_some() {
+                    self.text.clear();
+                }
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(200.)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.text)
+                        .code_editor()
+                        .hint_text("Paste a game here"),
+                );
+            });
+
+        imported
+    }
+
+    /// Return the imported game if it was parsed, or show the error in the window otherwise.
+    fn take_result(&mut self, result: Result<SavedGame<C, M>, String>) -> Option<SavedGame<C, M>> {
+        match result {
+            Ok(game) => {
+                self.message = None;
+                Some(game)
+            }
+            Err(e) => {
+                self.message = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Return the game in the first file dropped onto the window this frame, if any. If the file
+    /// isn't a game of this variant, then the window opens to show the error instead.
+    pub fn receive_dropped_file(&mut self, ctx: &egui::Context) -> Option<SavedGame<C, M>> {
+        let file = ctx.input().raw.dropped_files.first().cloned()?;
+
+        // Native gives us the path, and the web gives us the contents
+        let result = match (&file.path, &file.bytes) {
+            (Some(path), _) => {
+                std::fs::read_to_string(path).map_err(|e| format!("Failed to read the game: {e}"))
+            }
+            (None, Some(bytes)) => String::from_utf8(bytes.to_vec())
+                .map_err(|e| format!("Failed to read the game: {e}")),
+            (None, None) => return None,
+        }
+        .and_then(|json| SavedGame::from_json(&json));
+
+        let game = self.take_result(result);
+        if game.is_none() {
+            self.open = true;
+        }
+        game
+    }
+}
+
+impl<C: Serialize + DeserializeOwned, M: Serialize + DeserializeOwned> SaveSlots<C, M> {
+    /// Load the slots of the variant with the given name from the storage, using the key
+    /// `{name}_saved_games`.
+    pub fn load(storage: Option<&dyn Storage>, name: &str) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, &format!("{name}_saved_games")))
+            .unwrap_or_default()
+    }
+
+    /// Save the slots of the variant with the given name to the storage, using the same key as
+    /// [`load`](Self::load).
+    pub fn save(&self, storage: &mut dyn Storage, name: &str) {
+        eframe::set_value(storage, &format!("{name}_saved_games"), self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellShape;
+
+    #[test]
+    fn save_slots_test() {
+        let game = |moves: Vec<(usize, usize)>| SavedGame {
+            config: 1,
+            record: GameRecord {
+                moves,
+                ..GameRecord::new(CellShape::X)
+            },
+        };
+
+        let mut slots = SaveSlots::default();
+        assert!(!slots.insert(" ", game(vec![])));
+        assert!(slots.insert("Opening", game(vec![(1, 1)])));
+        assert!(slots.insert("Endgame", game(vec![(0, 0), (1, 1)])));
+        assert_eq!(
+            slots.names().collect::<Vec<_>>(),
+            vec!["Endgame", "Opening"]
+        );
+
+        // Saving into a slot with the same name replaces it
+        assert!(slots.insert("Opening ", game(vec![(2, 2)])));
+        assert_eq!(slots.get("Opening"), Some(&game(vec![(2, 2)])));
+
+        assert!(slots.delete("Endgame"));
+        assert!(!slots.delete("Endgame"));
+        assert_eq!(slots.names().collect::<Vec<_>>(), vec!["Opening"]);
+    }
+
+    #[test]
+    fn game_file_test() {
+        let mut record = GameRecord::new(CellShape::O);
+        record.push_move((1, 1), web_time::Duration::from_secs(2));
+        let game = SavedGame { config: 7, record };
+
+        assert_eq!(SavedGame::from_json(&game.to_json()), Ok(game));
+
+        // A game of another variant has a different type of move
+        let ultimate_game = SavedGame {
+            config: 7,
+            record: GameRecord {
+                moves: vec![(1, 1, (0, 0))],
+                ..GameRecord::new(CellShape::X)
+            },
+        };
+        assert!(SavedGame::<i32, (usize, usize)>::from_json(&ultimate_game.to_json()).is_err());
+        assert!(SavedGame::<i32, (usize, usize)>::from_json("not json").is_err());
+    }
+}
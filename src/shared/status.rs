@@ -0,0 +1,163 @@
+//! This module describes the state of a game in words, which is shown in the status bar of both
+//! apps. See [`status_text`].
+
+use super::board::GameResult;
+use crate::CellShape;
+
+/// Return the name of the given position on a 3x3 board, like `"top-right"` or `"middle"`.
+pub fn position_name((x, y): (usize, usize)) -> &'static str {
+    match (x, y) {
+        (0, 0) => "top-left",
+        (1, 0) => "top",
+        (2, 0) => "top-right",
+        (0, 1) => "left",
+        (1, 1) => "middle",
+        (2, 1) => "right",
+        (0, 2) => "bottom-left",
+        (1, 2) => "bottom",
+        (2, 2) => "bottom-right",
+        _ => "unknown",
+    }
+}
+
+/// Return the name of the cell in the given column and row of a grid, with a letter for the column
+/// and a number for the row like on a chess board, so `"a1"` is the top-left cell. This matches the
+/// coordinate labels around the boards and the cells in chat. See
+/// [`draw_coordinate_labels`](super::gui::draw_coordinate_labels).
+pub fn cell_name((column, row): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + column as u8) as char, row + 1)
+}
+
+/// Describe the state of a game in words, like `"O to play in the top-right board - you are X,
+/// the AI is O"`.
+///
+/// `to_move` is the shape which will play next, `ai_shape` is the shape of the AI if we're
+/// playing against it, and `location` describes where `to_move` has to play, if there's a
+/// restriction.
+pub fn status_text(
+    result: GameResult,
+    to_move: CellShape,
+    ai_shape: Option<CellShape>,
+    location: Option<&str>,
+) -> String {
+    let mut text = match result {
+        GameResult::Ongoing => match location {
+            Some(location) => format!("{to_move:?} to play {location}"),
+            None => format!("{to_move:?} to play"),
+        },
+        GameResult::Draw => "It's a draw".to_string(),
+        GameResult::Won { shape, .. } => format!("{shape:?} wins"),
+    };
+
+    if let Some(ai_shape) = ai_shape {
+        text.push_str(&format!(
+            " - you are {:?}, the AI is {ai_shape:?}",
+            ai_shape.other()
+        ));
+    }
+
+    text
+}
+
+/// Describe the state of a game from the point of view of the player in a few words, like
+/// `"your move"` or `"the AI wins"`, for the window title.
+///
+/// The arguments are the same as for [`status_text`]. Without an AI, the shapes are named instead.
+pub fn short_status_text(
+    result: GameResult,
+    to_move: CellShape,
+    ai_shape: Option<CellShape>,
+) -> String {
+    match (result, ai_shape) {
+        (GameResult::Ongoing, Some(ai_shape)) if to_move == ai_shape => "AI's move".to_string(),
+        (GameResult::Ongoing, Some(_)) => "your move".to_string(),
+        (GameResult::Ongoing, None) => format!("{to_move:?} to play"),
+        (GameResult::Draw, _) => "draw".to_string(),
+        (GameResult::Won { shape, .. }, Some(ai_shape)) if shape == ai_shape => {
+            "the AI wins".to_string()
+        }
+        (GameResult::Won { .. }, Some(_)) => "you win".to_string(),
+        (GameResult::Won { shape, .. }, None) => format!("{shape:?} wins"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_name_test() {
+        assert_eq!(cell_name((0, 0)), "a1");
+        assert_eq!(cell_name((1, 1)), "b2");
+        assert_eq!(cell_name((2, 0)), "c1");
+        assert_eq!(cell_name((4, 4)), "e5");
+        assert_eq!(cell_name((8, 2)), "i3");
+    }
+
+    #[test]
+    fn status_text_test() {
+        assert_eq!(
+            status_text(GameResult::Ongoing, CellShape::X, None, None),
+            "X to play"
+        );
+        assert_eq!(
+            status_text(
+                GameResult::Ongoing,
+                CellShape::O,
+                Some(CellShape::O),
+                Some(&format!("in the {} board", position_name((2, 0))))
+            ),
+            "O to play in the top-right board - you are X, the AI is O"
+        );
+        assert_eq!(
+            status_text(GameResult::Draw, CellShape::X, Some(CellShape::X), None),
+            "It's a draw - you are O, the AI is X"
+        );
+        assert_eq!(
+            status_text(
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0), (1, 1), (2, 2)],
+                },
+                CellShape::O,
+                None,
+                None
+            ),
+            "X wins"
+        );
+    }
+
+    #[test]
+    fn short_status_text_test() {
+        let x_wins = GameResult::Won {
+            shape: CellShape::X,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        assert_eq!(
+            short_status_text(GameResult::Ongoing, CellShape::X, Some(CellShape::O)),
+            "your move"
+        );
+        assert_eq!(
+            short_status_text(GameResult::Ongoing, CellShape::O, Some(CellShape::O)),
+            "AI's move"
+        );
+        assert_eq!(
+            short_status_text(GameResult::Ongoing, CellShape::O, None),
+            "O to play"
+        );
+        assert_eq!(
+            short_status_text(x_wins, CellShape::O, Some(CellShape::O)),
+            "you win"
+        );
+        assert_eq!(
+            short_status_text(x_wins, CellShape::O, Some(CellShape::X)),
+            "the AI wins"
+        );
+        assert_eq!(short_status_text(x_wins, CellShape::O, None), "X wins");
+        assert_eq!(
+            short_status_text(GameResult::Draw, CellShape::O, None),
+            "draw"
+        );
+    }
+}
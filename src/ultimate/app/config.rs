@@ -1,9 +1,20 @@
 //! This module handles app configuration.
 
 use super::UltimateTTTApp;
-use crate::CellShape;
-use eframe::egui::{self, Context};
+use crate::{
+    shared::{
+        adaptive::AdaptiveDifficulty, ai_task::AiTask, clock::TimeControl, ladder::Ladder,
+        personality::AiPersonality,
+    },
+    ultimate::board::{
+        calibration::{calibrate, Calibration, CALIBRATION_SETTINGS},
+        DrawRule, OpeningRule, RuleSet, WinCondition, DRAW_REWARD,
+    },
+    CellShape,
+};
+use eframe::egui::{self, Context, Ui};
 use serde::{Deserialize, Serialize};
+use web_time::Duration;
 
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
@@ -19,6 +30,29 @@ cfg_if::cfg_if! {
     }
 }
 
+/// The kinds of AI that the player can play against. See [`AiPlayer`](crate::shared::ai::AiPlayer).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UltimateAiPlayer {
+    /// An easy player which plays randomly. See [`RandomBot`](crate::shared::ai::RandomBot).
+    Random,
+
+    /// A medium player which takes wins and blocks losses. See
+    /// [`GreedyBot`](crate::shared::ai::GreedyBot).
+    Greedy,
+
+    /// A player using MCTS. See [`MctsPlayer`](crate::ultimate::board::MctsPlayer).
+    #[default]
+    Mcts,
+
+    /// A player using MCTS with fewer iterations when the player has been losing, up to
+    /// [`UltimateConfig::max_mcts_expansions`]. See [`AdaptiveDifficulty`].
+    Adaptive,
+
+    /// The player's current level on the ladder, where the AI gets stronger each time the player
+    /// beats it, up to [`UltimateConfig::max_mcts_expansions`]. See [`Ladder`].
+    Ladder,
+}
+
 /// A struct representing the app configuration, meant to be saved and loaded between sessions.
 #[derive(Clone, Copy, Serialize, Deserialize)]
 #[serde(default)]
@@ -32,11 +66,99 @@ pub struct UltimateConfig {
     /// Whether the player is playing against an AI.
     pub playing_ai: bool,
 
-    /// The maximum number of expansions in the AI's MCTS algorithm.
+    /// Whether games start from a random balanced mid-game position instead of an empty board.
+    /// See [`random_start`](crate::shared::chaos::random_start).
+    pub chaos_start: bool,
+
+    /// The time limits of each game. See [`GameClock`](crate::shared::clock::GameClock).
+    pub time_control: TimeControl,
+
+    /// Which AI the player is playing against.
+    pub ai_player: UltimateAiPlayer,
+
+    /// Whether to show the evaluation bar next to the board.
+    pub show_evaluation_bar: bool,
+
+    /// Whether to show a small map of the winners of the local boards. See
+    /// [`draw_mini_map`](UltimateTTTApp::draw_mini_map).
+    pub show_mini_map: bool,
+
+    /// Whether to shade each legal move by how likely the current player is to win after it.
+    pub show_heatmap: bool,
+
+    /// Whether to show coordinate labels around the board, which match the names of the moves in
+    /// the list of moves. See [`cell_name`](crate::shared::status::cell_name).
+    pub show_coordinates: bool,
+
+    /// Whether to shade the moves that the AI is considering while it's thinking, by how much it
+    /// has searched each of them so far.
+    pub show_ai_thinking: bool,
+
+    /// Whether to explain each AI move with the best moves that it considered. See
+    /// [`MoveExplanation`](crate::shared::explain::MoveExplanation).
+    pub explain_ai_moves: bool,
+
+    /// Whether the player gets a free extra move after their first move, as a handicap for the
+    /// AI.
+    pub player_extra_move: bool,
+
+    /// The rules for which local boards can be played in and sent to.
+    pub rules: RuleSet,
+
+    /// The rule for where the first move of the game can be played.
+    pub opening: OpeningRule,
+
+    /// What happens when neither shape can win a line of local boards any more.
+    pub draw_rule: DrawRule,
+
+    /// The maximum number of iterations of the AI's MCTS algorithm, each of which expands one
+    /// node of the game tree.
     pub max_mcts_expansions: u16,
 
     /// The number of playouts to do in each iteration of MCTS.
     pub mcts_playouts: u8,
+
+    /// The longest that the AI should take to make a move, in milliseconds, when calibrating the
+    /// AI settings. See [`calibrate`].
+    pub calibration_budget_ms: u32,
+
+    /// The state of the [`Adaptive`](UltimateAiPlayer::Adaptive) AI, which is updated at the end
+    /// of every game against it.
+    pub adaptive: AdaptiveDifficulty,
+
+    /// The player's position on the [`Ladder`](UltimateAiPlayer::Ladder), which is updated at the
+    /// end of every game against it without a handicap.
+    pub ladder: Ladder,
+
+    /// The style of play of the MCTS AIs, which chooses between the moves that were searched
+    /// about as much as the best one.
+    pub personality: AiPersonality,
+
+    /// The reward of a drawn playout for the MCTS AIs, which is [`DRAW_REWARD`] by default. A
+    /// lower reward makes the AI press for a win against weaker opponents. See
+    /// [`MctsPlayer::draw_reward`](crate::ultimate::board::MctsPlayer::draw_reward).
+    pub draw_reward: f64,
+
+    /// Whether the MCTS AIs remember the results of the openings that they choose against the
+    /// player, and avoid the ones that they've lost with. See
+    /// [`LearningTable`](crate::shared::learning::LearningTable).
+    pub learn_from_games: bool,
+
+    /// Whether to ask for confirmation before discarding a game in progress, like when
+    /// restarting. See [`show_discard_dialog`](crate::shared::gui::show_discard_dialog).
+    pub confirm_discard: bool,
+}
+
+/// The state of calibrating the AI settings in the settings window.
+pub enum CalibrationState {
+    /// We're not calibrating, and haven't calibrated yet.
+    Idle,
+
+    /// We're currently calibrating in the background.
+    Running(AiTask),
+
+    /// We've finished calibrating, and have a recommendation if any settings were fast enough.
+    Done(Option<Calibration>),
 }
 
 impl Default for UltimateConfig {
@@ -45,19 +167,123 @@ impl Default for UltimateConfig {
             player_plays_first: true,
             player_shape: CellShape::X,
             playing_ai: false,
+            chaos_start: false,
+            time_control: TimeControl::default(),
+            ai_player: UltimateAiPlayer::default(),
+            show_evaluation_bar: false,
+            show_mini_map: false,
+            show_heatmap: false,
+            show_coordinates: false,
+            show_ai_thinking: false,
+            explain_ai_moves: false,
+            player_extra_move: false,
+            rules: RuleSet::default(),
+            opening: OpeningRule::default(),
+            draw_rule: DrawRule::default(),
             max_mcts_expansions: DEFAULT_MAX_EXPANSIONS,
             mcts_playouts: DEFAULT_PLAYOUTS,
+            calibration_budget_ms: 1000,
+            adaptive: AdaptiveDifficulty::default(),
+            ladder: Ladder::default(),
+            personality: AiPersonality::default(),
+            draw_reward: DRAW_REWARD,
+            learn_from_games: false,
+            confirm_discard: true,
         }
     }
 }
 
-impl UltimateTTTApp {
+/// The settings in [`UltimateConfig`] that define a game, which only take effect when a new game
+/// starts. All the other settings take effect immediately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UltimateGameSettings {
+    /// Whether the player should make the first move.
+    pub player_plays_first: bool,
+
+    /// Which shape the player uses.
+    pub player_shape: CellShape,
+
+    /// Whether the player is playing against an AI.
+    pub playing_ai: bool,
+
+    /// Whether games start from a random balanced mid-game position instead of an empty board.
+    /// See [`random_start`](crate::shared::chaos::random_start).
+    pub chaos_start: bool,
+
+    /// The time limits of each game. See [`GameClock`](crate::shared::clock::GameClock).
+    pub time_control: TimeControl,
+
+    /// Whether the player gets a free extra move after their first move, as a handicap for the
+    /// AI.
+    pub player_extra_move: bool,
+
+    /// The rules for which local boards can be played in and sent to.
+    pub rules: RuleSet,
+
+    /// The rule for where the first move of the game can be played.
+    pub opening: OpeningRule,
+
+    /// What happens when neither shape can win a line of local boards any more.
+    pub draw_rule: DrawRule,
+}
+
+impl UltimateConfig {
+    /// Get the settings that define a game, which only take effect when a new game starts.
+    pub fn game_settings(&self) -> UltimateGameSettings {
+        UltimateGameSettings {
+            player_plays_first: self.player_plays_first,
+            player_shape: self.player_shape,
+            playing_ai: self.playing_ai,
+            chaos_start: self.chaos_start,
+            time_control: self.time_control,
+            player_extra_move: self.player_extra_move,
+            rules: self.rules,
+            opening: self.opening,
+            draw_rule: self.draw_rule,
+        }
+    }
+
+    /// Return a copy of this config with the given game settings, like to save the settings that
+    /// the current game was started with. See [`game_settings`](Self::game_settings).
+    pub fn with_game_settings(self, game_settings: UltimateGameSettings) -> Self {
+        let UltimateGameSettings {
+            player_plays_first,
+            player_shape,
+            playing_ai,
+            chaos_start,
+            time_control,
+            player_extra_move,
+            rules,
+            opening,
+            draw_rule,
+        } = game_settings;
+
+        Self {
+            player_plays_first,
+            player_shape,
+            playing_ai,
+            chaos_start,
+            time_control,
+            player_extra_move,
+            rules,
+            opening,
+            draw_rule,
+            ..self
+        }
+    }
+}
+
+impl<const N: usize> UltimateTTTApp<N> {
     /// Draw the settings window as a non-collapsible, non-resizable, closable `egui` window.
+    ///
+    /// This is only used in the narrow layout, since the wide layout shows the settings in the
+    /// side panel instead. See [`show_settings`](Self::show_settings).
     pub fn draw_settings_window(&mut self, ctx: &Context) {
+        let mut open = self.showing_settings_window;
         egui::Window::new("Settings")
             .collapsible(false)
             .resizable(false)
-            .open(&mut self.showing_settings_window)
+            .open(&mut open)
             .show(ctx, |ui| {
                 let mut style = (*ctx.style()).clone();
                 for id in style.text_styles.values_mut() {
@@ -65,53 +291,278 @@ impl UltimateTTTApp {
                 }
                 ui.set_style(style);
 
-                ui.checkbox(&mut self.config.playing_ai, "Play against AI");
-                ui.add_enabled(
-                    self.config.playing_ai,
-                    egui::Checkbox::new(&mut self.config.player_plays_first, "Player plays first"),
+                self.show_settings(ui);
+            });
+        self.showing_settings_window = open;
+    }
+
+    /// Show the settings in the given [`Ui`].
+    pub fn show_settings(&mut self, ui: &mut Ui) {
+        if let Ok(calibration) = self.calibration_rx.try_recv() {
+            self.calibration = CalibrationState::Done(calibration);
+        }
+
+        self.profiles.show_selector(ui, &mut self.config);
+        ui.separator();
+
+        ui.checkbox(&mut self.config.playing_ai, "Play against AI");
+        ui.add_enabled(
+            self.config.playing_ai,
+            egui::Checkbox::new(&mut self.config.player_plays_first, "Player plays first"),
+        );
+        ui.horizontal(|ui| {
+            ui.label(if self.config.playing_ai {
+                "Player shape"
+            } else {
+                "First player shape"
+            });
+            ui.radio_value(&mut self.config.player_shape, CellShape::X, "X");
+            ui.radio_value(&mut self.config.player_shape, CellShape::O, "O");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Rules");
+            ui.radio_value(&mut self.config.rules, RuleSet::Standard, "Standard")
+                .on_hover_text("Won boards can still be played in until they're full");
+            ui.radio_value(
+                &mut self.config.rules,
+                RuleSet::ClosedBoards,
+                "Closed boards",
+            )
+            .on_hover_text("Won and drawn boards can't be played in");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Opening");
+            ui.radio_value(&mut self.config.opening, OpeningRule::Free, "Free")
+                .on_hover_text("The first move can be played anywhere");
+            ui.radio_value(&mut self.config.opening, OpeningRule::CentreBoard, "Centre")
+                .on_hover_text("The first move must be played in the centre board");
+            ui.radio_value(
+                &mut self.config.opening,
+                OpeningRule::SecondPlayerChooses,
+                "Chosen",
+            )
+            .on_hover_text("The second player chooses the board for the first move");
+        });
+        // A single local board wins nine-board tic-tac-toe, so it can't be blocked
+        if self.win_condition == WinCondition::LineOfBoards {
+            ui.horizontal(|ui| {
+                ui.label("Blocked game");
+                ui.radio_value(&mut self.config.draw_rule, DrawRule::Draw, "Draw")
+                    .on_hover_text("The game is a draw when neither side can win a line");
+                ui.radio_value(
+                    &mut self.config.draw_rule,
+                    DrawRule::MostBoards,
+                    "Most boards",
+                )
+                .on_hover_text(
+                    "When neither side can win a line, whoever wins more local boards wins",
                 );
-                ui.horizontal(|ui| {
-                    ui.label(if self.config.playing_ai {
-                        "Player shape"
-                    } else {
-                        "First player shape"
-                    });
-                    ui.radio_value(&mut self.config.player_shape, CellShape::X, "X");
-                    ui.radio_value(&mut self.config.player_shape, CellShape::O, "O");
-                });
+            });
+        }
+        ui.checkbox(&mut self.config.chaos_start, "Chaos start")
+            .on_hover_text(
+                "Start from a random mid-game position where neither side is clearly winning, \
+                ignoring the opening rule",
+            );
+        ui.horizontal(|ui| {
+            ui.label("Time control");
+            ui.radio_value(
+                &mut self.config.time_control,
+                TimeControl::Untimed,
+                "Untimed",
+            )
+            .on_hover_text("Take as long as you like over each move");
+            ui.radio_value(&mut self.config.time_control, TimeControl::Blitz, "Blitz")
+                .on_hover_text(
+                    "One minute each, plus a second after every move. Whoever runs out of time \
+                    first loses, including the AI",
+                );
+        });
+        ui.checkbox(&mut self.config.show_evaluation_bar, "Show evaluation bar");
+        ui.checkbox(
+            &mut self.config.show_mini_map,
+            "Show local board winners map",
+        );
+        ui.checkbox(&mut self.config.show_heatmap, "Show win chance heatmap");
+        ui.checkbox(&mut self.config.show_coordinates, "Show coordinates");
+        ui.add_enabled(
+            self.config.playing_ai,
+            egui::Checkbox::new(
+                &mut self.config.show_ai_thinking,
+                "Show what the AI is considering",
+            ),
+        );
+        ui.add_enabled(
+            self.config.playing_ai,
+            egui::Checkbox::new(&mut self.config.explain_ai_moves, "Explain AI moves"),
+        );
+        ui.checkbox(
+            &mut self.config.confirm_discard,
+            "Ask before discarding a game in progress",
+        );
 
-                if self.config.playing_ai {
-                    ui.separator();
+        if self.config.playing_ai {
+            ui.separator();
 
-                    ui.collapsing("AI Config", |ui| {
-                        ui.add(
-                            egui::Slider::new(
-                                &mut self.config.max_mcts_expansions,
-                                1..=SLIDER_MAX_EXPANSIONS,
-                            )
-                            .clamp_to_range(true)
-                            .text("Max expansions in MCTS"),
-                        );
-                        ui.add(
-                            egui::Slider::new(
-                                &mut self.config.mcts_playouts,
-                                1..=SLIDER_MAX_PLAYOUTS,
-                            )
+            ui.collapsing("AI Config", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("AI difficulty");
+                    ui.radio_value(&mut self.config.ai_player, UltimateAiPlayer::Random, "Easy")
+                        .on_hover_text("Plays randomly");
+                    ui.radio_value(
+                        &mut self.config.ai_player,
+                        UltimateAiPlayer::Greedy,
+                        "Medium",
+                    )
+                    .on_hover_text("Takes wins and blocks losses, but otherwise plays randomly");
+                    ui.radio_value(&mut self.config.ai_player, UltimateAiPlayer::Mcts, "Hard")
+                        .on_hover_text("Uses MCTS with the settings below");
+                    ui.radio_value(
+                        &mut self.config.ai_player,
+                        UltimateAiPlayer::Adaptive,
+                        "Adaptive",
+                    )
+                    .on_hover_text(
+                        "Uses MCTS with more iterations when you win and fewer when you \
+                        lose, up to the maximum below",
+                    );
+                    ui.radio_value(
+                        &mut self.config.ai_player,
+                        UltimateAiPlayer::Ladder,
+                        "Ladder",
+                    )
+                    .on_hover_text(
+                        "Climb a level each time you win, and drop one when you lose. The AI \
+                        uses more iterations at each level, up to the maximum below",
+                    );
+                });
+                match self.config.ai_player {
+                    UltimateAiPlayer::Adaptive => self.config.adaptive.show_settings(ui),
+                    UltimateAiPlayer::Ladder => self.config.ladder.show_settings(ui),
+                    _ => (),
+                }
+                if !matches!(
+                    self.config.ai_player,
+                    UltimateAiPlayer::Random | UltimateAiPlayer::Greedy
+                ) {
+                    self.config.personality.show_settings(ui);
+                    ui.add(
+                        egui::Slider::new(&mut self.config.draw_reward, 0.0..=DRAW_REWARD)
                             .clamp_to_range(true)
-                            .text("Number of playouts in each MCTS expansion"),
+                            .text("Draw score (contempt)"),
+                    )
+                    .on_hover_text(
+                        "Lower scores make the AI treat draws as worse, \
+                        so it presses for a win instead",
+                    );
+                    ui.checkbox(&mut self.config.learn_from_games, "Learn from games")
+                        .on_hover_text(
+                            "Remember how games went after the openings that the AI chooses, \
+                            so that it avoids the ones it has lost with",
                         );
+                    if self.config.learn_from_games {
+                        self.learning.show_settings(ui);
+                    }
+                }
+                ui.checkbox(
+                    &mut self.config.player_extra_move,
+                    "Handicap: player gets a free extra move",
+                )
+                .on_hover_text("The AI skips its turn after the player's first move");
+                ui.separator();
 
-                        if ui.button("Reset to defaults").clicked() {
-                            self.config = UltimateConfig {
-                                max_mcts_expansions: DEFAULT_MAX_EXPANSIONS,
-                                mcts_playouts: DEFAULT_PLAYOUTS,
-                                ..self.config
-                            };
-                        }
-                    });
+                ui.add(
+                    egui::Slider::new(
+                        &mut self.config.max_mcts_expansions,
+                        1..=SLIDER_MAX_EXPANSIONS,
+                    )
+                    .clamp_to_range(true)
+                    .text("Max iterations in MCTS"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.config.mcts_playouts, 1..=SLIDER_MAX_PLAYOUTS)
+                        .clamp_to_range(true)
+                        .text("Number of playouts in each MCTS iteration"),
+                );
+
+                if ui.button("Reset to defaults").clicked() {
+                    self.config = UltimateConfig {
+                        max_mcts_expansions: DEFAULT_MAX_EXPANSIONS,
+                        mcts_playouts: DEFAULT_PLAYOUTS,
+                        ..self.config
+                    };
                 }
 
-                ui.small("Changes will require a game restart.");
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut self.config.calibration_budget_ms, 100..=5000)
+                        .clamp_to_range(true)
+                        .suffix(" ms")
+                        .text("Think time budget"),
+                );
+
+                ui.horizontal(|ui| {
+                    let running = matches!(self.calibration, CalibrationState::Running(_));
+
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Calibrate"))
+                        .on_hover_text(
+                            "Play some quick games against itself to find the strongest \
+                            AI settings that can move within the think time budget",
+                        )
+                        .clicked()
+                    {
+                        let budget =
+                            Duration::from_millis(self.config.calibration_budget_ms as u64);
+                        self.calibration = CalibrationState::Running(AiTask::spawn(
+                            Duration::ZERO,
+                            self.calibration_tx.clone(),
+                            move |_| calibrate(budget, &CALIBRATION_SETTINGS),
+                        ));
+                    }
+
+                    match self.calibration {
+                        CalibrationState::Idle => (),
+                        CalibrationState::Running(_) => {
+                            ui.label("Calibrating\u{2026}");
+                        }
+                        CalibrationState::Done(None) => {
+                            ui.label("Your machine is too slow for any AI settings");
+                        }
+                        CalibrationState::Done(Some(Calibration {
+                            max_mcts_expansions,
+                            mcts_playouts,
+                            think_time,
+                        })) => {
+                            ui.label(format!(
+                                "Recommended: {max_mcts_expansions} iterations, \
+                                {mcts_playouts} playouts ({}ms)",
+                                think_time.as_millis()
+                            ));
+
+                            if ui.button("Apply").clicked() {
+                                self.config = UltimateConfig {
+                                    max_mcts_expansions: max_mcts_expansions
+                                        .min(SLIDER_MAX_EXPANSIONS),
+                                    mcts_playouts: mcts_playouts.min(SLIDER_MAX_PLAYOUTS),
+                                    ..self.config
+                                };
+                            }
+                        }
+                    }
+                });
             });
+        }
+
+        if self.config.game_settings() != self.game_settings {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Some changes will apply to the next game.");
+                if ui.button("Restart now").clicked() {
+                    self.request_restart();
+                }
+            });
+        }
     }
 }
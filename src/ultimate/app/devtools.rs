@@ -0,0 +1,169 @@
+//! This module provides [`TreeDebugger`], a window to inspect the MCTS game tree of the current
+//! position, which is only available with the `devtools` feature.
+
+use super::config::UltimateConfig;
+use crate::{
+    shared::ai_task::AiTask,
+    ultimate::board::{GlobalBoard, SearchTree, TreeNode},
+    CellShape,
+};
+use eframe::egui::{self, Context, Ui};
+use std::sync::mpsc;
+use web_time::Duration;
+
+/// A debug window which searches the current position with MCTS and shows the whole game tree,
+/// with the visits, wins, and UCT of every node.
+pub struct TreeDebugger<const N: usize> {
+    /// Whether the window is open.
+    open: bool,
+
+    /// The most recent search, along with the position that was searched.
+    tree: Option<(GlobalBoard<N>, SearchTree)>,
+
+    /// The handle to the search currently being computed, if there is one.
+    task: Option<AiTask>,
+
+    /// The sender that we pass to the background thread to get the tree back.
+    tx: mpsc::Sender<Option<(GlobalBoard<N>, SearchTree)>>,
+
+    /// The receiver that receives the tree of the search.
+    rx: mpsc::Receiver<Option<(GlobalBoard<N>, SearchTree)>>,
+}
+
+impl<const N: usize> Default for TreeDebugger<N> {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            open: false,
+            tree: None,
+            task: None,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl<const N: usize> TreeDebugger<N> {
+    /// Show a button to open and close the window.
+    pub fn show_button(&mut self, ui: &mut Ui) {
+        if ui
+            .add(egui::Button::new("\u{1F333}").fill(egui::Color32::TRANSPARENT))
+            .on_hover_text("Inspect the MCTS tree")
+            .clicked()
+        {
+            self.open = !self.open;
+        }
+    }
+
+    /// Search the given position in the background with the MCTS settings of the config.
+    fn start_search(
+        &mut self,
+        board: &GlobalBoard<N>,
+        shape_to_play: CellShape,
+        config: &UltimateConfig,
+    ) {
+        let board = board.clone();
+        let max_iters = config.max_mcts_expansions;
+        let playouts = config.mcts_playouts;
+
+        self.task = Some(AiTask::spawn(Duration::ZERO, self.tx.clone(), move |_| {
+            let tree = board.search_tree(shape_to_play, max_iters, playouts)?;
+            Some((board, tree))
+        }));
+    }
+
+    /// Show the window if it's open, with a button to search the given position.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        board: &GlobalBoard<N>,
+        shape_to_play: CellShape,
+        config: &UltimateConfig,
+    ) {
+        if let Ok(tree) = self.rx.try_recv() {
+            self.tree = tree;
+            self.task = None;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("MCTS tree")
+            .open(&mut open)
+            .default_width(400.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let searching = self.task.is_some();
+                    if ui
+                        .add_enabled(!searching, egui::Button::new("Search current position"))
+                        .clicked()
+                    {
+                        self.start_search(board, shape_to_play, config);
+                    }
+                    if searching {
+                        ui.spinner();
+                    }
+                });
+
+                let Some((searched_board, tree)) = &self.tree else {
+                    ui.label("No search yet");
+                    return;
+                };
+
+                if searched_board != board {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "This tree is for an earlier position",
+                    );
+                }
+                ui.label(format!(
+                    "{} nodes, {} playouts, wins are for {:?}",
+                    tree.root.size(),
+                    tree.root.playouts,
+                    tree.ai_shape
+                ));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, child) in tree.root.children.iter().enumerate() {
+                        show_node(ui, child, tree.ai_shape, i);
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// Return a one-line description of the node, with its move and statistics.
+fn describe_node(node: &TreeNode, ai_shape: CellShape) -> String {
+    let mv = node.mv.map_or_else(
+        || "root".to_string(),
+        |(x, y, (lx, ly))| format!("({x}, {y}, ({lx}, {ly}))"),
+    );
+    let mover = node.shape_to_play.other();
+    let uct = node
+        .uct
+        .map_or_else(|| "-".to_string(), |uct| format!("{uct:.3}"));
+
+    format!(
+        "{mover:?} {mv}: {} visits, {:.1} wins for {ai_shape:?}, {:.1}% won by {mover:?}, UCT {uct}",
+        node.playouts,
+        node.wins,
+        100. * node.mover_win_rate(ai_shape),
+    )
+}
+
+/// Show the node as a collapsing header containing its children, or as a label if it has none.
+fn show_node(ui: &mut Ui, node: &TreeNode, ai_shape: CellShape, index: usize) {
+    let text = describe_node(node, ai_shape);
+
+    if node.children.is_empty() {
+        ui.label(text);
+    } else {
+        egui::CollapsingHeader::new(text)
+            .id_source(index)
+            .show(ui, |ui| {
+                for (i, child) in node.children.iter().enumerate() {
+                    show_node(ui, child, ai_shape, i);
+                }
+            });
+    }
+}
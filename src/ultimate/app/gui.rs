@@ -1,78 +1,408 @@
 //! This module only exists to separate the long methods used for drawing the board and cells.
 
-use super::{send_move_when_ready, UltimateTTTApp};
+use super::{config::CalibrationState, UltimateTTTApp, UpdateCellError, ILLEGAL_MOVE_FEEDBACK};
 use crate::{
     shared::{
-        board::WinnerError,
-        gui::{centered_square_in_rect, draw_cellshape_in_rect, draw_winning_line_in_rect},
+        board::GameResult,
+        clock::clock_text,
+        gui::{
+            centered_square_in_rect, draw_cellshape_in_rect, draw_coordinate_labels,
+            draw_game_over_banner_in_rect, draw_heat_in_rect, draw_last_move_arrow,
+            draw_thinking_overlay, draw_visits_in_rect, grid_line_shapes_in_rect,
+            grid_winning_line_shape_in_rect, piece_scale, request_repaint, SIDE_PANEL_WIDTH,
+        },
+        status::cell_name,
     },
-    ultimate::GlobalCoord,
+    ultimate::{
+        board::{LocalBoardState, MoveError, WinCondition},
+        GlobalCoord,
+    },
+    CellShape,
 };
 use eframe::{
     egui::{self, Context, Painter, Response, Sense, Ui},
-    epaint::{Color32, Pos2, Rect, Shape, Stroke, Vec2},
+    epaint::{Color32, Pos2, Rect, Vec2},
 };
+use web_time::Instant;
+
+/// Return the message explaining why a move with the given error is illegal.
+fn illegal_move_message(error: UpdateCellError) -> &'static str {
+    match error {
+        UpdateCellError::Illegal(error) => match error {
+            MoveError::WrongLocalBoard => "You must play in the highlighted board",
+            MoveError::CellAlreadyFull => "That cell is already taken",
+            MoveError::WrongTurn => "It's not your turn",
+            MoveError::OutOfBounds => "That cell isn't on the board",
+            MoveError::LocalBoardClosed => "That board has already been decided",
+            MoveError::OpeningNotChosen => "The board for the first move hasn't been chosen yet",
+            MoveError::NoOpeningChoice => "The board for the first move has already been chosen",
+        },
+        UpdateCellError::GameOver => "The game is already over",
+    }
+}
+
+/// Return the centre of the cell at the given coordinate and the length of the cells of the local
+/// boards, when the global board with `N` local boards along each side is drawn in the given rect.
+fn cell_centre_in_rect<const N: usize>(rect: Rect, (x, y, (lx, ly)): GlobalCoord) -> (Pos2, f32) {
+    let global_cell_length = rect.width() / N as f32;
+    let local_rect = centered_square_in_rect(
+        Rect::from_min_size(
+            rect.min + Vec2::new(x as f32, y as f32) * global_cell_length,
+            Vec2::splat(global_cell_length),
+        ),
+        0.85,
+    );
+    let cell_length = local_rect.width() / 3.;
+
+    (
+        local_rect.min + Vec2::new(lx as f32 + 0.5, ly as f32 + 0.5) * cell_length,
+        cell_length,
+    )
+}
+
+/// Return the coordinate of the cell at the given point, when the global board with `N` local
+/// boards along each side is drawn in the given rect, or [`None`] if the point isn't in a cell,
+/// like in the gaps between local boards.
+fn global_coord_at<const N: usize>(rect: Rect, pos: Pos2) -> Option<GlobalCoord> {
+    let global_cell_length = rect.width() / N as f32;
+    let global = (pos - rect.min) / global_cell_length;
+    let (x, y) = (global.x as usize, global.y as usize);
+    if global.x < 0. || global.y < 0. || x >= N || y >= N {
+        return None;
+    }
+
+    let local_rect = centered_square_in_rect(
+        Rect::from_min_size(
+            rect.min + Vec2::new(x as f32, y as f32) * global_cell_length,
+            Vec2::splat(global_cell_length),
+        ),
+        0.85,
+    );
+    if !local_rect.contains(pos) {
+        return None;
+    }
+
+    let local = (pos - local_rect.min) / (local_rect.width() / 3.);
+    Some((x, y, ((local.x as usize).min(2), (local.y as usize).min(2))))
+}
+
+/// Return the translucent grey used to cover drawn local boards.
+fn drawn_board_color(ctx: &Context) -> Color32 {
+    if ctx.style().visuals.dark_mode {
+        Color32::from_rgba_unmultiplied(40, 40, 40, 192)
+    } else {
+        Color32::from_rgba_unmultiplied(200, 200, 200, 192)
+    }
+}
+
+/// Return the fainter translucent grey used to dim the local boards which didn't win the game.
+fn dimmed_board_color(ctx: &Context) -> Color32 {
+    if ctx.style().visuals.dark_mode {
+        Color32::from_rgba_unmultiplied(40, 40, 40, 128)
+    } else {
+        Color32::from_rgba_unmultiplied(200, 200, 200, 128)
+    }
+}
 
-impl UltimateTTTApp {
-    /// Draw board lines in the given rect with the given painter and return the width of the
-    /// resultant cells.
+impl<const N: usize> UltimateTTTApp<N> {
+    /// Return the local board that the next move must be played in, which is highlighted, unless
+    /// the game is over.
+    fn highlighted_local_board(&self) -> Option<(usize, usize)> {
+        self.global_board
+            .next_local_board()
+            .filter(|_| !self.game_over())
+    }
+
+    /// Check if the local board at the given coordinates should be dimmed because the game has
+    /// been won without it. Nothing is dimmed when a shape wins on the count of local boards,
+    /// since every board counted.
+    fn dims_local_board(&self, coords: (usize, usize)) -> bool {
+        match self.global_board.get_winner() {
+            GameResult::Won { line, .. } => {
+                !line.contains(&coords)
+                    && (line[0] != line[2]
+                        || self.global_board.win_condition() == WinCondition::AnyBoard)
+            }
+            _ => false,
+        }
+    }
+
+    /// Draw the lines of an `n` by `n` grid in the given rect with the given painter and return
+    /// the width of the resultant cells.
     fn draw_board_lines(
         &self,
         ctx: &Context,
         painter: &Painter,
         rect: &Rect,
+        n: usize,
         color: Option<Color32>,
     ) -> f32 {
-        let cell_length = rect.size().x / 3.0;
+        let cell_length = rect.size().x / n as f32;
 
-        let stroke = Stroke {
-            width: rect.width() / 80.0,
-            color: color.unwrap_or(if self.global_board.next_local_board().is_some() {
-                if ctx.style().visuals.dark_mode {
-                    Color32::DARK_GRAY
-                } else {
-                    Color32::LIGHT_GRAY
-                }
+        let color = color.unwrap_or(if self.highlighted_local_board().is_some() {
+            if ctx.style().visuals.dark_mode {
+                Color32::DARK_GRAY
             } else {
-                Color32::GRAY
-            }),
+                Color32::LIGHT_GRAY
+            }
+        } else {
+            Color32::GRAY
+        });
+        painter.extend(grid_line_shapes_in_rect(rect, color, n));
+
+        cell_length
+    }
+
+    /// Draw a bar in the given rect showing how likely each player is to win according to the most
+    /// recent [`evaluation`](UltimateTTTApp::evaluation).
+    ///
+    /// The bar is vertical if the rect is taller than it is wide, and horizontal otherwise. The top
+    /// or left of the bar is filled in the colour of X and the bottom or right is filled in the
+    /// colour of O, and the bar is split evenly if there is no evaluation yet.
+    pub fn draw_evaluation_bar(&self, ui: &mut Ui, rect: Rect) {
+        let x_win_rate = self
+            .evaluation
+            .as_ref()
+            .map_or(0.5, |(_, stats)| stats.win_rate(CellShape::X)) as f32;
+
+        let (x_rect, o_rect) = if rect.height() > rect.width() {
+            let split_y = rect.min.y + x_win_rate * rect.height();
+            (
+                Rect::from_min_max(rect.min, Pos2::new(rect.max.x, split_y)),
+                Rect::from_min_max(Pos2::new(rect.min.x, split_y), rect.max),
+            )
+        } else {
+            let split_x = rect.min.x + x_win_rate * rect.width();
+            (
+                Rect::from_min_max(rect.min, Pos2::new(split_x, rect.max.y)),
+                Rect::from_min_max(Pos2::new(split_x, rect.min.y), rect.max),
+            )
         };
-        for i in [1.0, 2.0] {
-            // Draw vertical lines
-            let x = rect.min.x + (i / 3.0) * rect.width();
-            let y = rect.max.y;
-            painter.add(Shape::LineSegment {
-                points: [Pos2 { x, y: rect.min.y }, Pos2 { x, y }],
-                stroke,
-            });
 
-            // Draw horizontal lines
-            let y = rect.min.y + (i / 3.0) * rect.height();
-            let x = rect.max.x;
-            painter.add(Shape::LineSegment {
-                points: [Pos2 { x: rect.min.x, y }, Pos2 { x, y }],
-                stroke,
+        let painter = ui.painter();
+        painter.rect_filled(x_rect, 0.0, Color32::LIGHT_RED);
+        painter.rect_filled(o_rect, 0.0, Color32::LIGHT_BLUE);
+
+        ui.allocate_rect(rect, Sense::hover())
+            .on_hover_text(match &self.evaluation {
+                Some((_, stats)) => format!(
+                    "X wins {:.0}% of playouts, O wins {:.0}% of playouts",
+                    100. * stats.win_rate(CellShape::X),
+                    100. * stats.win_rate(CellShape::O)
+                ),
+                None => "No evaluation yet".to_string(),
             });
+    }
+
+    /// Draw a small map of the winners of the local boards in the given rect, which is the board
+    /// that decides the winner of the whole game.
+    ///
+    /// Drawn local boards are greyed out, the local board where the next move must be played is
+    /// outlined, and the winning line is drawn if the game has been won.
+    pub fn draw_mini_map(&self, ui: &mut Ui, rect: Rect) {
+        let dark_mode = ui.ctx().style().visuals.dark_mode;
+        let painter = ui.painter();
+        painter.rect_filled(rect, rect.width() / 20., ui.visuals().extreme_bg_color);
+
+        let rect = centered_square_in_rect(rect, 0.9);
+        painter.extend(grid_line_shapes_in_rect(&rect, Color32::GRAY, N));
+
+        let cell_length = rect.width() / N as f32;
+        for y in 0..N {
+            for x in 0..N {
+                let cell_rect = Rect::from_min_size(
+                    Pos2::new(
+                        rect.min.x + x as f32 * cell_length,
+                        rect.min.y + y as f32 * cell_length,
+                    ),
+                    Vec2::splat(cell_length),
+                );
+
+                match self.global_board.local_board_state(x, y) {
+                    LocalBoardState::Won(shape) => draw_cellshape_in_rect(
+                        painter,
+                        &centered_square_in_rect(cell_rect, 0.8),
+                        Some(shape),
+                        false,
+                    ),
+                    LocalBoardState::Drawn => painter.rect_filled(
+                        centered_square_in_rect(cell_rect, 0.8),
+                        0.,
+                        drawn_board_color(ui.ctx()),
+                    ),
+                    LocalBoardState::Open => (),
+                }
+
+                if self.highlighted_local_board() == Some((x, y)) {
+                    painter.rect_stroke(
+                        centered_square_in_rect(cell_rect, 0.9),
+                        0.,
+                        (
+                            1.,
+                            if dark_mode {
+                                Color32::WHITE
+                            } else {
+                                Color32::BLACK
+                            },
+                        ),
+                    );
+                }
+            }
         }
 
-        cell_length
+        if let GameResult::Won {
+            line: [start_coord, _, end_coord],
+            ..
+        } = self.global_board.get_winner()
+        {
+            if start_coord != end_coord {
+                painter.add(grid_winning_line_shape_in_rect(
+                    &rect,
+                    dark_mode,
+                    N,
+                    start_coord,
+                    end_coord,
+                ));
+            }
+        }
+
+        ui.allocate_rect(rect, Sense::hover())
+            .on_hover_text("The winners of the local boards, which decide the game");
+    }
+
+    /// Draw the side panel of the wide layout, with the settings, the evaluation bar, and the list
+    /// of moves.
+    pub fn draw_side_panel(&mut self, ctx: &Context) {
+        egui::SidePanel::right("side_panel")
+            .resizable(false)
+            .exact_width(SIDE_PANEL_WIDTH)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Settings");
+                    self.show_settings(ui);
+
+                    if self.config.show_evaluation_bar {
+                        ui.separator();
+                        ui.heading("Evaluation");
+                        let rect = Rect::from_min_size(
+                            ui.cursor().min,
+                            Vec2::new(ui.available_width(), 20.),
+                        );
+                        self.draw_evaluation_bar(ui, rect);
+                    }
+
+                    if self.config.show_mini_map {
+                        ui.separator();
+                        ui.heading("Local board winners");
+                        let rect = Rect::from_min_size(ui.cursor().min, Vec2::splat(120.));
+                        self.draw_mini_map(ui, rect);
+                    }
+
+                    ui.separator();
+                    ui.heading("Moves");
+                    let format_move =
+                        |(x, y, (lx, ly)): GlobalCoord| cell_name((3 * x + lx, 3 * y + ly));
+
+                    // Clicking a move reviews the game from there, except in the tutorial, which
+                    // has its own moves to play
+                    if self.tutorial_step.is_some() {
+                        self.record.show_move_list(ui, format_move);
+                    } else {
+                        let selected = self.review_history().map(|history| history.ply());
+                        if let Some(ply) =
+                            self.record
+                                .show_clickable_move_list(ui, selected, format_move)
+                        {
+                            self.jump_to_ply(ply);
+                        }
+
+                        if let Some(ply) = selected.filter(|&ply| ply > 0) {
+                            ui.separator();
+                            ui.heading(format!("Annotate move {ply}"));
+                            self.record.show_annotation_editor(ui, ply - 1);
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Return how far through the [`ILLEGAL_MOVE_FEEDBACK`] we are for the most recent
+    /// [`illegal_move`](UltimateTTTApp::illegal_move), from 0 to 1, or `None` if the feedback has
+    /// finished.
+    fn illegal_move_progress(&self) -> Option<f32> {
+        let (_, _, time) = self.illegal_move?;
+        let progress = time.elapsed().as_secs_f32() / ILLEGAL_MOVE_FEEDBACK.as_secs_f32();
+        (progress < 1.).then_some(progress)
     }
 
     /// Draw the board in the given rect.
     pub fn draw_global_board(&mut self, ctx: &Context, ui: &mut Ui, rect: Rect) {
-        ctx.request_repaint();
+        request_repaint(
+            ctx,
+            (self.waiting_on_move() && self.review.is_none())
+                || self.illegal_move_progress().is_some(),
+            self.waiting_on_move()
+                || self
+                    .evaluation_task
+                    .as_ref()
+                    .map_or(false, |task| !task.is_finished())
+                || matches!(self.calibration, CalibrationState::Running(_))
+                || self.blunder_check.is_running()
+                || self
+                    .clock
+                    .as_ref()
+                    .map_or(false, |clock| clock.running().is_some())
+                || (self.kiosk && self.game_ended_at.is_some()),
+        );
+
+        if self.illegal_move_progress().is_none() {
+            self.illegal_move = None;
+        }
 
         let painter = Painter::new(
             ctx.clone(),
             egui::LayerId::new(egui::Order::Background, egui::Id::new("board_painter")),
             rect,
         );
+        let rect = if self.config.show_coordinates {
+            draw_coordinate_labels(&painter, rect, 3 * N, Color32::GRAY)
+        } else {
+            rect
+        };
 
-        let cell_length = self.draw_board_lines(ctx, &painter, &rect, None);
+        let cell_length = self.draw_board_lines(ctx, &painter, &rect, N, None);
+        self.drawings
+            .handle_input(ui, rect, |pos| global_coord_at::<N>(rect, pos));
 
-        let nums = [0, 1, 2];
-        for y in nums {
-            for x in nums {
+        let heatmap = if self.config.show_heatmap && !self.waiting_on_move() {
+            let heatmap = self.heatmap();
+            if heatmap.is_none()
+                && self.global_board.get_winner() == GameResult::Ongoing
+                && self
+                    .evaluation_task
+                    .as_ref()
+                    .map_or(true, |task| task.is_finished())
+            {
+                self.start_evaluation();
+            }
+            heatmap.unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let thinking = if let Some(review) = &self.review {
+            // Highlight the move that the AI would play in the position being reviewed
+            review
+                .suggestion()
+                .map(|mv| vec![(mv, 1.)])
+                .unwrap_or_default()
+        } else if self.config.show_ai_thinking && self.waiting_on_move() {
+            self.receive_ai_snapshot()
+        } else {
+            vec![]
+        };
+
+        for y in 0..N {
+            for x in 0..N {
                 let cell_rect = Rect::from_min_size(
                     Pos2::new(
                         rect.min.x + (x as f32 * cell_length),
@@ -81,44 +411,135 @@ impl UltimateTTTApp {
                     Vec2::splat(cell_length),
                 );
 
-                self.draw_local_board((x, y), ui, &painter, cell_rect);
+                self.draw_local_board((x, y), ui, &painter, cell_rect, &heatmap, &thinking);
             }
         }
 
-        if self.waiting_on_move {
-            if let Ok(Some(coord)) = self.mv_rx.try_recv() {
-                self.update_cell(coord);
-                self.waiting_on_move = false;
+        // Show observers where the last move was played, coming from the move before it
+        if self.presenting {
+            if let [.., from, to] = self.record.moves[..] {
+                let (from, _) = cell_centre_in_rect::<N>(rect, from);
+                let (to, cell_length) = cell_centre_in_rect::<N>(rect, to);
+                draw_last_move_arrow(&painter, from, to, cell_length);
             }
         }
 
-        // Draw the winning line
-        if let Ok((_, [start_coord, _, end_coord])) = self.global_board.get_winner() {
-            draw_winning_line_in_rect(
+        self.drawings
+            .draw(&painter, |coord| cell_centre_in_rect::<N>(rect, coord));
+
+        if self.waiting_on_move() && self.review.is_none() {
+            draw_thinking_overlay(
+                &painter,
+                &rect,
+                ui.ctx().style().visuals.dark_mode,
+                ctx.input().time,
+            );
+        }
+
+        if let Some((_, error, _)) = self.illegal_move {
+            egui::show_tooltip_at_pointer(ctx, egui::Id::new("illegal_move_tooltip"), |ui| {
+                ui.label(illegal_move_message(error));
+            });
+        }
+
+        // Draw the winning line, or a banner if a single local board or the count of local boards
+        // won the game, since there's no line to draw
+        if let GameResult::Won {
+            shape,
+            line: [start_coord, _, end_coord],
+        } = self.global_board.get_winner()
+        {
+            if start_coord == end_coord {
+                let message = match self.global_board.win_condition() {
+                    WinCondition::AnyBoard => format!("{shape:?} wins!"),
+                    WinCondition::LineOfBoards => format!("{shape:?} wins on boards!"),
+                };
+                draw_game_over_banner_in_rect(
+                    &rect,
+                    &painter,
+                    ui.ctx().style().visuals.dark_mode,
+                    &message,
+                );
+            } else {
+                painter.add(grid_winning_line_shape_in_rect(
+                    &rect,
+                    ui.ctx().style().visuals.dark_mode,
+                    N,
+                    start_coord,
+                    end_coord,
+                ));
+            }
+        } else if self.global_board.get_winner() == GameResult::Draw {
+            draw_game_over_banner_in_rect(
+                &rect,
+                &painter,
+                ui.ctx().style().visuals.dark_mode,
+                "It's a draw!",
+            );
+        } else if let Some(shape) = self.flagged() {
+            draw_game_over_banner_in_rect(
                 &rect,
                 &painter,
                 ui.ctx().style().visuals.dark_mode,
-                start_coord,
-                end_coord,
+                &format!("{:?} wins on time!", shape.other()),
             );
         }
     }
 
-    /// Draw the specified local board in the given rect.
+    /// Show the time that each shape has left if the game is timed, with the running clock in
+    /// bold.
+    pub fn show_clock(&self, ui: &mut Ui) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+
+        for shape in CellShape::iter() {
+            ui.separator();
+            let text =
+                egui::RichText::new(format!("{shape} {}", clock_text(clock.remaining(shape))))
+                    .monospace();
+            ui.label(if clock.running() == Some(shape) {
+                text.strong()
+            } else {
+                text
+            });
+        }
+    }
+
+    /// Draw the specified local board in the given rect, shading any cells which are in the
+    /// heatmap, or which the AI is `thinking` about with their share of its visits.
     fn draw_local_board(
         &mut self,
         coords: (usize, usize),
         ui: &mut Ui,
         painter: &Painter,
         rect: Rect,
+        heatmap: &[(GlobalCoord, f64)],
+        thinking: &[(GlobalCoord, f64)],
     ) {
         let rect = centered_square_in_rect(rect, 0.85);
 
+        // Flash the board that the player should have played in
+        if let (
+            Some(progress),
+            Some((_, UpdateCellError::Illegal(MoveError::WrongLocalBoard), _)),
+        ) = (self.illegal_move_progress(), self.illegal_move)
+        {
+            if self.highlighted_local_board() == Some(coords) {
+                painter.rect_filled(
+                    rect,
+                    rect.width() / 20.,
+                    Color32::from_rgba_unmultiplied(255, 215, 0, (128. * (1. - progress)) as u8),
+                );
+            }
+        }
+
         let cell_length = self.draw_board_lines(
             ui.ctx(),
             painter,
             &rect,
-            if let Some(c) = self.global_board.next_local_board() {
+            3,
+            if let Some(c) = self.highlighted_local_board() {
                 if c == coords {
                     Some(if ui.ctx().style().visuals.dark_mode {
                         Color32::WHITE
@@ -133,6 +554,10 @@ impl UltimateTTTApp {
             },
         );
 
+        // The board is inert while the AI is thinking, while reviewing a finished game, and once
+        // the game is over
+        let input_enabled = !self.waiting_on_move() && self.review.is_none() && !self.game_over();
+
         let nums = [0, 1, 2];
         for y in nums {
             for x in nums {
@@ -145,60 +570,135 @@ impl UltimateTTTApp {
                 );
 
                 let global_coord = (coords.0, coords.1, (x, y));
+                if let Some(&(_, win_chance)) =
+                    heatmap.iter().find(|&&(coord, _)| coord == global_coord)
+                {
+                    draw_heat_in_rect(
+                        painter,
+                        &centered_square_in_rect(cell_rect, 0.9),
+                        win_chance as f32,
+                    );
+                }
+                if let Some(&(_, share)) =
+                    thinking.iter().find(|&&(coord, _)| coord == global_coord)
+                {
+                    draw_visits_in_rect(
+                        painter,
+                        &centered_square_in_rect(cell_rect, 0.9),
+                        share as f32,
+                    );
+                }
+
                 if self
-                    .draw_cell(ui, painter, cell_rect, global_coord)
+                    .draw_cell(ui, painter, cell_rect, global_coord, input_enabled)
                     .clicked()
-                    && !self.waiting_on_move
                 {
-                    self.update_cell(global_coord);
-
-                    if self.config.playing_ai
-                        && self.global_board.get_winner() == Err(WinnerError::NoWinnerYet)
-                    {
-                        send_move_when_ready(
-                            self.global_board.clone(),
-                            self.config.max_mcts_expansions,
-                            self.config.mcts_playouts,
-                            self.mv_tx.clone(),
-                        );
-                        self.waiting_on_move = true;
+                    // Under `OpeningRule::SecondPlayerChooses`, clicking any cell of a local
+                    // board chooses it for the first move
+                    if self.global_board.awaiting_opening_choice() {
+                        self.choose_opening_board(coords);
+                        continue;
+                    }
+
+                    let extra_move = self.extra_move_pending();
+
+                    match self.update_cell(global_coord) {
+                        Ok(()) => {
+                            self.illegal_move = None;
+
+                            if self.tutorial_step.is_some() {
+                                self.advance_tutorial_after_move();
+                            } else if self.game_settings.playing_ai
+                                && self.global_board.get_winner() == GameResult::Ongoing
+                            {
+                                if extra_move {
+                                    self.skip_turn();
+                                } else {
+                                    self.start_ai_move();
+                                }
+                            }
+                        }
+                        Err(error) => {
+                            self.illegal_move = Some((global_coord, error, Instant::now()));
+                        }
                     }
                 }
             }
         }
 
-        if let Ok((winning_shape, _)) =
-            self.global_board.local_boards[coords.0][coords.1].get_winner()
+        match self.global_board.local_board_state(coords.0, coords.1) {
+            LocalBoardState::Won(winning_shape) => {
+                draw_cellshape_in_rect(painter, &rect, Some(winning_shape), true);
+            }
+            // Grey out drawn boards, since they're dead and count as neither shape's
+            LocalBoardState::Drawn => {
+                painter.rect_filled(rect, rect.width() / 20., drawn_board_color(ui.ctx()));
+            }
+            LocalBoardState::Open => (),
+        }
+
+        if self.dims_local_board(coords)
+            && self.global_board.local_board_state(coords.0, coords.1) != LocalBoardState::Drawn
         {
-            draw_cellshape_in_rect(painter, &rect, Some(winning_shape), true);
+            painter.rect_filled(rect, rect.width() / 20., dimmed_board_color(ui.ctx()));
         }
     }
 
     /// Draw the appropriate cell (specified by the [`GlobalCoord`]) in the given rect.
+    ///
+    /// Empty cells can be clicked even when the move would be illegal, so that the player can be
+    /// told why, but no cells can be clicked unless `input_enabled` is true. If the most recent
+    /// [`illegal_move`](UltimateTTTApp::illegal_move) was in this cell, then the rejected shape is
+    /// drawn shaking in it. While a legal empty cell is hovered, a faint preview of the
+    /// [`active_shape`](UltimateTTTApp::active_shape) is drawn in it, so that the player can see
+    /// what clicking it would do.
     fn draw_cell(
         &mut self,
         ui: &mut Ui,
         painter: &Painter,
         rect: Rect,
         coord: GlobalCoord,
+        input_enabled: bool,
     ) -> Response {
-        let rect = centered_square_in_rect(rect, 0.8);
+        let rect = centered_square_in_rect(rect, piece_scale(self.presenting));
         let (x, y, (lx, ly)) = coord;
-        let shape = self.global_board.local_boards[x][y].cells[lx][ly];
-        let interactive: bool = (self.global_board.next_local_board() == Some((x, y))
-            || self.global_board.next_local_board().is_none())
-            && shape.is_none()
-            && self.global_board.get_winner().is_err();
+        let shape = self.global_board.local_boards()[x][y].cells()[lx][ly];
+        let interactive: bool = input_enabled && shape.is_none() && self.tutorial_allows(coord);
 
         draw_cellshape_in_rect(painter, &rect, shape, false);
+        self.draw_tutorial_highlight(painter, rect, coord);
 
-        ui.allocate_rect(
+        if let (Some(progress), Some((illegal_coord, _, _))) =
+            (self.illegal_move_progress(), self.illegal_move)
+        {
+            if illegal_coord == coord {
+                let amplitude = rect.width() / 10. * (1. - progress);
+                let offset = Vec2::new(amplitude * (progress * 40.).sin(), 0.);
+                draw_cellshape_in_rect(
+                    painter,
+                    &rect.translate(offset),
+                    Some(self.active_shape),
+                    true,
+                );
+            }
+        }
+
+        let response = ui.allocate_rect(
             rect,
-            if interactive && shape.is_none() {
+            if interactive {
                 Sense::click()
             } else {
                 Sense::focusable_noninteractive()
             },
-        )
+        );
+        if interactive
+            && response.hovered()
+            && !self.global_board.awaiting_opening_choice()
+            && self.global_board.legal_moves().any(|mv| mv == coord)
+        {
+            draw_cellshape_in_rect(painter, &rect, Some(self.active_shape), true);
+        }
+
+        response
     }
 }
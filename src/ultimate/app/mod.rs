@@ -1,82 +1,311 @@
 //! This module handles the `egui` interface to the game.
 
-mod config;
+pub mod config;
 mod gui;
+mod review;
+mod tutorial;
 
-use self::config::UltimateConfig;
-use super::{board::GlobalBoard, GlobalCoord};
-use crate::{app::TTTVariantApp, shared::gui::centered_square_in_rect, CellShape};
-use eframe::{egui, epaint::Color32};
-use std::sync::mpsc;
+#[cfg(feature = "devtools")]
+mod devtools;
+
+use self::config::{CalibrationState, UltimateAiPlayer, UltimateConfig, UltimateGameSettings};
+use super::{
+    board::{
+        calibration::Calibration, DrawRule, GlobalBoard, MctsPlayer, MoveError, OpeningRule,
+        RootStats, RuleSet, WinCondition,
+    },
+    GlobalCoord,
+};
+use crate::{
+    app::{Difficulty, LaunchOptions, TTTVariantApp, KIOSK_RESTART_DELAY},
+    shared::{
+        ai::{AiPlayer, GreedyBot, RandomBot},
+        ai_task::{AiTask, MoveChannel},
+        blunders::BlunderCheck,
+        board::GameResult,
+        chaos::random_start,
+        clock::GameClock,
+        crash_report::{self, CrashContext},
+        drawings::BoardDrawings,
+        events::{EventBus, GameEvent},
+        explain::MoveExplanation,
+        export::{show_export_buttons, ultimate_board_shapes},
+        gui::{
+            centered_square_in_rect, is_wide_layout, show_discard_dialog, show_file_drop_hint,
+            show_turn_indicator,
+        },
+        ladder::LadderPolicy,
+        learning::{LearningTable, LEARNING_PLIES},
+        profiles::{Profiles, VariantSettings},
+        record::GameRecord,
+        save_slots::{LoadedGame, SaveSlots, SavedGame},
+        status::{cell_name, position_name, short_status_text, status_text},
+    },
+    CellShape,
+};
+use eframe::{
+    egui,
+    epaint::{Color32, Pos2, Rect, Vec2},
+};
+use rand::Rng;
+use std::{ops::RangeInclusive, sync::mpsc};
+use thiserror::Error;
 use web_time::{Duration, Instant};
 
-/// This method sends an AI-generated move down an `mpsc` channel when it's ready.
-#[cfg(not(target_arch = "wasm32"))]
-pub fn send_move_when_ready(
-    global_board: GlobalBoard,
-    max_iters: u16,
-    playouts: u8,
-    tx: mpsc::Sender<Option<GlobalCoord>>,
-) {
-    use std::thread;
-
-    thread::spawn(move || {
-        let start = Instant::now();
-        let mv = global_board.generate_ai_move(max_iters, playouts);
-        thread::sleep(Duration::saturating_sub(
-            Duration::from_millis(750),
-            start.elapsed(),
-        ));
-        let _ = tx.send(mv);
-    });
+/// Why the app rejected a move in [`update_cell`](UltimateTTTApp::update_cell).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+enum UpdateCellError {
+    /// The move is illegal in the current position.
+    #[error(transparent)]
+    Illegal(#[from] MoveError),
+
+    /// The game has already been decided. [`GlobalBoard::make_move`] doesn't check this, so that
+    /// finished positions can still be explored, but the app rejects any moves once the game is
+    /// over.
+    #[error("game already decided")]
+    GameOver,
 }
 
-/// This method sends an AI-generated move down an `mpsc` channel when it's ready.
-#[cfg(target_arch = "wasm32")]
-pub fn send_move_when_ready(
-    global_board: GlobalBoard,
-    max_iters: u16,
-    playouts: u8,
-    tx: mpsc::Sender<Option<GlobalCoord>>,
-) {
-    let start = Instant::now();
-
-    gloo_timers::callback::Timeout::new(
-        Duration::saturating_sub(Duration::from_millis(750), start.elapsed()).as_millis() as u32,
-        move || {
-            let mv = global_board.generate_ai_move(max_iters, playouts);
-            let _ = tx.send(mv);
-        },
-    )
-    .forget();
+/// The minimum time that the AI will take to make a move, so that it doesn't appear instant.
+const AI_MOVE_DELAY: Duration = Duration::from_millis(750);
+
+/// The number of MCTS expansions used by the [`Adaptive`](UltimateAiPlayer::Adaptive) AI when
+/// it's at its weakest, and by the lowest level of the [`Ladder`](UltimateAiPlayer::Ladder) which
+/// uses MCTS.
+const MIN_ADAPTIVE_EXPANSIONS: u16 = 20;
+
+/// The share of the visits of the most visited move that a move needs for the AI to consider it
+/// when choosing with what it has learned from previous games, so that it never plays a move that
+/// it barely searched. See [`learned_move`].
+const LEARNED_MOVE_VISIT_SHARE: f64 = 0.25;
+
+/// The range of the number of random moves played to reach the starting position in chaos mode.
+/// See [`UltimateConfig::chaos_start`].
+const CHAOS_PLIES: RangeInclusive<usize> = 8..=20;
+
+/// The number of MCTS expansions used to check that a chaos mode starting position is balanced.
+/// See [`GlobalBoard::is_balanced`].
+const CHAOS_BALANCE_EXPANSIONS: u16 = 300;
+
+/// The win rate in the MCTS evaluation of a position above which a shape is considered to be
+/// winning, when looking for the first losing mistake of each shape. See
+/// [`UltimateTTTApp::start_blunder_check`].
+const WINNING_WIN_RATE: f64 = 0.75;
+
+/// How long to show the feedback for an illegal move, like flashing the correct local board.
+const ILLEGAL_MOVE_FEEDBACK: Duration = Duration::from_millis(800);
+
+/// The width and height of the mini-map in the corner of the narrow layout. See
+/// [`UltimateTTTApp::draw_mini_map`].
+const MINI_MAP_SIZE: f32 = 72.;
+
+/// An evaluation of a position, tagged with the [`generation`](UltimateTTTApp::generation) of the
+/// game and the position that was evaluated.
+type EvaluationMessage<const N: usize> = (u32, GlobalBoard<N>, Option<RootStats>);
+
+/// Return the name of the variant with `N` local boards along each side and the given win
+/// condition, which is used as the key of its settings in the storage, and to identify it in logs
+/// and crash reports.
+///
+/// The 2x2 variant is always won by a line of local boards, so it only has the one key.
+fn variant_key<const N: usize>(win_condition: WinCondition) -> &'static str {
+    match win_condition {
+        _ if N == 2 => "small_ultimate",
+        WinCondition::LineOfBoards => "ultimate",
+        WinCondition::AnyBoard => "nine_board",
+    }
+}
+
+/// Return the name of the given local board on a global board with `N` local boards along each
+/// side, like `"top-right"`.
+fn local_board_name<const N: usize>((x, y): (usize, usize)) -> &'static str {
+    if N != 2 {
+        return position_name((x, y));
+    }
+
+    match (x, y) {
+        (0, 0) => "top-left",
+        (1, 0) => "top-right",
+        (0, 1) => "bottom-left",
+        (1, 1) => "bottom-right",
+        _ => "unknown",
+    }
+}
+
+/// Choose between the moves that the AI searched enough, according to
+/// [`LEARNED_MOVE_VISIT_SHARE`], using what it has learned from previous games. See
+/// [`LearningTable::choose`].
+///
+/// This returns `None` if the AI hasn't learned anything about these moves yet.
+fn learned_move<const N: usize>(
+    learning: &LearningTable,
+    board: &GlobalBoard<N>,
+    stats: &RootStats,
+) -> Option<GlobalCoord> {
+    let candidates = stats
+        .visit_shares()
+        .into_iter()
+        .zip(stats.move_win_rates())
+        .filter(|&((_, share), _)| share >= LEARNED_MOVE_VISIT_SHARE)
+        .filter_map(|((mv, _), (_, win_rate))| {
+            let mut board = board.clone();
+            board.make_move(mv, board.to_move()).ok()?;
+            Some((mv, board.position_hash(), win_rate))
+        });
+
+    learning.choose(candidates)
 }
 
 /// The struct to hold the state of the app.
-pub struct UltimateTTTApp {
+///
+/// The global board has `N` local boards along each side, which is 3 in ultimate tic-tac-toe,
+/// and 2 in the smaller variant played on a [`SmallGlobalBoard`](super::board::SmallGlobalBoard).
+pub struct UltimateTTTApp<const N: usize = 3> {
     /// The configuration of the app.
     config: UltimateConfig,
 
+    /// The settings that the current game was started with. Changes to these settings in
+    /// [`config`](Self::config) only take effect when a new game starts.
+    game_settings: UltimateGameSettings,
+
+    /// The condition for winning every game, which decides whether this app is playing ultimate
+    /// or nine-board tic-tac-toe. Each has its own config.
+    win_condition: WinCondition,
+
+    /// The named configs that the player can switch between. The selected profile is updated
+    /// from [`config`](Self::config) when the config is saved.
+    profiles: Profiles<UltimateConfig>,
+
+    /// The games that the player has saved to load later.
+    save_slots: SaveSlots<UltimateConfig, GlobalCoord>,
+
+    /// What the AI has learned from the results of previous games against the player. See
+    /// [`UltimateConfig::learn_from_games`].
+    learning: LearningTable,
+
+    /// The hashes of the positions that the AI has moved into during the opening of the current
+    /// game, which are recorded in the [`learning`](Self::learning) table when the game ends.
+    learning_positions: Vec<u64>,
+
     /// Whether the settings window is currently being shown.
     showing_settings_window: bool,
 
     /// The full global board.
-    global_board: GlobalBoard,
+    global_board: GlobalBoard<N>,
 
     /// The shape that will be used for the next cell to be placed.
     ///
     /// See [`update_cell`](UltimateTTTApp::update_cell).
     active_shape: CellShape,
 
-    /// Whether we're currently waiting for the AI to make a move.
-    waiting_on_move: bool,
+    /// The handle to the AI move currently being computed, if there is one, which reports
+    /// snapshots of the search if [`UltimateConfig::show_ai_thinking`] was enabled when it
+    /// started. See [`waiting_on_move`](Self::waiting_on_move).
+    ai_task: Option<AiTask<RootStats>>,
 
-    /// The AI moves are computed in a background thread to make the UI more snappy. This is the
-    /// sender that we pass to the background thread to get the AI move back.
-    mv_tx: mpsc::Sender<Option<GlobalCoord>>,
+    /// The generation of the current game, which is incremented every time the game is restarted.
+    ///
+    /// Every AI move is tagged with the generation of the game it was computed for, so that moves
+    /// computed for a previous game can be discarded.
+    generation: u32,
 
     /// The AI moves are computed in a background thread to make the UI more snappy. This is the
-    /// receiver that receives the computed AI moves.
-    mv_rx: mpsc::Receiver<Option<GlobalCoord>>,
+    /// channel that we get them back from, tagged with the [`generation`](Self::generation) of
+    /// the game.
+    ai_moves: MoveChannel<GlobalCoord>,
+
+    /// The most recent MCTS evaluation of the game, along with the position that was evaluated.
+    ///
+    /// This is shown in the evaluation bar, and in the heatmap if it's an evaluation of the
+    /// current position.
+    evaluation: Option<(GlobalBoard<N>, RootStats)>,
+
+    /// The handle to the evaluation currently being computed on demand, if there is one.
+    evaluation_task: Option<AiTask>,
+
+    /// The sender that we pass to background threads to get evaluations back, tagged with the
+    /// [`generation`](Self::generation) of the game.
+    evaluation_tx: mpsc::Sender<EvaluationMessage<N>>,
+
+    /// The receiver that receives evaluations, tagged with the [`generation`](Self::generation) of
+    /// the game.
+    evaluation_rx: mpsc::Receiver<EvaluationMessage<N>>,
+
+    /// The state of calibrating the AI settings. See [`config::CalibrationState`].
+    calibration: CalibrationState,
+
+    /// The sender that we pass to the background thread to get the result of calibration back.
+    calibration_tx: mpsc::Sender<Option<Calibration>>,
+
+    /// The receiver that receives the result of calibration.
+    calibration_rx: mpsc::Receiver<Option<Calibration>>,
+
+    /// The explanation of the most recent AI move, if [`UltimateConfig::explain_ai_moves`] is
+    /// enabled.
+    explanation: Option<MoveExplanation<GlobalCoord>>,
+
+    /// The sender that we pass to the background thread to get the explanation of the AI move
+    /// back, tagged with the [`generation`](Self::generation) of the game.
+    explanation_tx: mpsc::Sender<(u32, MoveExplanation<GlobalCoord>)>,
+
+    /// The receiver that receives the explanations of AI moves, tagged with the
+    /// [`generation`](Self::generation) of the game.
+    explanation_rx: mpsc::Receiver<(u32, MoveExplanation<GlobalCoord>)>,
+
+    /// The record of the current game, used to export the game as a GIF.
+    record: GameRecord<GlobalCoord>,
+
+    /// The most recent illegal move that the player tried to make, along with why it was illegal
+    /// and when it was made, which is shown for [`ILLEGAL_MOVE_FEEDBACK`].
+    illegal_move: Option<(GlobalCoord, UpdateCellError, Instant)>,
+
+    /// The index of the current step of the tutorial in [`tutorial::TUTORIAL_STEPS`], if we're
+    /// in the tutorial.
+    tutorial_step: Option<usize>,
+
+    /// Whether we're showing the dialog to confirm restarting a game in progress. See
+    /// [`request_restart`](Self::request_restart).
+    confirming_restart: bool,
+
+    /// Whether the app is in kiosk mode, which hides the settings and export buttons and restarts
+    /// the game automatically. See [`LaunchOptions::kiosk`].
+    kiosk: bool,
+
+    /// Whether the app is in presentation mode, which hides all the buttons and shows a big turn
+    /// indicator, bigger pieces, and an arrow to the last move. See
+    /// [`TTTVariantApp::set_presentation_mode`].
+    presenting: bool,
+
+    /// When the current turn started, so that the [`record`](Self::record) can time each move.
+    turn_started_at: Instant,
+
+    /// When the current game ended, if it has. Used to restart automatically in kiosk mode.
+    game_ended_at: Option<Instant>,
+
+    /// The clock of the current game, if it's timed. See [`UltimateConfig::time_control`].
+    clock: Option<GameClock>,
+
+    /// The subscribers to the [`GameEvent`]s of this app. See
+    /// [`subscribe_to_events`](Self::subscribe_to_events).
+    events: EventBus<GlobalCoord>,
+
+    /// The `tracing` span of the current game, which the spans of AI moves are children of.
+    game_span: tracing::Span,
+
+    /// The state of reviewing the finished game, if we're reviewing it. See
+    /// [`start_review`](Self::start_review).
+    review: Option<review::Review<N>>,
+
+    /// The arrows and highlighted cells drawn over the board with the right mouse button.
+    drawings: BoardDrawings<GlobalCoord>,
+
+    /// The check for the first losing mistake of each shape, which starts when the game ends. See
+    /// [`start_blunder_check`](Self::start_blunder_check).
+    blunder_check: BlunderCheck<GlobalCoord>,
+
+    /// The debug window to inspect the MCTS tree of the current position.
+    #[cfg(feature = "devtools")]
+    tree_debugger: devtools::TreeDebugger<N>,
 }
 
 impl Default for UltimateTTTApp {
@@ -86,75 +315,892 @@ impl Default for UltimateTTTApp {
 }
 
 impl UltimateTTTApp {
-    /// Create a new app with the given config.
+    /// Create a new app for nine-board tic-tac-toe, where winning any local board wins the game,
+    /// loading its config from the storage.
+    pub fn new_nine_board_app(storage: Option<&dyn eframe::Storage>) -> Self {
+        Self::load(storage, WinCondition::AnyBoard)
+    }
+
+    /// Create a new app for ultimate tic-tac-toe with the given config.
     ///
-    /// If [`UltimateConfig::player_plays_first`] is false, then we also start an AI move in the
-    /// background by calling [`send_move_when_ready`].
+    /// See [`start_game`](Self::start_game).
     fn new_with_config(config: UltimateConfig) -> Self {
-        let (mv_tx, mv_rx) = mpsc::channel();
-
-        let global_board = GlobalBoard::new(config.player_shape.other());
-        let waiting_on_move = config.playing_ai && !config.player_plays_first;
+        Self::new_with_config_and_win_condition(config, WinCondition::LineOfBoards)
+    }
+}
 
-        let active_shape = if waiting_on_move && config.playing_ai {
-            send_move_when_ready(
-                global_board.clone(),
-                config.max_mcts_expansions,
-                config.mcts_playouts,
-                mv_tx.clone(),
-            );
-            config.player_shape.other()
-        } else {
-            config.player_shape
-        };
+impl<const N: usize> UltimateTTTApp<N> {
+    /// Create a new app with the given win condition, loading the config of its variant from the
+    /// storage.
+    fn load(storage: Option<&dyn eframe::Storage>, win_condition: WinCondition) -> Self {
+        let VariantSettings { config, profiles } =
+            VariantSettings::load(storage, variant_key::<N>(win_condition));
 
         Self {
+            profiles,
+            save_slots: SaveSlots::load(storage, variant_key::<N>(win_condition)),
+            learning: LearningTable::load(storage, variant_key::<N>(win_condition)),
+            ..Self::new_with_config_and_win_condition(config, win_condition)
+        }
+    }
+
+    /// Create a new app with the given config and win condition.
+    ///
+    /// See [`start_game`](Self::start_game).
+    fn new_with_config_and_win_condition(
+        config: UltimateConfig,
+        win_condition: WinCondition,
+    ) -> Self {
+        let (evaluation_tx, evaluation_rx) = mpsc::channel();
+        let (calibration_tx, calibration_rx) = mpsc::channel();
+        let (explanation_tx, explanation_rx) = mpsc::channel();
+
+        let mut app = Self {
             config,
+            game_settings: config.game_settings(),
+            win_condition,
+            profiles: Profiles::new(config),
+            save_slots: SaveSlots::default(),
+            learning: LearningTable::default(),
+            learning_positions: vec![],
             showing_settings_window: false,
-            global_board,
-            active_shape,
-            waiting_on_move,
-            mv_tx,
-            mv_rx,
+            global_board: GlobalBoard::empty(config.player_shape.other(), CellShape::X),
+            active_shape: config.player_shape,
+            ai_task: None,
+            generation: 0,
+            ai_moves: MoveChannel::default(),
+            evaluation: None,
+            evaluation_task: None,
+            evaluation_tx,
+            evaluation_rx,
+            calibration: CalibrationState::Idle,
+            calibration_tx,
+            calibration_rx,
+            explanation: None,
+            explanation_tx,
+            explanation_rx,
+            record: GameRecord::new(config.player_shape),
+            illegal_move: None,
+            tutorial_step: None,
+            confirming_restart: false,
+            kiosk: false,
+            presenting: false,
+            turn_started_at: Instant::now(),
+            game_ended_at: None,
+            clock: None,
+            events: EventBus::new(),
+            game_span: tracing::Span::none(),
+            review: None,
+            drawings: BoardDrawings::default(),
+            blunder_check: BlunderCheck::default(),
+            #[cfg(feature = "devtools")]
+            tree_debugger: devtools::TreeDebugger::default(),
+        };
+
+        app.start_game();
+        app
+    }
+
+    /// Reset the board to start a new game with the current config, including its
+    /// [`game_settings`](Self::game_settings).
+    ///
+    /// If [`UltimateConfig::chaos_start`] is set, then the board starts from a random balanced
+    /// position instead of being empty, with the moves that reach it in the
+    /// [`record`](Self::record).
+    ///
+    /// If it's the AI's turn, like when [`UltimateConfig::player_plays_first`] is false, then we
+    /// also start an AI move in the background by calling [`start_ai_move`](Self::start_ai_move).
+    /// In the tutorial, there is no AI, no chaos start, no clock, and X always moves first.
+    fn start_game(&mut self) {
+        self.start_game_with_settings(self.config.game_settings());
+    }
+
+    /// Start a new game like [`start_game`](Self::start_game), but with the given game settings
+    /// instead of the ones from the config, which is left alone.
+    fn start_game_with_settings(&mut self, game_settings: UltimateGameSettings) {
+        self.game_settings = game_settings;
+        let ai_plays_first = self.tutorial_step.is_none()
+            && self.game_settings.playing_ai
+            && !self.game_settings.player_plays_first;
+        self.active_shape = if self.tutorial_step.is_some() {
+            CellShape::X
+        } else if ai_plays_first {
+            self.game_settings.player_shape.other()
+        } else {
+            self.game_settings.player_shape
+        };
+        let chaos_start = self.tutorial_step.is_none() && self.game_settings.chaos_start;
+        // The tutorial always uses the standard rules with a free opening, and a chaos start has
+        // already played the opening, so the opening rule doesn't apply
+        let (rules, opening, draw_rule) = if self.tutorial_step.is_some() {
+            (RuleSet::Standard, OpeningRule::Free, DrawRule::Draw)
+        } else if chaos_start {
+            (
+                self.game_settings.rules,
+                OpeningRule::Free,
+                self.game_settings.draw_rule,
+            )
+        } else {
+            (
+                self.game_settings.rules,
+                self.game_settings.opening,
+                self.game_settings.draw_rule,
+            )
+        };
+        self.global_board =
+            GlobalBoard::empty(self.game_settings.player_shape.other(), self.active_shape)
+                .with_rules(rules)
+                .with_win_condition(self.win_condition)
+                .with_draw_rule(draw_rule)
+                .with_opening(opening);
+        self.game_span = tracing::info_span!(
+            "game",
+            variant = variant_key::<N>(self.win_condition),
+            generation = self.generation,
+            rules = ?rules,
+            opening = ?opening,
+            draw_rule = ?draw_rule,
+            playing_ai = self.game_settings.playing_ai,
+            ai_player = ?self.config.ai_player,
+            tutorial = self.tutorial_step.is_some()
+        );
+        self.record = GameRecord::new(self.active_shape);
+        self.learning_positions.clear();
+        self.ai_task = None;
+        self.evaluation = None;
+        self.explanation = None;
+        self.turn_started_at = Instant::now();
+        self.game_ended_at = None;
+        self.illegal_move = None;
+        self.review = None;
+        self.drawings.clear();
+        self.blunder_check.cancel();
+        self.clock = if self.tutorial_step.is_some() {
+            None
+        } else {
+            self.game_settings.time_control.clock()
+        };
+
+        if chaos_start {
+            let (board, moves) = random_start(
+                &self.global_board,
+                CHAOS_PLIES,
+                &mut rand::thread_rng(),
+                |board| board.is_valid_position().is_ok(),
+                |board| board.is_balanced(CHAOS_BALANCE_EXPANSIONS, 1),
+            );
+            self.global_board = board;
+            self.record.moves = moves;
+            self.active_shape = self.global_board.to_move();
+        }
+
+        self.update_crash_context();
+        self.start_playing();
+    }
+
+    /// Start the clock and the AI move if it's the AI's turn, once the board of a new or loaded
+    /// game has been set up.
+    fn start_playing(&mut self) {
+        // If the AI is the second player, then it chooses the opening board straight away, which
+        // also starts its first move if it plays first. Otherwise, the player chooses it by
+        // clicking a local board. The clock starts once the opening board has been chosen
+        if self.global_board.awaiting_opening_choice() {
+            if self.ai_shape() == Some(self.active_shape.other()) {
+                let mut rng = rand::thread_rng();
+                self.choose_opening_board((rng.gen_range(0..N), rng.gen_range(0..N)));
+            }
+        } else {
+            self.start_clock();
+            if self.ai_shape() == Some(self.active_shape) {
+                self.start_ai_move();
+            }
+        }
+    }
+
+    /// Choose the local board that the first move must be played in, under
+    /// [`OpeningRule::SecondPlayerChooses`], and start an AI move if the AI plays first.
+    fn choose_opening_board(&mut self, coord: (usize, usize)) {
+        if self.global_board.choose_opening_board(coord).is_err() {
+            return;
+        }
+
+        self.update_crash_context();
+        tracing::debug!(parent: &self.game_span, board = ?coord, "opening board chosen");
+
+        self.turn_started_at = Instant::now();
+        self.start_clock();
+        if self.ai_shape() == Some(self.active_shape) {
+            self.start_ai_move();
         }
     }
 
     /// Update the interior state of the app with the current config.
     ///
-    /// See [`Self::new_with_config`]
+    /// Any AI move currently being computed is cancelled, and the
+    /// [`generation`](Self::generation) is incremented so that any stale AI moves will be ignored.
     fn restart_game(&mut self) {
-        *self = Self::new_with_config(self.config);
+        self.restart_game_with_settings(self.config.game_settings());
+    }
+
+    /// Start a new game like [`restart_game`](Self::restart_game), but with the given game
+    /// settings instead of the ones from the config.
+    fn restart_game_with_settings(&mut self, game_settings: UltimateGameSettings) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        if let Some(mut task) = self.evaluation_task.take() {
+            task.cancel();
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        self.showing_settings_window = false;
+        self.start_game_with_settings(game_settings);
+        self.events.emit(GameEvent::GameRestarted);
+    }
+
+    /// Start a rematch of the game that just ended with the sides swapped, so that the player
+    /// takes the other shape and the other turn to move first.
+    ///
+    /// The swap only applies to the rematch, so the config isn't changed, and the game after the
+    /// rematch goes back to the settings in the config.
+    fn rematch_swapped(&mut self) {
+        let mut game_settings = self.game_settings;
+        game_settings.player_shape = game_settings.player_shape.other();
+        game_settings.player_plays_first = !game_settings.player_plays_first;
+        self.restart_game_with_settings(game_settings);
+    }
+
+    /// Restart the game, unless there's a game in progress and
+    /// [`UltimateConfig::confirm_discard`] is set, in which case we ask for confirmation first.
+    fn request_restart(&mut self) {
+        if self.config.confirm_discard && self.game_in_progress() {
+            self.confirming_restart = true;
+        } else {
+            self.restart_game();
+        }
+    }
+
+    /// Show the dialog to confirm restarting a game in progress if we're waiting for
+    /// confirmation, and restart the game if it's confirmed.
+    fn draw_restart_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirming_restart {
+            return;
+        }
+
+        if let Some(discard) = show_discard_dialog(ctx, &mut self.config.confirm_discard) {
+            self.confirming_restart = false;
+            if discard {
+                self.restart_game();
+            }
+        }
+    }
+
+    /// Check if the player can [switch sides](Self::switch_sides) with the AI, which is only
+    /// possible in a game against the AI which is still going.
+    fn can_switch_sides(&self) -> bool {
+        self.ai_shape().is_some()
+            && self.review.is_none()
+            && self.flagged().is_none()
+            && !self.global_board.awaiting_opening_choice()
+            && self.global_board.get_winner() == GameResult::Ongoing
+    }
+
+    /// Hand the player's position to the AI and continue as the other shape.
+    ///
+    /// Any AI move currently being computed is cancelled, and the
+    /// [`generation`](Self::generation) is incremented so that it will be ignored. If it's then
+    /// the AI's turn, the AI starts a new move straight away.
+    fn switch_sides(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        if let Some(mut task) = self.evaluation_task.take() {
+            task.cancel();
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        self.game_settings.player_shape = self.game_settings.player_shape.other();
+        self.global_board
+            .set_ai_shape(self.game_settings.player_shape.other());
+        self.evaluation = None;
+        self.explanation = None;
+        self.update_crash_context();
+        tracing::info!(
+            parent: &self.game_span,
+            player_shape = ?self.game_settings.player_shape,
+            "switched sides"
+        );
+
+        if self.ai_shape() == Some(self.active_shape) {
+            self.start_ai_move();
+        }
+    }
+
+    /// Start the clock of the [`active_shape`](Self::active_shape), if the game is timed.
+    fn start_clock(&mut self) {
+        if let Some(clock) = &mut self.clock {
+            clock.start(self.active_shape);
+        }
+    }
+
+    /// Return the shape which ran out of time on the [`clock`](Self::clock), if either has, which
+    /// ends the game.
+    fn flagged(&self) -> Option<CellShape> {
+        self.clock.as_ref().and_then(GameClock::flagged)
+    }
+
+    /// Check if the game is over, either because the global board has been decided or because a
+    /// shape has [run out of time](Self::flagged).
+    fn game_over(&self) -> bool {
+        self.global_board.get_winner() != GameResult::Ongoing || self.flagged().is_some()
+    }
+
+    /// Cancel the AI move and the evaluation being computed, if there are any, and increment the
+    /// [`generation`](Self::generation) so that anything they've already sent is ignored.
+    fn stop_ai(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        if let Some(mut task) = self.evaluation_task.take() {
+            task.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Check if the shape to move has just run out of time, and end the game if it has, throwing
+    /// away any AI move being computed.
+    fn update_clock(&mut self) {
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        if clock.flagged().is_some() {
+            return;
+        }
+        let Some(shape) = clock.update() else {
+            return;
+        };
+
+        self.stop_ai();
+        self.game_ended_at = Some(Instant::now());
+        tracing::info!(
+            parent: &self.game_span,
+            shape = ?shape,
+            moves = ?self.record.moves,
+            "ran out of time"
+        );
+    }
+
+    /// In kiosk mode, restart the game once [`KIOSK_RESTART_DELAY`] has passed since it ended.
+    fn kiosk_auto_restart(&mut self) {
+        if self.kiosk
+            && self
+                .game_ended_at
+                .map_or(false, |time| time.elapsed() >= KIOSK_RESTART_DELAY)
+        {
+            self.restart_game();
+        }
+    }
+
+    /// Subscribe to the [`GameEvent`]s of this app, which will be sent down the returned receiver.
+    pub fn subscribe_to_events(&mut self) -> mpsc::Receiver<GameEvent<GlobalCoord>> {
+        self.events.subscribe()
+    }
+
+    /// Update the state of the game which is attached to crash reports. See
+    /// [`crash_report`](crate::shared::crash_report).
+    fn update_crash_context(&self) {
+        crash_report::set_context(&CrashContext {
+            variant: variant_key::<N>(self.win_condition),
+            board: &self.global_board,
+            config: &self.config,
+            record: &self.record,
+        });
+    }
+
+    /// Start computing an AI move in the background with an [`AiTask`].
+    ///
+    /// The move is sent down [`ai_moves`](Self::ai_moves) when it's ready, but no sooner than 750ms
+    /// after starting. If the AI had to search for the move, then the statistics of the search are
+    /// sent down [`evaluation_tx`](Self::evaluation_tx) as soon as the move has been found.
+    ///
+    /// If [`UltimateConfig::explain_ai_moves`] is enabled, then the explanation of the move is
+    /// sent down [`explanation_tx`](Self::explanation_tx) as soon as the move has been found.
+    ///
+    /// If [`UltimateConfig::show_ai_thinking`] is enabled, then snapshots of the search are
+    /// reported as the progress of the task while the move is being computed.
+    ///
+    /// If the game is timed, then the search stops when the AI's clock runs out, so the AI loses
+    /// on time if it's too slow, just like the player would.
+    fn start_ai_move(&mut self) {
+        if self.game_over() {
+            return;
+        }
+
+        let global_board = self.global_board.clone();
+        let playouts = self.config.mcts_playouts;
+        let personality = self.config.personality;
+        let draw_reward = self.config.draw_reward;
+        let generation = self.generation;
+        let evaluation_tx = self.evaluation_tx.clone();
+        let deadline = self
+            .clock
+            .as_ref()
+            .map(|clock| Instant::now() + clock.remaining(self.active_shape));
+        let explanation_tx = self
+            .config
+            .explain_ai_moves
+            .then(|| self.explanation_tx.clone());
+        let (weakest, strongest) = (
+            MIN_ADAPTIVE_EXPANSIONS as f32,
+            self.config.max_mcts_expansions as f32,
+        );
+
+        // The lowest levels of the ladder play like the random and greedy AIs, and the rest use
+        // MCTS with more expansions at each level
+        let (ai_player, max_iters) = match (self.config.ai_player, self.config.ladder.policy()) {
+            (UltimateAiPlayer::Ladder, LadderPolicy::Random) => {
+                (UltimateAiPlayer::Random, self.config.max_mcts_expansions)
+            }
+            (UltimateAiPlayer::Ladder, LadderPolicy::Greedy) => {
+                (UltimateAiPlayer::Greedy, self.config.max_mcts_expansions)
+            }
+            (UltimateAiPlayer::Ladder, LadderPolicy::Search { strength }) => (
+                UltimateAiPlayer::Ladder,
+                (weakest + strength * (strongest - weakest)).max(1.) as u16,
+            ),
+            (UltimateAiPlayer::Adaptive, _) => (
+                UltimateAiPlayer::Adaptive,
+                self.config.adaptive.interpolate(weakest, strongest).max(1.) as u16,
+            ),
+            (ai_player, _) => (ai_player, self.config.max_mcts_expansions),
+        };
+        let show_ai_thinking = self.config.show_ai_thinking;
+        let game_span = self.game_span.clone();
+        let learning = (self.config.learn_from_games
+            && !self.game_settings.chaos_start
+            && self.record.moves.len() < LEARNING_PLIES)
+            .then(|| self.learning.clone());
+
+        self.ai_task = Some(AiTask::spawn_with_progress(
+            AI_MOVE_DELAY,
+            self.ai_moves.sender(),
+            move |_, snapshot_tx| {
+                let span = tracing::info_span!(
+                    parent: &game_span,
+                    "ai_move",
+                    position_hash = global_board.position_hash(),
+                    ai_player = ?ai_player,
+                    iterations = max_iters,
+                    playouts
+                );
+                let _guard = span.enter();
+
+                // The random and greedy AIs don't consider any candidates
+                let (mv, candidates) = match ai_player {
+                    UltimateAiPlayer::Random => (RandomBot.choose_move(&global_board), vec![]),
+                    UltimateAiPlayer::Greedy => (GreedyBot.choose_move(&global_board), vec![]),
+                    UltimateAiPlayer::Mcts
+                    | UltimateAiPlayer::Adaptive
+                    | UltimateAiPlayer::Ladder => {
+                        let mut player = MctsPlayer {
+                            deadline,
+                            personality,
+                            draw_reward,
+                            ..MctsPlayer::new(max_iters, playouts)
+                        };
+                        let mv = if show_ai_thinking {
+                            player.choose_move_with_snapshots(&global_board, &snapshot_tx)
+                        } else {
+                            player.choose_move(&global_board)
+                        };
+                        let mv = learning
+                            .as_ref()
+                            .zip(player.last_stats.as_ref())
+                            .and_then(|(learning, stats)| {
+                                learned_move(learning, &global_board, stats)
+                            })
+                            .or(mv);
+                        let candidates = player
+                            .last_stats
+                            .as_ref()
+                            .map_or_else(Vec::new, RootStats::annotated_moves);
+                        if let Some(stats) = player.last_stats {
+                            tracing::info!(
+                                wins = stats.wins,
+                                playouts = stats.playouts,
+                                children = ?stats.children,
+                                "searched the root"
+                            );
+                            let _ =
+                                evaluation_tx.send((generation, global_board.clone(), Some(stats)));
+                        }
+                        (mv, candidates)
+                    }
+                };
+
+                tracing::info!(chosen = ?mv, "chose a move");
+
+                if let (Some(explanation_tx), Some(mv)) = (explanation_tx, mv) {
+                    let _ = explanation_tx.send((
+                        generation,
+                        MoveExplanation::new(&global_board, mv, candidates),
+                    ));
+                }
+
+                (generation, mv)
+            },
+        ));
+    }
+
+    /// Check if we're currently waiting for the AI to make a move, which is exactly while there is
+    /// an [`ai_task`](Self::ai_task).
+    fn waiting_on_move(&self) -> bool {
+        self.ai_task.is_some()
+    }
+
+    /// Start evaluating the current position in the background with an [`AiTask`].
+    ///
+    /// The evaluation is sent down [`evaluation_tx`](Self::evaluation_tx) when it's ready.
+    fn start_evaluation(&mut self) {
+        let global_board = self.global_board.clone();
+        let shape_to_play = self.active_shape;
+        let max_iters = self.config.max_mcts_expansions;
+        let playouts = self.config.mcts_playouts;
+        let generation = self.generation;
+        let evaluation_tx = self.evaluation_tx.clone();
+
+        self.evaluation_task = Some(AiTask::spawn(Duration::ZERO, evaluation_tx, move |_| {
+            let stats = global_board.evaluate(shape_to_play, max_iters, playouts);
+            (generation, global_board, stats)
+        }));
+    }
+
+    /// Update the [`evaluation`](Self::evaluation) with the most recent one for the current game,
+    /// if any have arrived.
+    ///
+    /// Any evaluations from previous [`generation`](Self::generation)s are discarded.
+    fn receive_evaluation(&mut self) {
+        let generation = self.generation;
+
+        if let Some(evaluation) = self
+            .evaluation_rx
+            .try_iter()
+            .filter_map(|(g, board, stats)| match stats {
+                Some(stats) if g == generation => Some((board, stats)),
+                _ => None,
+            })
+            .last()
+        {
+            self.evaluation = Some(evaluation);
+        }
+    }
+
+    /// Return the AI move for the current game if it has arrived, which is `Some(None)` if the AI
+    /// couldn't find a move.
+    ///
+    /// Any moves from previous [`generation`](Self::generation)s are discarded.
+    fn receive_ai_move(&self) -> Option<Option<GlobalCoord>> {
+        self.ai_moves.receive(self.generation)
+    }
+
+    /// Play the AI move if it has arrived.
+    fn handle_ai_move(&mut self) {
+        if !self.waiting_on_move() {
+            return;
+        }
+
+        if let Some(coord) = self.receive_ai_move() {
+            self.ai_task = None;
+            if let Some(coord) = coord {
+                let _ = self.update_cell(coord);
+            }
+        }
+    }
+
+    /// Return the share of the visits of each move in the most recent snapshot of the search of
+    /// the AI move, if any have arrived.
+    fn receive_ai_snapshot(&mut self) -> Vec<(GlobalCoord, f64)> {
+        self.ai_task
+            .as_mut()
+            .and_then(AiTask::progress)
+            .map_or_else(Vec::new, RootStats::visit_shares)
+    }
+
+    /// Update the [`explanation`](Self::explanation) with the most recent one for the current
+    /// game, if any have arrived.
+    ///
+    /// Any explanations from previous [`generation`](Self::generation)s are discarded.
+    fn receive_explanation(&mut self) {
+        let generation = self.generation;
+
+        if let Some((_, explanation)) = self
+            .explanation_rx
+            .try_iter()
+            .filter(|&(g, _)| g == generation)
+            .last()
+        {
+            self.explanation = Some(explanation);
+        }
+    }
+
+    /// Return every legal move along with the proportion of MCTS playouts after that move which
+    /// were won by the [`active_shape`](Self::active_shape), if the most recent
+    /// [`evaluation`](Self::evaluation) is of the current position.
+    fn heatmap(&self) -> Option<Vec<(GlobalCoord, f64)>> {
+        match &self.evaluation {
+            Some((board, stats)) if *board == self.global_board => Some(stats.move_win_rates()),
+            _ => None,
+        }
+    }
+
+    /// Check if the player will get a free extra move after their next move, because of the
+    /// [`UltimateConfig::player_extra_move`] handicap.
+    fn extra_move_pending(&self) -> bool {
+        self.tutorial_step.is_none()
+            && self.game_settings.playing_ai
+            && self.game_settings.player_extra_move
+            && self.record.skipped_turns.is_empty()
+            && self.active_shape == self.game_settings.player_shape
+    }
+
+    /// Check if the player is currently playing their free extra move.
+    fn playing_extra_move(&self) -> bool {
+        self.record.skipped_turns.last() == Some(&self.record.moves.len())
+    }
+
+    /// Skip the turn of the [`active_shape`](Self::active_shape), so that the other shape moves
+    /// next. See [`GlobalBoard::skip_turn`].
+    fn skip_turn(&mut self) {
+        self.global_board.skip_turn();
+        self.record.skip_turn();
+        self.active_shape = self.active_shape.other();
+        self.start_clock();
+    }
+
+    /// Return the shape of the AI, if we're playing against it. There is no AI in the tutorial.
+    fn ai_shape(&self) -> Option<CellShape> {
+        (self.game_settings.playing_ai && self.tutorial_step.is_none())
+            .then(|| self.game_settings.player_shape.other())
+    }
+
+    /// Describe the state of the game in words for the status bar, like whose turn it is, which
+    /// shape the AI is, and which local board has to be played in.
+    pub fn status_text(&self) -> String {
+        if let Some(shape) = self.flagged() {
+            return format!("{shape:?} ran out of time, so {:?} wins", shape.other());
+        }
+
+        let location = match self.global_board.next_local_board() {
+            _ if self.global_board.awaiting_opening_choice() => {
+                format!("in the board that {:?} chooses", self.active_shape.other())
+            }
+            Some(coord) => format!("in the {} board", local_board_name::<N>(coord)),
+            None => "anywhere".to_string(),
+        };
+
+        status_text(
+            self.global_board.get_winner(),
+            self.active_shape,
+            self.ai_shape(),
+            Some(&location),
+        )
     }
 
     /// Update the board to reflect a cell being clicked.
     ///
     /// This method uses [`active_shape`](UltimateTTTApp::active_shape) as the shape to place in
-    /// the cell and [`GlobalBoard::make_move`] to actually make the move, ignoring any error.
-    fn update_cell(&mut self, coord: GlobalCoord) {
-        if self
-            .global_board
-            .make_move(coord, self.active_shape)
-            .is_ok()
-        {
-            self.active_shape = self.active_shape.other();
+    /// the cell and [`GlobalBoard::make_move`] to actually make the move, returning any error so
+    /// that the GUI can explain why the move is illegal.
+    ///
+    /// Once the game is over, every move is rejected with [`UpdateCellError::GameOver`], and the
+    /// move that ends the game also [stops the AI](Self::stop_ai).
+    fn update_cell(&mut self, coord: GlobalCoord) -> Result<(), UpdateCellError> {
+        let (x, y, local_coord) = coord;
+
+        if self.game_over() {
+            return Err(UpdateCellError::GameOver);
+        }
+        self.global_board.make_move(coord, self.active_shape)?;
+
+        self.record.push_move(coord, self.turn_started_at.elapsed());
+        self.turn_started_at = Instant::now();
+        self.update_crash_context();
+        tracing::debug!(
+            parent: &self.game_span,
+            mv = ?coord,
+            shape = ?self.active_shape,
+            "move played"
+        );
+        self.events.emit(GameEvent::MovePlayed {
+            mv: coord,
+            shape: self.active_shape,
+        });
+
+        // The cell was empty before this move, so if the winning line goes through it, then this
+        // move must have won the local board
+        if let GameResult::Won { shape, line } = self.global_board.has_local_board_won(x, y) {
+            if line.contains(&local_coord) {
+                self.events.emit(GameEvent::LocalBoardWon {
+                    coord: (x, y),
+                    shape,
+                });
+            }
+        }
+
+        if self.ai_shape() == Some(self.active_shape) && self.record.moves.len() <= LEARNING_PLIES {
+            self.learning_positions
+                .push(self.global_board.position_hash());
+        }
+
+        self.active_shape = self.active_shape.other();
+
+        let result = self.global_board.get_winner();
+        if let Some(clock) = &mut self.clock {
+            if result == GameResult::Ongoing {
+                clock.press();
+            } else {
+                clock.stop();
+            }
+        }
+
+        if result != GameResult::Ongoing {
+            tracing::info!(
+                parent: &self.game_span,
+                result = ?result,
+                moves = ?self.record.moves,
+                "game ended"
+            );
+            self.events.emit(GameEvent::GameEnded(result));
+            self.game_ended_at = Some(Instant::now());
+            self.stop_ai();
+
+            // Nobody is there to look at the mistakes in kiosk mode, and the tutorial has its own
+            // explanations
+            if !self.kiosk && self.tutorial_step.is_none() {
+                self.start_blunder_check();
+            }
+
+            if self.game_settings.playing_ai && self.config.ai_player == UltimateAiPlayer::Adaptive
+            {
+                self.config
+                    .adaptive
+                    .record_result(result, self.game_settings.player_shape);
+            }
+
+            // The AI didn't choose the opening of a chaos start, so there's nothing to learn
+            if self.config.learn_from_games && !self.game_settings.chaos_start {
+                if let Some(ai_shape) = self.ai_shape() {
+                    self.learning
+                        .record_game(&self.learning_positions, result, ai_shape);
+                }
+            }
+
+            // A handicap would make it too easy to climb the ladder
+            if self.game_settings.playing_ai
+                && self.config.ai_player == UltimateAiPlayer::Ladder
+                && !self.game_settings.player_extra_move
+            {
+                self.config
+                    .ladder
+                    .record_result(result, self.game_settings.player_shape);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the given saved game by replaying its moves with the game settings that it was started
+    /// with, and return whether it was loaded. The config itself isn't changed, and the clock
+    /// starts again from the full time.
+    ///
+    /// If any of the moves are illegal, then nothing is loaded. If it's then the AI's turn, the AI
+    /// starts a move straight away.
+    fn load_saved_game(&mut self, saved: &SavedGame<UltimateConfig, GlobalCoord>) -> bool {
+        let game_settings = saved.config.game_settings();
+        let mut start =
+            GlobalBoard::<N>::empty(game_settings.player_shape.other(), saved.record.first_shape)
+                .with_rules(game_settings.rules)
+                .with_win_condition(self.win_condition)
+                .with_draw_rule(game_settings.draw_rule)
+                .with_opening(if game_settings.chaos_start {
+                    OpeningRule::Free
+                } else {
+                    game_settings.opening
+                });
+        // The first move is always in the opening board, and this fails harmlessly if there
+        // wasn't one to choose
+        if let Some(&(x, y, _)) = saved.record.moves.first() {
+            let _ = start.choose_opening_board((x, y));
+        }
+        let mut positions = saved.record.replay(start);
+        if positions.len() != saved.record.moves.len() + 1 {
+            return false;
+        }
+
+        // The moves of a chaos start are already in the record
+        self.tutorial_step = None;
+        self.restart_game_with_settings(UltimateGameSettings {
+            chaos_start: false,
+            ..game_settings
+        });
+
+        // Throw away any AI move that was started for the empty board
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
         }
+        self.generation = self.generation.wrapping_add(1);
+
+        self.global_board = positions
+            .pop()
+            .expect("There should always be a starting position");
+        self.active_shape = self.global_board.to_move();
+        self.record = saved.record.clone();
+        self.turn_started_at = Instant::now();
+        self.clock = game_settings.time_control.clock();
+        self.update_crash_context();
+
+        if self.global_board.get_winner() == GameResult::Ongoing {
+            self.start_playing();
+        }
+        true
     }
 }
 
-impl TTTVariantApp for UltimateTTTApp {
+impl<const N: usize> TTTVariantApp for UltimateTTTApp<N> {
     fn new_app(storage: Option<&dyn eframe::Storage>) -> Self
     where
         Self: Sized,
     {
-        let config = storage.map_or_else(UltimateConfig::default, |storage| {
-            eframe::get_value(storage, "ultimate_config").unwrap_or_default()
-        });
+        Self::load(storage, WinCondition::LineOfBoards)
+    }
 
-        Self::new_with_config(config)
+    fn tick(&mut self) {
+        self.kiosk_auto_restart();
+        self.update_clock();
+        self.handle_ai_move();
+        self.receive_evaluation();
     }
 
     fn show_ui(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        self.tick();
+
+        // Kiosk mode and presentation mode have no settings, so they don't need the side panel
+        let wide_layout = !self.kiosk && !self.presenting && is_wide_layout(ctx);
+        if wide_layout {
+            self.draw_side_panel(ctx);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            // Presentation mode has no buttons, just a big turn indicator and the clock above the
+            // board
+            if self.presenting {
+                show_turn_indicator(ui, &self.status_text());
+                ui.horizontal(|ui| self.show_clock(ui));
+                let board_rect = centered_square_in_rect(ui.available_rect_before_wrap(), 0.95);
+                self.draw_global_board(ctx, ui, board_rect);
+                return;
+            }
+
             // Show the restart game and settings buttons
             ui.horizontal(|ui| {
                 use eframe::epaint::{FontFamily, FontId};
@@ -170,44 +1216,541 @@ impl TTTVariantApp for UltimateTTTApp {
                     .add(egui::Button::new("\u{27F3}").fill(Color32::TRANSPARENT))
                     .clicked()
                 {
-                    self.restart_game();
+                    if self.tutorial_step.is_some() {
+                        self.end_tutorial();
+                    } else {
+                        self.request_restart();
+                    }
                 }
 
-                if ui
-                    .add(
-                        egui::Button::new("\u{2699}").fill(if self.showing_settings_window {
-                            if ctx.style().visuals.dark_mode {
-                                Color32::from_rgb(0x00, 0x5C, 0x80)
+                if !wide_layout
+                    && !self.kiosk
+                    && ui
+                        .add(
+                            egui::Button::new("\u{2699}").fill(if self.showing_settings_window {
+                                if ctx.style().visuals.dark_mode {
+                                    Color32::from_rgb(0x00, 0x5C, 0x80)
+                                } else {
+                                    Color32::from_rgb(0x90, 0xD1, 0xFF)
+                                }
                             } else {
-                                Color32::from_rgb(0x90, 0xD1, 0xFF)
-                            }
-                        } else {
-                            Color32::TRANSPARENT
-                        }),
-                    )
-                    .clicked()
+                                Color32::TRANSPARENT
+                            }),
+                        )
+                        .clicked()
                 {
                     self.showing_settings_window = !self.showing_settings_window;
                 }
+
+                // The tutorial teaches the rules of ultimate tic-tac-toe on the full board, so it
+                // doesn't make sense in nine-board or 2x2 tic-tac-toe
+                if N == 3
+                    && self.win_condition == WinCondition::LineOfBoards
+                    && ui
+                        .add_enabled(
+                            self.tutorial_step.is_none(),
+                            egui::Button::new("?").fill(Color32::TRANSPARENT),
+                        )
+                        .on_hover_text("Learn the rules with a tutorial")
+                        .clicked()
+                {
+                    self.start_tutorial();
+                }
+
+                if !self.kiosk {
+                    show_export_buttons(ui, |size| ultimate_board_shapes(&self.global_board, size));
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if self.global_board.get_winner() != GameResult::Ongoing {
+                        crate::shared::export::show_gif_export_button(ui, |size| {
+                            crate::shared::export::ultimate_game_frames::<N>(&self.record, size)
+                        });
+                    }
+                }
+
+                if !self.kiosk
+                    && self.tutorial_step.is_none()
+                    && self.review.is_none()
+                    && self.global_board.get_winner() != GameResult::Ongoing
+                    && ui
+                        .add(egui::Button::new("\u{1F50D}").fill(Color32::TRANSPARENT))
+                        .on_hover_text("Review the game and see what the AI would have done")
+                        .clicked()
+                {
+                    self.start_review();
+                }
+
+                if !self.kiosk
+                    && self.tutorial_step.is_none()
+                    && self.review.is_none()
+                    && ui
+                        .add(egui::Button::new("\u{1F4BE}").fill(Color32::TRANSPARENT))
+                        .on_hover_text("Save and load games")
+                        .clicked()
+                {
+                    self.save_slots.open = !self.save_slots.open;
+                }
+
+                if !self.kiosk
+                    && ui
+                        .add_enabled(
+                            self.can_switch_sides(),
+                            egui::Button::new("\u{21C4}").fill(Color32::TRANSPARENT),
+                        )
+                        .on_hover_text("Switch sides with the AI")
+                        .clicked()
+                {
+                    self.switch_sides();
+                }
+
+                self.drawings.show_clear_button(ui);
+
+                if self.extra_move_pending() {
+                    ui.label("Handicap: you get a free extra move after your first move");
+                } else if self.playing_extra_move() {
+                    ui.label("Free move! Play again");
+                }
+
+                if self.config.show_evaluation_bar
+                    && ui
+                        .add_enabled(
+                            !self.waiting_on_move()
+                                && self
+                                    .evaluation_task
+                                    .as_ref()
+                                    .map_or(true, AiTask::is_finished),
+                            egui::Button::new("Evaluate").fill(Color32::TRANSPARENT),
+                        )
+                        .clicked()
+                {
+                    self.start_evaluation();
+                }
+
+                #[cfg(feature = "devtools")]
+                self.tree_debugger.show_button(ui);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label(self.status_text());
+                ui.separator();
+                ui.label(self.global_board.local_summary().to_string())
+                    .on_hover_text("The number of local boards won by each shape");
+                self.show_clock(ui);
             });
+            if self.global_board.get_winner() != GameResult::Ongoing || self.flagged().is_some() {
+                self.record.show_timing_stats(ui);
+                if let Some(ply) = self
+                    .blunder_check
+                    .show(ui, |(x, y, (lx, ly))| cell_name((3 * x + lx, 3 * y + ly)))
+                {
+                    self.jump_to_ply(ply);
+                }
 
-            self.draw_global_board(ctx, ui, centered_square_in_rect(ui.clip_rect(), 0.9));
+                // The tutorial and the review have their own ways to carry on
+                if !self.kiosk
+                    && self.tutorial_step.is_none()
+                    && self.review.is_none()
+                    && ui
+                        .button("Rematch (swap sides)")
+                        .on_hover_text("Play again as the other shape, and swap who moves first")
+                        .clicked()
+                {
+                    self.rematch_swapped();
+                }
+            }
+
+            let board_rect = if wide_layout {
+                centered_square_in_rect(ui.available_rect_before_wrap(), 0.95)
+            } else {
+                centered_square_in_rect(ui.clip_rect(), 0.9)
+            };
+
+            // The wide layout shows the evaluation bar in the side panel instead
+            if self.config.show_evaluation_bar && !wide_layout {
+                let width = board_rect.width() / 40.;
+                self.draw_evaluation_bar(
+                    ui,
+                    Rect::from_min_max(
+                        Pos2::new(board_rect.min.x - 2. * width, board_rect.min.y),
+                        Pos2::new(board_rect.min.x - width, board_rect.max.y),
+                    ),
+                );
+            }
+
+            self.draw_global_board(ctx, ui, board_rect);
+
+            // The wide layout shows the mini-map in the side panel instead
+            if self.config.show_mini_map && !wide_layout {
+                let clip_rect = ui.clip_rect();
+                self.draw_mini_map(
+                    ui,
+                    Rect::from_min_size(
+                        Pos2::new(clip_rect.max.x - MINI_MAP_SIZE, clip_rect.min.y),
+                        Vec2::splat(MINI_MAP_SIZE),
+                    ),
+                );
+            }
         });
 
-        if self.showing_settings_window {
+        self.receive_explanation();
+        if let Some(explanation) = &self.explanation {
+            // Wait until the move is actually shown to explain it
+            if self.config.explain_ai_moves && !self.waiting_on_move() && !self.presenting {
+                explanation
+                    .show_window(ctx, |(x, y, (lx, ly))| cell_name((3 * x + lx, 3 * y + ly)));
+            }
+        }
+
+        self.draw_tutorial_window(ctx);
+        self.draw_review_window(ctx);
+
+        #[cfg(feature = "devtools")]
+        self.tree_debugger
+            .show(ctx, &self.global_board, self.active_shape, &self.config);
+
+        if !self.kiosk && !self.presenting && self.tutorial_step.is_none() && self.review.is_none()
+        {
+            show_file_drop_hint(ctx, "Drop a game file to analyse it");
+            let dropped = self.save_slots.receive_dropped_file(ctx);
+            let loaded = self.save_slots.show_window(ctx, || SavedGame {
+                config: self.config.with_game_settings(self.game_settings),
+                record: self.record.clone(),
+            });
+
+            // Games from files are opened in the review, so that they can be stepped through and
+            // analysed
+            match loaded.or(dropped.map(LoadedGame::File)) {
+                Some(LoadedGame::Slot(saved)) => {
+                    self.load_saved_game(&saved);
+                }
+                Some(LoadedGame::File(saved)) => {
+                    if self.load_saved_game(&saved) {
+                        self.start_review();
+                    }
+                }
+                None => (),
+            }
+        }
+
+        if self.showing_settings_window && !wide_layout && !self.presenting {
             self.draw_settings_window(ctx);
         }
+
+        self.draw_restart_dialog(ctx);
+    }
+
+    fn game_in_progress(&self) -> bool {
+        self.tutorial_step.is_none()
+            && !self.record.moves.is_empty()
+            && self.flagged().is_none()
+            && self.global_board.get_winner() == GameResult::Ongoing
+    }
+
+    fn confirm_discard_mut(&mut self) -> &mut bool {
+        &mut self.config.confirm_discard
+    }
+
+    fn short_status(&self) -> String {
+        if let Some(shape) = self.flagged() {
+            return match self.ai_shape() {
+                Some(ai_shape) if ai_shape == shape => "you win on time".to_string(),
+                Some(_) => "the AI wins on time".to_string(),
+                None => format!("{:?} wins on time", shape.other()),
+            };
+        }
+
+        short_status_text(
+            self.global_board.get_winner(),
+            self.active_shape,
+            self.ai_shape(),
+        )
+    }
+
+    fn apply_launch_options(&mut self, options: &LaunchOptions) {
+        if let Some(playing_ai) = options.playing_ai {
+            self.config.playing_ai = playing_ai;
+        }
+        if let Some(difficulty) = options.difficulty {
+            self.config.ai_player = match difficulty {
+                Difficulty::Easy => UltimateAiPlayer::Random,
+                Difficulty::Medium => UltimateAiPlayer::Greedy,
+                Difficulty::Hard => UltimateAiPlayer::Mcts,
+                Difficulty::Adaptive => UltimateAiPlayer::Adaptive,
+            };
+        }
+
+        self.kiosk = options.kiosk;
+
+        self.restart_game();
+    }
+
+    fn set_presentation_mode(&mut self, presenting: bool) {
+        self.presenting = presenting;
+    }
+
+    fn subscribe_to_results(&mut self) -> Option<mpsc::Receiver<GameResult>> {
+        Some(self.events.subscribe_to_results())
     }
 
     fn save_config(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, "ultimate_config", &self.config);
+        let mut settings = VariantSettings {
+            config: self.config,
+            profiles: self.profiles.clone(),
+        };
+        settings.save(storage, variant_key::<N>(self.win_condition));
+        self.save_slots
+            .save(storage, variant_key::<N>(self.win_condition));
+        self.learning
+            .save(storage, variant_key::<N>(self.win_condition));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ultimate::test_utils::make_global_board;
+    use crate::{
+        shared::clock::TimeControl,
+        ultimate::{board::MoveStats, test_utils::make_global_board},
+    };
+
+    #[test]
+    fn events_test() {
+        let mut app = UltimateTTTApp::default();
+        let events = app.subscribe_to_events();
+        app.global_board = make_global_board! {
+            next = (0, 0),
+            (X X _; O O _; _) () ();
+            () () ();
+            () () ();
+        };
+
+        app.update_cell((0, 0, (2, 0))).unwrap();
+        app.update_cell((2, 0, (0, 0))).unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                GameEvent::MovePlayed {
+                    mv: (0, 0, (2, 0)),
+                    shape: CellShape::X
+                },
+                GameEvent::LocalBoardWon {
+                    coord: (0, 0),
+                    shape: CellShape::X
+                },
+                GameEvent::MovePlayed {
+                    mv: (2, 0, (0, 0)),
+                    shape: CellShape::O
+                },
+            ]
+        );
+
+        app.restart_game();
+        assert_eq!(events.try_recv(), Ok(GameEvent::GameRestarted));
+    }
+
+    #[test]
+    fn status_text_test() {
+        let mut app = UltimateTTTApp::default();
+        app.global_board = make_global_board! {
+            next = (0, 0),
+            (X X _; O O _; _) () ();
+            () () ();
+            () () ();
+        };
+        app.active_shape = CellShape::X;
+        assert_eq!(app.status_text(), "X to play in the top-left board");
+
+        app.update_cell((0, 0, (2, 0))).unwrap();
+        assert_eq!(app.status_text(), "O to play in the top-right board");
+    }
+
+    #[test]
+    fn opening_rule_test() {
+        // The AI is the second player, so it chooses the opening board for the player
+        let app = UltimateTTTApp::new_with_config(UltimateConfig {
+            playing_ai: true,
+            opening: OpeningRule::SecondPlayerChooses,
+            ..UltimateConfig::default()
+        });
+        assert!(!app.global_board.awaiting_opening_choice());
+        assert!(app.global_board.next_local_board().is_some());
+        assert!(!app.waiting_on_move());
+
+        // The player is the second player, so the AI waits for them to choose
+        let mut app = UltimateTTTApp::new_with_config(UltimateConfig {
+            playing_ai: true,
+            player_plays_first: false,
+            opening: OpeningRule::SecondPlayerChooses,
+            ..UltimateConfig::default()
+        });
+        assert!(app.global_board.awaiting_opening_choice());
+        assert!(!app.waiting_on_move());
+        assert_eq!(
+            app.status_text(),
+            "O to play in the board that X chooses - you are X, the AI is O"
+        );
+
+        app.choose_opening_board((2, 2));
+        assert_eq!(app.global_board.next_local_board(), Some((2, 2)));
+        assert!(app.waiting_on_move());
+    }
+
+    #[test]
+    fn chaos_start_test() {
+        // The opening rule is ignored, since the opening has already been played
+        let app = UltimateTTTApp::new_with_config(UltimateConfig {
+            chaos_start: true,
+            opening: OpeningRule::SecondPlayerChooses,
+            ..UltimateConfig::default()
+        });
+        assert!(!app.global_board.awaiting_opening_choice());
+        assert!(CHAOS_PLIES.contains(&app.record.moves.len()));
+        assert_eq!(app.global_board.get_winner(), GameResult::Ongoing);
+        assert!(app.global_board.is_valid_position().is_ok());
+        assert_eq!(app.active_shape, app.global_board.to_move());
+        assert_eq!(
+            app.record.replay(GlobalBoard::new(CellShape::O)).len(),
+            app.record.moves.len() + 1
+        );
+    }
+
+    #[test]
+    fn blitz_test() {
+        let mut app = UltimateTTTApp::new_with_config(UltimateConfig {
+            time_control: TimeControl::Blitz,
+            ..UltimateConfig::default()
+        });
+        let clock = app.clock.as_ref().unwrap();
+        assert_eq!(clock.running(), Some(CellShape::X));
+        assert_eq!(clock.remaining(CellShape::O), Duration::from_secs(60));
+
+        // X gets the increment after its move
+        app.update_cell((1, 1, (0, 0))).unwrap();
+        let clock = app.clock.as_ref().unwrap();
+        assert_eq!(clock.running(), Some(CellShape::O));
+        assert!(clock.remaining(CellShape::X) > Duration::from_secs(60));
+
+        // O runs out of time and loses
+        let mut clock = GameClock::new(Duration::ZERO, Duration::ZERO);
+        clock.start(CellShape::O);
+        app.clock = Some(clock);
+        app.update_clock();
+        assert_eq!(app.flagged(), Some(CellShape::O));
+        assert!(!app.game_in_progress());
+        assert_eq!(app.status_text(), "O ran out of time, so X wins");
+        assert_eq!(app.short_status(), "X wins on time");
+
+        // The tutorial is never timed
+        app.start_tutorial();
+        assert_eq!(app.clock, None);
+    }
+
+    #[test]
+    fn load_saved_game_test() {
+        let mut app = UltimateTTTApp::default();
+        app.update_cell((1, 1, (0, 0))).unwrap();
+        app.update_cell((0, 0, (1, 1))).unwrap();
+        let saved = SavedGame {
+            config: app.config.with_game_settings(app.game_settings),
+            record: app.record.clone(),
+        };
+
+        app.restart_game();
+        assert!(app.load_saved_game(&saved));
+        assert_eq!(
+            app.global_board.local_boards()[0][0].cells()[1][1],
+            Some(CellShape::O)
+        );
+        assert_eq!(app.global_board.next_local_board(), Some((1, 1)));
+        assert_eq!(app.active_shape, CellShape::X);
+        assert_eq!(app.record.moves, vec![(1, 1, (0, 0)), (0, 0, (1, 1))]);
+
+        // A game with an illegal move isn't loaded
+        let mut illegal = saved.clone();
+        illegal.record.moves.push((2, 2, (0, 0)));
+        app.restart_game();
+        assert!(!app.load_saved_game(&illegal));
+        assert!(app.record.moves.is_empty());
+    }
+
+    #[test]
+    fn rematch_swapped_test() {
+        let mut app = UltimateTTTApp::new_with_config(UltimateConfig {
+            playing_ai: true,
+            ..UltimateConfig::default()
+        });
+        app.update_cell((1, 1, (0, 0))).unwrap();
+
+        // The player was X and moved first, so the AI is X and moves first in the rematch
+        app.rematch_swapped();
+        assert_eq!(app.ai_shape(), Some(CellShape::X));
+        assert_eq!(app.active_shape, CellShape::X);
+        assert!(app.record.moves.is_empty());
+        assert!(app.waiting_on_move());
+        assert_eq!(app.config.player_shape, CellShape::X);
+        assert!(app.config.player_plays_first);
+
+        // The game after the rematch goes back to the config
+        app.restart_game();
+        assert_eq!(app.ai_shape(), Some(CellShape::O));
+        assert!(!app.waiting_on_move());
+    }
+
+    #[test]
+    fn nine_board_test() {
+        let mut app = UltimateTTTApp::new_nine_board_app(None);
+        assert_eq!(app.global_board.win_condition(), WinCondition::AnyBoard);
+
+        for mv in [
+            (1, 1, (0, 1)),
+            (0, 1, (2, 2)),
+            (2, 2, (0, 0)),
+            (0, 0, (2, 2)),
+            (2, 2, (1, 0)),
+            (1, 0, (2, 2)),
+            (2, 2, (2, 0)),
+        ] {
+            app.update_cell(mv).unwrap();
+        }
+        assert_eq!(app.short_status(), "X wins");
+        assert!(app.game_ended_at.is_some());
+
+        // The board is frozen once the game is over
+        assert_eq!(
+            app.update_cell((2, 0, (0, 0))),
+            Err(UpdateCellError::GameOver)
+        );
+        assert!(!app.waiting_on_move());
+
+        // Restarting keeps the win condition
+        app.restart_game();
+        assert_eq!(app.global_board.win_condition(), WinCondition::AnyBoard);
+    }
+
+    #[test]
+    fn small_ultimate_test() {
+        let mut app = UltimateTTTApp::<2>::new_with_config_and_win_condition(
+            UltimateConfig {
+                playing_ai: false,
+                ..UltimateConfig::default()
+            },
+            WinCondition::LineOfBoards,
+        );
+        assert!(!app.game_in_progress());
+        assert_eq!(app.status_text(), "X to play anywhere");
+
+        app.update_cell((0, 0, (1, 0))).unwrap();
+        assert!(app.game_in_progress());
+        assert_eq!(app.status_text(), "O to play in the top-right board");
+        assert_eq!(
+            app.update_cell((0, 0, (0, 0))),
+            Err(UpdateCellError::Illegal(MoveError::WrongLocalBoard))
+        );
+
+        // Sending the next player off the grid lets them play anywhere
+        app.update_cell((1, 0, (2, 1))).unwrap();
+        assert_eq!(app.status_text(), "X to play anywhere");
+    }
 
     #[test]
     fn update_cell_test() {
@@ -263,7 +1806,7 @@ mod tests {
         assert_eq!(app.global_board, GlobalBoard::default());
 
         for (coord, global_board) in moves_map {
-            app.update_cell(coord);
+            let _ = app.update_cell(coord);
             assert_eq!(
                 app.global_board, global_board,
                 "coord = {:?}; global_board = {:?}",
@@ -271,4 +1814,143 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn receive_ai_snapshot_test() {
+        let mut app = UltimateTTTApp::default();
+        assert_eq!(app.receive_ai_snapshot(), vec![]);
+
+        let snapshot = |playouts| RootStats {
+            ai_shape: CellShape::O,
+            shape_to_play: CellShape::O,
+            wins: 0.,
+            playouts,
+            children: vec![MoveStats {
+                mv: (1, 1, (1, 1)),
+                wins: 0.,
+                playouts,
+            }],
+        };
+        let (tx, rx) = mpsc::channel();
+        app.ai_task = Some(AiTask::spawn_with_progress(
+            Duration::ZERO,
+            tx,
+            move |_, snapshot_tx| {
+                snapshot_tx.send(snapshot(1)).unwrap();
+                snapshot_tx.send(snapshot(2)).unwrap();
+            },
+        ));
+        rx.recv().unwrap();
+
+        // Only the latest snapshot is kept, and it's still shown until a new one arrives
+        assert_eq!(app.receive_ai_snapshot(), vec![((1, 1, (1, 1)), 1.)]);
+        assert_eq!(
+            app.ai_task.as_mut().unwrap().progress().unwrap().playouts,
+            2
+        );
+        assert_eq!(app.receive_ai_snapshot(), vec![((1, 1, (1, 1)), 1.)]);
+
+        app.restart_game();
+        assert_eq!(app.receive_ai_snapshot(), vec![]);
+    }
+
+    #[test]
+    fn tick_test() {
+        let mut app = UltimateTTTApp::default();
+
+        // Nothing happens until the AI's move arrives
+        app.ai_task = Some(AiTask::placeholder());
+        app.tick();
+        assert!(app.waiting_on_move());
+
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((1, 1, (1, 1)))))
+            .unwrap();
+        app.tick();
+        assert!(!app.waiting_on_move());
+        assert_eq!(app.record.moves, vec![(1, 1, (1, 1))]);
+    }
+
+    #[test]
+    fn receive_ai_move_test() {
+        let mut app = UltimateTTTApp::default();
+        assert_eq!(app.receive_ai_move(), None);
+
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((1, 1, (0, 2)))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), Some(Some((1, 1, (0, 2)))));
+
+        let old_generation = app.generation;
+        app.restart_game();
+        assert_ne!(app.generation, old_generation);
+
+        app.ai_moves
+            .sender()
+            .send((old_generation, Some((0, 0, (0, 0)))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), None);
+
+        app.ai_moves
+            .sender()
+            .send((old_generation, Some((0, 0, (0, 0)))))
+            .unwrap();
+        app.ai_moves
+            .sender()
+            .send((app.generation, Some((2, 2, (1, 0)))))
+            .unwrap();
+        assert_eq!(app.receive_ai_move(), Some(Some((2, 2, (1, 0)))));
+    }
+
+    #[test]
+    fn learned_move_test() {
+        let board = GlobalBoard::default();
+        let stats = RootStats {
+            ai_shape: CellShape::X,
+            shape_to_play: CellShape::X,
+            wins: 10.25,
+            playouts: 17,
+            children: vec![
+                MoveStats {
+                    mv: (1, 1, (1, 1)),
+                    wins: 6.,
+                    playouts: 10,
+                },
+                MoveStats {
+                    mv: (0, 0, (0, 0)),
+                    wins: 2.25,
+                    playouts: 5,
+                },
+                MoveStats {
+                    mv: (2, 2, (2, 2)),
+                    wins: 2.,
+                    playouts: 2,
+                },
+            ],
+        };
+        let mut after_best = board.clone();
+        after_best.make_move((1, 1, (1, 1)), CellShape::X).unwrap();
+        let loss = GameResult::Won {
+            shape: CellShape::O,
+            line: [(0, 0), (1, 1), (2, 2)],
+        };
+
+        let mut learning = LearningTable::default();
+        assert_eq!(learned_move(&learning, &board, &stats), None);
+
+        learning.record_game(&[after_best.position_hash()], loss, CellShape::X);
+        assert_eq!(
+            learned_move(&learning, &board, &stats),
+            Some((1, 1, (1, 1)))
+        );
+
+        // The last move has the best win rate, but it was barely searched
+        learning.record_game(&[after_best.position_hash()], loss, CellShape::X);
+        assert_eq!(
+            learned_move(&learning, &board, &stats),
+            Some((0, 0, (0, 0)))
+        );
+    }
 }
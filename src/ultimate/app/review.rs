@@ -0,0 +1,364 @@
+//! This module handles reviewing a finished game move by move, asking the AI what it would play
+//! at any point, and continuing the game from there. It also handles finding the first losing
+//! mistake of each shape, to review the game from there.
+
+use super::{UltimateTTTApp, WINNING_WIN_RATE};
+use crate::{
+    shared::{ai::AiPlayer, ai_task::AiTask, board::GameResult, history::History},
+    ultimate::{
+        board::{GlobalBoard, MctsPlayer},
+        GlobalCoord,
+    },
+    CellShape,
+};
+use eframe::egui::{self, Context};
+use std::sync::mpsc;
+use web_time::{Duration, Instant};
+
+/// The state of reviewing a finished game. See [`UltimateTTTApp::start_review`].
+pub struct Review<const N: usize> {
+    /// The history of the game being reviewed, with the position being shown selected.
+    history: History<GlobalBoard<N>>,
+
+    /// The move that the AI would play in the position with the given ply, once it has been
+    /// found. The move is `None` if there were no legal moves.
+    suggestion: Option<(usize, Option<GlobalCoord>)>,
+
+    /// The handle to the AI move being computed for the suggestion, if there is one.
+    task: Option<AiTask>,
+
+    /// The sender that we pass to the background thread to get the suggestion back, tagged with
+    /// the ply that it's for.
+    tx: mpsc::Sender<(usize, Option<GlobalCoord>)>,
+
+    /// The receiver that receives suggestions, tagged with the ply that they're for.
+    rx: mpsc::Receiver<(usize, Option<GlobalCoord>)>,
+}
+
+impl<const N: usize> Review<N> {
+    /// Return the AI's suggested move for the position being shown, if it has been found.
+    pub fn suggestion(&self) -> Option<GlobalCoord> {
+        match self.suggestion {
+            Some((ply, mv)) if ply == self.history.ply() => mv,
+            _ => None,
+        }
+    }
+}
+
+impl<const N: usize> UltimateTTTApp<N> {
+    /// Return the history of the game being reviewed, if we're reviewing it.
+    pub fn review_history(&self) -> Option<&History<GlobalBoard<N>>> {
+        self.review.as_ref().map(|review| &review.history)
+    }
+
+    /// Start reviewing the current game from its final position.
+    ///
+    /// Nobody plays during the review, so any AI move being computed is thrown away and the clock
+    /// is stopped until the review ends or the game is continued.
+    pub fn start_review(&mut self) {
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        self.review = Some(Review {
+            history: History::new(&self.record, self.starting_position()),
+            suggestion: None,
+            task: None,
+            tx,
+            rx,
+        });
+    }
+
+    /// Return the position that the current game started from, before its first move but after the
+    /// opening board was chosen.
+    fn starting_position(&self) -> GlobalBoard<N> {
+        let mut start = GlobalBoard::empty(self.global_board.ai_shape(), self.record.first_shape)
+            .with_rules(self.global_board.rules())
+            .with_win_condition(self.global_board.win_condition())
+            .with_draw_rule(self.global_board.draw_rule())
+            .with_opening(self.game_settings.opening);
+        if let Some(opening_board) = self.global_board.opening_board() {
+            // This fails harmlessly if the opening board wasn't chosen
+            let _ = start.choose_opening_board(opening_board);
+        }
+        start
+    }
+
+    /// Start looking for the first losing mistake of each shape in the background, by evaluating
+    /// every position of the game with MCTS, using the MCTS settings of the config.
+    ///
+    /// A shape is considered to be winning if it wins more than [`WINNING_WIN_RATE`] of the
+    /// playouts, so the mistakes that are found may not be losing with perfect play.
+    pub fn start_blunder_check(&mut self) {
+        let max_iters = self.config.max_mcts_expansions;
+        let playouts = self.config.mcts_playouts;
+        let positions = self.record.replay(self.starting_position());
+
+        self.blunder_check
+            .start(&self.record, positions, move |board: &GlobalBoard<N>| {
+                let win_rate = board
+                    .evaluate(board.to_move(), max_iters, playouts)?
+                    .win_rate(CellShape::X);
+                if win_rate > WINNING_WIN_RATE {
+                    Some(CellShape::X)
+                } else if win_rate < 1. - WINNING_WIN_RATE {
+                    Some(CellShape::O)
+                } else {
+                    None
+                }
+            });
+    }
+
+    /// Show the position after the given number of moves in the review.
+    fn set_review_ply(&mut self, ply: usize) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+
+        review.history.set_ply(ply);
+        self.global_board = review.history.position().clone();
+    }
+
+    /// Show the position after the given number of moves, starting a review if we're not already
+    /// reviewing. This is used to jump to a move in the list of moves.
+    pub fn jump_to_ply(&mut self, ply: usize) {
+        if self.review.is_none() {
+            self.start_review();
+        }
+        self.set_review_ply(ply);
+    }
+
+    /// Stop reviewing and go back to the final position of the game.
+    ///
+    /// If the game is still going, then the clock carries on, and if it's the AI's turn, the AI
+    /// starts its move again.
+    pub fn end_review(&mut self) {
+        let Some(review) = self.review.take() else {
+            return;
+        };
+
+        self.global_board = review.history.final_position().clone();
+        if self.global_board.get_winner() == GameResult::Ongoing && self.flagged().is_none() {
+            self.turn_started_at = Instant::now();
+            self.start_clock();
+            if self.ai_shape() == Some(self.active_shape) {
+                self.start_ai_move();
+            }
+        }
+    }
+
+    /// Ask the AI for its move in the position being shown, in the background with an
+    /// [`AiTask`]. The AI always searches at full strength with the MCTS settings of the config.
+    fn request_suggestion(&mut self) {
+        let max_iters = self.config.max_mcts_expansions;
+        let playouts = self.config.mcts_playouts;
+        let Some(review) = &mut self.review else {
+            return;
+        };
+
+        let board = review.history.position().clone();
+        let ply = review.history.ply();
+        review.task = Some(AiTask::spawn(
+            Duration::ZERO,
+            review.tx.clone(),
+            move |_| {
+                (
+                    ply,
+                    MctsPlayer::new(max_iters, playouts).choose_move(&board),
+                )
+            },
+        ));
+    }
+
+    /// Leave the review and continue the game as a live game from the position being shown,
+    /// throwing away the moves after it.
+    ///
+    /// If `play_suggestion` is true, then the AI's suggested move is played first. If it's then
+    /// the AI's turn, the AI moves straight away.
+    pub fn continue_from_review(&mut self, play_suggestion: bool) {
+        let Some(review) = self.review.take() else {
+            return;
+        };
+
+        let ply = review.history.ply();
+        let (board, record) = review.history.fork(&self.record);
+
+        if let Some(mut task) = self.ai_task.take() {
+            task.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+        self.global_board = board;
+        self.active_shape = self.global_board.to_move();
+        self.record = record;
+        self.turn_started_at = Instant::now();
+        self.evaluation = None;
+        self.explanation = None;
+        self.game_ended_at = None;
+        self.blunder_check.cancel();
+        self.update_crash_context();
+
+        // The clock is stopped during the review, so it carries on for the shape to move
+        self.start_clock();
+
+        let suggestion = match review.suggestion {
+            Some((suggestion_ply, mv)) if suggestion_ply == ply => mv,
+            _ => None,
+        };
+        if let (true, Some(mv)) = (play_suggestion, suggestion) {
+            let _ = self.update_cell(mv);
+        }
+
+        if self.global_board.get_winner() == GameResult::Ongoing
+            && self.ai_shape() == Some(self.active_shape)
+        {
+            self.start_ai_move();
+        }
+    }
+
+    /// Draw the window with the controls to step through the review, if we're reviewing.
+    pub fn draw_review_window(&mut self, ctx: &Context) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+
+        if let Ok(suggestion) = review.rx.try_recv() {
+            review.suggestion = Some(suggestion);
+            review.task = None;
+        }
+
+        let last_ply = review.history.last_ply();
+        let mut ply = review.history.ply();
+        let thinking = review.task.is_some();
+        let suggestion = review.suggestion();
+        let has_suggestion = review.suggestion.map_or(false, |(p, _)| p == ply);
+
+        let mut ask_clicked = false;
+        let mut continue_clicked = None;
+        let mut close_clicked = false;
+
+        egui::Window::new("Review")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-10., 10.))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(ply > 0, egui::Button::new("\u{23EE}"))
+                        .clicked()
+                    {
+                        ply = 0;
+                    }
+                    if ui
+                        .add_enabled(ply > 0, egui::Button::new("\u{25C0}"))
+                        .clicked()
+                    {
+                        ply -= 1;
+                    }
+                    ui.label(format!("Move {ply} of {last_ply}"));
+                    if ui
+                        .add_enabled(ply < last_ply, egui::Button::new("\u{25B6}"))
+                        .clicked()
+                    {
+                        ply += 1;
+                    }
+                    if ui
+                        .add_enabled(ply < last_ply, egui::Button::new("\u{23ED}"))
+                        .clicked()
+                    {
+                        ply = last_ply;
+                    }
+                });
+                ui.add(egui::Slider::new(&mut ply, 0..=last_ply).show_value(false));
+
+                ui.horizontal(|ui| {
+                    ask_clicked = ui
+                        .add_enabled(
+                            !thinking && !has_suggestion,
+                            egui::Button::new("What would the AI do?"),
+                        )
+                        .clicked();
+                    if thinking {
+                        ui.spinner();
+                    }
+                });
+
+                if has_suggestion {
+                    ui.label(match suggestion {
+                        Some((x, y, (lx, ly))) => {
+                            format!("The AI would play ({x}, {y}, ({lx}, {ly}))")
+                        }
+                        None => "There are no moves to play".to_string(),
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if suggestion.is_some() && ui.button("Play the AI's move").clicked() {
+                        continue_clicked = Some(true);
+                    }
+                    if ply < last_ply
+                        && ui
+                            .button("Play from here")
+                            .on_hover_text("Play on from this position as a new game")
+                            .clicked()
+                    {
+                        continue_clicked = Some(false);
+                    }
+                    close_clicked = ui.button("Close").clicked();
+                });
+            });
+
+        if ply != review.history.ply() {
+            self.set_review_ply(ply);
+        }
+
+        if close_clicked {
+            self.end_review();
+        } else if let Some(play_suggestion) = continue_clicked {
+            self.continue_from_review(play_suggestion);
+        } else if ask_clicked {
+            self.request_suggestion();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continue_from_review_test() {
+        let mut app = UltimateTTTApp::default();
+        for mv in [(1, 1, (0, 0)), (0, 0, (2, 2)), (2, 2, (1, 1))] {
+            app.update_cell(mv).unwrap();
+        }
+        let final_board = app.global_board.clone();
+
+        app.start_review();
+        assert_eq!(app.review_history().unwrap().ply(), 3);
+
+        app.set_review_ply(1);
+        assert_eq!(app.global_board.next_local_board(), Some((0, 0)));
+
+        // Leaving the review goes back to the end of the game
+        app.end_review();
+        assert!(app.review.is_none());
+        assert_eq!(app.global_board, final_board);
+
+        app.start_review();
+        app.set_review_ply(1);
+        let review = app.review.as_mut().unwrap();
+        review.suggestion = Some((1, Some((0, 0, (1, 1)))));
+        assert_eq!(review.suggestion(), Some((0, 0, (1, 1))));
+
+        app.continue_from_review(true);
+        assert!(app.review.is_none());
+        assert_eq!(app.record.moves, vec![(1, 1, (0, 0)), (0, 0, (1, 1))]);
+        assert_eq!(app.active_shape, CellShape::X);
+        assert_eq!(app.global_board.next_local_board(), Some((1, 1)));
+    }
+}
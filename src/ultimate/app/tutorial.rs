@@ -0,0 +1,300 @@
+//! This module handles the interactive tutorial, which walks through the rules of ultimate
+//! tic-tac-toe with guided moves on the real board.
+
+use super::UltimateTTTApp;
+use crate::{
+    shared::record::GameRecord,
+    ultimate::{board::GlobalBoard, GlobalCoord},
+    CellShape,
+};
+use eframe::{
+    egui::{self, Context, Painter},
+    epaint::{Color32, Rect, Stroke},
+};
+
+/// What the player has to do to finish a [`TutorialStep`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepGoal {
+    /// Press the next button.
+    Next,
+
+    /// Play the given move, which is highlighted on the board. No other cells can be clicked.
+    Move(GlobalCoord),
+
+    /// Play any legal move.
+    AnyMove,
+}
+
+/// A single step of the tutorial.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TutorialStep {
+    /// The text explaining this step.
+    pub text: &'static str,
+
+    /// The moves to play from an empty board, with [`X`](CellShape::X) moving first, to set up
+    /// the position for this step. If this is empty, then the step continues from the position
+    /// left by the previous step.
+    pub setup: &'static [GlobalCoord],
+
+    /// What the player has to do to finish this step.
+    pub goal: StepGoal,
+}
+
+/// The moves to give X two cells in the middle row of the top left board, with O sending X back
+/// there.
+const WIN_LOCAL_BOARD_SETUP: [GlobalCoord; 4] = [
+    (0, 0, (1, 1)),
+    (1, 1, (0, 0)),
+    (0, 0, (0, 1)),
+    (0, 1, (0, 0)),
+];
+
+/// The moves to fill the top left board with X, with O sending X back there every time, so that
+/// X's last move sends O to a full board.
+const FULL_LOCAL_BOARD_SETUP: [GlobalCoord; 17] = [
+    (0, 0, (1, 1)),
+    (1, 1, (0, 0)),
+    (0, 0, (0, 1)),
+    (0, 1, (0, 0)),
+    (0, 0, (2, 1)),
+    (2, 1, (0, 0)),
+    (0, 0, (1, 0)),
+    (1, 0, (0, 0)),
+    (0, 0, (2, 0)),
+    (2, 0, (0, 0)),
+    (0, 0, (0, 2)),
+    (0, 2, (0, 0)),
+    (0, 0, (1, 2)),
+    (1, 2, (0, 0)),
+    (0, 0, (2, 2)),
+    (2, 2, (0, 0)),
+    (0, 0, (0, 0)),
+];
+
+/// The steps of the tutorial, in order.
+pub const TUTORIAL_STEPS: [TutorialStep; 8] = [
+    TutorialStep {
+        text: "Ultimate tic-tac-toe is played on nine small boards, arranged in a big 3x3 grid. \
+            Win a small board to claim it, and claim three small boards in a row to win the game.",
+        setup: &[],
+        goal: StepGoal::Next,
+    },
+    TutorialStep {
+        text: "X moves first, and can play anywhere. Play X in the highlighted cell, in the top \
+            right corner of the middle board.",
+        setup: &[],
+        goal: StepGoal::Move((1, 1, (2, 0))),
+    },
+    TutorialStep {
+        text: "Where you play decides where your opponent plays next. You played in the top right \
+            corner of a small board, so O is sent to the top right board, which is outlined. \
+            That's why no other cells can be clicked. Play O in the middle of that board.",
+        setup: &[],
+        goal: StepGoal::Move((2, 0, (1, 1))),
+    },
+    TutorialStep {
+        text: "O played in the middle of a small board, so X is sent back to the middle board. \
+            Play X in its bottom left corner.",
+        setup: &[],
+        goal: StepGoal::Move((1, 1, (0, 2))),
+    },
+    TutorialStep {
+        text: "Here X has two cells in the middle row of the top left board, and O has sent X \
+            there. Complete the row to claim that board.",
+        setup: &WIN_LOCAL_BOARD_SETUP,
+        goal: StepGoal::Move((0, 0, (2, 1))),
+    },
+    TutorialStep {
+        text: "X has claimed the top left board. Players can still be sent to a claimed board and \
+            play in it, but it can't be claimed again.",
+        setup: &[],
+        goal: StepGoal::Next,
+    },
+    TutorialStep {
+        text: "If you're sent to a board which is full, then you can play anywhere. X has just \
+            filled the top left board and sent O there, so O can play in any empty cell.",
+        setup: &FULL_LOCAL_BOARD_SETUP,
+        goal: StepGoal::AnyMove,
+    },
+    TutorialStep {
+        text: "That's all of the rules! Press finish to start a real game.",
+        setup: &[],
+        goal: StepGoal::Next,
+    },
+];
+
+impl<const N: usize> UltimateTTTApp<N> {
+    /// Return the current step of the tutorial, if we're in the tutorial.
+    fn current_tutorial_step(&self) -> Option<&'static TutorialStep> {
+        self.tutorial_step
+            .and_then(|index| TUTORIAL_STEPS.get(index))
+    }
+
+    /// Restart the game and start the tutorial from the first step.
+    pub fn start_tutorial(&mut self) {
+        self.tutorial_step = Some(0);
+        self.restart_game();
+        self.enter_tutorial_step(0);
+    }
+
+    /// Leave the tutorial and restart the game with the current config.
+    pub fn end_tutorial(&mut self) {
+        self.tutorial_step = None;
+        self.restart_game();
+    }
+
+    /// Go to the step of the tutorial with the given index, setting up its position if it has
+    /// one, or end the tutorial if there are no more steps.
+    fn enter_tutorial_step(&mut self, index: usize) {
+        let Some(step) = TUTORIAL_STEPS.get(index) else {
+            self.end_tutorial();
+            return;
+        };
+        self.tutorial_step = Some(index);
+
+        if !step.setup.is_empty() {
+            self.global_board = GlobalBoard::empty(CellShape::O, CellShape::X);
+            self.record = GameRecord::new(CellShape::X);
+            self.active_shape = CellShape::X;
+            self.evaluation = None;
+
+            for &mv in step.setup {
+                let _ = self.update_cell(mv);
+            }
+        }
+    }
+
+    /// Check if the tutorial allows the given cell to be clicked. This is always true outside of
+    /// the tutorial.
+    pub fn tutorial_allows(&self, coord: GlobalCoord) -> bool {
+        match self.current_tutorial_step().map(|step| step.goal) {
+            None | Some(StepGoal::AnyMove) => true,
+            Some(StepGoal::Next) => false,
+            Some(StepGoal::Move(mv)) => mv == coord,
+        }
+    }
+
+    /// Go to the next step of the tutorial after the player has made a move, if the current step
+    /// was waiting for a move.
+    pub fn advance_tutorial_after_move(&mut self) {
+        if let Some(index) = self.tutorial_step {
+            if matches!(
+                self.current_tutorial_step().map(|step| step.goal),
+                Some(StepGoal::Move(_) | StepGoal::AnyMove)
+            ) {
+                self.enter_tutorial_step(index + 1);
+            }
+        }
+    }
+
+    /// Highlight the given cell in the given rect if it's the move that the tutorial is waiting
+    /// for.
+    pub fn draw_tutorial_highlight(&self, painter: &Painter, rect: Rect, coord: GlobalCoord) {
+        if let Some(StepGoal::Move(mv)) = self.current_tutorial_step().map(|step| step.goal) {
+            if mv == coord {
+                painter.rect_stroke(
+                    rect,
+                    rect.width() / 10.,
+                    Stroke {
+                        width: rect.width() / 12.,
+                        color: Color32::GOLD,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Draw the tutorial window as a non-collapsible, non-resizable `egui` window, if we're in
+    /// the tutorial.
+    pub fn draw_tutorial_window(&mut self, ctx: &Context) {
+        let (Some(index), Some(step)) = (self.tutorial_step, self.current_tutorial_step()) else {
+            return;
+        };
+
+        let mut next_clicked = false;
+        let mut skip_clicked = false;
+
+        egui::Window::new("Tutorial")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-10., 10.))
+            .show(ctx, |ui| {
+                ui.small(format!("Step {} of {}", index + 1, TUTORIAL_STEPS.len()));
+                ui.label(step.text);
+
+                ui.horizontal(|ui| {
+                    if step.goal == StepGoal::Next {
+                        let last_step = index + 1 == TUTORIAL_STEPS.len();
+                        next_clicked = ui
+                            .button(if last_step { "Finish" } else { "Next" })
+                            .clicked();
+                    }
+
+                    skip_clicked = ui.button("Skip tutorial").clicked();
+                });
+            });
+
+        if skip_clicked {
+            self.end_tutorial();
+        } else if next_clicked {
+            self.enter_tutorial_step(index + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::board::GameResult;
+
+    #[test]
+    fn tutorial_steps_test() {
+        let mut board = GlobalBoard::new_with_first_shape(CellShape::O, CellShape::X);
+
+        for step in TUTORIAL_STEPS {
+            if !step.setup.is_empty() {
+                board = GlobalBoard::new_with_first_shape(CellShape::O, CellShape::X);
+                for &mv in step.setup {
+                    let shape = board.to_move();
+                    assert_eq!(board.make_move(mv, shape), Ok(()));
+                }
+            }
+
+            match step.goal {
+                StepGoal::Next => (),
+                StepGoal::Move(mv) => {
+                    let shape = board.to_move();
+                    assert_eq!(board.make_move(mv, shape), Ok(()));
+                }
+                StepGoal::AnyMove => assert_eq!(board.next_local_board(), None),
+            }
+
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+        }
+    }
+
+    #[test]
+    fn tutorial_flow_test() {
+        let mut app = UltimateTTTApp::default();
+        app.start_tutorial();
+        assert_eq!(app.tutorial_step, Some(0));
+        assert!(!app.tutorial_allows((1, 1, (2, 0))));
+
+        app.enter_tutorial_step(1);
+        assert!(app.tutorial_allows((1, 1, (2, 0))));
+        assert!(!app.tutorial_allows((1, 1, (0, 0))));
+
+        app.update_cell((1, 1, (2, 0))).unwrap();
+        app.advance_tutorial_after_move();
+        assert_eq!(app.tutorial_step, Some(2));
+        assert_eq!(app.global_board.next_local_board(), Some((2, 0)));
+
+        app.enter_tutorial_step(6);
+        assert_eq!(app.active_shape, CellShape::O);
+        assert!(app.tutorial_allows((2, 2, (2, 2))));
+
+        app.enter_tutorial_step(TUTORIAL_STEPS.len());
+        assert_eq!(app.tutorial_step, None);
+        assert_eq!(app.global_board, GlobalBoard::default());
+    }
+}
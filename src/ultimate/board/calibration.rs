@@ -0,0 +1,116 @@
+//! This module provides a way to calibrate the strength of the AI to the speed of the machine.
+//!
+//! We play quick self-play games with increasingly strong AI settings, timing each move, and
+//! recommend the strongest settings which keep the AI's think time within a given budget.
+
+use super::GlobalBoard;
+use crate::CellShape;
+use web_time::{Duration, Instant};
+
+/// The `(max_mcts_expansions, mcts_playouts)` settings to try when calibrating, in order of
+/// increasing strength (and therefore increasing think time).
+pub const CALIBRATION_SETTINGS: [(u16, u8); 12] = [
+    (100, 1),
+    (250, 1),
+    (500, 1),
+    (1000, 1),
+    (1000, 2),
+    (2000, 2),
+    (3000, 3),
+    (5000, 3),
+    (7500, 3),
+    (7500, 5),
+    (10_000, 5),
+    (15_000, 5),
+];
+
+/// The number of moves to play in each self-play game.
+///
+/// The first moves of a game are the slowest for the AI because there are the most legal moves, so
+/// we don't need to play any more than this.
+const CALIBRATION_PLIES: usize = 4;
+
+/// The AI settings recommended by [`calibrate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// The recommended maximum number of expansions in the AI's MCTS algorithm.
+    pub max_mcts_expansions: u16,
+
+    /// The recommended number of playouts to do in each iteration of MCTS.
+    pub mcts_playouts: u8,
+
+    /// The time taken by the slowest move with these settings.
+    pub think_time: Duration,
+}
+
+/// Play a quick self-play game with each of the given `(max_mcts_expansions, mcts_playouts)`
+/// settings in order, and return the last settings whose slowest move took no longer than
+/// `budget`.
+///
+/// The settings should be in order of increasing strength (like [`CALIBRATION_SETTINGS`]), since
+/// we stop as soon as one of them goes over the budget.
+///
+/// # Errors
+///
+/// If even the first settings take longer than `budget`, then we return `None`.
+pub fn calibrate(budget: Duration, settings: &[(u16, u8)]) -> Option<Calibration> {
+    let mut recommended = None;
+
+    for &(max_mcts_expansions, mcts_playouts) in settings {
+        let think_time = slowest_self_play_move(max_mcts_expansions, mcts_playouts);
+        if think_time > budget {
+            break;
+        }
+
+        recommended = Some(Calibration {
+            max_mcts_expansions,
+            mcts_playouts,
+            think_time,
+        });
+    }
+
+    recommended
+}
+
+/// Play the first few moves of a self-play game with the given settings and return the time taken
+/// by the slowest move.
+fn slowest_self_play_move(max_mcts_expansions: u16, mcts_playouts: u8) -> Duration {
+    let mut board = GlobalBoard::new(CellShape::X);
+    let mut slowest = Duration::ZERO;
+
+    for _ in 0..CALIBRATION_PLIES {
+        let start = Instant::now();
+        let Some(mv) = board.generate_ai_move(max_mcts_expansions, mcts_playouts) else {
+            break;
+        };
+        slowest = slowest.max(start.elapsed());
+
+        board
+            .make_move(mv, board.ai_shape)
+            .expect("An AI move should never result in a `MoveError`");
+
+        // The AI plays against itself, so it needs to play as the other shape next time
+        board.ai_shape = board.ai_shape.other();
+    }
+
+    slowest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibrate_test() {
+        let settings = [(10, 1), (20, 1)];
+
+        assert_eq!(calibrate(Duration::ZERO, &settings), None);
+
+        let calibration = calibrate(Duration::from_secs(60), &settings).unwrap();
+        assert_eq!(
+            (calibration.max_mcts_expansions, calibration.mcts_playouts),
+            (20, 1)
+        );
+        assert!(calibration.think_time <= Duration::from_secs(60));
+    }
+}
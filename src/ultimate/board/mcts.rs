@@ -1,54 +1,362 @@
 //! This module provides functionality for an AI based on Monte Carlo tree search (MCTS).
 
 use super::GlobalBoard;
-use crate::{shared::board::WinnerError, ultimate::GlobalCoord, CellShape};
-use rand::{seq::SliceRandom, thread_rng};
+use crate::{
+    shared::{
+        ai::{search_rng, AiPlayer, Game},
+        board::GameResult,
+        explain::{Candidate, CandidateScore},
+        personality::{AiPersonality, GridGame},
+    },
+    ultimate::GlobalCoord,
+    CellShape,
+};
+use arrayvec::ArrayVec;
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng};
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    ops::RangeInclusive,
     rc::{Rc, Weak},
+    sync::mpsc,
 };
+use web_time::{Duration, Instant};
+
+#[cfg(feature = "value-net")]
+use super::value_net::ValueNet;
+#[cfg(feature = "value-net")]
+use std::sync::Arc;
+
+/// How often a snapshot of the search is published while searching. See
+/// [`GlobalBoard::generate_ai_move_with_snapshots`].
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The range of win rates for the shape to play in which a position counts as balanced. See
+/// [`GlobalBoard::is_balanced`].
+const BALANCED_WIN_RATE: RangeInclusive<f64> = 0.35..=0.65;
+
+/// The share of the visits of the most visited move that another move needs for the
+/// [`personality`](MctsPlayer::personality) of the AI to consider it. See
+/// [`RootStats::near_best_moves`].
+const NEAR_BEST_VISIT_SHARE: f64 = 0.75;
+
+/// The most legal moves that a global board can have, which is every cell of a 3x3 global board.
+/// This is the size of the move buffer in [`GlobalBoard::play_out_randomly`].
+pub(super) const MAX_LEGAL_MOVES: usize = 81;
+
+/// The exploration parameter of the UCT formula, which balances exploring moves that haven't been
+/// searched much against exploiting moves that have won a lot. See [`Node::compute_uct`].
+pub(super) const EXPLORATION_PARAMETER: f64 = 1.414;
+
+/// The default reward of a playout for the AI when the game is drawn, which is halfway between a
+/// loss and a win. See [`MctsPlayer::draw_reward`].
+pub const DRAW_REWARD: f64 = 0.5;
+
+/// The statistics of one of the possible moves from the root of the game tree. See [`RootStats`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveStats {
+    /// The move itself.
+    pub mv: GlobalCoord,
+
+    /// The total reward for the AI of the playouts after this move, where a win is 1 and a draw is
+    /// [`DRAW_REWARD`] by default. See [`MctsPlayer::draw_reward`].
+    pub wins: f64,
+
+    /// The total number of playouts after this move.
+    pub playouts: u32,
+}
+
+/// The statistics of the root of the game tree after a search. See
+/// [`GlobalBoard::generate_ai_move_with_stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootStats {
+    /// The shape that the AI plays as, which the wins refer to.
+    pub ai_shape: CellShape,
+
+    /// The shape to play next in the position that was searched.
+    pub shape_to_play: CellShape,
+
+    /// The total reward for the AI of the playouts in the game tree, where a win is 1 and a draw
+    /// is [`DRAW_REWARD`] by default. See [`MctsPlayer::draw_reward`].
+    pub wins: f64,
+
+    /// The total number of playouts in the game tree.
+    pub playouts: u32,
+
+    /// The statistics of each of the possible moves from the root.
+    pub children: Vec<MoveStats>,
+}
+
+/// Return the proportion of the playouts that were won by the given shape, given the total reward
+/// of the playouts for the AI.
+///
+/// Draws are counted as half a win for both shapes by default. See [`DRAW_REWARD`]. If the AI
+/// had contempt for draws, then the rest of the reward of each draw counts for the other shape.
+fn win_rate(ai_shape: CellShape, shape: CellShape, wins: f64, playouts: u32) -> f64 {
+    if playouts == 0 {
+        return 0.5;
+    }
+
+    let ai_win_rate = wins / playouts as f64;
+    if shape == ai_shape {
+        ai_win_rate
+    } else {
+        1. - ai_win_rate
+    }
+}
+
+impl RootStats {
+    /// Return the proportion of playouts that were won by the given shape.
+    ///
+    /// Draws are counted as half a win for both shapes by default, and the win rates of the two
+    /// shapes always add up to 1.
+    pub fn win_rate(&self, shape: CellShape) -> f64 {
+        win_rate(self.ai_shape, shape, self.wins, self.playouts)
+    }
+
+    /// Return each of the possible moves from the root, along with the proportion of the playouts
+    /// after that move which were won by the [`shape_to_play`](Self::shape_to_play).
+    pub fn move_win_rates(&self) -> Vec<(GlobalCoord, f64)> {
+        self.children
+            .iter()
+            .map(|child| {
+                (
+                    child.mv,
+                    win_rate(
+                        self.ai_shape,
+                        self.shape_to_play,
+                        child.wins,
+                        child.playouts,
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Return every move from the root along with its share of the visits, relative to the most
+    /// visited move, so that the most visited move has a share of 1.
+    pub fn visit_shares(&self) -> Vec<(GlobalCoord, f64)> {
+        let most_visits = self
+            .children
+            .iter()
+            .map(|child| child.playouts)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        self.children
+            .iter()
+            .map(|child| (child.mv, child.playouts as f64 / most_visits as f64))
+            .collect()
+    }
+
+    /// Return the moves from the root which were visited almost as much as the most visited move,
+    /// according to [`NEAR_BEST_VISIT_SHARE`].
+    pub fn near_best_moves(&self) -> Vec<GlobalCoord> {
+        self.visit_shares()
+            .into_iter()
+            .filter(|&(_, share)| share >= NEAR_BEST_VISIT_SHARE)
+            .map(|(mv, _)| mv)
+            .collect()
+    }
+
+    /// Return each of the possible moves from the root, annotated with its number of playouts and
+    /// the proportion of them which were won by the AI, to explain the AI's choice of move.
+    pub fn annotated_moves(&self) -> Vec<Candidate<GlobalCoord>> {
+        self.children
+            .iter()
+            .map(|child| Candidate {
+                mv: child.mv,
+                score: CandidateScore::Mcts {
+                    playouts: child.playouts,
+                    win_rate: win_rate(self.ai_shape, self.ai_shape, child.wins, child.playouts),
+                },
+            })
+            .collect()
+    }
+}
+
+/// A copy of a whole MCTS game tree, used to inspect the search. See
+/// [`GlobalBoard::search_tree`].
+#[cfg(feature = "devtools")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchTree {
+    /// The shape that the AI plays as, which the wins refer to.
+    pub ai_shape: CellShape,
+
+    /// The root of the game tree.
+    pub root: TreeNode,
+}
+
+/// A copy of a node in an MCTS game tree. See [`SearchTree`].
+#[cfg(feature = "devtools")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeNode {
+    /// The move taken to get to this node, which is `None` for the root.
+    pub mv: Option<GlobalCoord>,
+
+    /// The shape to play next in this node.
+    pub shape_to_play: CellShape,
+
+    /// The total reward for the AI of the playouts through this node, where a win is 1 and a draw
+    /// is [`DRAW_REWARD`] by default.
+    pub wins: f64,
+
+    /// The total number of playouts through this node.
+    pub playouts: u32,
+
+    /// The UCT of this node when the search finished, which is `None` for the root.
+    pub uct: Option<f64>,
+
+    /// The children of this node, with the most visited first.
+    pub children: Vec<TreeNode>,
+}
+
+#[cfg(feature = "devtools")]
+impl TreeNode {
+    /// Copy the given node and all of its descendants.
+    fn from_node<const N: usize>(node: &Node<GlobalBoard<N>>) -> Self {
+        let (wins, playouts) = *node.wins_vs_playouts.borrow();
+        let mut children: Vec<TreeNode> = node
+            .children
+            .borrow()
+            .iter()
+            .map(|child| TreeNode::from_node(child))
+            .collect();
+        children.sort_by(|a, b| b.playouts.cmp(&a.playouts));
+
+        Self {
+            mv: node.previous_move,
+            shape_to_play: node.shape_to_play_next,
+            wins,
+            playouts,
+            uct: node.compute_uct(),
+            children,
+        }
+    }
+
+    /// Return the proportion of the playouts through this node which were won by the shape that
+    /// made the move to get here, given the shape of the AI.
+    pub fn mover_win_rate(&self, ai_shape: CellShape) -> f64 {
+        win_rate(
+            ai_shape,
+            self.shape_to_play.other(),
+            self.wins,
+            self.playouts,
+        )
+    }
+
+    /// Return the total number of nodes in this subtree, including this one.
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(TreeNode::size).sum::<usize>()
+    }
+}
+
+/// The result of the game with perfect play from a node in a game tree, once the search has
+/// proven it. See [`Node::propagate_proof`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Proof {
+    /// The given shape can force a win.
+    Win(CellShape),
 
-/// A struct to represent a node in a game tree.
-#[derive(Clone, Debug)]
-struct Node {
+    /// Both shapes can force at least a draw.
+    Draw,
+}
+
+/// A struct to represent a node in a game tree of any [`Game`].
+struct Node<G: Game> {
     /// The move taken to get to this board state.
-    previous_move: Option<GlobalCoord>,
+    previous_move: Option<G::Move>,
 
     /// The board state of this node.
-    board: RefCell<GlobalBoard>,
+    board: RefCell<G>,
 
     /// The shape to play next.
     shape_to_play_next: CellShape,
 
-    /// The total `(wins, playouts)` of this node.
+    /// The shape that the AI plays as, which the wins refer to.
+    ai_shape: CellShape,
+
+    /// The reward of a drawn playout for the AI, which is [`DRAW_REWARD`] by default. See
+    /// [`MctsPlayer::draw_reward`].
+    draw_reward: f64,
+
+    /// The total `(wins, playouts)` of this node, where the wins are the total reward of the
+    /// playouts, so a draw counts as [`draw_reward`](Self::draw_reward) of a win.
     ///
     /// The wins *always* refer to wins for the AI, as opposed to a win for the player about to
     /// play in this position.
-    wins_vs_playouts: RefCell<(u16, u16)>,
+    wins_vs_playouts: RefCell<(f64, u32)>,
 
     /// The parent node in the game tree.
-    parent: Weak<Node>,
+    parent: Weak<Node<G>>,
 
     /// A vector of child nodes.
-    children: RefCell<Vec<Rc<Node>>>,
+    children: RefCell<Vec<Rc<Node<G>>>>,
+
+    /// The legal moves which don't have a child node yet. This is empty once the game is over.
+    untried_moves: RefCell<Vec<G::Move>>,
+
+    /// The result of the game from this node with perfect play, if the search has proven it.
+    /// Proven nodes are never selected again, since more playouts can't tell us anything new.
+    proof: Cell<Option<Proof>>,
 }
 
-impl Node {
-    /// Create a root node with no parent or children, and the given data.
-    fn make_root(board: &GlobalBoard, shape_to_play_next: CellShape) -> Self {
+impl<G: Game> Node<G> {
+    /// Create a node with no children and no playouts yet, where every legal move is untried.
+    fn new(
+        previous_move: Option<G::Move>,
+        board: G,
+        shape_to_play_next: CellShape,
+        ai_shape: CellShape,
+        draw_reward: f64,
+        parent: Weak<Node<G>>,
+    ) -> Self {
+        let (untried_moves, proof) = match board.get_winner() {
+            GameResult::Ongoing => (board.moves(), None),
+            GameResult::Won { shape, .. } => (vec![], Some(Proof::Win(shape))),
+            GameResult::Draw => (vec![], Some(Proof::Draw)),
+        };
+
         Self {
-            previous_move: None,
-            board: RefCell::new(board.clone()),
+            previous_move,
+            board: RefCell::new(board),
             shape_to_play_next,
-            wins_vs_playouts: RefCell::new((0, 0)),
-            parent: Weak::new(),
+            ai_shape,
+            draw_reward,
+            wins_vs_playouts: RefCell::new((0., 0)),
+            parent,
             children: RefCell::new(vec![]),
+            untried_moves: RefCell::new(untried_moves),
+            proof: Cell::new(proof),
         }
     }
 
-    /// Check if this node is a leaf.
-    fn is_leaf(&self) -> bool {
-        self.children.borrow().len() == 0
+    /// Create a root node with no parent or children, and the given data.
+    fn make_root(
+        board: &G,
+        shape_to_play_next: CellShape,
+        ai_shape: CellShape,
+        draw_reward: f64,
+    ) -> Self {
+        let mut board = board.clone();
+        if board.to_move() != shape_to_play_next {
+            board.skip_turn();
+        }
+
+        Self::new(
+            None,
+            board,
+            shape_to_play_next,
+            ai_shape,
+            draw_reward,
+            Weak::new(),
+        )
+    }
+
+    /// Check if this node has a child for every legal move, so that the search should go
+    /// deeper rather than expanding it.
+    fn is_fully_expanded(&self) -> bool {
+        self.untried_moves.borrow().is_empty()
     }
 
     /// Compute the [UCT](https://en.wikipedia.org/wiki/Monte_Carlo_tree_search#Exploration_and_exploitation) of this node.
@@ -69,9 +377,7 @@ impl Node {
     /// have a UCT because it's the root. We don't have any other sibling nodes to choose to
     /// explore instead.
     fn compute_uct(&self) -> Option<f64> {
-        const EXPLORATION_PARAMETER: f64 = 1.414;
-
-        let wins: f64 = self.wins_vs_playouts.borrow().0 as f64;
+        let wins: f64 = self.wins_vs_playouts.borrow().0;
         let total_playouts: f64 = self.wins_vs_playouts.borrow().1 as f64;
         let parent_total_playouts: f64 = self.parent.upgrade()?.wins_vs_playouts.borrow().1 as f64;
 
@@ -81,14 +387,16 @@ impl Node {
         Some(uct)
     }
 
-    /// Find the best child of the given node by comparing UCT values.
-    fn best_child_by_uct(node: &Rc<Node>) -> Option<Rc<Node>> {
+    /// Find the best child of the given node by comparing UCT values, skipping the children which
+    /// have been proven.
+    fn best_child_by_uct(node: &Rc<Node<G>>) -> Option<Rc<Node<G>>> {
         const MSG: &str =
             "We should never try to compute the UCT of the root node, so it should never be none";
 
         let children = node.children.borrow();
         children
             .iter()
+            .filter(|child| child.proof.get().is_none())
             .max_by(|&c1, &c2| {
                 c1.compute_uct()
                     .expect(MSG)
@@ -100,313 +408,1030 @@ impl Node {
     /// Select the next node to expand and return an [`Rc`] to it.
     ///
     /// This function should only be manually called on the root node, and then it will traverse
-    /// down the children to find the best leaf.
-    fn select_node(node: &Rc<Node>) -> Rc<Node> {
-        if node.is_leaf() {
+    /// down the children by UCT until it finds a node which still has untried moves, or a node
+    /// where the game is over.
+    fn select_node(node: &Rc<Node<G>>) -> Rc<Node<G>> {
+        if !node.is_fully_expanded() {
             return Rc::clone(node);
         }
 
-        let best_child = Node::best_child_by_uct(node)
-            .expect("We know this node has children, or else we would've returned self earlier");
-        Node::select_node(&best_child)
+        match Node::best_child_by_uct(node) {
+            Some(best_child) => Node::select_node(&best_child),
+            None => Rc::clone(node),
+        }
     }
 
-    /// Expand the current node if possible.
+    /// Expand the given node by creating a child for one of its untried moves, chosen randomly
+    /// with `rng`, and return that child.
     ///
-    /// If this board state results in a win, loss, or draw, or if there are no legal moves, then
-    /// no expansion will happen and no children will be created. Otherwise, we will create a child
-    /// node for each legal move.
-    fn expand(node: &Rc<Node>, playouts: u8) {
-        let legal_moves = node.board.borrow().legal_moves();
-        if node.board.borrow_mut().get_winner() != Err(WinnerError::NoWinnerYet)
-            || legal_moves.is_empty()
-        {
-            return;
-        }
-
-        let mut children = node.children.borrow_mut();
-
-        for mv in legal_moves {
-            let mut board: GlobalBoard = node.board.borrow().clone();
-            board
-                .make_move(mv, node.shape_to_play_next)
-                .expect("A legal move should never cause a `MoveError`");
+    /// If every legal move already has a child, or if the game is over, then no expansion will
+    /// happen and we return `None`.
+    fn expand(node: &Rc<Node<G>>, rng: &mut SmallRng) -> Option<Rc<Node<G>>> {
+        let mv = {
+            let mut untried_moves = node.untried_moves.borrow_mut();
+            if untried_moves.is_empty() {
+                return None;
+            }
+            untried_moves.swap_remove(rng.gen_range(0..untried_moves.len()))
+        };
 
-            let node = Node {
-                previous_move: Some(mv),
-                board: RefCell::new(board),
-                shape_to_play_next: node.shape_to_play_next.other(),
-                wins_vs_playouts: RefCell::new((0, 0)),
-                parent: Rc::downgrade(node),
-                children: RefCell::new(vec![]),
-            };
+        let mut board: G = node.board.borrow().clone();
+        let legal = board.play(mv);
+        debug_assert!(legal, "A legal move should always be playable");
+
+        let child = Rc::new(Node::new(
+            Some(mv),
+            board,
+            node.shape_to_play_next.other(),
+            node.ai_shape,
+            node.draw_reward,
+            Rc::downgrade(node),
+        ));
+        node.children.borrow_mut().push(Rc::clone(&child));
+        Some(child)
+    }
 
-            for _ in 0..playouts {
-                node.playout_and_backpropagate();
+    /// Do one iteration of MCTS from the given root, by selecting a node, expanding it by one
+    /// child, simulating `playouts` games from that child, and backpropagating each result.
+    ///
+    /// If the selected node can't be expanded because the game is over there, then the
+    /// simulations start from that node instead. The `scratch` game is overwritten by each
+    /// playout, so that it can be reused instead of cloning the board every time, and the random
+    /// moves are chosen with `rng`. See [`playout`](Self::playout).
+    ///
+    /// If there's a `leaf_value` and it can evaluate the new node, then its reward for the AI is
+    /// backpropagated once instead of doing any playouts, like with a
+    /// [`ValueNet`](super::value_net::ValueNet).
+    fn iterate(
+        root: &Rc<Node<G>>,
+        playouts: u8,
+        leaf_value: Option<&dyn Fn(&G) -> Option<f64>>,
+        scratch: &mut G,
+        rng: &mut SmallRng,
+    ) {
+        let selected = Node::select_node(root);
+        let node = Node::expand(&selected, rng).unwrap_or(selected);
+
+        let value = match leaf_value {
+            Some(leaf_value) if node.proof.get().is_none() => leaf_value(&node.board.borrow()),
+            _ => None,
+        };
+        match value {
+            Some(reward) => node.backpropagate(reward),
+            None => {
+                for _ in 0..playouts {
+                    node.playout_and_backpropagate(scratch, rng);
+                }
             }
+        }
 
-            children.push(Rc::new(node));
+        if node.proof.get().is_some() {
+            node.propagate_proof();
         }
     }
 
-    /// Play the game to completion with random moves and return whether the AI won this simulation.
-    fn playout(&self) -> bool {
-        let mut board: GlobalBoard = self.board.borrow().clone();
-        let mut shape = self.shape_to_play_next;
+    /// Work out the result of the game from this node with perfect play from the proofs of its
+    /// children, or return `None` if it can't be proven yet.
+    ///
+    /// The shape to play can force a win if any child is a proven win for it. Otherwise, the node
+    /// is only proven once every legal move has a proven child, and then it's a draw if any of
+    /// them are drawn, and a win for the other shape if not.
+    fn prove_from_children(&self) -> Option<Proof> {
+        let shape = self.shape_to_play_next;
+        let children = self.children.borrow();
+
+        if children
+            .iter()
+            .any(|child| child.proof.get() == Some(Proof::Win(shape)))
+        {
+            return Some(Proof::Win(shape));
+        }
+        if !self.is_fully_expanded() || children.is_empty() {
+            return None;
+        }
 
-        // Keep making moves until either someone wins, or there's a draw
-        while let Err(WinnerError::NoWinnerYet) = board.get_winner() {
-            let Some(coord) = board.get_random_legal_move() else {
-                break;
-            };
+        let proofs: Option<Vec<Proof>> = children.iter().map(|child| child.proof.get()).collect();
+        proofs.map(|proofs| {
+            if proofs.contains(&Proof::Draw) {
+                Proof::Draw
+            } else {
+                Proof::Win(shape.other())
+            }
+        })
+    }
 
-            board
-                .make_move(coord, shape)
-                .expect("When making a random legal move, we should not get a `MoveError`");
-            shape = shape.other();
+    /// Prove the ancestors of this node from their children where possible, after this node has
+    /// been proven, so that exact results are propagated up the tree as far as they go. This is
+    /// the MCTS-Solver enhancement.
+    fn propagate_proof(&self) {
+        let Some(parent) = self.parent.upgrade() else {
+            return;
+        };
+        if parent.proof.get().is_some() {
+            return;
         }
 
-        matches!(board.get_winner(), Ok((winning_shape, _)) if winning_shape == self.board.borrow().ai_shape)
+        if let Some(proof) = parent.prove_from_children() {
+            parent.proof.set(Some(proof));
+            parent.propagate_proof();
+        }
     }
 
-    /// Propagate a win or loss up the game tree to the root node.
-    fn backpropagate(&self, has_won: bool) {
-        let mut tup = self.wins_vs_playouts.borrow_mut();
-        if has_won {
-            tup.0 += 1;
+    /// Play the game to completion with random moves and return the reward of this simulation for
+    /// the AI, which is 1 for a win, the [`draw_reward`](Self::draw_reward) for a draw, and 0 for a
+    /// loss.
+    ///
+    /// The game is played out in place in `scratch`, which is overwritten with the board of this
+    /// node first. See [`Game::play_out_randomly`].
+    fn playout(&self, scratch: &mut G, rng: &mut SmallRng) -> f64 {
+        scratch.clone_from(&self.board.borrow());
+        scratch.play_out_randomly(rng);
+
+        match scratch.get_winner() {
+            GameResult::Won { shape, .. } if shape == self.ai_shape => 1.,
+            GameResult::Won { .. } => 0.,
+            GameResult::Draw | GameResult::Ongoing => self.draw_reward,
         }
+    }
+
+    /// Propagate the reward of a playout up the game tree to the root node.
+    fn backpropagate(&self, reward: f64) {
+        let mut tup = self.wins_vs_playouts.borrow_mut();
+        tup.0 += reward;
         tup.1 += 1;
         drop(tup);
 
         match self.parent.upgrade() {
             None => (),
-            Some(parent) => parent.backpropagate(has_won),
+            Some(parent) => parent.backpropagate(reward),
         };
     }
 
     /// Playout this board state and backpropagate the result up to this node's parents.
     ///
     /// See [`playout`](Self::playout) and [`backpropagate`](Self::backpropagate).
-    fn playout_and_backpropagate(&self) {
-        self.backpropagate(self.playout());
+    fn playout_and_backpropagate(&self, scratch: &mut G, rng: &mut SmallRng) {
+        self.backpropagate(self.playout(scratch, rng));
     }
 }
 
-/// The coordinates of all the cells in the global board.
-#[rustfmt::skip]
-const ALL_CELLS: [GlobalCoord; 81] = [
-    (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
-    (0, 0, (0, 1)), (0, 0, (1, 1)), (0, 0, (2, 1)),
-    (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
-
-    (1, 0, (0, 0)), (1, 0, (1, 0)), (1, 0, (2, 0)),
-    (1, 0, (0, 1)), (1, 0, (1, 1)), (1, 0, (2, 1)),
-    (1, 0, (0, 2)), (1, 0, (1, 2)), (1, 0, (2, 2)),
-
-    (2, 0, (0, 0)), (2, 0, (1, 0)), (2, 0, (2, 0)),
-    (2, 0, (0, 1)), (2, 0, (1, 1)), (2, 0, (2, 1)),
-    (2, 0, (0, 2)), (2, 0, (1, 2)), (2, 0, (2, 2)),
-
-    (0, 1, (0, 0)), (0, 1, (1, 0)), (0, 1, (2, 0)),
-    (0, 1, (0, 1)), (0, 1, (1, 1)), (0, 1, (2, 1)),
-    (0, 1, (0, 2)), (0, 1, (1, 2)), (0, 1, (2, 2)),
-
-    (1, 1, (0, 0)), (1, 1, (1, 0)), (1, 1, (2, 0)),
-    (1, 1, (0, 1)), (1, 1, (1, 1)), (1, 1, (2, 1)),
-    (1, 1, (0, 2)), (1, 1, (1, 2)), (1, 1, (2, 2)),
-
-    (2, 1, (0, 0)), (2, 1, (1, 0)), (2, 1, (2, 0)),
-    (2, 1, (0, 1)), (2, 1, (1, 1)), (2, 1, (2, 1)),
-    (2, 1, (0, 2)), (2, 1, (1, 2)), (2, 1, (2, 2)),
-
-    (0, 2, (0, 0)), (0, 2, (1, 0)), (0, 2, (2, 0)),
-    (0, 2, (0, 1)), (0, 2, (1, 1)), (0, 2, (2, 1)),
-    (0, 2, (0, 2)), (0, 2, (1, 2)), (0, 2, (2, 2)),
-
-    (1, 2, (0, 0)), (1, 2, (1, 0)), (1, 2, (2, 0)),
-    (1, 2, (0, 1)), (1, 2, (1, 1)), (1, 2, (2, 1)),
-    (1, 2, (0, 2)), (1, 2, (1, 2)), (1, 2, (2, 2)),
-
-    (2, 2, (0, 0)), (2, 2, (1, 0)), (2, 2, (2, 0)),
-    (2, 2, (0, 1)), (2, 2, (1, 1)), (2, 2, (2, 1)),
-    (2, 2, (0, 2)), (2, 2, (1, 2)), (2, 2, (2, 2)),
-];
-
-impl GlobalBoard {
-    /// Return a vec of all the legal moves on the global board.
-    fn legal_moves(&self) -> Vec<GlobalCoord> {
-        match self.next_local_board() {
-            None => ALL_CELLS.to_vec(),
-            #[rustfmt::skip]
-            Some((x, y)) => vec![
-                (x, y, (0, 0)), (x, y, (1, 0)), (x, y, (2, 0)),
-                (x, y, (0, 1)), (x, y, (1, 1)), (x, y, (2, 1)),
-                (x, y, (0, 2)), (x, y, (1, 2)), (x, y, (2, 2)),
-            ],
+/// Check if the most visited of the given visit counts is further ahead of all the others than
+/// the `remaining` visits could make up, so that it will still be the most visited however those
+/// visits are spread out.
+fn is_unassailable(visits: impl Iterator<Item = u32>, remaining: u32) -> bool {
+    let (most, second) = visits.fold((0, 0), |(most, second), visits| {
+        if visits > most {
+            (visits, most)
+        } else {
+            (most, second.max(visits))
         }
-        .iter()
-        .filter_map(|&(x, y, (lx, ly))| {
-            if self.local_boards[x][y].cells[lx][ly].is_none() {
-                Some((x, y, (lx, ly)))
-            } else {
-                None
-            }
-        })
-        .collect()
+    });
+
+    most - second > remaining
+}
+
+impl<G: Game> Node<G> {
+    /// Return the children of this node which haven't been proven to lose for the shape to play.
+    fn children_not_lost(&self) -> Vec<Rc<Node<G>>> {
+        let lost = Some(Proof::Win(self.shape_to_play_next.other()));
+        self.children
+            .borrow()
+            .iter()
+            .filter(|child| child.proof.get() != lost)
+            .cloned()
+            .collect()
     }
 
-    /// Return a random legal move, or [`None`] if the board is full.
-    fn get_random_legal_move(&self) -> Option<GlobalCoord> {
-        self.legal_moves().choose(&mut thread_rng()).copied()
+    /// Return the move that the search from this root chooses.
+    ///
+    /// This is a move which has been proven to win if there is one, or otherwise the most visited
+    /// move which hasn't been proven to lose. If every move loses, then it's the most visited one.
+    fn chosen_move(&self) -> Option<G::Move> {
+        let won = Some(Proof::Win(self.shape_to_play_next));
+        let most_visited = |children: &[Rc<Node<G>>]| {
+            children
+                .iter()
+                .max_by_key(|child| child.wins_vs_playouts.borrow().1)
+                .and_then(|child| child.previous_move)
+        };
+
+        let children = self.children.borrow();
+        if let Some(child) = children.iter().find(|child| child.proof.get() == won) {
+            return child.previous_move;
+        }
+
+        most_visited(&self.children_not_lost()).or_else(|| most_visited(&children))
     }
 
-    /// Do the MCTS algorithm by creating a tree, selecting, expanding, playing out, and backpropagating.
-    fn do_mcts(&self, max_expansions: u16, playouts: u8) -> Option<GlobalCoord> {
-        if self.legal_moves().is_empty() {
-            return None;
+    /// Check if the search from this root can stop early, because more iterations can't change
+    /// the move that it would choose. See [`chosen_move`](Self::chosen_move).
+    ///
+    /// This is when the root has been proven, like when one of the moves wins the game on the
+    /// spot, or when the most visited move which hasn't been proven to lose can't be overtaken with
+    /// the `remaining` playouts. See [`is_unassailable`].
+    fn is_decided(&self, remaining: u32) -> bool {
+        self.proof.get().is_some()
+            || is_unassailable(
+                self.children_not_lost()
+                    .iter()
+                    .map(|child| child.wins_vs_playouts.borrow().1),
+                remaining,
+            )
+    }
+}
+
+/// The settings of one MCTS search of any [`Game`]. See [`grow_tree`] and
+/// [`MctsPlayer::search_options`].
+pub(super) struct SearchOptions<'a, G: Game> {
+    /// The maximum number of iterations, each of which expands one node of the game tree.
+    pub max_iterations: u16,
+
+    /// The number of playouts to do in each iteration.
+    pub playouts: u8,
+
+    /// The reward of a drawn playout for the AI. See [`MctsPlayer::draw_reward`].
+    pub draw_reward: f64,
+
+    /// The function which evaluates leaves instead of playouts where it can, if there is one. See
+    /// [`Node::iterate`].
+    pub leaf_value: Option<Box<dyn Fn(&G) -> Option<f64> + 'a>>,
+
+    /// The time at which to stop searching, if there is one. The search always does at least one
+    /// iteration.
+    pub deadline: Option<Instant>,
+
+    /// The seed for the random moves of the playouts. See [`search_rng`].
+    pub seed: Option<u64>,
+}
+
+impl<G: Game> SearchOptions<'_, G> {
+    /// Search with the given number of iterations and playouts, the default [`DRAW_REWARD`], and
+    /// no leaf evaluation, deadline, or seed.
+    pub fn new(max_iterations: u16, playouts: u8) -> Self {
+        Self {
+            max_iterations,
+            playouts,
+            draw_reward: DRAW_REWARD,
+            leaf_value: None,
+            deadline: None,
+            seed: None,
         }
+    }
+}
 
-        let root = &Rc::new(Node::make_root(self, self.ai_shape));
-        Node::expand(root, playouts);
-        let mut next = Node::select_node(root);
+/// Grow a game tree of any [`Game`] with the MCTS algorithm and return its root. The wins in the
+/// tree refer to `ai_shape`.
+///
+/// Each of the [`max_iterations`](SearchOptions::max_iterations) iterations selects a node,
+/// expands it by one child, and plays out and backpropagates
+/// [`playouts`](SearchOptions::playouts) simulations from there, so the tree gets one new node per
+/// iteration. See [`Node::iterate`].
+///
+/// `after_iteration` is called with the root after every iteration, so that the search can be
+/// watched while it happens. If there's a [`deadline`](SearchOptions::deadline), then the search
+/// stops early once it has passed, but always does at least one iteration. The search also stops
+/// early once the rest of the iterations couldn't change the most visited move. See
+/// [`Node::is_decided`].
+fn grow_tree<G: Game>(
+    game: &G,
+    ai_shape: CellShape,
+    shape_to_play: CellShape,
+    options: &SearchOptions<'_, G>,
+    mut after_iteration: impl FnMut(&Node<G>),
+) -> Option<Rc<Node<G>>> {
+    if game.moves().is_empty() {
+        return None;
+    }
 
-        for _ in 1..max_expansions {
-            Node::expand(&next, playouts);
-            next = Node::select_node(root);
+    let SearchOptions {
+        max_iterations,
+        playouts,
+        draw_reward,
+        ref leaf_value,
+        deadline,
+        seed,
+    } = *options;
+    let root = Rc::new(Node::make_root(game, shape_to_play, ai_shape, draw_reward));
+    let mut scratch = game.clone();
+    let mut rng = search_rng(seed);
+
+    let max_iterations = max_iterations.max(1);
+    for iteration in 0..max_iterations {
+        if iteration > 0 && deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            break;
         }
 
-        let children = root.children.borrow();
-        children
+        Node::iterate(
+            &root,
+            playouts,
+            leaf_value.as_deref(),
+            &mut scratch,
+            &mut rng,
+        );
+        after_iteration(&root);
+
+        let remaining = u32::from(max_iterations - iteration - 1) * u32::from(playouts);
+        if root.is_decided(remaining) {
+            break;
+        }
+    }
+
+    Some(root)
+}
+
+/// Return the principal variation of a game tree, which is the line of moves found by following
+/// the most visited child from the given node until reaching a node that hasn't been expanded.
+fn most_visited_line<G: Game>(node: &Rc<Node<G>>) -> Vec<G::Move> {
+    let mut line = vec![];
+    let mut node = Rc::clone(node);
+
+    loop {
+        let Some(child) = node
+            .children
+            .borrow()
             .iter()
-            .max_by_key(|&child| child.wins_vs_playouts.borrow().1)?
-            .previous_move
+            .max_by_key(|child| child.wins_vs_playouts.borrow().1)
+            .cloned()
+        else {
+            return line;
+        };
+
+        line.extend(child.previous_move);
+        node = child;
+    }
+}
+
+/// Search any [`Game`] with MCTS as the shape which is next to move, and return the move chosen at
+/// the root, or [`None`] if there are no legal moves. See [`Node::chosen_move`]. Drawn playouts
+/// are worth `draw_reward`, like [`MctsPlayer::draw_reward`].
+///
+/// This is the engine behind [`GlobalBoard::generate_ai_move`], without the shortcuts and
+/// statistics which are specific to ultimate tic-tac-toe.
+pub fn mcts_move<G: Game>(
+    game: &G,
+    max_mcts_expansions: u16,
+    playouts: u8,
+    draw_reward: f64,
+) -> Option<G::Move> {
+    let shape = game.to_move();
+    let options = SearchOptions {
+        draw_reward,
+        ..SearchOptions::new(max_mcts_expansions, playouts)
+    };
+    let root = grow_tree(game, shape, shape, &options, |_| ())?;
+
+    root.chosen_move()
+}
+
+impl<const N: usize> GlobalBoard<N> {
+    /// Collect the statistics of the root of the game tree.
+    fn root_stats(&self, root: &Node<Self>, shape_to_play: CellShape) -> RootStats {
+        let (wins, playouts) = *root.wins_vs_playouts.borrow();
+        RootStats {
+            ai_shape: self.ai_shape,
+            shape_to_play,
+            wins,
+            playouts,
+            children: root
+                .children
+                .borrow()
+                .iter()
+                .filter_map(|child| {
+                    let (wins, playouts) = *child.wins_vs_playouts.borrow();
+                    Some(MoveStats {
+                        mv: child.previous_move?,
+                        wins,
+                        playouts,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Grow a game tree with [`grow_tree`] and return its root.
+    ///
+    /// If there is a `snapshot_tx`, then the statistics of the root are sent down it every
+    /// [`SNAPSHOT_INTERVAL`] while searching.
+    fn grow_tree(
+        &self,
+        shape_to_play: CellShape,
+        options: &SearchOptions<'_, Self>,
+        snapshot_tx: Option<&mpsc::Sender<RootStats>>,
+    ) -> Option<Rc<Node<Self>>> {
+        let mut last_snapshot = Instant::now();
+
+        grow_tree(self, self.ai_shape, shape_to_play, options, |root| {
+            if let Some(snapshot_tx) = snapshot_tx {
+                if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+                    let _ = snapshot_tx.send(self.root_stats(root, shape_to_play));
+                    last_snapshot = Instant::now();
+                }
+            }
+        })
+    }
+
+    /// Do the MCTS algorithm with [`grow_tree`](Self::grow_tree).
+    ///
+    /// Return the move chosen at the root, along with the statistics of the root. See
+    /// [`Node::chosen_move`].
+    fn do_mcts(
+        &self,
+        shape_to_play: CellShape,
+        options: &SearchOptions<'_, Self>,
+        snapshot_tx: Option<&mpsc::Sender<RootStats>>,
+    ) -> Option<(GlobalCoord, RootStats)> {
+        let root = self.grow_tree(shape_to_play, options, snapshot_tx)?;
+        let stats = self.root_stats(&root, shape_to_play);
+        let mv = root.chosen_move()?;
+
+        Some((mv, stats))
     }
 
     /// Return the AI-chosen optimal move, which could be none if the board is full.
     pub fn generate_ai_move(&self, max_mcts_expansions: u16, playouts: u8) -> Option<GlobalCoord> {
-        let legal_moves = self.legal_moves();
+        self.generate_ai_move_with_stats(max_mcts_expansions, playouts)
+            .0
+    }
+
+    /// Return the AI-chosen optimal move like [`generate_ai_move`](Self::generate_ai_move), along
+    /// with the statistics of the root of the MCTS game tree.
+    ///
+    /// The statistics are `None` if the move was found without searching, which happens when
+    /// there is only one legal move, or when the AI can win immediately.
+    pub fn generate_ai_move_with_stats(
+        &self,
+        max_mcts_expansions: u16,
+        playouts: u8,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        self.search(&SearchOptions::new(max_mcts_expansions, playouts), None)
+    }
+
+    /// Return the AI-chosen optimal move and the statistics of the search like
+    /// [`generate_ai_move_with_stats`](Self::generate_ai_move_with_stats), while sending
+    /// snapshots of the statistics down `snapshot_tx` every [`SNAPSHOT_INTERVAL`] during the
+    /// search, so that the search can be shown as it happens.
+    ///
+    /// No snapshots are sent if the move was found without searching.
+    pub fn generate_ai_move_with_snapshots(
+        &self,
+        max_mcts_expansions: u16,
+        playouts: u8,
+        snapshot_tx: &mpsc::Sender<RootStats>,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        self.search(
+            &SearchOptions::new(max_mcts_expansions, playouts),
+            Some(snapshot_tx),
+        )
+    }
+
+    /// Find the AI move, searching with MCTS if it's not obvious, and stopping the search early
+    /// if there's a [`deadline`](SearchOptions::deadline) and it has passed. See
+    /// [`generate_ai_move_with_stats`](Self::generate_ai_move_with_stats).
+    fn search(
+        &self,
+        options: &SearchOptions<'_, Self>,
+        snapshot_tx: Option<&mpsc::Sender<RootStats>>,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        if let Some(mv) = self.obvious_move() {
+            return (mv, None);
+        }
+
+        match self.do_mcts(self.ai_shape, options, snapshot_tx) {
+            Some((mv, stats)) => (Some(mv), Some(stats)),
+            None => (None, None),
+        }
+    }
+
+    /// Return the AI move if it can be found without searching, which is when there are no legal
+    /// moves or only one, or when the AI can win immediately. If we have to search, then return
+    /// `None`.
+    pub(super) fn obvious_move(&self) -> Option<Option<GlobalCoord>> {
+        let legal_moves: Vec<GlobalCoord> = self.legal_moves().collect();
 
         match legal_moves.len() {
-            0 => None,
-            1 => legal_moves.first().copied(),
-            _ => {
-                for mv in legal_moves {
-                    let mut board = self.clone();
-                    board
-                        .make_move(mv, self.ai_shape)
-                        .expect("A legal move should never result in a `MoveError`");
-
-                    if matches!(board.get_winner(), Ok((shape, _)) if shape == self.ai_shape) {
-                        return Some(mv);
-                    }
-                }
+            0 => Some(None),
+            1 => Some(legal_moves.first().copied()),
+            _ => legal_moves.into_iter().find_map(|mv| {
+                let mut board = self.clone();
+                board.to_move = self.ai_shape;
+                board
+                    .make_move(mv, self.ai_shape)
+                    .expect("A legal move should never result in a `MoveError`");
+
+                matches!(board.get_winner(), GameResult::Won { shape, .. } if shape == self.ai_shape)
+                    .then_some(Some(mv))
+            }),
+        }
+    }
 
-                self.do_mcts(max_mcts_expansions, playouts)
-            }
+    /// Evaluate the current position with MCTS, with the given shape to play next, and return the
+    /// statistics of the root of the game tree.
+    ///
+    /// This returns `None` if there are no legal moves.
+    pub fn evaluate(
+        &self,
+        shape_to_play: CellShape,
+        max_mcts_expansions: u16,
+        playouts: u8,
+    ) -> Option<RootStats> {
+        self.do_mcts(
+            shape_to_play,
+            &SearchOptions::new(max_mcts_expansions, playouts),
+            None,
+        )
+        .map(|(_, stats)| stats)
+    }
+
+    /// Search the current position with MCTS like [`evaluate`](Self::evaluate), and return the
+    /// principal variation, which is the line of moves that the search expects both shapes to
+    /// play, starting with a move for `shape_to_play`. See
+    /// [`Board::evaluate_position_with_line`](crate::normal::board::Board::evaluate_position_with_line)
+    /// for the normal variant.
+    ///
+    /// The line follows the most visited move at each depth of the game tree, so it gets less
+    /// reliable the further it goes, and it stops where the tree does. It's empty if there are no
+    /// legal moves.
+    pub fn best_line(
+        &self,
+        shape_to_play: CellShape,
+        max_mcts_expansions: u16,
+        playouts: u8,
+    ) -> Vec<GlobalCoord> {
+        self.grow_tree(
+            shape_to_play,
+            &SearchOptions::new(max_mcts_expansions, playouts),
+            None,
+        )
+        .map_or_else(Vec::new, |root| most_visited_line(&root))
+    }
+
+    /// Check if neither shape is clearly winning, which is when the shape to play wins a
+    /// proportion of the MCTS playouts within [`BALANCED_WIN_RATE`]. Used to choose fair starting
+    /// positions for chaos mode. See [`random_start`](crate::shared::chaos::random_start).
+    pub fn is_balanced(&self, max_mcts_expansions: u16, playouts: u8) -> bool {
+        self.evaluate(self.to_move, max_mcts_expansions, playouts)
+            .map_or(false, |stats| {
+                BALANCED_WIN_RATE.contains(&stats.win_rate(self.to_move))
+            })
+    }
+
+    /// Search the current position with MCTS like [`evaluate`](Self::evaluate), and return the
+    /// whole game tree so that it can be inspected.
+    ///
+    /// This returns `None` if there are no legal moves.
+    #[cfg(feature = "devtools")]
+    pub fn search_tree(
+        &self,
+        shape_to_play: CellShape,
+        max_mcts_expansions: u16,
+        playouts: u8,
+    ) -> Option<SearchTree> {
+        let root = self.grow_tree(
+            shape_to_play,
+            &SearchOptions::new(max_mcts_expansions, playouts),
+            None,
+        )?;
+        Some(SearchTree {
+            ai_shape: self.ai_shape,
+            root: TreeNode::from_node(&root),
+        })
+    }
+}
+
+impl<const N: usize> Game for GlobalBoard<N> {
+    type Move = GlobalCoord;
+
+    fn moves(&self) -> Vec<GlobalCoord> {
+        self.legal_moves().collect()
+    }
+
+    fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    fn play(&mut self, mv: GlobalCoord) -> bool {
+        self.make_move(mv, self.to_move).is_ok()
+    }
+
+    fn skip_turn(&mut self) {
+        self.skip_turn();
+    }
+
+    fn get_winner(&self) -> GameResult {
+        self.winner
+    }
+
+    /// Play random legal moves until the game is over, collecting the legal moves into a buffer
+    /// on the stack rather than allocating a vec for every move.
+    ///
+    /// The buffer holds [`MAX_LEGAL_MOVES`], so this only supports global boards with up to 3
+    /// local boards along each side.
+    fn play_out_randomly(&mut self, rng: &mut SmallRng) {
+        let mut moves = ArrayVec::<GlobalCoord, MAX_LEGAL_MOVES>::new();
+
+        while self.winner == GameResult::Ongoing {
+            moves.clear();
+            moves.extend(self.legal_moves());
+            let Some(&mv) = moves.choose(rng) else {
+                break;
+            };
+
+            let legal = self.make_move(mv, self.to_move).is_ok();
+            debug_assert!(legal, "A random legal move should always be playable");
+        }
+    }
+}
+
+impl<const N: usize> GridGame for GlobalBoard<N> {
+    fn grid_of_move(
+        &self,
+        (x, y, local): GlobalCoord,
+    ) -> ([[Option<CellShape>; 3]; 3], (usize, usize)) {
+        (self.local_boards[x][y].cells, local)
+    }
+}
+
+/// An [`AiPlayer`] which uses MCTS. See [`GlobalBoard::generate_ai_move_with_stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MctsPlayer {
+    /// The maximum number of iterations of the MCTS algorithm, each of which expands one node of
+    /// the game tree.
+    pub max_mcts_expansions: u16,
+
+    /// The number of playouts to do in each iteration of MCTS.
+    pub playouts: u8,
+
+    /// The time at which to stop searching and play the best move found so far, if there is
+    /// one, like when the AI is running out of time on its clock. See
+    /// [`GameClock`](crate::shared::clock::GameClock).
+    pub deadline: Option<Instant>,
+
+    /// The statistics of the root of the game tree for the last move, if we had to search.
+    pub last_stats: Option<RootStats>,
+
+    /// The personality which chooses between the moves that were searched about as much as the
+    /// most visited one. See [`RootStats::near_best_moves`].
+    pub personality: AiPersonality,
+
+    /// The seed for the random playouts of each search, so that the same position always gets
+    /// the same move. If this is `None`, then each search is seeded randomly. See
+    /// [`search_rng`].
+    pub seed: Option<u64>,
+
+    /// The reward of a drawn playout for the AI, between 0 for a loss and 1 for a win. This is
+    /// [`DRAW_REWARD`] by default, and lower values are contempt for draws, so that the AI presses
+    /// for a win, like against weaker opponents.
+    pub draw_reward: f64,
+
+    /// The number of threads to search with, which share one game tree if there's more than one.
+    /// See [`GlobalBoard::generate_ai_move_parallel`]. Searching with several threads ignores the
+    /// [`seed`](Self::seed) and doesn't send snapshots, and it's only supported on native, so
+    /// this is ignored on Wasm.
+    pub threads: usize,
+
+    /// The value network which evaluates the leaves of the game tree instead of random playouts,
+    /// if there is one, so each iteration does one evaluation rather than
+    /// [`playouts`](Self::playouts) playouts. The search falls back to playouts for leaves that the
+    /// network can't evaluate. Searching with several threads always uses playouts. See
+    /// [`with_value_net_file`](Self::with_value_net_file).
+    #[cfg(feature = "value-net")]
+    pub value_net: Option<Arc<ValueNet>>,
+}
+
+impl MctsPlayer {
+    /// Create a new MCTS player with the given settings.
+    pub fn new(max_mcts_expansions: u16, playouts: u8) -> Self {
+        Self {
+            max_mcts_expansions,
+            playouts,
+            deadline: None,
+            last_stats: None,
+            personality: AiPersonality::Balanced,
+            seed: None,
+            draw_reward: DRAW_REWARD,
+            threads: 1,
+            #[cfg(feature = "value-net")]
+            value_net: None,
+        }
+    }
+
+    /// Stop searching at the given deadline, even if we haven't done all the iterations yet.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Seed the random playouts of each search, so that the player is deterministic. See
+    /// [`seed`](Self::seed).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Search with the given number of threads sharing one game tree. See
+    /// [`threads`](Self::threads).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Return the function which evaluates the leaves of the game tree with the
+    /// [`value_net`](Self::value_net), if there is one.
+    fn leaf_value<const N: usize>(
+        &self,
+    ) -> Option<Box<dyn Fn(&GlobalBoard<N>) -> Option<f64> + '_>> {
+        #[cfg(feature = "value-net")]
+        if let Some(value_net) = &self.value_net {
+            return Some(Box::new(move |board: &GlobalBoard<N>| {
+                value_net.reward(board)
+            }));
+        }
+
+        None
+    }
+
+    /// Return the settings of a search with this player.
+    fn search_options<const N: usize>(&self) -> SearchOptions<'_, GlobalBoard<N>> {
+        SearchOptions {
+            max_iterations: self.max_mcts_expansions,
+            playouts: self.playouts,
+            draw_reward: self.draw_reward,
+            leaf_value: self.leaf_value(),
+            deadline: self.deadline,
+            seed: self.seed,
+        }
+    }
+
+    /// Search the given board, with several threads if there are more than one and we're on
+    /// native. See [`threads`](Self::threads).
+    fn search<const N: usize>(
+        &self,
+        board: &GlobalBoard<N>,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        let options = self.search_options();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.threads > 1 {
+            return board.search_parallel(&options, self.threads);
+        }
+
+        board.search(&options, None)
+    }
+
+    /// Choose a move like [`AiPlayer::choose_move`], while sending snapshots of the search down
+    /// `snapshot_tx`. See [`GlobalBoard::generate_ai_move_with_snapshots`].
+    pub fn choose_move_with_snapshots<const N: usize>(
+        &mut self,
+        game: &GlobalBoard<N>,
+        snapshot_tx: &mpsc::Sender<RootStats>,
+    ) -> Option<GlobalCoord> {
+        let mut board = game.clone();
+        board.ai_shape = board.to_move;
+
+        let (mv, stats) = board.search(&self.search_options(), Some(snapshot_tx));
+        self.last_stats = stats;
+        self.apply_personality(&board, mv)
+    }
+
+    /// Let the [`personality`](Self::personality) choose between the best moves of the last
+    /// search, if we had to search, or return the given move otherwise.
+    fn apply_personality<const N: usize>(
+        &self,
+        board: &GlobalBoard<N>,
+        mv: Option<GlobalCoord>,
+    ) -> Option<GlobalCoord> {
+        match &self.last_stats {
+            Some(stats) if self.personality != AiPersonality::Balanced => self
+                .personality
+                .choose(board, &stats.near_best_moves())
+                .or(mv),
+            _ => mv,
         }
     }
 }
 
+impl<const N: usize> AiPlayer<GlobalBoard<N>> for MctsPlayer {
+    fn choose_move(&mut self, game: &GlobalBoard<N>) -> Option<GlobalCoord> {
+        let mut board = game.clone();
+        board.ai_shape = board.to_move;
+
+        let (mv, stats) = self.search(&board);
+        self.last_stats = stats;
+        self.apply_personality(&board, mv)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ultimate::test_utils::make_global_board;
 
     #[test]
-    fn legal_moves_test() {
-        let board = GlobalBoard::default();
-        assert_eq!(board.legal_moves(), ALL_CELLS);
+    fn mcts_player_test() {
+        let mut player = MctsPlayer::new(10, 1);
 
         let board = make_global_board! {
-            next = None,
-            (_; _ X _; _) (_; _ X _; _) (_; _ X _; _);
-            (_; _ X _; _) (O O O; O X O; O O O) (_; _ X _; _);
-            (_; _ X _; _) (_; _ X _; _) (_; _ X _; _);
+            next = (1, 1),
+            (_; _ X _; _) () ();
+            () (O _ _; _ X _; _) ();
+            () () ();
         };
-        #[rustfmt::skip]
-        assert_eq!(
-            board.legal_moves(),
-            vec![
-                (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
-                (0, 0, (0, 1)), (0, 0, (2, 1)),
-                (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
+        assert_eq!(board.to_move(), CellShape::O);
 
-                (1, 0, (0, 0)), (1, 0, (1, 0)), (1, 0, (2, 0)),
-                (1, 0, (0, 1)), (1, 0, (2, 1)),
-                (1, 0, (0, 2)), (1, 0, (1, 2)), (1, 0, (2, 2)),
+        let mv = player.choose_move(&board).unwrap();
+        assert!(board.legal_moves().any(|legal| legal == mv));
+        assert_eq!(player.last_stats.unwrap().ai_shape, CellShape::O);
+    }
 
-                (2, 0, (0, 0)), (2, 0, (1, 0)), (2, 0, (2, 0)),
-                (2, 0, (0, 1)), (2, 0, (2, 1)),
-                (2, 0, (0, 2)), (2, 0, (1, 2)), (2, 0, (2, 2)),
+    #[test]
+    fn seeded_mcts_player_test() {
+        let board = make_global_board! {
+            next = (1, 1),
+            (_; _ X _; _) () ();
+            () (O _ _; _ X _; _) ();
+            () () ();
+        };
 
-                (0, 1, (0, 0)), (0, 1, (1, 0)), (0, 1, (2, 0)),
-                (0, 1, (0, 1)), (0, 1, (2, 1)),
-                (0, 1, (0, 2)), (0, 1, (1, 2)), (0, 1, (2, 2)),
+        // The same seed gives the same search
+        let mut player = MctsPlayer::new(50, 2).with_seed(1963);
+        let mut other = MctsPlayer::new(50, 2).with_seed(1963);
+        assert_eq!(player.choose_move(&board), other.choose_move(&board));
+        assert!(player.last_stats.is_some());
+        assert_eq!(player.last_stats, other.last_stats);
+    }
 
-                (2, 1, (0, 0)), (2, 1, (1, 0)), (2, 1, (2, 0)),
-                (2, 1, (0, 1)), (2, 1, (2, 1)),
-                (2, 1, (0, 2)), (2, 1, (1, 2)), (2, 1, (2, 2)),
+    #[test]
+    fn best_line_test() {
+        let board = GlobalBoard::default();
+        let line = board.best_line(CellShape::X, 100, 1);
+        assert!(!line.is_empty());
 
-                (0, 2, (0, 0)), (0, 2, (1, 0)), (0, 2, (2, 0)),
-                (0, 2, (0, 1)), (0, 2, (2, 1)),
-                (0, 2, (0, 2)), (0, 2, (1, 2)), (0, 2, (2, 2)),
+        // The line alternates between the shapes, so it can be replayed move by move
+        let mut replayed = board.clone();
+        for mv in line {
+            assert!(replayed.play(mv));
+        }
 
-                (1, 2, (0, 0)), (1, 2, (1, 0)), (1, 2, (2, 0)),
-                (1, 2, (0, 1)), (1, 2, (2, 1)),
-                (1, 2, (0, 2)), (1, 2, (1, 2)), (1, 2, (2, 2)),
+        // There are no moves once the game is over
+        let board = make_global_board! {
+            next = None,
+            (X X X; _; _) (X X X; _; _) (X X X; _; _);
+            (O O O; _; _) (O O O; _; _) ();
+            () () ();
+        };
+        assert_ne!(board.get_winner(), GameResult::Ongoing);
+        assert!(board.best_line(CellShape::O, 100, 1).is_empty());
+    }
 
-                (2, 2, (0, 0)), (2, 2, (1, 0)), (2, 2, (2, 0)),
-                (2, 2, (0, 1)), (2, 2, (2, 1)),
-                (2, 2, (0, 2)), (2, 2, (1, 2)), (2, 2, (2, 2)),
-            ]
-        );
+    #[test]
+    fn is_unassailable_test() {
+        assert!(is_unassailable([10, 3, 5].into_iter(), 4));
+        assert!(!is_unassailable([10, 3, 5].into_iter(), 5));
+        assert!(!is_unassailable([5, 5].into_iter(), 0));
+        assert!(is_unassailable([1].into_iter(), 0));
+        assert!(!is_unassailable(std::iter::empty(), 0));
+    }
 
+    #[test]
+    fn early_stop_test() {
+        // X can win the game straight away, so the search stops once it has found that move,
+        // rather than doing all the iterations
         let board = make_global_board! {
-            next = (1, 1),
+            next = (2, 0),
+            (X X X; O O _; _) (X X X; O O _; _) (X X _; O O _; _);
             () () ();
-            () (_; _ X _; _) ();
             () () ();
         };
-        #[rustfmt::skip]
+        let stats = board.evaluate(CellShape::X, 5000, 1).unwrap();
+        assert!(stats.playouts < 5000);
+
+        // The winning move is chosen even if it hasn't been visited the most
+        let mut board = board;
+        board.to_move = CellShape::X;
         assert_eq!(
-            board.legal_moves(),
-            vec![
-                (1, 1, (0, 0)), (1, 1, (1, 0)), (1, 1, (2, 0)),
-                (1, 1, (0, 1)), (1, 1, (2, 1)),
-                (1, 1, (0, 2)), (1, 1, (1, 2)), (1, 1, (2, 2)),
-            ]
+            mcts_move(&board, 5000, 1, DRAW_REWARD),
+            Some((2, 0, (2, 0)))
         );
+    }
 
-        let board = make_global_board! {
-            next = (0, 0),
-            () () ();
-            (_; _ X _; O O _) (_; O X X; O _ _) (_; _ O _; _);
-            (O _ _; X _ _; X _ _) (_; X _ _; _) ();
+    #[test]
+    fn near_best_moves_test() {
+        let stats = RootStats {
+            ai_shape: CellShape::X,
+            shape_to_play: CellShape::X,
+            wins: 120.,
+            playouts: 230,
+            children: [(100, 60.), (80, 40.), (50, 20.)]
+                .into_iter()
+                .enumerate()
+                .map(|(x, (playouts, wins))| MoveStats {
+                    mv: (x, 0, (0, 0)),
+                    wins,
+                    playouts,
+                })
+                .collect(),
         };
-        #[rustfmt::skip]
+
         assert_eq!(
-            board.legal_moves(),
-            vec![
-                (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
-                (0, 0, (0, 1)), (0, 0, (1, 1)), (0, 0, (2, 1)),
-                (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
-            ]
+            stats.near_best_moves(),
+            vec![(0, 0, (0, 0)), (1, 0, (0, 0))]
         );
+    }
 
-        let board = make_global_board! {
-            next = (1, 1),
-            (_; _ X _; _) () ();
-            (_; _ X _; O O _) (_; O X X; O _ _) (_; _ O _; _);
-            (O _ _; X _ _; X _ _) (_; X _ _; _) ();
+    #[test]
+    fn mcts_player_deadline_test() {
+        // The deadline has already passed, so the search stops after the first iteration
+        let mut player = MctsPlayer::new(u16::MAX, 1).with_deadline(Instant::now());
+        let board = GlobalBoard::default();
+
+        let mv = player.choose_move(&board).unwrap();
+        assert!(board.legal_moves().any(|legal| legal == mv));
+        let stats = player.last_stats.unwrap();
+        assert_eq!(stats.children.len(), 1);
+        assert_eq!(stats.playouts, 1);
+    }
+
+    #[cfg(feature = "devtools")]
+    #[test]
+    fn search_tree_test() {
+        let board = GlobalBoard::default();
+        let tree = board.search_tree(CellShape::X, 20, 1).unwrap();
+        assert_eq!(tree.ai_shape, CellShape::O);
+        assert_eq!(tree.root.mv, None);
+        assert_eq!(tree.root.uct, None);
+        // Every iteration expands one child of the root, since it has more moves than iterations
+        assert_eq!(tree.root.children.len(), 20);
+        assert_eq!(
+            tree.root.playouts,
+            tree.root.children.iter().map(|child| child.playouts).sum()
+        );
+        assert_eq!(tree.root.size(), 21);
+        assert!(tree
+            .root
+            .children
+            .windows(2)
+            .all(|pair| pair[0].playouts >= pair[1].playouts));
+        assert!(tree.root.children.iter().all(|child| child.uct.is_some()));
+    }
+
+    #[test]
+    fn evaluate_test() {
+        let board = GlobalBoard::default();
+        let stats = board.evaluate(CellShape::X, 10, 1).unwrap();
+        assert_eq!(stats.ai_shape, CellShape::O);
+        assert_eq!(stats.playouts, 10);
+        assert!(stats.wins <= stats.playouts as f64);
+        assert!(
+            (stats.win_rate(CellShape::X) + stats.win_rate(CellShape::O) - 1.).abs() < f64::EPSILON
+        );
+
+        assert_eq!(stats.children.len(), 10);
+
+        let stats = RootStats {
+            ai_shape: CellShape::X,
+            shape_to_play: CellShape::O,
+            wins: 3.,
+            playouts: 4,
+            children: vec![
+                MoveStats {
+                    mv: (0, 0, (1, 1)),
+                    wins: 1.,
+                    playouts: 4,
+                },
+                MoveStats {
+                    mv: (0, 0, (2, 2)),
+                    wins: 0.,
+                    playouts: 0,
+                },
+            ],
         };
+        assert_eq!(stats.win_rate(CellShape::X), 0.75);
+        assert_eq!(stats.win_rate(CellShape::O), 0.25);
         assert_eq!(
-            board.legal_moves(),
+            stats.move_win_rates(),
+            vec![((0, 0, (1, 1)), 0.75), ((0, 0, (2, 2)), 0.5)]
+        );
+        assert_eq!(
+            stats.visit_shares(),
+            vec![((0, 0, (1, 1)), 1.), ((0, 0, (2, 2)), 0.)]
+        );
+        assert_eq!(
+            stats.annotated_moves(),
             vec![
-                (1, 1, (0, 0)),
-                (1, 1, (1, 0)),
-                (1, 1, (2, 0)),
-                (1, 1, (1, 2)),
-                (1, 1, (2, 2)),
+                Candidate {
+                    mv: (0, 0, (1, 1)),
+                    score: CandidateScore::Mcts {
+                        playouts: 4,
+                        win_rate: 0.25
+                    }
+                },
+                Candidate {
+                    mv: (0, 0, (2, 2)),
+                    score: CandidateScore::Mcts {
+                        playouts: 0,
+                        win_rate: 0.5
+                    }
+                },
             ]
         );
     }
@@ -414,41 +1439,180 @@ mod tests {
     mod tree {
         use super::*;
 
-        fn get_test_root_node() -> Rc<Node> {
+        fn get_test_root_node() -> Rc<Node<GlobalBoard>> {
             let board = make_global_board! {
                 next = (1, 1),
                 (_; _; _ _ X) () ();
                 () (O _ _; _ X _; _) ();
                 () () (_; _ O _; _);
             };
-            Rc::new(Node::make_root(&board, CellShape::O))
+            Rc::new(Node::make_root(
+                &board,
+                CellShape::O,
+                board.ai_shape,
+                DRAW_REWARD,
+            ))
         }
 
         #[test]
         fn expand_test() {
             let node_rc = get_test_root_node();
-            Node::expand(&node_rc, 1);
+            let mut rng = search_rng(None);
+
+            // 7 possible moves, which are expanded one at a time without any playouts
+            for expanded in 1..=7 {
+                assert!(!node_rc.is_fully_expanded());
+                let child = Node::expand(&node_rc, &mut rng).unwrap();
+                assert_eq!(node_rc.children.borrow().len(), expanded);
+                assert_eq!(child.wins_vs_playouts.borrow().1, 0);
+            }
+            assert!(node_rc.is_fully_expanded());
+            assert!(Node::expand(&node_rc, &mut rng).is_none());
+
+            // Every move was expanded exactly once
+            let mut moves: Vec<_> = node_rc
+                .children
+                .borrow()
+                .iter()
+                .map(|child| child.previous_move.unwrap())
+                .collect();
+            moves.sort();
+            moves.dedup();
+            assert_eq!(moves.len(), 7);
+        }
 
-            // 7 possible moves
-            assert_eq!(node_rc.children.borrow().len(), 7);
+        #[test]
+        fn draw_reward_test() {
+            let board = make_global_board! {
+                next = None,
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+            };
+            assert_eq!(board.get_winner(), GameResult::Draw);
 
-            for child in node_rc.children.borrow().iter() {
-                // 1 playout
-                assert_eq!(child.wins_vs_playouts.borrow().1, 1);
-                // <= 1 win(s)
-                assert!(child.wins_vs_playouts.borrow().0 <= 1);
+            // A draw is worth half a win, rather than counting as a loss
+            let node = Node::make_root(&board, CellShape::X, CellShape::O, DRAW_REWARD);
+            let reward = node.playout(&mut board.clone(), &mut search_rng(None));
+            assert_eq!(reward, DRAW_REWARD);
+
+            node.backpropagate(reward);
+            node.backpropagate(1.);
+            assert_eq!(*node.wins_vs_playouts.borrow(), (1.5, 2));
+
+            // But an AI with contempt for draws values them less
+            let node = Node::make_root(&board, CellShape::X, CellShape::O, 0.25);
+            let reward = node.playout(&mut board.clone(), &mut search_rng(None));
+            assert_eq!(reward, 0.25);
+        }
+
+        #[test]
+        fn prove_from_children_test() {
+            let node_rc = get_test_root_node();
+            let mut rng = search_rng(None);
+            assert_eq!(node_rc.proof.get(), None);
+
+            // O can't be proven to lose until every move has been tried
+            for _ in 0..6 {
+                let child = Node::expand(&node_rc, &mut rng).unwrap();
+                child.proof.set(Some(Proof::Win(CellShape::X)));
+                assert_eq!(node_rc.prove_from_children(), None);
             }
+            let last = Node::expand(&node_rc, &mut rng).unwrap();
+            last.proof.set(Some(Proof::Win(CellShape::X)));
+            assert_eq!(
+                node_rc.prove_from_children(),
+                Some(Proof::Win(CellShape::X))
+            );
+
+            // One drawn move is enough to save the draw
+            last.proof.set(Some(Proof::Draw));
+            assert_eq!(node_rc.prove_from_children(), Some(Proof::Draw));
+
+            // And one winning move is enough to win
+            last.proof.set(Some(Proof::Win(CellShape::O)));
+            assert_eq!(
+                node_rc.prove_from_children(),
+                Some(Proof::Win(CellShape::O))
+            );
         }
 
         #[test]
-        fn backpropagate_test() {
+        fn solver_test() {
+            let board = make_global_board! {
+                next = (2, 0),
+                (X X X; O O _; _) (X X X; O O _; _) (X X _; O O _; _);
+                () () ();
+                () () ();
+            };
+            let node_rc = Rc::new(Node::make_root(
+                &board,
+                CellShape::X,
+                CellShape::X,
+                DRAW_REWARD,
+            ));
+            let mut scratch = GlobalBoard::default();
+            let mut rng = search_rng(None);
+
+            // X has 5 moves, and the winning one is proven once it's been expanded
+            for _ in 0..5 {
+                if node_rc.proof.get().is_some() {
+                    break;
+                }
+                Node::iterate(&node_rc, 1, None, &mut scratch, &mut rng);
+            }
+            assert_eq!(node_rc.proof.get(), Some(Proof::Win(CellShape::X)));
+            assert!(node_rc.is_decided(u32::MAX));
+            assert_eq!(node_rc.chosen_move(), Some((2, 0, (2, 0))));
+
+            // Proven nodes are never selected again
+            assert!(
+                Node::best_child_by_uct(&node_rc).map_or(true, |child| child.proof.get().is_none())
+            );
+
+            // A finished game is proven straight away
+            let board = make_global_board! {
+                next = None,
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+                (X O X; X O O; O X X) (X O X; X O O; O X X) (X O X; X O O; O X X);
+            };
+            let node = Node::make_root(&board, CellShape::X, CellShape::O, DRAW_REWARD);
+            assert_eq!(node.proof.get(), Some(Proof::Draw));
+        }
+
+        #[test]
+        fn iterate_test() {
             let node_rc = get_test_root_node();
-            Node::expand(&node_rc, 1);
+            let mut scratch = GlobalBoard::default();
+            let mut rng = search_rng(None);
+
+            // Each iteration adds one child to the root until it's fully expanded
+            for _ in 0..7 {
+                Node::iterate(&node_rc, 2, None, &mut scratch, &mut rng);
+            }
+            assert_eq!(node_rc.children.borrow().len(), 7);
+            for child in node_rc.children.borrow().iter() {
+                // 2 playouts
+                assert_eq!(child.wins_vs_playouts.borrow().1, 2);
+                // <= 2 win(s)
+                assert!(child.wins_vs_playouts.borrow().0 <= 2.);
+            }
 
-            // 7 playouts
-            assert_eq!(node_rc.wins_vs_playouts.borrow().1, 7);
-            // <= 7 wins
-            assert!(node_rc.wins_vs_playouts.borrow().0 <= 7);
+            // 14 playouts backpropagated to the root
+            assert_eq!(node_rc.wins_vs_playouts.borrow().1, 14);
+            // <= 14 wins
+            assert!(node_rc.wins_vs_playouts.borrow().0 <= 14.);
+
+            // Then the next iteration goes a level deeper
+            Node::iterate(&node_rc, 2, None, &mut scratch, &mut rng);
+            assert_eq!(node_rc.children.borrow().len(), 7);
+            assert_eq!(node_rc.wins_vs_playouts.borrow().1, 16);
+            assert!(node_rc
+                .children
+                .borrow()
+                .iter()
+                .any(|child| child.children.borrow().len() == 1));
         }
     }
 }
@@ -4,18 +4,41 @@
 //! is one global board. This global board is a 3x3 grid of local boards, each of which is a 3x3
 //! grid of cells.
 
+pub mod calibration;
 mod mcts;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
+
+#[cfg(feature = "value-net")]
+pub mod value_net;
+
+pub use self::mcts::{mcts_move, MctsPlayer, MoveStats, RootStats, DRAW_REWARD};
+
+#[cfg(feature = "devtools")]
+pub use self::mcts::{SearchTree, TreeNode};
+
 use super::GlobalCoord;
 use crate::shared::{
     self,
-    board::{CellShape, WinnerError},
+    board::{CellShape, GameResult, InvalidPosition, WinnerError},
+};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    array,
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
 };
 use thiserror::Error;
 
 /// An enum to represent possible errors arising from making a move. See [`GlobalBoard::make_move`].
 #[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
 pub enum MoveError {
+    /// A move has been made with a shape which is not the [`to_move`](GlobalBoard::to_move).
+    #[error("wrong shape to move")]
+    WrongTurn,
+
     /// A move has been made in a local board which is not the
     /// [`next_local_board`](GlobalBoard::next_local_board).
     #[error("wrong local board")]
@@ -28,10 +51,180 @@ pub enum MoveError {
     /// The given coordinate is out of bounds.
     #[error("coordinate out of bounds")]
     OutOfBounds,
+
+    /// A move has been made in a local board which has been won or drawn, which isn't allowed by
+    /// [`RuleSet::ClosedBoards`].
+    #[error("local board already decided")]
+    LocalBoardClosed,
+
+    /// A move has been made before the second player has chosen the local board for the first
+    /// move, under [`OpeningRule::SecondPlayerChooses`].
+    #[error("opening local board not chosen yet")]
+    OpeningNotChosen,
+
+    /// The local board for the first move has been chosen when there was no choice to make. See
+    /// [`GlobalBoard::choose_opening_board`].
+    #[error("no opening local board to choose")]
+    NoOpeningChoice,
+}
+
+/// The rules for which local boards can be played in and sent to, since there are a few common
+/// rule sets for ultimate tic-tac-toe.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleSet {
+    /// Won local boards can still be played in until they're full, and being sent to a full
+    /// local board gives a free move.
+    #[default]
+    Standard,
+
+    /// Won and drawn local boards are closed, so they can't be played in, and being sent to one
+    /// gives a free move.
+    ClosedBoards,
+}
+
+/// The rules for where the first move of the game can be played, since some rule sets restrict it
+/// to stop the first player from having too much of an advantage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OpeningRule {
+    /// The first move can be played anywhere.
+    #[default]
+    Free,
+
+    /// The first move must be played in the centre local board.
+    CentreBoard,
+
+    /// The second player chooses the local board that the first move must be played in, before
+    /// the first move. See [`GlobalBoard::choose_opening_board`].
+    SecondPlayerChooses,
+}
+
+/// The condition for winning the global board, which is the difference between ultimate
+/// tic-tac-toe and its nine-board sibling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WinCondition {
+    /// The global board is won with a line of three local boards, like in ultimate tic-tac-toe.
+    #[default]
+    LineOfBoards,
+
+    /// The global board is won by winning any single local board, like in nine-board
+    /// tic-tac-toe. The winning "line" is that local board three times.
+    AnyBoard,
+}
+
+/// What happens when neither shape can win a line of local boards any more.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrawRule {
+    /// The game is a draw.
+    #[default]
+    Draw,
+
+    /// The shape which has won more local boards wins, once the other shape can't catch up. The
+    /// game is only a draw if they've won the same number once every local board is decided.
+    MostBoards,
+}
+
+/// Return every line of local boards which would win a global board with `N` local boards along
+/// each side, which are the columns, then the rows, and then the two diagonals.
+fn global_lines<const N: usize>() -> impl Iterator<Item = [(usize, usize); N]> {
+    let columns = (0..N).map(|x| array::from_fn(move |y| (x, y)));
+    let rows = (0..N).map(|y| array::from_fn(move |x| (x, y)));
+    let diagonals = [
+        array::from_fn(|i| (i, N - 1 - i)),
+        array::from_fn(|i| (i, i)),
+    ];
+
+    columns.chain(rows).chain(diagonals)
+}
+
+/// Convert a line of local boards into the three coordinates of a winning line in a
+/// [`GameResult`], which are the start, the middle, and the end of the line.
+///
+/// A line of two local boards repeats the second one, just like the "line" of a single local board
+/// under [`WinCondition::AnyBoard`] repeats that board.
+fn result_line<const N: usize>(line: [(usize, usize); N]) -> [(usize, usize); 3] {
+    [line[0], line[N / 2], line[N - 1]]
+}
+
+/// The coordinates of all the cells in the global board.
+#[rustfmt::skip]
+pub const ALL_CELLS: [GlobalCoord; 81] = [
+    (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
+    (0, 0, (0, 1)), (0, 0, (1, 1)), (0, 0, (2, 1)),
+    (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
+
+    (1, 0, (0, 0)), (1, 0, (1, 0)), (1, 0, (2, 0)),
+    (1, 0, (0, 1)), (1, 0, (1, 1)), (1, 0, (2, 1)),
+    (1, 0, (0, 2)), (1, 0, (1, 2)), (1, 0, (2, 2)),
+
+    (2, 0, (0, 0)), (2, 0, (1, 0)), (2, 0, (2, 0)),
+    (2, 0, (0, 1)), (2, 0, (1, 1)), (2, 0, (2, 1)),
+    (2, 0, (0, 2)), (2, 0, (1, 2)), (2, 0, (2, 2)),
+
+    (0, 1, (0, 0)), (0, 1, (1, 0)), (0, 1, (2, 0)),
+    (0, 1, (0, 1)), (0, 1, (1, 1)), (0, 1, (2, 1)),
+    (0, 1, (0, 2)), (0, 1, (1, 2)), (0, 1, (2, 2)),
+
+    (1, 1, (0, 0)), (1, 1, (1, 0)), (1, 1, (2, 0)),
+    (1, 1, (0, 1)), (1, 1, (1, 1)), (1, 1, (2, 1)),
+    (1, 1, (0, 2)), (1, 1, (1, 2)), (1, 1, (2, 2)),
+
+    (2, 1, (0, 0)), (2, 1, (1, 0)), (2, 1, (2, 0)),
+    (2, 1, (0, 1)), (2, 1, (1, 1)), (2, 1, (2, 1)),
+    (2, 1, (0, 2)), (2, 1, (1, 2)), (2, 1, (2, 2)),
+
+    (0, 2, (0, 0)), (0, 2, (1, 0)), (0, 2, (2, 0)),
+    (0, 2, (0, 1)), (0, 2, (1, 1)), (0, 2, (2, 1)),
+    (0, 2, (0, 2)), (0, 2, (1, 2)), (0, 2, (2, 2)),
+
+    (1, 2, (0, 0)), (1, 2, (1, 0)), (1, 2, (2, 0)),
+    (1, 2, (0, 1)), (1, 2, (1, 1)), (1, 2, (2, 1)),
+    (1, 2, (0, 2)), (1, 2, (1, 2)), (1, 2, (2, 2)),
+
+    (2, 2, (0, 0)), (2, 2, (1, 0)), (2, 2, (2, 0)),
+    (2, 2, (0, 1)), (2, 2, (1, 1)), (2, 2, (2, 1)),
+    (2, 2, (0, 2)), (2, 2, (1, 2)), (2, 2, (2, 2)),
+];
+
+/// The bitmask of a local board with every cell empty, where the cell `(lx, ly)` is the bit
+/// `3 * ly + lx`, so that the bits are in the same order as the cells in [`ALL_CELLS`].
+const ALL_EMPTY: u16 = 0b1_1111_1111;
+
+/// Return the bitmask of the empty cells in the given grid of cells. See [`ALL_EMPTY`].
+fn empty_mask(cells: &[[Option<CellShape>; 3]; 3]) -> u16 {
+    (0..9)
+        .filter(|cell| cells[cell % 3][cell / 3].is_none())
+        .fold(0, |mask, cell| mask | 1 << cell)
+}
+
+/// Return an iterator over the local coordinates of the cells in the given bitmask, in the same
+/// order as [`ALL_CELLS`]. See [`ALL_EMPTY`].
+fn cells_in_mask(mut mask: u16) -> impl Iterator<Item = (usize, usize)> {
+    std::iter::from_fn(move || {
+        (mask != 0).then(|| {
+            let cell = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            (cell % 3, cell / 3)
+        })
+    })
+}
+
+/// The state of a local board, which decides how it counts towards the global board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum LocalBoardState {
+    /// Nobody has won the board and it isn't full, so either shape could still win it.
+    #[default]
+    Open,
+
+    /// The board has been won by the given shape. Its empty cells can still be played in, but it
+    /// can't be won again.
+    Won(CellShape),
+
+    /// The board is full without a winner, so it counts as neither shape's and can't be sent to.
+    Drawn,
 }
 
 /// A struct to represent a simple local board with a grid of cells.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize)]
 pub struct LocalBoard {
     /// This 2D array represents all the cells, and is indexed as `cells[x][y]`, with the layout as so:
     ///
@@ -42,12 +235,23 @@ pub struct LocalBoard {
     /// ------------------------
     /// (0, 2) | (1, 2) | (2, 2)
     /// ```
-    pub cells: [[Option<CellShape>; 3]; 3],
+    ///
+    /// This is private so that the cached winner can't get out of sync with it.
+    cells: [[Option<CellShape>; 3]; 3],
 
     /// The winner of the board, used to cache the first winner so that multiple winners can't occur.
+    ///
+    /// This is updated by [`GlobalBoard::make_move`], so it's only [`None`] if nobody has won.
     winner: Option<(CellShape, [(usize, usize); 3])>,
 }
 
+impl PartialEq for LocalBoard {
+    /// Compare the cells of the boards, ignoring the cached winner, which is derived from them.
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+    }
+}
+
 impl Default for LocalBoard {
     fn default() -> Self {
         Self::new()
@@ -63,29 +267,77 @@ impl LocalBoard {
         }
     }
 
+    /// Create a local board with the given cells.
+    pub fn with_cells(cells: [[Option<CellShape>; 3]; 3]) -> Self {
+        Self {
+            cells,
+            winner: shared::board::get_winner(cells).ok(),
+        }
+    }
+
+    /// Return the cells of the local board, indexed as `cells[x][y]`.
+    pub fn cells(&self) -> [[Option<CellShape>; 3]; 3] {
+        self.cells
+    }
+
     /// Check if the board is full.
     #[inline(always)]
     fn is_board_full(&self) -> bool {
         shared::board::is_board_full(self.cells)
     }
 
-    /// Return the winner of the current board. See [`shared::board::get_winner`].
-    pub fn get_winner(&mut self) -> Result<(CellShape, [(usize, usize); 3]), WinnerError> {
+    /// Return the state of this local board. See [`LocalBoardState`].
+    pub fn state(&self) -> LocalBoardState {
         match self.winner {
-            None => {
-                let winner = shared::board::get_winner(self.cells)?;
-                self.winner = Some(winner);
-                Ok(winner)
-            }
-            Some(x) => Ok(x),
+            Some((shape, _)) => LocalBoardState::Won(shape),
+            None if self.is_board_full() => LocalBoardState::Drawn,
+            None => LocalBoardState::Open,
+        }
+    }
+
+    /// Return the result of the game on this local board so far.
+    pub fn get_winner(&self) -> GameResult {
+        match self.winner {
+            Some((shape, line)) => GameResult::Won { shape, line },
+            None if self.is_board_full() => GameResult::Draw,
+            None => GameResult::Ongoing,
         }
     }
 }
 
+/// A tally of how many local boards have been won by each shape or drawn. See
+/// [`GlobalBoard::local_summary`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LocalBoardSummary {
+    /// The number of local boards won by X.
+    pub x_wins: u8,
+
+    /// The number of local boards won by O.
+    pub o_wins: u8,
+
+    /// The number of local boards which are full without a winner.
+    pub draws: u8,
+}
+
+impl fmt::Display for LocalBoardSummary {
+    /// Format the summary like `X:3 O:2`, adding the draws like `Drawn:1` if there are any.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "X:{} O:{}", self.x_wins, self.o_wins)?;
+        if self.draws > 0 {
+            write!(f, " Drawn:{}", self.draws)?;
+        }
+        Ok(())
+    }
+}
+
 /// A struct to represent the global board, with a grid of [`LocalBoard`]s.
-#[derive(Clone, Debug, PartialEq)]
-pub struct GlobalBoard {
-    /// This 2D array represents all the [`LocalBoard`]s, and is indexed as `cells[x][y]`, with the layout as so:
+///
+/// The global board has `N` local boards along each side, which is 3 in ultimate tic-tac-toe. See
+/// [`SmallGlobalBoard`] for the smaller variant.
+#[derive(Clone, Debug, Serialize)]
+pub struct GlobalBoard<const N: usize = 3> {
+    /// This 2D array represents all the [`LocalBoard`]s, and is indexed as `cells[x][y]`, with the
+    /// layout as so when `N` is 3:
     ///
     /// ```text
     /// (0, 0) | (1, 0) | (2, 0)
@@ -94,7 +346,21 @@ pub struct GlobalBoard {
     /// ------------------------
     /// (0, 2) | (1, 2) | (2, 2)
     /// ```
-    pub local_boards: [[LocalBoard; 3]; 3],
+    ///
+    /// This is private so that the cached state of each local board can't get out of sync with it.
+    #[serde(serialize_with = "serialize_grid")]
+    local_boards: [[LocalBoard; N]; N],
+
+    /// The state of each local board, indexed like [`local_boards`](Self::local_boards), which is
+    /// updated by each move. See [`local_board_state`](Self::local_board_state).
+    #[serde(serialize_with = "serialize_grid")]
+    local_states: [[LocalBoardState; N]; N],
+
+    /// A bitmask of the empty cells of each local board, indexed like
+    /// [`local_boards`](Self::local_boards), which is updated by each move so that
+    /// [`legal_moves`](Self::legal_moves) doesn't have to check every cell. See [`empty_mask`].
+    #[serde(skip)]
+    empty_masks: [[u16; N]; N],
 
     /// This is the shape that the AI will play as.
     ///
@@ -104,6 +370,62 @@ pub struct GlobalBoard {
 
     /// The local board in which the next move must be played.
     next_local_board: Option<(usize, usize)>,
+
+    /// The rules for which local boards can be played in and sent to.
+    rules: RuleSet,
+
+    /// The condition for winning the global board.
+    win_condition: WinCondition,
+
+    /// What happens when neither shape can win a line of local boards any more.
+    draw_rule: DrawRule,
+
+    /// The local board that the first move had to be played in, if the
+    /// [`OpeningRule`] restricted it.
+    opening_board: Option<(usize, usize)>,
+
+    /// Whether the second player still has to choose the local board for the first move, under
+    /// [`OpeningRule::SecondPlayerChooses`]. There are no legal moves until they have.
+    awaiting_opening_choice: bool,
+
+    /// The shape which must make the next move.
+    to_move: CellShape,
+
+    /// The winner of the global board, which is updated by each move so that we don't have to
+    /// check every line in [`get_winner`](Self::get_winner).
+    winner: GameResult,
+}
+
+/// Serialize a grid of any size as a sequence of columns, since `serde` only implements
+/// [`Serialize`] for arrays of each fixed length, and not for arrays of any length `N`.
+fn serialize_grid<T, S, const N: usize>(
+    grid: &[[T; N]; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(grid.iter().map(|column| column.as_slice()))
+}
+
+/// A global board with a 2x2 grid of local boards, which is a gentler introduction to ultimate
+/// tic-tac-toe. Any two local boards are in a line, so the first shape to win two local boards
+/// wins the game.
+pub type SmallGlobalBoard = GlobalBoard<2>;
+
+impl<const N: usize> PartialEq for GlobalBoard<N> {
+    /// Compare the boards, ignoring the cached winner, which is derived from the local boards.
+    fn eq(&self, other: &Self) -> bool {
+        self.local_boards == other.local_boards
+            && self.ai_shape == other.ai_shape
+            && self.next_local_board == other.next_local_board
+            && self.rules == other.rules
+            && self.win_condition == other.win_condition
+            && self.draw_rule == other.draw_rule
+            && self.awaiting_opening_choice == other.awaiting_opening_choice
+            && self.to_move == other.to_move
+    }
 }
 
 impl Default for GlobalBoard {
@@ -113,82 +435,536 @@ impl Default for GlobalBoard {
 }
 
 impl GlobalBoard {
-    /// Create a new, empty global board.
+    /// Create a new, empty global board, where [`X`](CellShape::X) plays first.
     pub fn new(ai_shape: CellShape) -> Self {
+        Self::new_with_first_shape(ai_shape, CellShape::X)
+    }
+
+    /// Create a new, empty global board, where the given shape plays first.
+    pub fn new_with_first_shape(ai_shape: CellShape, first_shape: CellShape) -> Self {
+        Self::empty(ai_shape, first_shape)
+    }
+
+    /// Create a global board with the given local boards and next local board, and check that
+    /// the position is valid with [`is_valid_position`](Self::is_valid_position).
+    ///
+    /// The shape to move is inferred from the number of each shape on the board.
+    pub fn from_position(
+        next_local_board: Option<(usize, usize)>,
+        local_boards: [[LocalBoard; 3]; 3],
+    ) -> Result<Self, InvalidPosition> {
+        let board = Self::with_position_unchecked(next_local_board, local_boards);
+        board.is_valid_position()?;
+        Ok(board)
+    }
+
+    /// Create a global board with the given local boards and next local board, without checking
+    /// that the position is valid. See [`from_position`](Self::from_position).
+    fn with_position_unchecked(
+        next_local_board: Option<(usize, usize)>,
+        local_boards: [[LocalBoard; 3]; 3],
+    ) -> Self {
+        let mut board = Self {
+            local_boards,
+            local_states: local_boards.map(|column| column.map(|board| board.state())),
+            empty_masks: local_boards.map(|column| column.map(|board| empty_mask(&board.cells))),
+            next_local_board,
+            to_move: shared::board::infer_shape_to_move(
+                local_boards
+                    .iter()
+                    .flatten()
+                    .flat_map(|board| board.cells.iter().flatten()),
+            ),
+            ..Default::default()
+        };
+        board.winner = shared::board::get_winner(board.local_winners()).into();
+        if board.winner == GameResult::Ongoing && !board.can_still_be_won() {
+            board.winner = board.result_without_line();
+        }
+        board
+    }
+}
+
+impl<const N: usize> GlobalBoard<N> {
+    /// Create a new, empty global board of any size, where the given shape plays first. See
+    /// [`new_with_first_shape`](GlobalBoard::new_with_first_shape) for the usual 3x3 board.
+    pub fn empty(ai_shape: CellShape, first_shape: CellShape) -> Self {
         Self {
-            local_boards: [[LocalBoard::new(); 3]; 3],
+            local_boards: [[LocalBoard::new(); N]; N],
+            local_states: [[LocalBoardState::Open; N]; N],
+            empty_masks: [[ALL_EMPTY; N]; N],
             ai_shape,
             next_local_board: None,
+            rules: RuleSet::default(),
+            win_condition: WinCondition::default(),
+            draw_rule: DrawRule::default(),
+            opening_board: None,
+            awaiting_opening_choice: false,
+            to_move: first_shape,
+            winner: GameResult::Ongoing,
         }
     }
 
+    /// Use the given rules for this board. This should be done before any moves are made.
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Return the rules of this board.
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    /// Use the given win condition for this board. This should be done before any moves are made.
+    pub fn with_win_condition(mut self, win_condition: WinCondition) -> Self {
+        self.win_condition = win_condition;
+        self
+    }
+
+    /// Return the win condition of this board.
+    pub fn win_condition(&self) -> WinCondition {
+        self.win_condition
+    }
+
+    /// Use the given draw rule for this board. This should be done before any moves are made.
+    pub fn with_draw_rule(mut self, draw_rule: DrawRule) -> Self {
+        self.draw_rule = draw_rule;
+        self
+    }
+
+    /// Return the draw rule of this board.
+    pub fn draw_rule(&self) -> DrawRule {
+        self.draw_rule
+    }
+
+    /// Use the given opening rule for this board, which restricts where the first move can be
+    /// played. This should be done before any moves are made.
+    ///
+    /// A board with an even number of local boards along each side has no centre board, so
+    /// [`OpeningRule::CentreBoard`] uses the one below and to the right of the centre.
+    pub fn with_opening(mut self, opening: OpeningRule) -> Self {
+        match opening {
+            OpeningRule::Free => {}
+            OpeningRule::CentreBoard => {
+                self.opening_board = Some((N / 2, N / 2));
+                self.next_local_board = Some((N / 2, N / 2));
+            }
+            OpeningRule::SecondPlayerChooses => self.awaiting_opening_choice = true,
+        }
+        self
+    }
+
+    /// Check if the second player still has to choose the local board for the first move with
+    /// [`choose_opening_board`](Self::choose_opening_board), in which case there are no legal
+    /// moves yet.
+    pub fn awaiting_opening_choice(&self) -> bool {
+        self.awaiting_opening_choice
+    }
+
+    /// Return the local board that the first move had to be played in, if the [`OpeningRule`]
+    /// restricted it and it has been chosen.
+    pub fn opening_board(&self) -> Option<(usize, usize)> {
+        self.opening_board
+    }
+
+    /// Choose the local board that the first move must be played in, under
+    /// [`OpeningRule::SecondPlayerChooses`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoveError::NoOpeningChoice`] if we're not [awaiting the
+    /// choice](Self::awaiting_opening_choice), or [`MoveError::OutOfBounds`] if the local board
+    /// isn't on the board.
+    pub fn choose_opening_board(&mut self, (x, y): (usize, usize)) -> Result<(), MoveError> {
+        if !self.awaiting_opening_choice {
+            return Err(MoveError::NoOpeningChoice);
+        }
+        if x >= N || y >= N {
+            return Err(MoveError::OutOfBounds);
+        }
+
+        self.awaiting_opening_choice = false;
+        self.opening_board = Some((x, y));
+        self.next_local_board = Some((x, y));
+        Ok(())
+    }
+
+    /// Return the shape that the AI plays as.
+    pub fn ai_shape(&self) -> CellShape {
+        self.ai_shape
+    }
+
+    /// Change the shape that the AI plays as, like when the player switches sides in the middle
+    /// of a game. Unlike the rules, this can be done at any time.
+    pub fn set_ai_shape(&mut self, ai_shape: CellShape) {
+        self.ai_shape = ai_shape;
+    }
+
+    /// Return the shape which must make the next move.
+    pub fn to_move(&self) -> CellShape {
+        self.to_move
+    }
+
+    /// Return a hash of the cells, the next local board, and the shape to move, to identify the
+    /// position in logs.
+    pub fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for column in &self.local_boards {
+            for local_board in column {
+                local_board.cells.hash(&mut hasher);
+            }
+        }
+        self.next_local_board.hash(&mut hasher);
+        self.awaiting_opening_choice.hash(&mut hasher);
+        self.to_move.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Skip the turn of the shape which is next to move, so that the other shape moves next in
+    /// the same [`next_local_board`](Self::next_local_board).
+    ///
+    /// This is used for handicaps, where one player gets an extra move.
+    pub fn skip_turn(&mut self) {
+        self.to_move = self.to_move.other();
+    }
+
     /// Return the coordinates of the local board in which the next move must be played.
     pub fn next_local_board(&self) -> Option<(usize, usize)> {
         self.next_local_board
     }
 
+    /// Return the local boards which can be played in next, numbered from left to right and top
+    /// to bottom, along with the bitmask of their empty cells. See [`ALL_EMPTY`].
+    fn playable_masks(&self) -> impl Iterator<Item = ((usize, usize), u16)> + '_ {
+        let boards = match self.next_local_board {
+            _ if self.awaiting_opening_choice => 0..0,
+            None => 0..N * N,
+            Some((x, y)) => N * y + x..N * y + x + 1,
+        };
+
+        boards
+            .map(|board| (board % N, board / N))
+            .filter(|&coord| self.can_play_in(coord))
+            .map(|(x, y)| ((x, y), self.empty_masks[x][y]))
+    }
+
+    /// Return an iterator over all the legal moves on the global board.
+    ///
+    /// The moves are given in the same order as [`ALL_CELLS`], reading the local boards and then
+    /// the cells within them from left to right and top to bottom. There are no legal moves while
+    /// we're [awaiting the opening choice](Self::awaiting_opening_choice).
+    ///
+    /// The empty cells of each local board are cached and updated by
+    /// [`make_move`](Self::make_move), so this doesn't allocate or check every cell.
+    pub fn legal_moves(&self) -> impl Iterator<Item = GlobalCoord> + '_ {
+        self.playable_masks()
+            .flat_map(|((x, y), mask)| cells_in_mask(mask).map(move |cell| (x, y, cell)))
+    }
+
+    /// Return the number of legal moves, which is the same as counting
+    /// [`legal_moves`](Self::legal_moves), but only has to count the bits of the cached empty
+    /// cells.
+    pub fn legal_move_count(&self) -> usize {
+        self.playable_masks()
+            .map(|(_, mask)| mask.count_ones() as usize)
+            .sum()
+    }
+
+    /// Check if moves can be played in the given local board under the [`rules`](Self::rules),
+    /// as long as there are empty cells.
+    fn can_play_in(&self, (x, y): (usize, usize)) -> bool {
+        match self.rules {
+            RuleSet::Standard => true,
+            RuleSet::ClosedBoards => self.local_states[x][y] == LocalBoardState::Open,
+        }
+    }
+
+    /// Check if the given local board is closed under the [`rules`](Self::rules), so that a move
+    /// which would send the opponent there gives them a free move instead.
+    fn is_closed(&self, (x, y): (usize, usize)) -> bool {
+        self.local_boards[x][y].is_board_full() || !self.can_play_in((x, y))
+    }
+
+    /// Work out the local board in which the next move must be played, after a move in the cell
+    /// with the given local coordinates.
+    ///
+    /// This is the local board with the same coordinates as that cell, unless the game is over,
+    /// that board is closed under the [`rules`](Self::rules), or there is no such board because
+    /// the global board is smaller than 3x3, in which case the next move can be played anywhere
+    /// and this returns [`None`].
+    pub fn compute_next_board(&self, target: (usize, usize)) -> Option<(usize, usize)> {
+        (self.winner == GameResult::Ongoing
+            && target.0 < N
+            && target.1 < N
+            && !self.is_closed(target))
+        .then_some(target)
+    }
+
+    /// Check that this position could have been reached in a real game.
+    ///
+    /// As well as the checks done for the normal variant, every local board must have at most
+    /// one winner, and the [`next_local_board`](Self::next_local_board) must be on the board and
+    /// not closed under the [`rules`](Self::rules).
+    ///
+    /// # Errors
+    ///
+    /// See [`InvalidPosition`], [`shared::board::check_shape_counts`], and
+    /// [`shared::board::check_winner`].
+    pub fn is_valid_position(&self) -> Result<(), InvalidPosition> {
+        shared::board::check_shape_counts(
+            self.local_boards
+                .iter()
+                .flatten()
+                .flat_map(|board| board.cells.iter().flatten()),
+            self.to_move,
+        )?;
+
+        for (x, column) in self.local_boards.iter().enumerate() {
+            for (y, board) in column.iter().enumerate() {
+                if shared::board::get_winner(board.cells) == Err(WinnerError::MultipleWinners) {
+                    return Err(InvalidPosition::MultipleLocalWinners((x, y)));
+                }
+            }
+        }
+
+        if let Some((x, y)) = self.next_local_board {
+            if x >= N || y >= N || self.is_closed((x, y)) {
+                return Err(InvalidPosition::InvalidNextLocalBoard((x, y)));
+            }
+        }
+
+        shared::board::check_winner(self.line_winner(), self.to_move)
+    }
+
+    /// Return all the local boards, indexed as `local_boards[x][y]`.
+    pub fn local_boards(&self) -> &[[LocalBoard; N]; N] {
+        &self.local_boards
+    }
+
+    /// Return the state of the given local board, which is cached and updated by
+    /// [`make_move`](Self::make_move).
+    pub fn local_board_state(&self, x: usize, y: usize) -> LocalBoardState {
+        self.local_states[x][y]
+    }
+
     /// Check if the given local board has won
-    pub fn has_local_board_won(
-        &mut self,
-        x: usize,
-        y: usize,
-    ) -> Result<(CellShape, [(usize, usize); 3]), WinnerError> {
+    pub fn has_local_board_won(&self, x: usize, y: usize) -> GameResult {
         self.local_boards[x][y].get_winner()
     }
 
     /// Update the board to reflect a move being made.
     ///
-    /// This method will also update the [`next_local_board`](Self::next_local_board), setting it
-    /// to [`None`] if the target board is full.
+    /// This method will also update the [`to_move`](Self::to_move), the cached winners of the
+    /// local and global boards, and the [`next_local_board`](Self::next_local_board) with
+    /// [`compute_next_board`](Self::compute_next_board).
     pub fn make_move(&mut self, coord: GlobalCoord, shape: CellShape) -> Result<(), MoveError> {
         let (x, y, (lx, ly)) = coord;
 
-        if x > 2 || y > 2 || lx > 2 || ly > 2 {
+        if x >= N || y >= N || lx > 2 || ly > 2 {
             return Err(MoveError::OutOfBounds);
         }
 
+        if shape != self.to_move {
+            return Err(MoveError::WrongTurn);
+        }
+
+        if self.awaiting_opening_choice {
+            return Err(MoveError::OpeningNotChosen);
+        }
+
         if let Some(coord) = self.next_local_board {
             if coord != (x, y) {
                 return Err(MoveError::WrongLocalBoard);
             }
         }
 
+        if !self.can_play_in((x, y)) {
+            return Err(MoveError::LocalBoardClosed);
+        }
+
         let lb = &mut self.local_boards[x][y];
         if lb.cells[lx][ly].is_some() {
             return Err(MoveError::CellAlreadyFull);
         }
 
         lb.cells[lx][ly] = Some(shape);
-        if self.local_boards[lx][ly].is_board_full() {
-            self.next_local_board = None;
-        } else {
-            self.next_local_board = Some((lx, ly));
+        self.empty_masks[x][y] &= !(1 << (3 * ly + lx));
+        let local_board_won = lb.winner.is_none() && {
+            lb.winner = shared::board::get_winning_line_through(lb.cells, (lx, ly));
+            lb.winner.is_some()
+        };
+
+        let local_board_filled = lb.is_board_full();
+        self.local_states[x][y] = lb.state();
+        self.to_move = shape.other();
+
+        if (local_board_won || local_board_filled) && self.winner == GameResult::Ongoing {
+            self.update_winner((x, y));
         }
+        self.next_local_board = self.compute_next_board((lx, ly));
 
         Ok(())
     }
 
-    /// Return the winner of the global board. See [`shared::board::get_winner`].
-    pub fn get_winner(&mut self) -> Result<(CellShape, [(usize, usize); 3]), WinnerError> {
-        let cells: [[Option<CellShape>; 3]; 3] = self.local_boards.map(|arr| {
-            arr.map(|mut board| board.get_winner().map_or(None, |(shape, _)| Some(shape)))
+    /// Update the cached winner of the global board after the local board at the given
+    /// coordinates has been won or filled.
+    ///
+    /// The game is decided as soon as neither shape can win, rather than when the board is full.
+    /// See [`can_still_be_won`](Self::can_still_be_won) and
+    /// [`result_without_line`](Self::result_without_line).
+    fn update_winner(&mut self, coord: (usize, usize)) {
+        let local_winners = self.local_winners();
+        let winner = match self.win_condition {
+            WinCondition::LineOfBoards => self.winning_line_through(coord),
+            WinCondition::AnyBoard => {
+                local_winners[coord.0][coord.1].map(|shape| (shape, [coord; 3]))
+            }
+        };
+
+        if let Some((shape, line)) = winner {
+            self.winner = GameResult::Won { shape, line };
+        } else if !self.can_still_be_won() {
+            self.winner = self.result_without_line();
+        }
+    }
+
+    /// Return the result of the game once neither shape can win a line of local boards, according
+    /// to the [`DrawRule`].
+    ///
+    /// Under [`DrawRule::MostBoards`], the game goes on while the shape with fewer local boards
+    /// could still catch up by winning the open ones. The winning "line" of a shape which wins on
+    /// the count is the first local board that it won, three times.
+    fn result_without_line(&self) -> GameResult {
+        if self.draw_rule == DrawRule::Draw {
+            return GameResult::Draw;
+        }
+
+        let summary = self.local_summary();
+        let open = (N * N) as u8 - summary.x_wins - summary.o_wins - summary.draws;
+        let leader = if summary.x_wins > summary.o_wins + open {
+            CellShape::X
+        } else if summary.o_wins > summary.x_wins + open {
+            CellShape::O
+        } else if open == 0 {
+            return GameResult::Draw;
+        } else {
+            return GameResult::Ongoing;
+        };
+
+        let first_board = (0..N)
+            .flat_map(|x| (0..N).map(move |y| (x, y)))
+            .find(|&(x, y)| self.local_states[x][y] == LocalBoardState::Won(leader))
+            .expect("A shape with more local boards should have won at least one");
+        GameResult::Won {
+            shape: leader,
+            line: [first_board; 3],
+        }
+    }
+
+    /// Check if either shape could still win the global board.
+    ///
+    /// A shape can only win with a line of local boards where each one has either been won by
+    /// that shape, or is still undecided and not full. Under [`WinCondition::AnyBoard`], the
+    /// game can be won as long as any local board is still undecided.
+    fn can_still_be_won(&self) -> bool {
+        if self.win_condition == WinCondition::AnyBoard {
+            return self
+                .local_states
+                .iter()
+                .flatten()
+                .any(|&state| state == LocalBoardState::Open);
+        }
+
+        CellShape::iter().any(|shape| {
+            global_lines::<N>().any(|line| {
+                line.iter().all(|&(x, y)| match self.local_states[x][y] {
+                    LocalBoardState::Open => true,
+                    LocalBoardState::Won(winner) => winner == shape,
+                    LocalBoardState::Drawn => false,
+                })
+            })
+        })
+    }
+
+    /// Return the shape which won the local board at the given coordinates and the line of local
+    /// boards through it, if that shape has won a whole line of local boards through it.
+    ///
+    /// This only checks the lines through the local board, so it's much cheaper than
+    /// [`line_winner`](Self::line_winner) when we know which local board was the last to change.
+    fn winning_line_through(
+        &self,
+        (x, y): (usize, usize),
+    ) -> Option<(CellShape, [(usize, usize); 3])> {
+        let local_winners = self.local_winners();
+        let shape = local_winners[x][y]?;
+
+        global_lines::<N>()
+            .filter(|line| line.contains(&(x, y)))
+            .find(|line| {
+                line.iter()
+                    .all(|&(x, y)| local_winners[x][y] == Some(shape))
+            })
+            .map(|line| (shape, result_line(line)))
+    }
+
+    /// Return the shape which has won a whole line of local boards and that line, like
+    /// [`shared::board::get_winner`] does for the cells of a 3x3 board.
+    ///
+    /// # Errors
+    ///
+    /// - [`NoWinnerYet`](WinnerError::NoWinnerYet): Neither shape has won a line of local boards.
+    /// - [`MultipleWinners`](WinnerError::MultipleWinners): Both shapes have won a line of local
+    ///   boards, which should never be achievable in normal play.
+    fn line_winner(&self) -> Result<(CellShape, [(usize, usize); 3]), WinnerError> {
+        let local_winners = self.local_winners();
+        let mut winners = global_lines::<N>().filter_map(|line| {
+            let shape = local_winners[line[0].0][line[0].1]?;
+            line.iter()
+                .all(|&(x, y)| local_winners[x][y] == Some(shape))
+                .then(|| (shape, result_line(line)))
         });
 
-        let result = shared::board::get_winner(cells);
-        if result.is_ok() {
-            self.next_local_board = None;
+        let Some((shape, line)) = winners.next() else {
+            return Err(WinnerError::NoWinnerYet);
+        };
+        if winners.any(|(other, _)| other != shape) {
+            Err(WinnerError::MultipleWinners)
+        } else {
+            Ok((shape, line))
         }
-        result
     }
-}
 
-#[cfg(any(test, feature = "bench"))]
-impl LocalBoard {
-    pub fn with_cells(cells: [[Option<CellShape>; 3]; 3]) -> Self {
-        Self {
-            cells,
-            ..Default::default()
+    /// Return a grid of the winners of each local board. Drawn local boards count as neither
+    /// shape's.
+    fn local_winners(&self) -> [[Option<CellShape>; N]; N] {
+        self.local_states.map(|column| {
+            column.map(|state| match state {
+                LocalBoardState::Won(shape) => Some(shape),
+                LocalBoardState::Open | LocalBoardState::Drawn => None,
+            })
+        })
+    }
+
+    /// Count the local boards that have been won by each shape or drawn.
+    pub fn local_summary(&self) -> LocalBoardSummary {
+        let mut summary = LocalBoardSummary::default();
+        for state in self.local_states.iter().flatten() {
+            match state {
+                LocalBoardState::Won(CellShape::X) => summary.x_wins += 1,
+                LocalBoardState::Won(CellShape::O) => summary.o_wins += 1,
+                LocalBoardState::Drawn => summary.draws += 1,
+                LocalBoardState::Open => (),
+            }
         }
+        summary
+    }
+
+    /// Return the result of the game so far.
+    ///
+    /// The result is cached and updated by [`make_move`](Self::make_move), so this is cheap.
+    pub fn get_winner(&self) -> GameResult {
+        self.winner
     }
 }
 
@@ -196,10 +972,7 @@ impl LocalBoard {
 impl GlobalBoard {
     /// Create a global board with the given array of local boards. Used in test macros.
     pub fn with_local_boards(local_boards: [[LocalBoard; 3]; 3]) -> Self {
-        Self {
-            local_boards,
-            ..Default::default()
-        }
+        Self::with_local_boards_and_next_local_board(None, local_boards)
     }
 
     /// Create a global board with the given array of local boards and the next local board.
@@ -208,11 +981,7 @@ impl GlobalBoard {
         next_local_board: Option<(usize, usize)>,
         local_boards: [[LocalBoard; 3]; 3],
     ) -> Self {
-        Self {
-            local_boards,
-            next_local_board,
-            ..Default::default()
-        }
+        Self::with_position_unchecked(next_local_board, local_boards)
     }
 }
 
@@ -248,6 +1017,448 @@ mod tests {
 
     mod global_board {
         use super::super::*;
+        use crate::ultimate::test_utils::make_global_board;
+
+        #[test]
+        fn position_hash_test() {
+            let mut board = GlobalBoard::default();
+            let empty_hash = board.position_hash();
+            assert_eq!(GlobalBoard::new(CellShape::X).position_hash(), empty_hash);
+
+            board.make_move((1, 1, (0, 0)), CellShape::X).unwrap();
+            assert_ne!(board.position_hash(), empty_hash);
+
+            // The same cells with a different shape to move is a different position
+            let hash = board.position_hash();
+            board.skip_turn();
+            assert_ne!(board.position_hash(), hash);
+        }
+
+        #[test]
+        fn get_winner_test() {
+            let mut board = make_global_board! {
+                next = (0, 0),
+                (X X _; O O _; _) () ();
+                () () ();
+                () () ();
+            };
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            assert!(board.make_move((0, 0, (2, 0)), CellShape::X).is_ok());
+            assert_eq!(
+                board.has_local_board_won(0, 0),
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0), (1, 0), (2, 0)]
+                }
+            );
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            let mut board = make_global_board! {
+                next = (2, 0),
+                (X X X; O O _; _) (X X X; O O _; _) (X X _; O O _; _);
+                () () ();
+                () () ();
+            };
+            board.to_move = CellShape::X;
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            assert!(board.make_move((2, 0, (2, 0)), CellShape::X).is_ok());
+            assert_eq!(
+                board.get_winner(),
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0), (1, 0), (2, 0)]
+                }
+            );
+            assert_eq!(board.next_local_board(), None);
+        }
+
+        #[test]
+        fn draw_test() {
+            // Every line of local boards has been won by both shapes, so nobody can win
+            let board = make_global_board! {
+                next = None,
+                (X X X; _; _) (O O O; _; _) (X X X; _; _);
+                (X X X; _; _) (O O O; _; _) (O O O; _; _);
+                (O O O; _; _) (X X X; _; _) ();
+            };
+            assert_eq!(board.get_winner(), GameResult::Draw);
+
+            let mut board = make_global_board! {
+                next = (1, 2),
+                (X X X; _; _) (O O O; _; _) (X X X; _; _);
+                (X X X; _; _) (O O O; _; _) (O O O; _; _);
+                (O O O; _; _) (X X _; _; _) ();
+            };
+            board.to_move = CellShape::X;
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            assert!(board.make_move((1, 2, (2, 0)), CellShape::X).is_ok());
+            assert_eq!(board.get_winner(), GameResult::Draw);
+            assert_eq!(board.next_local_board(), None);
+
+            // A full local board without a winner blocks every line through it
+            let mut board = make_global_board! {
+                next = (1, 1),
+                (X X X; _; _) () (X X X; _; _);
+                () (X O X; X O O; O X _) ();
+                (O O O; _; _) () (O O O; _; _);
+            };
+            board.to_move = CellShape::X;
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            // X can still win with the top row of local boards
+            assert!(board.make_move((1, 1, (2, 2)), CellShape::X).is_ok());
+            assert!(board.local_boards[1][1].is_board_full());
+            assert_eq!(board.local_board_state(1, 1), LocalBoardState::Drawn);
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            // Drawn boards can't be sent to, so this is a free move
+            assert_eq!(board.next_local_board(), Some((2, 2)));
+            assert!(board.make_move((2, 2, (1, 1)), CellShape::O).is_ok());
+            assert_eq!(board.next_local_board(), None);
+            assert_eq!(
+                board.local_board_state(2, 2),
+                LocalBoardState::Won(CellShape::O)
+            );
+            assert_eq!(board.local_board_state(1, 0), LocalBoardState::Open);
+        }
+
+        #[test]
+        fn draw_rule_test() {
+            let mut board = make_global_board! {
+                next = (1, 2),
+                (X X X; _; _) (O O O; _; _) (X X X; _; _);
+                (X X X; _; _) (O O O; _; _) (O O O; _; _);
+                (O O O; _; _) (X X _; _; _) ();
+            };
+            board.to_move = CellShape::X;
+            board.draw_rule = DrawRule::MostBoards;
+
+            // Neither shape can win a line, but the last local board decides the count
+            assert!(board.make_move((1, 2, (2, 0)), CellShape::X).is_ok());
+            assert_eq!(board.local_summary().x_wins, 4);
+            assert_eq!(board.local_summary().o_wins, 4);
+            assert_eq!(board.get_winner(), GameResult::Ongoing);
+
+            for mv in [
+                (2, 0, (2, 2)),
+                (2, 2, (0, 0)),
+                (0, 0, (2, 2)),
+                (2, 2, (1, 0)),
+                (1, 0, (2, 2)),
+                (2, 2, (2, 0)),
+            ] {
+                assert_eq!(board.get_winner(), GameResult::Ongoing);
+                assert!(board.make_move(mv, board.to_move()).is_ok());
+            }
+
+            // X wins the last local board, so X has won more of them
+            assert_eq!(
+                board.get_winner(),
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0); 3]
+                }
+            );
+        }
+
+        #[test]
+        fn compute_next_board_test() {
+            let board = make_global_board! {
+                next = (1, 1),
+                (X X X; O O _; _) () ();
+                () (O _ _; _; _) ();
+                () () ();
+            };
+            assert_eq!(board.to_move(), CellShape::X);
+
+            // Won boards can be sent to and played in with the standard rules
+            assert_eq!(board.rules(), RuleSet::Standard);
+            assert_eq!(board.compute_next_board((0, 0)), Some((0, 0)));
+            let mut standard = board.clone();
+            assert!(standard.make_move((1, 1, (0, 0)), CellShape::X).is_ok());
+            assert_eq!(standard.next_local_board(), Some((0, 0)));
+            assert!(standard.make_move((0, 0, (2, 1)), CellShape::O).is_ok());
+
+            // But they're closed with the closed boards rules
+            let mut closed = board.with_rules(RuleSet::ClosedBoards);
+            assert_eq!(closed.compute_next_board((0, 0)), None);
+            assert_eq!(closed.compute_next_board((1, 0)), Some((1, 0)));
+            assert!(closed.make_move((1, 1, (0, 0)), CellShape::X).is_ok());
+            assert_eq!(closed.next_local_board(), None);
+            assert_eq!(
+                closed.make_move((0, 0, (2, 1)), CellShape::O),
+                Err(MoveError::LocalBoardClosed)
+            );
+            assert!(closed.legal_moves().all(|(x, y, _)| (x, y) != (0, 0)));
+        }
+
+        #[test]
+        fn opening_rule_test() {
+            let mut centre = GlobalBoard::default().with_opening(OpeningRule::CentreBoard);
+            assert_eq!(centre.next_local_board(), Some((1, 1)));
+            assert!(centre.legal_moves().all(|(x, y, _)| (x, y) == (1, 1)));
+            assert_eq!(
+                centre.make_move((0, 0, (1, 1)), CellShape::X),
+                Err(MoveError::WrongLocalBoard)
+            );
+            assert_eq!(
+                centre.choose_opening_board((0, 0)),
+                Err(MoveError::NoOpeningChoice)
+            );
+            assert_eq!(centre.make_move((1, 1, (0, 2)), CellShape::X), Ok(()));
+            assert_eq!(centre.next_local_board(), Some((0, 2)));
+            assert_eq!(centre.opening_board(), Some((1, 1)));
+
+            let mut chosen = GlobalBoard::default().with_opening(OpeningRule::SecondPlayerChooses);
+            assert!(chosen.awaiting_opening_choice());
+            assert_eq!(chosen.legal_moves().count(), 0);
+            assert_eq!(
+                chosen.make_move((0, 0, (0, 0)), CellShape::X),
+                Err(MoveError::OpeningNotChosen)
+            );
+            assert_eq!(
+                chosen.choose_opening_board((3, 0)),
+                Err(MoveError::OutOfBounds)
+            );
+
+            assert_eq!(chosen.choose_opening_board((2, 0)), Ok(()));
+            assert!(!chosen.awaiting_opening_choice());
+            assert_eq!(chosen.opening_board(), Some((2, 0)));
+            assert!(chosen.legal_moves().all(|(x, y, _)| (x, y) == (2, 0)));
+            assert_eq!(chosen.legal_moves().count(), 9);
+            assert_eq!(
+                chosen.choose_opening_board((1, 1)),
+                Err(MoveError::NoOpeningChoice)
+            );
+            assert_eq!(chosen.make_move((2, 0, (1, 1)), CellShape::X), Ok(()));
+            assert_eq!(chosen.next_local_board(), Some((1, 1)));
+        }
+
+        #[test]
+        fn win_condition_test() {
+            let mut board = GlobalBoard::default().with_win_condition(WinCondition::AnyBoard);
+            for mv in [
+                (1, 1, (0, 1)),
+                (0, 1, (2, 2)),
+                (2, 2, (0, 0)),
+                (0, 0, (2, 2)),
+                (2, 2, (1, 0)),
+                (1, 0, (2, 2)),
+            ] {
+                board.make_move(mv, board.to_move()).unwrap();
+                assert_eq!(board.get_winner(), GameResult::Ongoing);
+            }
+
+            // Winning a single local board wins the game
+            board.make_move((2, 2, (2, 0)), CellShape::X).unwrap();
+            assert_eq!(
+                board.get_winner(),
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(2, 2); 3]
+                }
+            );
+        }
+
+        #[test]
+        fn local_summary_test() {
+            assert_eq!(
+                GlobalBoard::default().local_summary(),
+                LocalBoardSummary::default()
+            );
+
+            let board = make_global_board! {
+                next = None,
+                (X X X; _; _) () (X X X; _; _);
+                () (X O X; X O O; O X O) ();
+                (O O O; _; _) (_ X _; _ X _; _ X _) ();
+            };
+            let summary = board.local_summary();
+            assert_eq!(
+                summary,
+                LocalBoardSummary {
+                    x_wins: 3,
+                    o_wins: 1,
+                    draws: 1
+                }
+            );
+            assert_eq!(summary.to_string(), "X:3 O:1 Drawn:1");
+            assert_eq!(LocalBoardSummary::default().to_string(), "X:0 O:0");
+        }
+
+        #[test]
+        fn is_valid_position_test() {
+            assert_eq!(GlobalBoard::default().is_valid_position(), Ok(()));
+
+            let board = make_global_board! {
+                next = (1, 1),
+                (_; _ X _; _) () ();
+                () (O _ _; _ X _; _) ();
+                () () ();
+            };
+            assert_eq!(board.is_valid_position(), Ok(()));
+
+            let board = make_global_board! {
+                next = (1, 1),
+                (_; _ X _; _) () ();
+                () (_; _ X _; _) ();
+                () () ();
+            };
+            assert_eq!(
+                board.is_valid_position(),
+                Err(InvalidPosition::TooManyMoves(CellShape::X))
+            );
+
+            let board = make_global_board! {
+                next = None,
+                () () ();
+                () (X X X; O O O; _) ();
+                () () ();
+            };
+            assert_eq!(
+                board.is_valid_position(),
+                Err(InvalidPosition::MultipleLocalWinners((1, 1)))
+            );
+
+            let board = make_global_board! {
+                next = (1, 1),
+                () () ();
+                () (X O X; X O O; O X X) ();
+                () () (O _ _; _; _);
+            };
+            assert_eq!(
+                board.is_valid_position(),
+                Err(InvalidPosition::InvalidNextLocalBoard((1, 1)))
+            );
+        }
+
+        #[test]
+        fn legal_moves_test() {
+            let board = GlobalBoard::default();
+            assert_eq!(board.legal_moves().collect::<Vec<_>>(), ALL_CELLS);
+
+            let board = make_global_board! {
+                next = None,
+                (_; _ X _; _) (_; _ X _; _) (_; _ X _; _);
+                (_; _ X _; _) (O O O; O X O; O O O) (_; _ X _; _);
+                (_; _ X _; _) (_; _ X _; _) (_; _ X _; _);
+            };
+            #[rustfmt::skip]
+            assert_eq!(
+                board.legal_moves().collect::<Vec<_>>(),
+                vec![
+                    (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
+                    (0, 0, (0, 1)), (0, 0, (2, 1)),
+                    (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
+
+                    (1, 0, (0, 0)), (1, 0, (1, 0)), (1, 0, (2, 0)),
+                    (1, 0, (0, 1)), (1, 0, (2, 1)),
+                    (1, 0, (0, 2)), (1, 0, (1, 2)), (1, 0, (2, 2)),
+
+                    (2, 0, (0, 0)), (2, 0, (1, 0)), (2, 0, (2, 0)),
+                    (2, 0, (0, 1)), (2, 0, (2, 1)),
+                    (2, 0, (0, 2)), (2, 0, (1, 2)), (2, 0, (2, 2)),
+
+                    (0, 1, (0, 0)), (0, 1, (1, 0)), (0, 1, (2, 0)),
+                    (0, 1, (0, 1)), (0, 1, (2, 1)),
+                    (0, 1, (0, 2)), (0, 1, (1, 2)), (0, 1, (2, 2)),
+
+                    (2, 1, (0, 0)), (2, 1, (1, 0)), (2, 1, (2, 0)),
+                    (2, 1, (0, 1)), (2, 1, (2, 1)),
+                    (2, 1, (0, 2)), (2, 1, (1, 2)), (2, 1, (2, 2)),
+
+                    (0, 2, (0, 0)), (0, 2, (1, 0)), (0, 2, (2, 0)),
+                    (0, 2, (0, 1)), (0, 2, (2, 1)),
+                    (0, 2, (0, 2)), (0, 2, (1, 2)), (0, 2, (2, 2)),
+
+                    (1, 2, (0, 0)), (1, 2, (1, 0)), (1, 2, (2, 0)),
+                    (1, 2, (0, 1)), (1, 2, (2, 1)),
+                    (1, 2, (0, 2)), (1, 2, (1, 2)), (1, 2, (2, 2)),
+
+                    (2, 2, (0, 0)), (2, 2, (1, 0)), (2, 2, (2, 0)),
+                    (2, 2, (0, 1)), (2, 2, (2, 1)),
+                    (2, 2, (0, 2)), (2, 2, (1, 2)), (2, 2, (2, 2)),
+                ]
+            );
+
+            let board = make_global_board! {
+                next = (1, 1),
+                () () ();
+                () (_; _ X _; _) ();
+                () () ();
+            };
+            #[rustfmt::skip]
+            assert_eq!(
+                board.legal_moves().collect::<Vec<_>>(),
+                vec![
+                    (1, 1, (0, 0)), (1, 1, (1, 0)), (1, 1, (2, 0)),
+                    (1, 1, (0, 1)), (1, 1, (2, 1)),
+                    (1, 1, (0, 2)), (1, 1, (1, 2)), (1, 1, (2, 2)),
+                ]
+            );
+
+            let board = make_global_board! {
+                next = (0, 0),
+                () () ();
+                (_; _ X _; O O _) (_; O X X; O _ _) (_; _ O _; _);
+                (O _ _; X _ _; X _ _) (_; X _ _; _) ();
+            };
+            #[rustfmt::skip]
+            assert_eq!(
+                board.legal_moves().collect::<Vec<_>>(),
+                vec![
+                    (0, 0, (0, 0)), (0, 0, (1, 0)), (0, 0, (2, 0)),
+                    (0, 0, (0, 1)), (0, 0, (1, 1)), (0, 0, (2, 1)),
+                    (0, 0, (0, 2)), (0, 0, (1, 2)), (0, 0, (2, 2)),
+                ]
+            );
+
+            let board = make_global_board! {
+                next = (1, 1),
+                (_; _ X _; _) () ();
+                (_; _ X _; O O _) (_; O X X; O _ _) (_; _ O _; _);
+                (O _ _; X _ _; X _ _) (_; X _ _; _) ();
+            };
+            assert_eq!(
+                board.legal_moves().collect::<Vec<_>>(),
+                vec![
+                    (1, 1, (0, 0)),
+                    (1, 1, (1, 0)),
+                    (1, 1, (2, 0)),
+                    (1, 1, (1, 2)),
+                    (1, 1, (2, 2)),
+                ]
+            );
+        }
+
+        #[test]
+        fn legal_moves_after_moves_test() {
+            use rand::seq::IteratorRandom;
+
+            for rules in [RuleSet::Standard, RuleSet::ClosedBoards] {
+                let mut board = GlobalBoard::default().with_rules(rules);
+
+                while board.get_winner() == GameResult::Ongoing {
+                    // The cached empty cells should match checking every cell
+                    let expected: Vec<GlobalCoord> = ALL_CELLS
+                        .into_iter()
+                        .filter(|&(x, y, (lx, ly))| {
+                            board.next_local_board.map_or(true, |next| next == (x, y))
+                                && board.can_play_in((x, y))
+                                && board.local_boards[x][y].cells[lx][ly].is_none()
+                        })
+                        .collect();
+                    assert_eq!(board.legal_moves().collect::<Vec<_>>(), expected);
+                    assert_eq!(board.legal_move_count(), expected.len());
+
+                    let mv = board.legal_moves().choose(&mut rand::thread_rng()).unwrap();
+                    board.make_move(mv, board.to_move()).unwrap();
+                }
+            }
+        }
 
         #[test]
         fn make_move_test() {
@@ -259,6 +1470,8 @@ mod tests {
                     && board.local_boards[1][1].cells[0][0] == Some(CellShape::X)
             );
 
+            assert_eq!(board.to_move(), CellShape::O);
+            assert!(board.make_move((0, 0, (1, 2)), CellShape::X) == Err(MoveError::WrongTurn));
             assert!(
                 board.make_move((1, 1, (1, 1)), CellShape::O) == Err(MoveError::WrongLocalBoard)
             );
@@ -287,4 +1500,86 @@ mod tests {
             );
         }
     }
+
+    mod small_global_board {
+        use super::super::*;
+        use crate::shared::ai::{perft, Game};
+
+        #[test]
+        fn global_lines_test() {
+            assert_eq!(global_lines::<3>().count(), 8);
+            assert_eq!(
+                global_lines::<2>().collect::<Vec<_>>(),
+                vec![
+                    [(0, 0), (0, 1)],
+                    [(1, 0), (1, 1)],
+                    [(0, 0), (1, 0)],
+                    [(0, 1), (1, 1)],
+                    [(0, 1), (1, 0)],
+                    [(0, 0), (1, 1)],
+                ]
+            );
+            assert_eq!(result_line([(0, 1), (1, 0)]), [(0, 1), (1, 0), (1, 0)]);
+        }
+
+        #[test]
+        fn make_move_test() {
+            let mut board = SmallGlobalBoard::empty(CellShape::O, CellShape::X);
+            assert_eq!(board.legal_moves().count(), 36);
+            assert_eq!(
+                board.make_move((2, 0, (0, 0)), CellShape::X),
+                Err(MoveError::OutOfBounds)
+            );
+
+            // A cell with a matching local board sends the next move there
+            board.make_move((0, 0, (1, 0)), CellShape::X).unwrap();
+            assert_eq!(board.next_local_board(), Some((1, 0)));
+
+            // A cell without one gives a free move
+            board.make_move((1, 0, (2, 1)), CellShape::O).unwrap();
+            assert_eq!(board.next_local_board(), None);
+            assert_eq!(board.legal_moves().count(), 34);
+            assert!(board.is_valid_position().is_ok());
+        }
+
+        #[test]
+        fn winner_test() {
+            // X wins the top-left and bottom-right local boards, while O plays in the cells
+            // which give X a free move
+            let mut board = SmallGlobalBoard::empty(CellShape::O, CellShape::X);
+            for mv in [
+                (0, 0, (0, 0)),
+                (0, 0, (2, 2)),
+                (0, 0, (1, 0)),
+                (1, 0, (2, 2)),
+                (0, 0, (2, 0)),
+                (1, 0, (2, 1)),
+                (1, 1, (0, 0)),
+                (0, 0, (2, 1)),
+                (1, 1, (1, 1)),
+                (1, 1, (2, 0)),
+            ] {
+                assert!(board.play(mv));
+                assert_eq!(board.get_winner(), GameResult::Ongoing);
+            }
+            assert_eq!(board.local_summary().x_wins, 1);
+
+            assert!(board.play((1, 1, (2, 2))));
+            assert_eq!(
+                board.get_winner(),
+                GameResult::Won {
+                    shape: CellShape::X,
+                    line: [(0, 0), (1, 1), (1, 1)]
+                }
+            );
+            assert!(board.is_valid_position().is_ok());
+        }
+
+        #[test]
+        fn perft_test() {
+            let board = SmallGlobalBoard::empty(CellShape::O, CellShape::X);
+            assert_eq!(perft(&board, 1), 36);
+            assert_eq!(perft(&board, 2), 840);
+        }
+    }
 }
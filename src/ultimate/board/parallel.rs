@@ -0,0 +1,525 @@
+//! This module provides tree-parallel MCTS, where several threads search one shared game tree at
+//! the same time. This is only available on native, where we have real threads.
+//!
+//! The game tree is stored in an [`Arena`] of nodes whose fields are all atomics, so the threads
+//! never have to lock anything. Only one thread can expand each node, which claims it by changing
+//! the state of the node first. While a thread is searching below a node, it adds a virtual loss
+//! to that node, so that the other threads are steered towards other parts of the tree rather than
+//! all searching the same line.
+
+use super::{
+    mcts::{MoveStats, RootStats, SearchOptions, EXPLORATION_PARAMETER, MAX_LEGAL_MOVES},
+    GlobalBoard,
+};
+use crate::{
+    shared::{ai::search_rng, board::GameResult},
+    ultimate::GlobalCoord,
+    CellShape,
+};
+use arrayvec::ArrayVec;
+use rand::rngs::SmallRng;
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering},
+    thread,
+};
+use web_time::Instant;
+
+/// The most nodes that the arena of a search can hold, so that a search with lots of iterations
+/// doesn't allocate too much memory up front. Once the arena is full, the leaves of the tree are
+/// played out without being expanded.
+const MAX_ARENA_NODES: usize = 1 << 18;
+
+/// The rewards of the playouts are summed as integers so that they can be added atomically, in
+/// units of `1 / REWARD_SCALE`.
+const REWARD_SCALE: f64 = 65536.;
+
+/// The state of a node which hasn't been expanded yet.
+const UNEXPANDED: u8 = 0;
+
+/// The state of a node which a thread is currently expanding.
+const EXPANDING: u8 = 1;
+
+/// The state of a node whose children have all been allocated.
+const EXPANDED: u8 = 2;
+
+/// Pack a move into a `u32` so that it can be stored in an atomic, with one byte for each
+/// coordinate.
+fn pack_move((x, y, (lx, ly)): GlobalCoord) -> u32 {
+    (x as u32) | (y as u32) << 8 | (lx as u32) << 16 | (ly as u32) << 24
+}
+
+/// Unpack a move that was packed with [`pack_move`].
+fn unpack_move(packed: u32) -> GlobalCoord {
+    let byte = |shift: u32| ((packed >> shift) & 0xff) as usize;
+    (byte(0), byte(8), (byte(16), byte(24)))
+}
+
+/// A node in the [`Arena`]. The board of the node isn't stored, since each thread rebuilds it by
+/// playing the moves from the root while it descends the tree.
+#[derive(Debug, Default)]
+struct ArenaNode {
+    /// The move taken to get to this node, packed with [`pack_move`].
+    mv: AtomicU32,
+
+    /// Whether this node is [`UNEXPANDED`], [`EXPANDING`], or [`EXPANDED`].
+    state: AtomicU8,
+
+    /// The index of the first child of this node. The children of a node are next to each other
+    /// in the arena.
+    first_child: AtomicU32,
+
+    /// The number of children of this node.
+    child_count: AtomicU32,
+
+    /// The total number of playouts through this node.
+    playouts: AtomicU32,
+
+    /// The total reward for the AI of the playouts through this node, in units of
+    /// `1 / REWARD_SCALE`.
+    wins: AtomicU64,
+
+    /// The number of threads which are currently searching below this node, each of which counts
+    /// as a lost playout for the shape that moved into this node.
+    virtual_losses: AtomicU32,
+}
+
+impl ArenaNode {
+    /// Return the total reward for the AI of the playouts through this node.
+    fn wins(&self) -> f64 {
+        self.wins.load(Ordering::Relaxed) as f64 / REWARD_SCALE
+    }
+
+    /// Return the range of the indices of the children of this node, which is empty if it hasn't
+    /// been expanded yet.
+    fn children(&self) -> std::ops::Range<u32> {
+        if self.state.load(Ordering::Acquire) != EXPANDED {
+            return 0..0;
+        }
+
+        let first_child = self.first_child.load(Ordering::Relaxed);
+        first_child..first_child + self.child_count.load(Ordering::Relaxed)
+    }
+}
+
+/// A fixed-size arena of nodes, where the root is always at index 0.
+#[derive(Debug)]
+struct Arena {
+    /// Every node that the arena can hold, including the ones that haven't been allocated yet.
+    nodes: Vec<ArenaNode>,
+
+    /// The number of nodes that have been allocated.
+    len: AtomicU32,
+}
+
+impl Arena {
+    /// Create an arena which can hold `capacity` nodes, where only the root has been allocated.
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: (0..capacity.max(1)).map(|_| ArenaNode::default()).collect(),
+            len: AtomicU32::new(1),
+        }
+    }
+
+    /// Return the node at the given index.
+    fn node(&self, index: u32) -> &ArenaNode {
+        &self.nodes[index as usize]
+    }
+
+    /// Allocate `count` nodes next to each other and return the index of the first one, or
+    /// `None` if the arena doesn't have room for them.
+    fn alloc(&self, count: u32) -> Option<u32> {
+        let mut len = self.len.load(Ordering::Relaxed);
+        loop {
+            let new_len = len
+                .checked_add(count)
+                .filter(|&new_len| new_len as usize <= self.nodes.len())?;
+
+            match self
+                .len
+                .compare_exchange_weak(len, new_len, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return Some(len),
+                Err(actual) => len = actual,
+            }
+        }
+    }
+}
+
+/// A game tree which is shared between the threads of a tree-parallel search.
+struct SharedTree<'a, const N: usize> {
+    /// The board at the root of the tree.
+    root_board: &'a GlobalBoard<N>,
+
+    /// The nodes of the tree.
+    arena: Arena,
+
+    /// The reward of a drawn playout for the AI. See
+    /// [`MctsPlayer::draw_reward`](super::MctsPlayer::draw_reward).
+    draw_reward: f64,
+}
+
+impl<'a, const N: usize> SharedTree<'a, N> {
+    /// Compute the UCT value of the given child, for a parent with `parent_visits` visits where
+    /// `shape_to_play` is next to move. Virtual losses count as visits which weren't won.
+    fn compute_uct(&self, child: &ArenaNode, shape_to_play: CellShape, parent_visits: u32) -> f64 {
+        let playouts = child.playouts.load(Ordering::Relaxed);
+        let visits = playouts + child.virtual_losses.load(Ordering::Relaxed);
+        if visits == 0 {
+            return f64::INFINITY;
+        }
+
+        let wins = if shape_to_play == self.root_board.ai_shape {
+            child.wins()
+        } else {
+            playouts as f64 - child.wins()
+        };
+
+        wins / visits as f64
+            + EXPLORATION_PARAMETER
+                * f64::sqrt(f64::ln(parent_visits.max(1) as f64) / visits as f64)
+    }
+
+    /// Return the index of the child of the given node with the best UCT value, or `None` if it
+    /// has no children.
+    fn best_child(&self, node: &ArenaNode, shape_to_play: CellShape) -> Option<u32> {
+        let parent_visits =
+            node.playouts.load(Ordering::Relaxed) + node.virtual_losses.load(Ordering::Relaxed);
+
+        node.children().max_by(|&c1, &c2| {
+            self.compute_uct(self.arena.node(c1), shape_to_play, parent_visits)
+                .total_cmp(&self.compute_uct(self.arena.node(c2), shape_to_play, parent_visits))
+        })
+    }
+
+    /// Expand the given node by allocating a child for every legal move in `board`, which is the
+    /// board of the node. Return whether the node has children afterwards.
+    ///
+    /// If another thread is already expanding the node, or the arena is full, then the node isn't
+    /// expanded, so that it's played out as a leaf instead.
+    fn expand(&self, node: &ArenaNode, board: &GlobalBoard<N>) -> bool {
+        if node
+            .state
+            .compare_exchange(UNEXPANDED, EXPANDING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return node.state.load(Ordering::Acquire) == EXPANDED;
+        }
+
+        let moves: ArrayVec<GlobalCoord, MAX_LEGAL_MOVES> = board.legal_moves().collect();
+        let Some(first_child) = self.arena.alloc(moves.len() as u32) else {
+            node.state.store(UNEXPANDED, Ordering::Release);
+            return false;
+        };
+
+        for (index, &mv) in (first_child..).zip(&moves) {
+            self.arena
+                .node(index)
+                .mv
+                .store(pack_move(mv), Ordering::Relaxed);
+        }
+        node.first_child.store(first_child, Ordering::Relaxed);
+        node.child_count
+            .store(moves.len() as u32, Ordering::Relaxed);
+        node.state.store(EXPANDED, Ordering::Release);
+
+        !moves.is_empty()
+    }
+
+    /// Descend the tree from the root by UCT until reaching a leaf, adding a virtual loss to each
+    /// node on the way, and expanding the first leaf that has already been played out from.
+    ///
+    /// The indices of the nodes on the path are left in `path`, and the board of the leaf is left
+    /// in `board`.
+    fn select(&self, board: &mut GlobalBoard<N>, path: &mut Vec<u32>) {
+        board.clone_from(self.root_board);
+        path.clear();
+        path.push(0);
+
+        let mut node = self.arena.node(0);
+        while board.winner == GameResult::Ongoing {
+            let expanded = match node.state.load(Ordering::Acquire) {
+                EXPANDED => true,
+                UNEXPANDED if path.len() == 1 || node.playouts.load(Ordering::Relaxed) > 0 => {
+                    self.expand(node, board)
+                }
+                _ => false,
+            };
+            if !expanded {
+                return;
+            }
+
+            let Some(index) = self.best_child(node, board.to_move) else {
+                return;
+            };
+            node = self.arena.node(index);
+            node.virtual_losses.fetch_add(1, Ordering::Relaxed);
+            path.push(index);
+
+            let legal = board
+                .make_move(unpack_move(node.mv.load(Ordering::Relaxed)), board.to_move)
+                .is_ok();
+            debug_assert!(legal, "A legal move should always be playable");
+        }
+    }
+
+    /// Play `playouts` random games from `board` in `scratch`, and return the total reward for the
+    /// AI.
+    fn simulate(
+        &self,
+        board: &GlobalBoard<N>,
+        scratch: &mut GlobalBoard<N>,
+        playouts: u8,
+        rng: &mut SmallRng,
+    ) -> f64 {
+        (0..playouts)
+            .map(|_| {
+                scratch.clone_from(board);
+                scratch.play_out_randomly(rng);
+
+                match scratch.winner {
+                    GameResult::Won { shape, .. } if shape == self.root_board.ai_shape => 1.,
+                    GameResult::Won { .. } => 0.,
+                    GameResult::Draw | GameResult::Ongoing => self.draw_reward,
+                }
+            })
+            .sum()
+    }
+
+    /// Add the results of some playouts to every node on the path, and take back the virtual
+    /// losses that were added by [`select`](Self::select).
+    fn backpropagate(&self, path: &[u32], reward: f64, playouts: u8) {
+        let wins = (reward * REWARD_SCALE).round() as u64;
+
+        for (depth, &index) in path.iter().enumerate() {
+            let node = self.arena.node(index);
+            node.wins.fetch_add(wins, Ordering::Relaxed);
+            node.playouts
+                .fetch_add(u32::from(playouts), Ordering::Relaxed);
+            if depth > 0 {
+                node.virtual_losses.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Do iterations of MCTS on this thread until the threads have done `max_iterations` between
+    /// them, or the `deadline` has passed. At least one iteration is always done before stopping
+    /// for the deadline.
+    fn work(
+        &self,
+        iterations: &AtomicU32,
+        max_iterations: u32,
+        playouts: u8,
+        deadline: Option<Instant>,
+        rng: &mut SmallRng,
+    ) {
+        let mut board = self.root_board.clone();
+        let mut scratch = self.root_board.clone();
+        let mut path = vec![];
+
+        while iterations.fetch_add(1, Ordering::Relaxed) < max_iterations {
+            if self.arena.node(0).playouts.load(Ordering::Relaxed) > 0
+                && deadline.map_or(false, |deadline| Instant::now() >= deadline)
+            {
+                break;
+            }
+
+            self.select(&mut board, &mut path);
+            let reward = self.simulate(&board, &mut scratch, playouts, rng);
+            self.backpropagate(&path, reward, playouts);
+        }
+    }
+
+    /// Collect the statistics of the root of the tree.
+    fn root_stats(&self) -> RootStats {
+        let root = self.arena.node(0);
+        RootStats {
+            ai_shape: self.root_board.ai_shape,
+            shape_to_play: self.root_board.to_move,
+            wins: root.wins(),
+            playouts: root.playouts.load(Ordering::Relaxed),
+            children: root
+                .children()
+                .map(|index| {
+                    let child = self.arena.node(index);
+                    MoveStats {
+                        mv: unpack_move(child.mv.load(Ordering::Relaxed)),
+                        wins: child.wins(),
+                        playouts: child.playouts.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<const N: usize> GlobalBoard<N> {
+    /// Return the AI-chosen optimal move like
+    /// [`generate_ai_move_with_stats`](Self::generate_ai_move_with_stats), but search with
+    /// `threads` threads which all share one game tree.
+    ///
+    /// The `max_mcts_expansions` iterations are shared between the threads, so this does the same
+    /// amount of work as a single-threaded search, just faster. Each thread adds a virtual loss
+    /// to the nodes that it's searching below, so that the threads spread out over the tree. The
+    /// search is nondeterministic, since it depends on how the threads are scheduled.
+    pub fn generate_ai_move_parallel(
+        &self,
+        max_mcts_expansions: u16,
+        playouts: u8,
+        threads: usize,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        self.search_parallel(&SearchOptions::new(max_mcts_expansions, playouts), threads)
+    }
+
+    /// Find the AI move with tree-parallel MCTS if it's not obvious, stopping the search early if
+    /// there's a [`deadline`](SearchOptions::deadline) and it has passed. The move chosen is the
+    /// most visited one. See [`generate_ai_move_parallel`](Self::generate_ai_move_parallel).
+    ///
+    /// The leaves are always played out, and the [`seed`](SearchOptions::seed) is ignored, since
+    /// the search depends on how the threads are scheduled anyway.
+    pub(super) fn search_parallel(
+        &self,
+        options: &SearchOptions<'_, Self>,
+        threads: usize,
+    ) -> (Option<GlobalCoord>, Option<RootStats>) {
+        if let Some(mv) = self.obvious_move() {
+            return (mv, None);
+        }
+
+        let max_iterations = u32::from(options.max_iterations.max(1));
+        let capacity = (1 + max_iterations as usize * MAX_LEGAL_MOVES).min(MAX_ARENA_NODES);
+        let tree = SharedTree {
+            root_board: self,
+            arena: Arena::with_capacity(capacity),
+            draw_reward: options.draw_reward,
+        };
+        let iterations = AtomicU32::new(0);
+        let (playouts, deadline) = (options.playouts.max(1), options.deadline);
+
+        // Expand the root up front, so that every iteration goes through one of its children
+        tree.expand(tree.arena.node(0), self);
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                let (tree, iterations) = (&tree, &iterations);
+                scope.spawn(move || {
+                    tree.work(
+                        iterations,
+                        max_iterations,
+                        playouts,
+                        deadline,
+                        &mut search_rng(None),
+                    );
+                });
+            }
+        });
+
+        let stats = tree.root_stats();
+        let mv = stats
+            .children
+            .iter()
+            .max_by_key(|stats| stats.playouts)
+            .map(|stats| stats.mv);
+        (mv, Some(stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ultimate::{board::DRAW_REWARD, test_utils::make_global_board};
+
+    #[test]
+    fn pack_move_test() {
+        for mv in [
+            (0, 0, (0, 0)),
+            (2, 1, (0, 2)),
+            (1, 2, (2, 1)),
+            (2, 2, (2, 2)),
+        ] {
+            assert_eq!(unpack_move(pack_move(mv)), mv);
+        }
+    }
+
+    #[test]
+    fn arena_alloc_test() {
+        let arena = Arena::with_capacity(10);
+        assert_eq!(arena.alloc(4), Some(1));
+        assert_eq!(arena.alloc(5), Some(5));
+        assert_eq!(arena.alloc(1), None);
+        assert_eq!(arena.len.load(Ordering::Relaxed), 10);
+    }
+
+    #[test]
+    fn search_parallel_test() {
+        let mut board = make_global_board! {
+            next = (1, 1),
+            (_; _ X _; _) () ();
+            () (O _ _; _ X _; _) ();
+            () () ();
+        };
+        board.ai_shape = board.to_move;
+
+        let (mv, stats) = board.generate_ai_move_parallel(200, 2, 4);
+        let mv = mv.unwrap();
+        let stats = stats.unwrap();
+        assert!(board.legal_moves().any(|legal| legal == mv));
+
+        // Every iteration is done exactly once between the threads, and every legal move at the
+        // root has a child
+        assert_eq!(stats.playouts, 400);
+        assert_eq!(stats.children.len(), board.legal_move_count());
+        assert_eq!(
+            stats
+                .children
+                .iter()
+                .map(|child| child.playouts)
+                .sum::<u32>(),
+            stats.playouts
+        );
+        assert!(stats.wins <= 400.);
+    }
+
+    #[test]
+    fn virtual_loss_test() {
+        let board = GlobalBoard::default();
+        let tree = SharedTree {
+            root_board: &board,
+            arena: Arena::with_capacity(1000),
+            draw_reward: DRAW_REWARD,
+        };
+        let mut scratch = board.clone();
+        let mut path = vec![];
+
+        // Without any playouts yet, a second thread is steered away from the child that the first
+        // thread is searching below
+        tree.select(&mut scratch, &mut path);
+        assert_eq!(path.len(), 2);
+        let first = path[1];
+        assert_eq!(
+            tree.arena
+                .node(first)
+                .virtual_losses
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        let mut other_path = vec![];
+        tree.select(&mut scratch, &mut other_path);
+        assert_ne!(other_path[1], first);
+
+        // And the virtual losses are taken back once the playouts are backpropagated
+        tree.backpropagate(&path, 1., 1);
+        tree.backpropagate(&other_path, 0., 1);
+        for index in tree.arena.node(0).children() {
+            assert_eq!(
+                tree.arena
+                    .node(index)
+                    .virtual_losses
+                    .load(Ordering::Relaxed),
+                0
+            );
+        }
+        assert_eq!(tree.arena.node(0).playouts.load(Ordering::Relaxed), 2);
+        assert_eq!(tree.arena.node(0).wins(), 1.);
+    }
+}
@@ -0,0 +1,306 @@
+//! This module provides a tiny value network, which MCTS can use to evaluate the leaves of the game
+//! tree instead of playing them out randomly. See [`MctsPlayer::value_net`].
+//!
+//! The network is a small fully connected network with ReLU between the layers and tanh at the
+//! end, which is loaded from a JSON file like
+//! `{"layers": [{"weights": [[...], ...], "biases": [...]}, ...]}`. The weights of each layer
+//! have one row for each output and one column for each input. See [`ValueNet::features`] for the
+//! inputs, which match the columns of a [dataset](crate::ultimate::dataset).
+
+use super::{GlobalBoard, MctsPlayer};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path, sync::Arc};
+use thiserror::Error;
+
+/// An enum to represent the ways that loading a [`ValueNet`] can fail.
+#[derive(Debug, Error)]
+pub enum ValueNetError {
+    /// The file couldn't be read.
+    #[error("couldn't read the value network: {0}")]
+    Io(#[from] io::Error),
+
+    /// The file isn't a valid JSON value network.
+    #[error("couldn't parse the value network: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    /// The weights of the layer with the given index don't fit the layers around it, or the last
+    /// layer doesn't have exactly one output.
+    #[error("layer {0} has the wrong shape")]
+    WrongShape(usize),
+}
+
+/// One fully connected layer of a [`ValueNet`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Layer {
+    /// The weights, with one row for each output and one column for each input.
+    weights: Vec<Vec<f32>>,
+
+    /// The bias of each output.
+    biases: Vec<f32>,
+}
+
+impl Layer {
+    /// Return the outputs of this layer for the given inputs, before the activation function.
+    fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        self.weights
+            .iter()
+            .zip(&self.biases)
+            .map(|(row, bias)| row.iter().zip(inputs).map(|(w, x)| w * x).sum::<f32>() + bias)
+            .collect()
+    }
+}
+
+/// A tiny value network, which estimates the result of the game for the shape to move.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueNet {
+    /// The layers of the network, from the inputs to the output.
+    layers: Vec<Layer>,
+}
+
+impl ValueNet {
+    /// Parse a value network from JSON, and check that its layers fit together.
+    pub fn from_json(json: &str) -> Result<Self, ValueNetError> {
+        let net: Self = serde_json::from_str(json)?;
+
+        let mut inputs = None;
+        for (index, layer) in net.layers.iter().enumerate() {
+            let rows_fit = layer
+                .weights
+                .iter()
+                .all(|row| inputs.map_or(true, |inputs| row.len() == inputs));
+            let columns = layer.weights.first().map(Vec::len);
+            if layer.weights.is_empty()
+                || layer.weights.len() != layer.biases.len()
+                || !rows_fit
+                || layer.weights.iter().any(|row| Some(row.len()) != columns)
+            {
+                return Err(ValueNetError::WrongShape(index));
+            }
+            inputs = Some(layer.biases.len());
+        }
+
+        match net.layers.last() {
+            Some(layer) if layer.biases.len() == 1 => Ok(net),
+            _ => Err(ValueNetError::WrongShape(
+                net.layers.len().saturating_sub(1),
+            )),
+        }
+    }
+
+    /// Load a value network from the JSON file at the given path. See
+    /// [`from_json`](Self::from_json).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ValueNetError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Return the number of inputs of the network.
+    pub fn input_count(&self) -> usize {
+        self.layers
+            .first()
+            .and_then(|layer| layer.weights.first())
+            .map_or(0, Vec::len)
+    }
+
+    /// Return the inputs of the network for the given position, from the perspective of the
+    /// shape to move.
+    ///
+    /// There are two inputs for each cell, which are 1 if the shape to move or the other shape
+    /// is in that cell respectively, and then one input for each local board, which is 1 if the
+    /// next move can be played there. The cells are in the order of
+    /// [`local_boards`](GlobalBoard::local_boards) and then the cells of each local board, so a
+    /// 3x3 global board has 171 inputs.
+    pub fn features<const N: usize>(board: &GlobalBoard<N>) -> Vec<f32> {
+        let to_move = board.to_move;
+        let mut features: Vec<f32> = board
+            .local_boards
+            .iter()
+            .flatten()
+            .flat_map(|local_board| local_board.cells.iter().flatten())
+            .flat_map(|&cell| {
+                [
+                    f32::from(u8::from(cell == Some(to_move))),
+                    f32::from(u8::from(cell == Some(to_move.other()))),
+                ]
+            })
+            .collect();
+
+        let mut playable = [[0.; N]; N];
+        for (x, y, _) in board.legal_moves() {
+            playable[x][y] = 1.;
+        }
+        features.extend(playable.iter().flatten());
+
+        features
+    }
+
+    /// Estimate the result of the game for the shape to move, from -1 for a loss to 1 for a win.
+    ///
+    /// This returns `None` if the network was made for a different size of global board.
+    pub fn evaluate<const N: usize>(&self, board: &GlobalBoard<N>) -> Option<f64> {
+        let mut values = Self::features(board);
+        if values.len() != self.input_count() {
+            return None;
+        }
+
+        let (last, hidden) = self.layers.split_last()?;
+        for layer in hidden {
+            values = layer.forward(&values);
+            for value in &mut values {
+                *value = value.max(0.);
+            }
+        }
+
+        Some(f64::from(last.forward(&values)[0].tanh()))
+    }
+
+    /// Estimate the reward of the given position for the [AI shape](GlobalBoard::ai_shape), from
+    /// 0 for a loss to 1 for a win, like the reward of a playout. See
+    /// [`evaluate`](Self::evaluate).
+    pub fn reward<const N: usize>(&self, board: &GlobalBoard<N>) -> Option<f64> {
+        let value = self.evaluate(board)?;
+        let value = if board.to_move == board.ai_shape {
+            value
+        } else {
+            -value
+        };
+
+        Some((value + 1.) / 2.)
+    }
+}
+
+impl MctsPlayer {
+    /// Evaluate the leaves of the game tree with the value network in the given file, rather than
+    /// with random playouts. If the file is missing or invalid, then we log a warning and keep
+    /// using random playouts. See [`value_net`](Self::value_net).
+    pub fn with_value_net_file(mut self, path: impl AsRef<Path>) -> Self {
+        match ValueNet::load(&path) {
+            Ok(value_net) => self.value_net = Some(Arc::new(value_net)),
+            Err(e) => tracing::warn!(
+                path = %path.as_ref().display(),
+                "falling back to random playouts: {e}"
+            ),
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shared::ai::AiPlayer, ultimate::test_utils::make_global_board, CellShape};
+
+    /// Return a network with one hidden layer that only looks at whether the shape to move or the
+    /// other shape is in the very center of the global board.
+    fn center_net() -> ValueNet {
+        let mut center = vec![0.; 171];
+        // The center is cell (1, 1) of local board (1, 1), which is cell 40
+        center[2 * 40] = 1.;
+        center[2 * 40 + 1] = -1.;
+
+        ValueNet {
+            layers: vec![
+                Layer {
+                    weights: vec![center.clone(), center.iter().map(|w| -w).collect()],
+                    biases: vec![0., 0.],
+                },
+                Layer {
+                    weights: vec![vec![2., -2.]],
+                    biases: vec![0.],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn from_json_test() {
+        let net = ValueNet::from_json(
+            r#"{"layers": [
+                {"weights": [[1, 2], [3, 4], [5, 6]], "biases": [0, 0, 0]},
+                {"weights": [[1, 1, 1]], "biases": [0.5]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(net.input_count(), 2);
+
+        // The second layer has 2 inputs but the first layer has 3 outputs
+        assert!(matches!(
+            ValueNet::from_json(
+                r#"{"layers": [
+                    {"weights": [[1, 2], [3, 4], [5, 6]], "biases": [0, 0, 0]},
+                    {"weights": [[1, 1]], "biases": [0.5]}
+                ]}"#
+            ),
+            Err(ValueNetError::WrongShape(1))
+        ));
+
+        // The last layer must have exactly one output
+        assert!(matches!(
+            ValueNet::from_json(r#"{"layers": [{"weights": [[1], [2]], "biases": [0, 0]}]}"#),
+            Err(ValueNetError::WrongShape(0))
+        ));
+        assert!(matches!(
+            ValueNet::from_json(r#"{"layers": []}"#),
+            Err(ValueNetError::WrongShape(0))
+        ));
+        assert!(matches!(
+            ValueNet::from_json("not json"),
+            Err(ValueNetError::Parse(_))
+        ));
+        assert!(matches!(
+            ValueNet::load("/this/file/does/not/exist.json"),
+            Err(ValueNetError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn evaluate_test() {
+        let net = center_net();
+        let board = make_global_board! {
+            next = (1, 1),
+            (_; _ O _; _) () ();
+            () (_; _ X _; _) ();
+            () () ();
+        };
+        assert_eq!(ValueNet::features(&board).len(), 171);
+
+        // X has the center, and it's X to move
+        assert_eq!(board.to_move(), CellShape::X);
+        assert!(net.evaluate(&board).unwrap() > 0.9);
+
+        // The reward is from the perspective of the AI
+        let mut board = board;
+        board.set_ai_shape(CellShape::X);
+        assert!(net.reward(&board).unwrap() > 0.95);
+        board.set_ai_shape(CellShape::O);
+        assert!(net.reward(&board).unwrap() < 0.05);
+
+        // A network for 3x3 global boards can't evaluate smaller ones
+        let small = GlobalBoard::<2>::empty(CellShape::O, CellShape::X);
+        assert_eq!(net.evaluate(&small), None);
+    }
+
+    #[test]
+    fn mcts_with_value_net_test() {
+        let board = make_global_board! {
+            next = (1, 1),
+            (_; _ X _; _) () ();
+            () (O _ _; _ X _; _) ();
+            () () ();
+        };
+
+        // Each iteration evaluates one leaf instead of doing the playouts
+        let mut player = MctsPlayer {
+            value_net: Some(Arc::new(center_net())),
+            ..MctsPlayer::new(50, 5)
+        };
+        let mv = player.choose_move(&board).unwrap();
+        assert!(board.legal_moves().any(|legal| legal == mv));
+        assert!(player.last_stats.unwrap().playouts <= 50);
+
+        // A missing file falls back to random playouts
+        let mut player = MctsPlayer::new(50, 5).with_value_net_file("/does/not/exist.json");
+        assert_eq!(player.value_net, None);
+        player.choose_move(&board).unwrap();
+        assert!(player.last_stats.unwrap().playouts > 50);
+    }
+}
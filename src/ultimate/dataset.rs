@@ -0,0 +1,239 @@
+//! This module generates datasets of positions from games of the MCTS AI against itself, for
+//! training learned evaluation functions against the engine. See [`write_dataset`].
+//!
+//! A dataset is a CSV file with a header, and one row for each position where the AI had to
+//! search, with these columns:
+//!
+//! - `game`: the number of the game, starting from 1.
+//! - `ply`: the number of moves played before this position.
+//! - `cells`: the 81 cells of the global board as `X`, `O`, or `.`, row by row from the top left,
+//!   like [`ImportFormat::Ascii`](super::import::ImportFormat::Ascii).
+//! - `next_board`: the local board that the next move must be played in, numbered from 1 to 9 in
+//!   row-major order from the top left, or 0 if it can be played in any local board.
+//! - `to_move`: the shape to move, `X` or `O`.
+//! - `outcome`: the result of the game for the shape to move, which is 1 for a win, 0 for a draw,
+//!   and -1 for a loss.
+//! - `visits`: the number of playouts after each move that the AI searched, as space-separated
+//!   `cell:playouts` pairs, where the cells are numbered from 0 to 80 in the same order as
+//!   `cells`.
+
+use super::{
+    board::{GlobalBoard, MctsPlayer, RootStats},
+    GlobalCoord,
+};
+use crate::{
+    shared::{ai::AiPlayer, board::GameResult},
+    CellShape,
+};
+use rayon::prelude::*;
+use std::io::{self, Write};
+
+/// The header row of a dataset.
+pub const CSV_HEADER: &str = "game,ply,cells,next_board,to_move,outcome,visits";
+
+/// A position from a self-play game where the AI had to search. See [`selfplay_game`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// The position before the AI moved.
+    pub board: GlobalBoard,
+
+    /// The number of moves played before this position.
+    pub ply: u32,
+
+    /// The statistics of the search from this position.
+    pub stats: RootStats,
+}
+
+/// Return the index of the given cell in the 9x9 grid of cells, row by row from the top left.
+fn cell_index((x, y, (lx, ly)): GlobalCoord) -> usize {
+    (3 * y + ly) * 9 + 3 * x + lx
+}
+
+impl Sample {
+    /// Format this sample as a row of a dataset, for the game with the given number and result.
+    /// See the [module docs](self) for the columns.
+    pub fn csv_row(&self, game: u32, result: GameResult) -> String {
+        let cells: String = (0..81)
+            .map(|i| {
+                let (row, column) = (i / 9, i % 9);
+                match self.board.local_boards()[column / 3][row / 3].cells()[column % 3][row % 3] {
+                    Some(CellShape::X) => 'X',
+                    Some(CellShape::O) => 'O',
+                    None => '.',
+                }
+            })
+            .collect();
+        let next_board = self
+            .board
+            .next_local_board()
+            .map_or(0, |(x, y)| 3 * y + x + 1);
+        let to_move = self.board.to_move();
+        let outcome = match result {
+            GameResult::Won { shape, .. } if shape == to_move => 1,
+            GameResult::Won { .. } => -1,
+            GameResult::Draw | GameResult::Ongoing => 0,
+        };
+        let visits = self
+            .stats
+            .children
+            .iter()
+            .map(|child| format!("{}:{}", cell_index(child.mv), child.playouts))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "{game},{},{cells},{next_board},{to_move},{outcome},{visits}",
+            self.ply
+        )
+    }
+}
+
+/// Play a game of the given player against itself from an empty board, and return the positions
+/// where it had to search, along with the result of the game.
+pub fn selfplay_game(player: &mut MctsPlayer) -> (Vec<Sample>, GameResult) {
+    let mut board = GlobalBoard::default();
+    let mut samples = vec![];
+
+    for ply in 0.. {
+        if board.get_winner() != GameResult::Ongoing {
+            break;
+        }
+
+        let Some(mv) = player.choose_move(&board) else {
+            break;
+        };
+        if let Some(stats) = player.last_stats.take() {
+            samples.push(Sample {
+                board: board.clone(),
+                ply,
+                stats,
+            });
+        }
+        if board.make_move(mv, board.to_move()).is_err() {
+            break;
+        }
+    }
+
+    (samples, board.get_winner())
+}
+
+/// Play `games` games of MCTS with the given settings against itself, and write them to `out` as
+/// a dataset. The games are played in parallel, but they're written in order.
+pub fn write_dataset(
+    out: &mut impl Write,
+    games: u32,
+    max_mcts_expansions: u16,
+    playouts: u8,
+) -> io::Result<()> {
+    writeln!(out, "{CSV_HEADER}")?;
+
+    // Write the games in batches, so that the dataset isn't all held in memory at once
+    let batch_size = rayon::current_num_threads().max(1) as u32;
+    let mut first_game = 1;
+    while first_game <= games {
+        let last_game = games.min(first_game + batch_size - 1);
+        let batch: Vec<Vec<String>> = (first_game..=last_game)
+            .into_par_iter()
+            .map(|game| {
+                let (samples, result) =
+                    selfplay_game(&mut MctsPlayer::new(max_mcts_expansions, playouts));
+                samples
+                    .iter()
+                    .map(|sample| sample.csv_row(game, result))
+                    .collect()
+            })
+            .collect();
+
+        for row in batch.iter().flatten() {
+            writeln!(out, "{row}")?;
+        }
+        first_game = last_game + 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ultimate::{board::MoveStats, test_utils::make_global_board};
+
+    #[test]
+    fn cell_index_test() {
+        assert_eq!(cell_index((0, 0, (0, 0))), 0);
+        assert_eq!(cell_index((2, 0, (2, 0))), 8);
+        assert_eq!(cell_index((1, 1, (1, 1))), 40);
+        assert_eq!(cell_index((0, 1, (2, 0))), 29);
+        assert_eq!(cell_index((2, 2, (2, 2))), 80);
+    }
+
+    #[test]
+    fn csv_row_test() {
+        let board = make_global_board! {
+            next = (1, 1),
+            (_; _ X _; _) () ();
+            () (_; _ O _; _) ();
+            () () ();
+        };
+        let sample = Sample {
+            board,
+            ply: 2,
+            stats: RootStats {
+                ai_shape: CellShape::X,
+                shape_to_play: CellShape::X,
+                wins: 3.,
+                playouts: 5,
+                children: vec![
+                    MoveStats {
+                        mv: (1, 1, (0, 0)),
+                        wins: 2.5,
+                        playouts: 4,
+                    },
+                    MoveStats {
+                        mv: (1, 1, (2, 2)),
+                        wins: 0.5,
+                        playouts: 1,
+                    },
+                ],
+            },
+        };
+
+        let cells = format!("{}X{}O{}", ".".repeat(10), ".".repeat(29), ".".repeat(40));
+        assert_eq!(
+            sample.csv_row(
+                7,
+                GameResult::Won {
+                    shape: CellShape::O,
+                    line: [(0, 0), (1, 1), (2, 2)],
+                }
+            ),
+            format!("7,2,{cells},5,X,-1,30:4 50:1")
+        );
+        assert_eq!(
+            sample.csv_row(7, GameResult::Draw),
+            format!("7,2,{cells},5,X,0,30:4 50:1")
+        );
+    }
+
+    #[test]
+    fn write_dataset_test() {
+        let mut out = vec![];
+        write_dataset(&mut out, 2, 10, 1).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert!(!rows.is_empty());
+        for row in &rows {
+            assert_eq!(row.len(), 7);
+            assert!(["1", "2"].contains(&row[0]));
+            assert_eq!(row[2].len(), 81);
+            assert!(["1", "0", "-1"].contains(&row[5]));
+        }
+
+        // The games are written in order
+        assert!(rows.windows(2).all(|pair| pair[0][0] <= pair[1][0]));
+    }
+}
@@ -0,0 +1,222 @@
+//! This module imports ultimate games written in the formats used by online ultimate tic-tac-toe
+//! playgrounds. See [`ImportFormat`] for the formats, and [`import`] to detect the format and
+//! import a game.
+
+use super::{
+    board::{GlobalBoard, LocalBoard},
+    GlobalCoord,
+};
+use crate::{
+    shared::{board::InvalidPosition, record::GameRecord},
+    CellShape,
+};
+use thiserror::Error;
+
+/// The formats that ultimate games can be imported from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A string of digits from 1 to 9, two for each move, which gives the whole history of the
+    /// game with [`X`](CellShape::X) moving first.
+    ///
+    /// The first digit of each move is the local board and the second is the cell within it,
+    /// both numbered in row-major order from the top left, so `55` is the very centre of the
+    /// board. Whitespace and commas between moves are ignored.
+    MoveDigits,
+
+    /// A 9x9 grid of `X`, `O`, and `.` (or `_`) for empty cells, row by row from the top left,
+    /// which gives a position but no history.
+    ///
+    /// Spaces, `|`, `+`, `-`, and `=` are ignored, so the grid can be drawn with separators
+    /// between the local boards.
+    Ascii,
+}
+
+/// A game imported by [`import`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportedGame {
+    /// A game with its full history, from [`ImportFormat::MoveDigits`].
+    Record(GameRecord<GlobalCoord>),
+
+    /// A position without any history, from [`ImportFormat::Ascii`]. Since the previous move is
+    /// unknown, the next move can be played in any local board.
+    Position(GlobalBoard),
+}
+
+/// An enum to represent the ways that importing a game can fail.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum ImportError {
+    /// The input isn't in any of the formats in [`ImportFormat`].
+    #[error("unknown format")]
+    UnknownFormat,
+
+    /// The move at the given index in the history is illegal.
+    #[error("move {0} is illegal")]
+    IllegalMove(usize),
+
+    /// The grid of an ASCII position has the given number of cells, rather than 81.
+    #[error("expected 81 cells but found {0}")]
+    WrongCellCount(usize),
+
+    /// The imported position is invalid.
+    #[error("invalid position: {0}")]
+    InvalidPosition(#[from] InvalidPosition),
+}
+
+/// Convert a 1-based row-major index into a coordinate, like `1` to `(0, 0)` and `6` to `(2, 1)`.
+fn index_to_coord(index: u32) -> (usize, usize) {
+    let index = index as usize - 1;
+    (index % 3, index / 3)
+}
+
+/// Detect which format the input is in, if any.
+pub fn detect_format(input: &str) -> Option<ImportFormat> {
+    let digits: Vec<char> = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .collect();
+    if !digits.is_empty() && digits.len() % 2 == 0 && digits.iter().all(|c| ('1'..='9').contains(c))
+    {
+        return Some(ImportFormat::MoveDigits);
+    }
+
+    if !input.trim().is_empty()
+        && input
+            .chars()
+            .all(|c| c.is_whitespace() || "XxOo._|+-=".contains(c))
+    {
+        return Some(ImportFormat::Ascii);
+    }
+
+    None
+}
+
+/// Detect the format of the input and import the game.
+pub fn import(input: &str) -> Result<ImportedGame, ImportError> {
+    match detect_format(input).ok_or(ImportError::UnknownFormat)? {
+        ImportFormat::MoveDigits => import_move_digits(input).map(ImportedGame::Record),
+        ImportFormat::Ascii => import_ascii(input).map(ImportedGame::Position),
+    }
+}
+
+/// Import a game in the [`MoveDigits`](ImportFormat::MoveDigits) format, checking that every
+/// move is legal.
+pub fn import_move_digits(input: &str) -> Result<GameRecord<GlobalCoord>, ImportError> {
+    let digits: Vec<u32> = input.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    let mut record = GameRecord::new(CellShape::X);
+    let mut board = GlobalBoard::new_with_first_shape(CellShape::O, CellShape::X);
+
+    for (index, pair) in digits.chunks(2).enumerate() {
+        let &[board_index, cell_index] = pair else {
+            return Err(ImportError::UnknownFormat);
+        };
+        if board_index == 0 || cell_index == 0 {
+            return Err(ImportError::UnknownFormat);
+        }
+
+        let (x, y) = index_to_coord(board_index);
+        let coord = (x, y, index_to_coord(cell_index));
+
+        let shape = board.to_move();
+        board
+            .make_move(coord, shape)
+            .map_err(|_| ImportError::IllegalMove(index))?;
+        record.moves.push(coord);
+    }
+
+    Ok(record)
+}
+
+/// Import a position in the [`Ascii`](ImportFormat::Ascii) format, checking that it's valid.
+pub fn import_ascii(input: &str) -> Result<GlobalBoard, ImportError> {
+    let cells: Vec<Option<CellShape>> = input
+        .chars()
+        .filter_map(|c| match c {
+            '.' | '_' => Some(None),
+            c => CellShape::try_from(c).ok().map(Some),
+        })
+        .collect();
+    if cells.len() != 81 {
+        return Err(ImportError::WrongCellCount(cells.len()));
+    }
+
+    let mut local_cells = [[[[None; 3]; 3]; 3]; 3];
+    for (i, &cell) in cells.iter().enumerate() {
+        let (row, column) = (i / 9, i % 9);
+        local_cells[column / 3][row / 3][column % 3][row % 3] = cell;
+    }
+
+    Ok(GlobalBoard::from_position(
+        None,
+        local_cells.map(|column| column.map(LocalBoard::with_cells)),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ultimate::test_utils::make_global_board;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn detect_format_test() {
+        assert_eq!(detect_format("5519"), Some(ImportFormat::MoveDigits));
+        assert_eq!(detect_format("55, 19\n93"), Some(ImportFormat::MoveDigits));
+        assert_eq!(detect_format("X.O|...|..."), Some(ImportFormat::Ascii));
+        assert_eq!(detect_format("551"), None);
+        assert_eq!(detect_format("5510"), None);
+        assert_eq!(detect_format("hello"), None);
+        assert_eq!(detect_format(""), None);
+    }
+
+    #[test]
+    fn import_move_digits_test() {
+        assert_eq!(
+            import("55 51 15"),
+            Ok(ImportedGame::Record(GameRecord {
+                first_shape: CellShape::X,
+                moves: vec![(1, 1, (1, 1)), (1, 1, (0, 0)), (0, 0, (1, 1))],
+                skipped_turns: vec![],
+                move_times: vec![],
+                annotations: BTreeMap::new(),
+            }))
+        );
+
+        // The second move is in the wrong local board
+        assert_eq!(import("5519"), Err(ImportError::IllegalMove(1)));
+    }
+
+    #[test]
+    fn import_ascii_test() {
+        let input = "
+            X . . | . . . | . . .
+            . . . | . . . | . . .
+            . . . | . . . | . . .
+            ------+-------+------
+            . . . | . O . | . . .
+            . . . | . . . | . . .
+            . . . | . . . | . . .
+            ------+-------+------
+            . . . | . . . | . . .
+            . . . | . . . | . . .
+            . . . | . . . | . . x
+        ";
+
+        assert_eq!(
+            import(input),
+            Ok(ImportedGame::Position(make_global_board! {
+                (X _ _; _; _) () ();
+                () (_ O _; _; _) ();
+                () () (_; _; _ _ X)
+            }))
+        );
+
+        assert_eq!(import("X . ."), Err(ImportError::WrongCellCount(3)));
+        assert_eq!(
+            import(&input.replace('O', "X")),
+            Err(ImportError::InvalidPosition(InvalidPosition::TooManyMoves(
+                CellShape::X
+            )))
+        );
+    }
+}
@@ -11,6 +11,10 @@ pub type GlobalCoord = (usize, usize, LocalCoord);
 
 pub mod app;
 pub mod board;
+pub mod import;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dataset;
 
 pub use self::app::UltimateTTTApp;
 
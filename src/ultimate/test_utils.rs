@@ -200,15 +200,15 @@ mod tests {
         // -----
         // O| |
         let board = _make_local_board!((X X _; _ O _; O _ _));
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], Some(CellShape::X));
-        assert_eq!(board.cells[2][0], None);
-        assert_eq!(board.cells[0][1], None);
-        assert_eq!(board.cells[1][1], Some(CellShape::O));
-        assert_eq!(board.cells[2][1], None);
-        assert_eq!(board.cells[0][2], Some(CellShape::O));
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], None);
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], Some(CellShape::X));
+        assert_eq!(board.cells()[2][0], None);
+        assert_eq!(board.cells()[0][1], None);
+        assert_eq!(board.cells()[1][1], Some(CellShape::O));
+        assert_eq!(board.cells()[2][1], None);
+        assert_eq!(board.cells()[0][2], Some(CellShape::O));
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], None);
 
         // X| |O
         // -----
@@ -216,15 +216,15 @@ mod tests {
         // -----
         //  | |
         let board = _make_local_board!((X _ O; X O _; _));
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], None);
-        assert_eq!(board.cells[2][0], Some(CellShape::O));
-        assert_eq!(board.cells[0][1], Some(CellShape::X));
-        assert_eq!(board.cells[1][1], Some(CellShape::O));
-        assert_eq!(board.cells[2][1], None);
-        assert_eq!(board.cells[0][2], None);
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], None);
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], None);
+        assert_eq!(board.cells()[2][0], Some(CellShape::O));
+        assert_eq!(board.cells()[0][1], Some(CellShape::X));
+        assert_eq!(board.cells()[1][1], Some(CellShape::O));
+        assert_eq!(board.cells()[2][1], None);
+        assert_eq!(board.cells()[0][2], None);
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], None);
 
         // X|X|O
         // -----
@@ -232,15 +232,15 @@ mod tests {
         // -----
         // O| |O
         let board = _make_local_board!((X X O; O X X; O _ O));
-        assert_eq!(board.cells[0][0], Some(CellShape::X));
-        assert_eq!(board.cells[1][0], Some(CellShape::X));
-        assert_eq!(board.cells[2][0], Some(CellShape::O));
-        assert_eq!(board.cells[0][1], Some(CellShape::O));
-        assert_eq!(board.cells[1][1], Some(CellShape::X));
-        assert_eq!(board.cells[2][1], Some(CellShape::X));
-        assert_eq!(board.cells[0][2], Some(CellShape::O));
-        assert_eq!(board.cells[1][2], None);
-        assert_eq!(board.cells[2][2], Some(CellShape::O));
+        assert_eq!(board.cells()[0][0], Some(CellShape::X));
+        assert_eq!(board.cells()[1][0], Some(CellShape::X));
+        assert_eq!(board.cells()[2][0], Some(CellShape::O));
+        assert_eq!(board.cells()[0][1], Some(CellShape::O));
+        assert_eq!(board.cells()[1][1], Some(CellShape::X));
+        assert_eq!(board.cells()[2][1], Some(CellShape::X));
+        assert_eq!(board.cells()[0][2], Some(CellShape::O));
+        assert_eq!(board.cells()[1][2], None);
+        assert_eq!(board.cells()[2][2], Some(CellShape::O));
     }
 
     #[test]
@@ -250,10 +250,10 @@ mod tests {
             () () ();
             () () ()
         };
-        assert!(arr[0][0].cells[0][0] == Some(CellShape::X));
-        assert!(arr[0][0].cells[1][0] == Some(CellShape::X));
-        assert!(arr[0][0].cells[0][1] == Some(CellShape::O));
-        assert!(arr[0][0].cells[2][1] == Some(CellShape::O));
+        assert!(arr[0][0].cells()[0][0] == Some(CellShape::X));
+        assert!(arr[0][0].cells()[1][0] == Some(CellShape::X));
+        assert!(arr[0][0].cells()[0][1] == Some(CellShape::O));
+        assert!(arr[0][0].cells()[2][1] == Some(CellShape::O));
     }
 
     #[test]
@@ -270,11 +270,13 @@ mod tests {
             () () ();
             () () ()
         };
-        let mut board = GlobalBoard::default();
-        board.local_boards[0][0].cells[0][0] = Some(CellShape::X);
-        board.local_boards[0][0].cells[1][0] = Some(CellShape::X);
-        board.local_boards[0][0].cells[0][1] = Some(CellShape::O);
-        board.local_boards[0][0].cells[2][1] = Some(CellShape::O);
+        let mut local_boards = [[LocalBoard::new(); 3]; 3];
+        local_boards[0][0] = LocalBoard::with_cells([
+            [Some(CellShape::X), Some(CellShape::O), None],
+            [Some(CellShape::X), None, None],
+            [None, Some(CellShape::O), None],
+        ]);
+        let board = GlobalBoard::with_local_boards(local_boards);
         assert_eq!(board, macro_board);
 
         let macro_board = make_global_board! {
@@ -282,17 +284,23 @@ mod tests {
             () (X O _; _; O _ X) ();
             (X X _; _; O _ O) () ()
         };
-        let mut board = GlobalBoard::default();
-        board.local_boards[0][0].cells[0][2] = Some(CellShape::X);
-        board.local_boards[0][0].cells[1][2] = Some(CellShape::O);
-        board.local_boards[1][1].cells[0][0] = Some(CellShape::X);
-        board.local_boards[1][1].cells[1][0] = Some(CellShape::O);
-        board.local_boards[1][1].cells[0][2] = Some(CellShape::O);
-        board.local_boards[1][1].cells[2][2] = Some(CellShape::X);
-        board.local_boards[0][2].cells[0][0] = Some(CellShape::X);
-        board.local_boards[0][2].cells[1][0] = Some(CellShape::X);
-        board.local_boards[0][2].cells[0][2] = Some(CellShape::O);
-        board.local_boards[0][2].cells[2][2] = Some(CellShape::O);
+        let mut local_boards = [[LocalBoard::new(); 3]; 3];
+        local_boards[0][0] = LocalBoard::with_cells([
+            [None, None, Some(CellShape::X)],
+            [None, None, Some(CellShape::O)],
+            [None, None, None],
+        ]);
+        local_boards[1][1] = LocalBoard::with_cells([
+            [Some(CellShape::X), None, Some(CellShape::O)],
+            [Some(CellShape::O), None, None],
+            [None, None, Some(CellShape::X)],
+        ]);
+        local_boards[0][2] = LocalBoard::with_cells([
+            [Some(CellShape::X), None, Some(CellShape::O)],
+            [Some(CellShape::X), None, None],
+            [None, None, Some(CellShape::O)],
+        ]);
+        let board = GlobalBoard::with_local_boards(local_boards);
         assert_eq!(board, macro_board);
 
         let macro_board = make_global_board! {
@@ -300,19 +308,28 @@ mod tests {
             () (O X _; _; _) (_; _ O _; X _ _);
             () () ()
         };
-        let mut board = GlobalBoard::default();
-        board.local_boards[0][0].cells[0][0] = Some(CellShape::X);
-        board.local_boards[0][0].cells[1][0] = Some(CellShape::X);
-        board.local_boards[0][0].cells[2][0] = Some(CellShape::X);
-        board.local_boards[0][0].cells[1][1] = Some(CellShape::O);
-        board.local_boards[0][0].cells[2][1] = Some(CellShape::O);
-        board.local_boards[0][0].cells[2][2] = Some(CellShape::O);
-        board.local_boards[1][0].cells[0][1] = Some(CellShape::X);
-        board.local_boards[1][0].cells[2][1] = Some(CellShape::O);
-        board.local_boards[1][1].cells[0][0] = Some(CellShape::O);
-        board.local_boards[1][1].cells[1][0] = Some(CellShape::X);
-        board.local_boards[2][1].cells[1][1] = Some(CellShape::O);
-        board.local_boards[2][1].cells[0][2] = Some(CellShape::X);
+        let mut local_boards = [[LocalBoard::new(); 3]; 3];
+        local_boards[0][0] = LocalBoard::with_cells([
+            [Some(CellShape::X), None, None],
+            [Some(CellShape::X), Some(CellShape::O), None],
+            [Some(CellShape::X), Some(CellShape::O), Some(CellShape::O)],
+        ]);
+        local_boards[1][0] = LocalBoard::with_cells([
+            [None, Some(CellShape::X), None],
+            [None, None, None],
+            [None, Some(CellShape::O), None],
+        ]);
+        local_boards[1][1] = LocalBoard::with_cells([
+            [Some(CellShape::O), None, None],
+            [Some(CellShape::X), None, None],
+            [None, None, None],
+        ]);
+        local_boards[2][1] = LocalBoard::with_cells([
+            [None, None, Some(CellShape::X)],
+            [None, Some(CellShape::O), None],
+            [None, None, None],
+        ]);
+        let board = GlobalBoard::with_local_boards(local_boards);
         assert_eq!(board, macro_board);
 
         let macro_board = make_global_board! {
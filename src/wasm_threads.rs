@@ -0,0 +1,28 @@
+//! This module starts a thread pool of Web Workers so that rayon can use real multithreading on
+//! Wasm.
+
+use wasm_bindgen_futures::JsFuture;
+
+/// Start the rayon thread pool with one Web Worker per logical core, if `SharedArrayBuffer` is
+/// available.
+///
+/// `SharedArrayBuffer` is only available when the page is cross-origin isolated. If it isn't, then
+/// we don't start a thread pool, and rayon falls back to running everything sequentially on the
+/// main thread.
+pub async fn init_thread_pool() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+
+    if !window.cross_origin_isolated() {
+        web_sys::console::warn_1(
+            &"Page is not cross-origin isolated, so the AI will run on a single thread".into(),
+        );
+        return;
+    }
+
+    let threads = window.navigator().hardware_concurrency() as usize;
+    if let Err(e) = JsFuture::from(wasm_bindgen_rayon::init_thread_pool(threads)).await {
+        web_sys::console::warn_1(&e);
+    }
+}